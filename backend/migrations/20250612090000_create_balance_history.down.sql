@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS balance_history;
+ALTER TABLE eth_account_balances DROP COLUMN IF EXISTS block_number;
@@ -0,0 +1,17 @@
+-- Add up migration script here
+ALTER TABLE eth_account_balances ADD COLUMN IF NOT EXISTS block_number BIGINT;
+
+CREATE TABLE IF NOT EXISTS balance_history (
+        id BIGSERIAL PRIMARY KEY,
+        address CHAR(42) NOT NULL,
+        token_address CHAR(42) NOT NULL,
+        block_number BIGINT NOT NULL,
+        balance NUMERIC NOT NULL,
+        observed_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX IF NOT EXISTS balance_history_account_block_idx
+    ON balance_history (address, token_address, block_number);
+
+CREATE INDEX IF NOT EXISTS balance_history_account_observed_idx
+    ON balance_history (address, token_address, observed_at);
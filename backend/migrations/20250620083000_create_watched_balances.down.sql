@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS watched_balances;
+ALTER TABLE eth_account_balances DROP COLUMN IF EXISTS changed_at;
@@ -0,0 +1,14 @@
+-- Add up migration script here
+ALTER TABLE eth_account_balances ADD COLUMN IF NOT EXISTS changed_at TIMESTAMP WITHOUT TIME ZONE;
+UPDATE eth_account_balances SET changed_at = updated_at WHERE changed_at IS NULL;
+ALTER TABLE eth_account_balances ALTER COLUMN changed_at SET DEFAULT NOW();
+ALTER TABLE eth_account_balances ALTER COLUMN changed_at SET NOT NULL;
+
+CREATE TABLE IF NOT EXISTS watched_balances (
+        id BIGSERIAL PRIMARY KEY,
+        address CHAR(42) NOT NULL,
+        token_address CHAR(42) NOT NULL,
+        label TEXT,
+        created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW(),
+        UNIQUE (address, token_address)
+    );
@@ -0,0 +1,29 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS webhooks (
+        id BIGSERIAL PRIMARY KEY,
+        url TEXT NOT NULL,
+        secret TEXT NOT NULL,
+        address CHAR(42) NOT NULL,
+        -- NULL matches the native balance and every token
+        token_address CHAR(42),
+        threshold NUMERIC NOT NULL DEFAULT 0,
+        active BOOLEAN NOT NULL DEFAULT TRUE,
+        created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX IF NOT EXISTS webhooks_address_idx ON webhooks (address);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+        id BIGSERIAL PRIMARY KEY,
+        webhook_id BIGINT NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+        -- Stable across retries and replays so receivers can deduplicate
+        event_id TEXT NOT NULL,
+        payload JSONB NOT NULL,
+        attempt INTEGER NOT NULL,
+        status_code INTEGER,
+        error TEXT,
+        succeeded BOOLEAN NOT NULL,
+        created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_webhook_idx ON webhook_deliveries (webhook_id, id);
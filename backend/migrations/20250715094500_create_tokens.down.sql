@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE eth_account_balances DROP CONSTRAINT IF EXISTS eth_account_balances_token_fkey;
+ALTER TABLE eth_account_balances DROP COLUMN IF EXISTS chain_id;
+DROP TABLE IF EXISTS tokens;
@@ -0,0 +1,25 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS tokens (
+        chain_id BIGINT NOT NULL,
+        address CHAR(42) NOT NULL,
+        -- Metadata is NULL until resolved from the chain or imported
+        symbol TEXT,
+        name TEXT,
+        decimals SMALLINT,
+        logo_uri TEXT,
+        tags TEXT[] NOT NULL DEFAULT '{}',
+        verified BOOLEAN NOT NULL DEFAULT FALSE,
+        created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (chain_id, address)
+    );
+
+-- The native asset of the connected chain is registered at startup, see tokens::register_native_token
+
+-- Balances reference the registry of their chain. Rows without a chain, stored before the
+-- registry existed or of a removed token, are kept but detached until written again.
+ALTER TABLE eth_account_balances ADD COLUMN IF NOT EXISTS chain_id BIGINT;
+ALTER TABLE eth_account_balances
+    ADD CONSTRAINT eth_account_balances_token_fkey
+    FOREIGN KEY (chain_id, token_address) REFERENCES tokens (chain_id, address)
+    ON DELETE SET NULL (chain_id);
@@ -0,0 +1,26 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS api_keys (
+        id BIGSERIAL PRIMARY KEY,
+        -- SHA-256 of the key, the key itself is only shown once at creation
+        key_hash CHAR(64) NOT NULL UNIQUE,
+        -- First characters of the key, to tell keys apart
+        key_prefix VARCHAR(16) NOT NULL,
+        owner TEXT NOT NULL,
+        -- Router groups the key may call, e.g. public or private, every group if empty
+        scopes TEXT[] NOT NULL DEFAULT '{}',
+        rate_limit_per_second INTEGER NOT NULL,
+        daily_quota BIGINT NOT NULL,
+        active BOOLEAN NOT NULL DEFAULT TRUE,
+        created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE TABLE IF NOT EXISTS api_key_usage (
+        api_key_id BIGINT NOT NULL REFERENCES api_keys (id) ON DELETE CASCADE,
+        -- Route template, e.g. /v1/public/eth/accounts/{address}
+        route TEXT NOT NULL,
+        day DATE NOT NULL,
+        request_count BIGINT NOT NULL DEFAULT 0,
+        PRIMARY KEY (api_key_id, route, day)
+    );
+
+CREATE INDEX IF NOT EXISTS api_key_usage_day_idx ON api_key_usage (day);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS outgoing_transactions;
+DROP TABLE IF EXISTS wallet_nonces;
@@ -0,0 +1,43 @@
+-- Add up migration script here
+-- Next nonce of every hot wallet, the row is locked while a transaction is sent
+CREATE TABLE IF NOT EXISTS wallet_nonces (
+        address CHAR(42) NOT NULL,
+        chain_id BIGINT NOT NULL,
+        next_nonce BIGINT NOT NULL,
+        updated_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (address, chain_id)
+    );
+
+-- Every transaction sent by a hot wallet, resubmissions of a nonce are separate rows
+CREATE TABLE IF NOT EXISTS outgoing_transactions (
+        id BIGSERIAL PRIMARY KEY,
+        from_address CHAR(42) NOT NULL,
+        chain_id BIGINT NOT NULL,
+        nonce BIGINT NOT NULL,
+        tx_hash CHAR(66) NOT NULL UNIQUE,
+        -- What the transaction is for, e.g. faucet, mint or cancel
+        purpose TEXT NOT NULL,
+        to_address CHAR(42),
+        value NUMERIC(78, 0) NOT NULL,
+        input BYTEA NOT NULL,
+        gas_limit BIGINT NOT NULL,
+        max_fee_per_gas NUMERIC(78, 0) NOT NULL,
+        max_priority_fee_per_gas NUMERIC(78, 0) NOT NULL,
+        status TEXT NOT NULL DEFAULT 'pending' CHECK (
+            status IN ('pending', 'mined', 'confirmed', 'dropped', 'replaced')
+        ),
+        -- Resubmission of the same nonce that replaced this transaction
+        replaced_by BIGINT REFERENCES outgoing_transactions (id),
+        block_number BIGINT,
+        -- Receipt status, FALSE if the transaction reverted
+        succeeded BOOLEAN,
+        -- Set when the nonce is given up on, it is left to an operator instead of resubmitted
+        stuck_at TIMESTAMP WITHOUT TIME ZONE,
+        created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX IF NOT EXISTS outgoing_transactions_open_idx ON outgoing_transactions (status)
+WHERE status IN ('pending', 'mined');
+
+CREATE INDEX IF NOT EXISTS outgoing_transactions_nonce_idx ON outgoing_transactions (from_address, chain_id, nonce);
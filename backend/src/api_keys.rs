@@ -0,0 +1,265 @@
+// API keys of partner teams: authentication, per-key quotas and usage metering
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use alloy::hex;
+use alloy::primitives::B256;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::DistCache;
+use crate::config::CONFIG;
+use crate::db::{ApiKey, ApiKeyUsageDelta, Repository};
+use crate::error::{ForbiddenError, Result, TooManyRequestsError, UnauthorizedError};
+use crate::rate_limit::{self, RateLimitStatus};
+use crate::state::AppState;
+
+/// Header carrying the API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Prefix of every generated key, so leaked keys are easy to recognize
+const KEY_PREFIX: &str = "ak_";
+
+/// Number of leading key characters stored in clear text
+const STORED_PREFIX_LEN: usize = 11;
+
+/// Cache key prefix of the quota counters
+const QUOTA_KEY_PREFIX: &str = "api_key:quota";
+
+/// Configuration for API key authentication
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Whether requests without an API key are rejected
+    pub require_api_key: bool,
+    /// Requests per second of keys created without an explicit limit
+    pub default_rate_limit_per_second: i32,
+    /// Requests per UTC day of keys created without an explicit quota
+    pub default_daily_quota: i64,
+    /// How often buffered usage is written to the database, in seconds
+    pub usage_flush_interval: u64,
+}
+
+/// Generates a new random API key
+pub fn generate_key() -> String {
+    format!("{}{}", KEY_PREFIX, hex::encode(B256::random()))
+}
+
+/// Returns the hex encoded SHA-256 of a key, the only form keys are stored in
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Returns the leading characters of a key, stored to tell keys apart
+pub fn key_prefix(key: &str) -> &str {
+    &key[..key.len().min(STORED_PREFIX_LEN)]
+}
+
+/// Returns the router group of a route, e.g. `public` for `/v1/public/eth/misc`
+/// Scopes of API keys are router groups
+pub fn route_scope(route: &str) -> Option<&str> {
+    route.strip_prefix("/v1/")?.split('/').next()
+}
+
+/// Counts a request against the per-second limit and the daily quota of a key
+/// Both counters are fixed windows, increased atomically in Redis so every instance
+/// of the service shares them. Rejected requests count too, so a client that keeps
+/// retrying stays throttled.
+///
+/// # Arguments
+/// * `cache` - Cache holding the counters
+/// * `key` - API key making the request
+/// * `now` - Time of the request
+pub async fn check_quota(cache: &DistCache, key: &ApiKey, now: DateTime<Utc>) -> Result<()> {
+    let second_key = format!("{}:{}:s:{}", QUOTA_KEY_PREFIX, key.id, now.timestamp());
+    let day_key = format!(
+        "{}:{}:d:{}",
+        QUOTA_KEY_PREFIX,
+        key.id,
+        now.date_naive().format("%Y%m%d")
+    );
+    // The daily counter outlives its day a little, so clock skew between instances
+    // doesn't restart it
+    let end_of_day = now.date_naive().and_hms_opt(23, 59, 59).unwrap().and_utc();
+    let until_tomorrow = (end_of_day - now).num_seconds().max(0) as u64 + 1;
+    let day_ttl = until_tomorrow + 60;
+
+    let counts = cache
+        .incr_ex(&[(&second_key, 2), (&day_key, day_ttl)])
+        .await?;
+
+    if counts[0] > key.rate_limit_per_second as i64 {
+        return Err(TooManyRequestsError {
+            message: format!(
+                "Rate limit of {} requests per second exceeded",
+                key.rate_limit_per_second
+            ),
+            retry_after: 1,
+            status: RateLimitStatus {
+                limit: key.rate_limit_per_second as u64,
+                remaining: 0,
+                reset: 1,
+            },
+        }
+        .into());
+    }
+    if counts[1] > key.daily_quota {
+        return Err(TooManyRequestsError {
+            message: format!("Daily quota of {} requests exceeded", key.daily_quota),
+            retry_after: until_tomorrow,
+            status: RateLimitStatus {
+                limit: key.daily_quota as u64,
+                remaining: 0,
+                reset: until_tomorrow,
+            },
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Buffered request counts, by key id, route and day
+type PendingUsage = HashMap<(i64, String, NaiveDate), i64>;
+
+/// UsageMeter buffers the requests of every key per route and day in memory,
+/// and adds them to the stored usage every `usage_flush_interval`
+#[derive(Clone, Default)]
+pub struct UsageMeter {
+    pending: Arc<Mutex<PendingUsage>>,
+}
+
+impl UsageMeter {
+    /// Create a new, empty instance of `UsageMeter`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a request of a key to a route
+    pub fn record(&self, api_key_id: i64, route: &str, day: NaiveDate) {
+        let mut pending = self.pending.lock().unwrap();
+        *pending
+            .entry((api_key_id, route.to_string(), day))
+            .or_default() += 1;
+    }
+
+    /// Takes the buffered usage, leaving the buffer empty
+    pub fn take(&self) -> Vec<ApiKeyUsageDelta> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        let mut usage: Vec<_> = pending
+            .into_iter()
+            .map(
+                |((api_key_id, route, day), request_count)| ApiKeyUsageDelta {
+                    api_key_id,
+                    route,
+                    day,
+                    request_count,
+                },
+            )
+            .collect();
+        usage.sort_by(|a, b| (a.api_key_id, a.day, &a.route).cmp(&(b.api_key_id, b.day, &b.route)));
+        usage
+    }
+
+    /// Writes the buffered usage to the database
+    /// The usage is put back in the buffer if the write fails, so it is retried later
+    pub async fn flush(&self, repo: &Repository) -> Result<usize> {
+        let usage = self.take();
+        if usage.is_empty() {
+            return Ok(0);
+        }
+        if let Err(err) = repo.record_api_key_usage(&usage).await {
+            let mut pending = self.pending.lock().unwrap();
+            for delta in usage {
+                *pending
+                    .entry((delta.api_key_id, delta.route, delta.day))
+                    .or_default() += delta.request_count;
+            }
+            return Err(err);
+        }
+        Ok(usage.len())
+    }
+
+    /// Flushes the buffered usage every `usage_flush_interval` seconds,
+    /// and a last time once `shutdown` is cancelled
+    pub async fn run(self, repo: Repository, config: Config, shutdown: CancellationToken) {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.usage_flush_interval));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            let stopping = tokio::select! {
+                _ = interval.tick() => false,
+                _ = shutdown.cancelled() => true,
+            };
+            if let Err(err) = self.flush(&repo).await {
+                tracing::error!("Failed to record API key usage: {}", err);
+            }
+            if stopping {
+                return;
+            }
+        }
+    }
+}
+
+/// Middleware authenticating the `X-API-Key` header of `/v1` requests
+/// A valid key must allow the router group of the route, and be within its quotas.
+/// Requests without a key are let through unless `require_api_key` is set, and are then
+/// limited by `rate_limit::limit_anonymous`.
+/// The key is added to the request extensions for handlers.
+pub async fn authenticate(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response> {
+    let Some(raw_key) = request.headers().get(API_KEY_HEADER) else {
+        if CONFIG.api_keys.require_api_key {
+            return Err(UnauthorizedError("Missing API key".to_string()).into());
+        }
+        return Ok(next.run(request).await);
+    };
+
+    let key = match raw_key.to_str() {
+        Ok(raw_key) => state.repo.find_api_key_by_hash(&hash_key(raw_key)).await?,
+        Err(_) => None,
+    }
+    .filter(|key| key.active);
+    let Some(key) = key else {
+        // Invalid keys are limited like anonymous requests, and get a 429 once limited
+        rate_limit::charge_invalid_key(&state, request.extensions(), request.headers()).await?;
+        return Err(UnauthorizedError("Invalid API key".to_string()).into());
+    };
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    if let Some(scope) = route_scope(&route)
+        && !key.scopes.is_empty()
+        && !key.scopes.iter().any(|allowed| allowed == scope)
+    {
+        return Err(ForbiddenError(format!(
+            "API key is not allowed to call {} endpoints",
+            scope
+        ))
+        .into());
+    }
+
+    // Quotas fail open, an unavailable cache must not take every partner down
+    let now = Utc::now();
+    match check_quota(&state.cache, &key, now).await {
+        Err(err) if err.is::<TooManyRequestsError>() => return Err(err),
+        Err(err) => tracing::error!("Failed to check quota of API key {}: {}", key.id, err),
+        Ok(()) => {}
+    }
+
+    state.usage.record(key.id, &route, now.date_naive());
+    request.extensions_mut().insert(key);
+    Ok(next.run(request).await)
+}
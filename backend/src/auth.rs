@@ -0,0 +1,323 @@
+// Sign-In With Ethereum (EIP-4361) messages, nonces and Redis backed sessions
+use std::str::FromStr;
+
+use alloy::primitives::{Address, B256, eip191_hash_message};
+use alloy::providers::DynProvider;
+use axum::{extract::FromRequestParts, http::request::Parts};
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::Deserialize;
+
+use crate::cache::DistCache;
+use crate::config::CONFIG;
+use crate::error::{AppError, ForbiddenError, Result, UnauthorizedError, ValidateError};
+use crate::signatures;
+use crate::state::AppState;
+
+/// Cache key prefixes of nonces and sessions
+const NONCE_KEY_PREFIX: &str = "siwe:nonce";
+const SESSION_KEY_PREFIX: &str = "siwe:session";
+
+/// Configuration for SIWE authentication
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Domain that messages must be issued for, e.g. `app.example.com`
+    pub domain: String,
+    /// How long a nonce can be used, in seconds
+    pub nonce_ttl: u64,
+    /// Maximum lifetime of a session, in seconds
+    pub session_ttl: u64,
+    /// Accounts allowed to call endpoints guarded by `AdminUser`
+    #[serde(default)]
+    pub admin_addresses: Vec<Address>,
+}
+
+/// A parsed EIP-4361 message
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiweMessage {
+    pub domain: String,
+    pub address: Address,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expiration_time: Option<DateTime<Utc>>,
+    pub not_before: Option<DateTime<Utc>>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+impl FromStr for SiweMessage {
+    type Err = AppError;
+
+    fn from_str(message: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            AppError::from(ValidateError(format!("Invalid SIWE message: {}", reason)))
+        };
+        let mut lines = message.split('\n').peekable();
+
+        let domain = lines
+            .next()
+            .and_then(|line| line.strip_suffix(" wants you to sign in with your Ethereum account:"))
+            .ok_or_else(|| invalid("missing header"))?
+            .to_string();
+        let address = lines
+            .next()
+            .and_then(|line| line.parse().ok())
+            .ok_or_else(|| invalid("invalid address"))?;
+
+        // The statement is optional, and surrounded by empty lines when present
+        if lines.next() != Some("") {
+            return Err(invalid("missing empty line after address"));
+        }
+        let mut statement = None;
+        if lines.peek().is_some_and(|line| !line.starts_with("URI: ")) {
+            statement = lines.next().map(str::to_string);
+            if lines.next() != Some("") {
+                return Err(invalid("missing empty line after statement"));
+            }
+        }
+
+        let mut field = |name: &str, required: bool| -> Result<Option<String>> {
+            let prefix = format!("{}: ", name);
+            match lines.peek().and_then(|line| line.strip_prefix(&prefix)) {
+                Some(value) => {
+                    let value = value.to_string();
+                    lines.next();
+                    Ok(Some(value))
+                }
+                None if required => Err(invalid(&format!("missing {}", name))),
+                None => Ok(None),
+            }
+        };
+        let parse_time = |value: String| -> Result<DateTime<Utc>> {
+            Ok(DateTime::parse_from_rfc3339(&value)
+                .map_err(|_| invalid("invalid timestamp"))?
+                .with_timezone(&Utc))
+        };
+
+        let uri = field("URI", true)?.unwrap_or_default();
+        let version = field("Version", true)?.unwrap_or_default();
+        let chain_id = field("Chain ID", true)?
+            .and_then(|chain_id| chain_id.parse().ok())
+            .ok_or_else(|| invalid("invalid chain id"))?;
+        let nonce = field("Nonce", true)?.unwrap_or_default();
+        if nonce.len() < 8 || !nonce.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(invalid("nonce must be at least 8 alphanumeric characters"));
+        }
+        let issued_at = parse_time(field("Issued At", true)?.unwrap_or_default())?;
+        let expiration_time = field("Expiration Time", false)?
+            .map(parse_time)
+            .transpose()?;
+        let not_before = field("Not Before", false)?.map(parse_time).transpose()?;
+        let request_id = field("Request ID", false)?;
+
+        let mut resources = Vec::new();
+        if lines.peek() == Some(&"Resources:") {
+            lines.next();
+            while let Some(resource) = lines.peek().and_then(|line| line.strip_prefix("- ")) {
+                resources.push(resource.to_string());
+                lines.next();
+            }
+        }
+        if lines.any(|line| !line.is_empty()) {
+            return Err(invalid("unexpected trailing content"));
+        }
+
+        Ok(Self {
+            domain,
+            address,
+            statement,
+            uri,
+            version,
+            chain_id,
+            nonce,
+            issued_at,
+            expiration_time,
+            not_before,
+            request_id,
+            resources,
+        })
+    }
+}
+
+impl SiweMessage {
+    /// Checks the message was issued for this service and is usable at `now`
+    pub fn validate(&self, domain: &str, chain_id: u64, now: DateTime<Utc>) -> Result<()> {
+        let invalid =
+            |reason: &str| Err(ValidateError(format!("Invalid SIWE message: {}", reason)).into());
+
+        if self.domain != domain {
+            return invalid("domain mismatch");
+        }
+        if self.version != "1" {
+            return invalid("unsupported version");
+        }
+        if self.chain_id != chain_id {
+            return invalid("chain id mismatch");
+        }
+        if self
+            .expiration_time
+            .is_some_and(|expiration_time| expiration_time <= now)
+        {
+            return invalid("message expired");
+        }
+        if self.not_before.is_some_and(|not_before| not_before > now) {
+            return invalid("message not yet valid");
+        }
+        Ok(())
+    }
+}
+
+/// A signed-in session
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub token: String,
+    pub address: Address,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Issues a single use nonce, valid for `ttl` seconds
+pub async fn issue_nonce(cache: &DistCache, ttl: u64) -> Result<String> {
+    let nonce = alloy::hex::encode(&B256::random()[..16]);
+    cache
+        .set_ex(&format!("{}:{}", NONCE_KEY_PREFIX, nonce), 1, ttl)
+        .await?;
+    Ok(nonce)
+}
+
+/// Consumes a nonce, returns false if it was never issued, expired or was already used
+async fn consume_nonce(cache: &DistCache, nonce: &str) -> Result<bool> {
+    let mut conn = cache.get_conn().await?;
+    let issued: Option<u8> = conn
+        .get_del(format!("{}:{}", NONCE_KEY_PREFIX, nonce))
+        .await?;
+    Ok(issued.is_some())
+}
+
+/// Verifies a signed SIWE message and opens a session for its address
+/// The session ends with the message's expiration time, or after `session_ttl`
+///
+/// # Arguments
+/// * `cache` - Cache holding nonces and sessions
+/// * `provider` - Provider used to verify contract wallet signatures
+/// * `config` - Expected domain and session lifetime
+/// * `chain_id` - Chain the service is connected to
+/// * `message` - EIP-4361 message text that was signed
+/// * `signature` - Raw signature bytes
+pub async fn sign_in(
+    cache: &DistCache,
+    provider: &DynProvider,
+    config: &Config,
+    chain_id: u64,
+    message: &str,
+    signature: &[u8],
+) -> Result<Session> {
+    let parsed: SiweMessage = message.parse()?;
+    let now = Utc::now();
+    parsed.validate(&config.domain, chain_id, now)?;
+
+    // The nonce is spent even if the signature is wrong, so a message can't be retried
+    if !consume_nonce(cache, &parsed.nonce).await? {
+        return Err(UnauthorizedError("Unknown or already used nonce".to_string()).into());
+    }
+
+    let hash = eip191_hash_message(message);
+    let verification =
+        signatures::verify_signature(provider, parsed.address, hash, signature).await?;
+    if !verification.valid {
+        return Err(UnauthorizedError("Invalid signature".to_string()).into());
+    }
+
+    let mut ttl = config.session_ttl;
+    if let Some(expiration_time) = parsed.expiration_time {
+        ttl = ttl.min((expiration_time - now).num_seconds().max(1) as u64);
+    }
+    let token = B256::random().to_string();
+    cache
+        .set_ex(
+            &format!("{}:{}", SESSION_KEY_PREFIX, token),
+            parsed.address.to_string(),
+            ttl,
+        )
+        .await?;
+
+    Ok(Session {
+        token,
+        address: parsed.address,
+        expires_at: now + chrono::Duration::seconds(ttl as i64),
+    })
+}
+
+/// Returns the address of a session, none if it doesn't exist or expired
+pub async fn get_session(cache: &DistCache, token: &str) -> Result<Option<Address>> {
+    let mut conn = cache.get_conn().await?;
+    let address: Option<String> = conn
+        .get(format!("{}:{}", SESSION_KEY_PREFIX, token))
+        .await?;
+    Ok(address.and_then(|address| address.parse().ok()))
+}
+
+/// Ends a session
+pub async fn delete_session(cache: &DistCache, token: &str) -> Result<()> {
+    let mut conn = cache.get_conn().await?;
+    let _: () = conn
+        .del(format!("{}:{}", SESSION_KEY_PREFIX, token))
+        .await?;
+    Ok(())
+}
+
+/// Extractor for the address signed in with the `Authorization: Bearer <token>` header
+/// Rejects the request with 401 Unauthorized without a valid session
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub address: Address,
+    /// Session token, used to sign out
+    pub token: String,
+}
+
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self> {
+        let token = parts
+            .headers
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| UnauthorizedError("Missing bearer token".to_string()))?;
+
+        let address = get_session(&state.cache, token)
+            .await?
+            .ok_or_else(|| UnauthorizedError("Invalid or expired session".to_string()))?;
+
+        Ok(Self {
+            address,
+            token: token.to_string(),
+        })
+    }
+}
+
+/// Extractor for a signed-in account listed in `auth.admin_addresses`
+/// Rejects the request with 401 Unauthorized without a session, 403 Forbidden for other accounts
+#[derive(Debug, Clone)]
+pub struct AdminUser {
+    pub address: Address,
+}
+
+impl FromRequestParts<AppState> for AdminUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self> {
+        let user = AuthenticatedUser::from_request_parts(parts, state).await?;
+        if !CONFIG.auth.admin_addresses.contains(&user.address) {
+            return Err(ForbiddenError(format!("{} is not an admin", user.address)).into());
+        }
+
+        Ok(Self {
+            address: user.address,
+        })
+    }
+}
@@ -0,0 +1,210 @@
+// Backfill of the balance history of watched balances over past blocks
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::Address;
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::Filter;
+use alloy::sol_types::SolEvent;
+use anyhow::anyhow;
+use chrono::DateTime;
+
+use crate::db::Repository;
+use crate::error::{Result, ValidateError};
+use crate::eth::{self, IERC20};
+
+/// Backfill reads past balances of the watchlist and records them in `balance_history`
+/// Reads go through Multicall at each block, so the RPC must serve historical state.
+pub struct Backfill {
+    provider: DynProvider,
+    repo: Repository,
+    /// Maximum number of pairs read in one Multicall
+    batch_size: usize,
+}
+
+impl Backfill {
+    /// Create a new instance of `Backfill`
+    pub fn new(provider: DynProvider, repo: Repository, batch_size: usize) -> Self {
+        Self {
+            provider,
+            repo,
+            batch_size: batch_size.max(1),
+        }
+    }
+
+    /// Reads every watched balance every `step` blocks from `from_block` to `to_block`,
+    /// both included. Returns the number of balance changes recorded.
+    pub async fn balances(&self, from_block: u64, to_block: u64, step: u64) -> Result<usize> {
+        check_range(from_block, to_block)?;
+        let pairs = self.watched_pairs().await?;
+
+        let mut recorded = 0;
+        for block_number in sample_blocks(from_block, to_block, step) {
+            recorded += self.record(block_number, &pairs).await?;
+        }
+
+        Ok(recorded)
+    }
+
+    /// Finds the blocks where watched ERC20 balances moved from their `Transfer` logs,
+    /// scanned `chunk_size` blocks at a time, and reads the balances at those blocks.
+    /// Native balances emit no logs and are skipped. Returns the number of balance
+    /// changes recorded.
+    pub async fn transfers(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        chunk_size: u64,
+    ) -> Result<usize> {
+        check_range(from_block, to_block)?;
+        let pairs: HashSet<_> = self
+            .watched_pairs()
+            .await?
+            .into_iter()
+            .filter(|(_, token)| !token.is_zero())
+            .collect();
+        let tokens: BTreeSet<_> = pairs.iter().map(|(_, token)| *token).collect();
+        if tokens.is_empty() {
+            return Ok(0);
+        }
+
+        let mut recorded = 0;
+        let mut chunk_start = from_block;
+        while chunk_start <= to_block {
+            let chunk_end = chunk_start
+                .saturating_add(chunk_size.max(1) - 1)
+                .min(to_block);
+            let filter = Filter::new()
+                .from_block(chunk_start)
+                .to_block(chunk_end)
+                .event_signature(IERC20::Transfer::SIGNATURE_HASH)
+                .address(tokens.iter().copied().collect::<Vec<_>>());
+            let logs = self.provider.get_logs(&filter).await?;
+
+            // Pairs moved by each block of the chunk, in block order
+            let mut moved: BTreeMap<u64, BTreeSet<(Address, Address)>> = BTreeMap::new();
+            for log in &logs {
+                // Tokens with a non standard Transfer event can't be decoded, skip them
+                let (Ok(decoded), Some(block_number)) =
+                    (log.log_decode::<IERC20::Transfer>(), log.block_number)
+                else {
+                    continue;
+                };
+                for account in [decoded.inner.data.from, decoded.inner.data.to] {
+                    let pair = (account, log.address());
+                    if pairs.contains(&pair) {
+                        moved.entry(block_number).or_default().insert(pair);
+                    }
+                }
+            }
+            for (block_number, moved_pairs) in moved {
+                let moved_pairs: Vec<_> = moved_pairs.into_iter().collect();
+                recorded += self.record(block_number, &moved_pairs).await?;
+            }
+
+            tracing::info!(
+                "Backfilled transfers of blocks {} to {}, {} logs",
+                chunk_start,
+                chunk_end,
+                logs.len()
+            );
+            chunk_start = chunk_end + 1;
+        }
+
+        Ok(recorded)
+    }
+
+    /// Returns every (address, token) pair of the watchlist
+    async fn watched_pairs(&self) -> Result<Vec<(Address, Address)>> {
+        let mut pairs = Vec::new();
+        let mut after_id = 0;
+        loop {
+            let watched = self
+                .repo
+                .list_watched_balances(after_id, self.batch_size as i64)
+                .await?;
+            let Some(last) = watched.last() else {
+                break;
+            };
+            after_id = last.id;
+
+            for item in &watched {
+                pairs.push((item.address.parse()?, item.token_address.parse()?));
+            }
+            if watched.len() < self.batch_size {
+                break;
+            }
+        }
+
+        Ok(pairs)
+    }
+
+    /// Reads the balances of `pairs` at a block and records them with the block timestamp
+    /// Returns the number of balance changes recorded.
+    async fn record(&self, block_number: u64, pairs: &[(Address, Address)]) -> Result<usize> {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(block_number))
+            .await?
+            .ok_or_else(|| anyhow!("Block {} not found", block_number))?;
+        let observed_at = DateTime::from_timestamp(block.header.timestamp as i64, 0)
+            .ok_or_else(|| anyhow!("Invalid timestamp of block {}", block_number))?
+            .naive_utc();
+
+        let mut recorded = 0;
+        for batch in pairs.chunks(self.batch_size) {
+            let balances = eth::get_balances(&self.provider, batch, block_number).await?;
+            for ((address, token_address), balance) in batch.iter().zip(balances) {
+                let Some(balance) = balance else {
+                    tracing::warn!(
+                        "Failed to read balance of {} for token {} at block {}",
+                        address,
+                        token_address,
+                        block_number
+                    );
+                    continue;
+                };
+                if self
+                    .repo
+                    .insert_balance_history(
+                        &address.to_string(),
+                        &token_address.to_string(),
+                        block_number,
+                        balance.to_string().parse()?,
+                        observed_at,
+                    )
+                    .await?
+                {
+                    recorded += 1;
+                }
+            }
+        }
+
+        tracing::debug!("Backfilled block {}, {} changes", block_number, recorded);
+        Ok(recorded)
+    }
+}
+
+/// Returns the blocks read by a balance backfill: every `step` blocks from `from_block`,
+/// and `to_block` so the range ends with its latest balances
+pub fn sample_blocks(from_block: u64, to_block: u64, step: u64) -> Vec<u64> {
+    let mut blocks: Vec<u64> = (from_block..=to_block)
+        .step_by(step.max(1) as usize)
+        .collect();
+    if from_block <= to_block && blocks.last() != Some(&to_block) {
+        blocks.push(to_block);
+    }
+    blocks
+}
+
+// Helper function to reject an empty block range
+fn check_range(from_block: u64, to_block: u64) -> Result<()> {
+    if from_block > to_block {
+        return Err(ValidateError(format!(
+            "from_block {} is after to_block {}",
+            from_block, to_block
+        ))
+        .into());
+    }
+    Ok(())
+}
@@ -1,7 +1,11 @@
-use redis::{self, AsyncCommands, AsyncConnectionConfig, ToRedisArgs, aio::MultiplexedConnection};
+use redis::{
+    self, AsyncCommands, AsyncConnectionConfig, ExistenceCheck, FromRedisValue, SetExpiry,
+    SetOptions, ToRedisArgs,
+};
 use serde::Deserialize;
 
 use crate::error::Result;
+use crate::telemetry::{self, InstrumentedConnection};
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub redis_url: String,
@@ -27,8 +31,8 @@ impl DistCache {
         }
     }
 
-    /// Get a connection to the Redis cache.
-    pub async fn get_conn(&self) -> Result<MultiplexedConnection> {
+    /// Get a connection to the Redis cache, tracing every command sent through it.
+    pub async fn get_conn(&self) -> Result<InstrumentedConnection> {
         let conn = self
             .client
             .get_multiplexed_async_connection_with_config(
@@ -37,7 +41,29 @@ impl DistCache {
             )
             .await?;
 
-        Ok(conn)
+        Ok(InstrumentedConnection(conn))
+    }
+
+    /// Get a value from the cache, none if the key doesn't exist.
+    /// Every lookup is counted as a hit, miss or error in the metrics.
+    pub async fn get<T>(&self, key: &str) -> Result<Option<T>>
+    where
+        T: FromRedisValue,
+    {
+        let value = async {
+            let mut conn = self.get_conn().await?;
+            let value: Option<T> = conn.get(key).await?;
+            Ok(value)
+        }
+        .await;
+
+        let result = match &value {
+            Ok(Some(_)) => "hit",
+            Ok(None) => "miss",
+            Err(_) => "error",
+        };
+        telemetry::record_cache_lookup(key, result);
+        value
     }
 
     /// Set a key-value pair in the cache with a specified TTL (time to live).
@@ -45,8 +71,69 @@ impl DistCache {
     where
         T: ToRedisArgs + Send + Sync,
     {
-        let mut conn: MultiplexedConnection = self.get_conn().await?;
+        let mut conn = self.get_conn().await?;
         let _: () = conn.set_ex(key, value, ttl).await?;
         Ok(())
     }
+
+    /// Set a key-value pair only if the key does not exist yet, with a specified TTL.
+    /// Returns true if the key was set, which makes it usable as a short-lived lock.
+    pub async fn set_nx_ex<T>(&self, key: &str, value: T, ttl: u64) -> Result<bool>
+    where
+        T: ToRedisArgs + Send + Sync,
+    {
+        let mut conn = self.get_conn().await?;
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(ttl));
+        let reply: Option<String> = conn.set_options(key, value, options).await?;
+        Ok(reply.is_some())
+    }
+
+    /// Deletes a key, e.g. to release a lock taken with `set_nx_ex`.
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        let mut conn = self.get_conn().await?;
+        let _: () = conn.del(key).await?;
+        Ok(())
+    }
+
+    /// Increments counters by one in a single transaction and sets their TTL.
+    /// Returns the new value of every counter, in order.
+    pub async fn incr_ex(&self, counters: &[(&str, u64)]) -> Result<Vec<i64>> {
+        let mut conn = self.get_conn().await?;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (key, ttl) in counters {
+            pipe.incr(*key, 1).expire(*key, *ttl as i64).ignore();
+        }
+        let values: Vec<i64> = pipe.query_async(&mut conn).await?;
+        Ok(values)
+    }
+
+    /// Deletes every key matching a glob pattern, e.g. `price:*`
+    /// Keys are found with `SCAN`, so the server is not blocked. Returns the number deleted.
+    pub async fn delete_matching(&self, pattern: &str) -> Result<usize> {
+        let mut conn = self.get_conn().await?;
+        let keys: Vec<String> = {
+            let mut iter = conn.scan_match::<_, String>(pattern).await?;
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+            keys
+        };
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        let deleted: usize = conn.del(&keys).await?;
+        Ok(deleted)
+    }
+
+    /// Deletes every key of the database, sessions and rate limits included
+    pub async fn flush_all(&self) -> Result<()> {
+        let mut conn = self.get_conn().await?;
+        let _: () = redis::cmd("FLUSHDB").query_async(&mut conn).await?;
+        Ok(())
+    }
 }
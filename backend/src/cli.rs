@@ -0,0 +1,244 @@
+// Command line interface of the backend binary: serving, migrations and maintenance tasks
+use alloy::providers::Provider;
+use anyhow::anyhow;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use redis::AsyncCommands;
+
+use crate::backfill::Backfill;
+use crate::cache::DistCache;
+use crate::config::{AppConfig, CONFIG};
+use crate::db::Repository;
+use crate::error::Result;
+use crate::handlers::utils;
+use crate::{eth, prices, wallet};
+
+/// Ethereum account information service
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Command to run, `serve` by default
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve the HTTP API
+    Serve(ServeArgs),
+    /// Manage database migrations
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Load the configuration and ping every dependency
+    CheckConfig,
+    /// Record past balances of the watchlist in the balance history
+    Backfill(BackfillArgs),
+    /// Manage the Redis cache
+    #[command(subcommand)]
+    Cache(CacheCommand),
+}
+
+#[derive(Debug, Default, Args)]
+pub struct ServeArgs {
+    /// Don't apply pending migrations on startup, for deploys running `migrate up` first
+    #[arg(long)]
+    pub skip_migrations: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply pending migrations
+    Up,
+    /// Revert the latest applied migrations
+    Down {
+        /// Number of migrations to revert
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+    },
+    /// List migrations and whether they are applied
+    Status,
+}
+
+/// What a backfill reads
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum BackfillKind {
+    /// Every watched balance, sampled every `--step` blocks
+    Balances,
+    /// Watched ERC20 balances, at the blocks of their `Transfer` logs
+    Transfers,
+}
+
+#[derive(Debug, Args)]
+pub struct BackfillArgs {
+    #[arg(value_enum)]
+    pub kind: BackfillKind,
+    /// First block of the range
+    #[arg(long)]
+    pub from_block: u64,
+    /// Last block of the range, the latest block by default
+    #[arg(long)]
+    pub to_block: Option<u64>,
+    /// Blocks between balance reads of `balances`
+    #[arg(long, default_value_t = 100)]
+    pub step: u64,
+    /// Blocks per log query of `transfers`
+    #[arg(long, default_value_t = 1000)]
+    pub chunk_size: u64,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// Delete cached values: block number, fees and prices
+    Flush {
+        /// Delete every key instead, sessions, rate limits and faucet cooldowns included
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+/// Runs a migrate command
+pub async fn migrate(command: MigrateCommand) -> Result<()> {
+    let repo = Repository::new_with_config(&CONFIG.database).await?;
+    match command {
+        MigrateCommand::Up => {
+            repo.run_migrations().await?;
+            println!("Migrations applied");
+        }
+        MigrateCommand::Down { steps } => {
+            let reverted = repo.revert_migrations(steps).await?;
+            if reverted.is_empty() {
+                println!("No migration to revert");
+            }
+            for version in reverted {
+                println!("Reverted {}", version);
+            }
+        }
+        MigrateCommand::Status => {
+            for migration in repo.migration_status().await? {
+                let status = match (migration.applied, migration.modified) {
+                    (true, true) => "modified",
+                    (true, false) => "applied",
+                    (false, _) => "pending",
+                };
+                println!(
+                    "{:<16} {:<10} {}",
+                    migration.version, status, migration.description
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Loads the configuration and pings the database, the RPC and Redis, and decrypts the
+/// wallet keystore when one is configured. Fails if any check failed.
+pub async fn check_config() -> Result<()> {
+    let config = match AppConfig::load() {
+        Ok(config) => config,
+        Err(err) => return Err(anyhow!("Invalid configuration: {}", err).into()),
+    };
+    println!("{:<12} ok", "config");
+
+    let mut failed = 0;
+    let mut report = |name: &str, result: Result<String>| match result {
+        Ok(details) => println!("{:<12} ok {}", name, details),
+        Err(err) => {
+            println!("{:<12} FAILED {}", name, err);
+            failed += 1;
+        }
+    };
+
+    let repo = Repository::new_with_config(&config.database).await;
+    let database = match &repo {
+        Ok(repo) => repo.migration_status().await.map(|migrations| {
+            let pending = migrations
+                .iter()
+                .filter(|migration| !migration.applied)
+                .count();
+            format!("({} pending migrations)", pending)
+        }),
+        Err(err) => Err(anyhow!("{}", err).into()),
+    };
+    report("database", database);
+
+    let provider = eth::setup_provider(&config.eth_rpc_url).await;
+    let chain_id = match &provider {
+        Ok(provider) => provider.get_chain_id().await.map_err(Into::into),
+        Err(err) => Err(anyhow!("{}", err).into()),
+    };
+    report(
+        "eth_rpc",
+        chain_id.map(|chain_id| format!("(chain {})", chain_id)),
+    );
+
+    let redis = async {
+        let cache = DistCache::new(&config.cache);
+        let _: () = cache.get_conn().await?.ping().await?;
+        Ok(String::new())
+    };
+    report("redis", redis.await);
+
+    // The keystore is only decrypted, sending needs the database and RPC checked above
+    let wallet = wallet::load_signer(&config.wallet).map(|signer| match signer {
+        Some(signer) => format!("({})", signer.address()),
+        None => "(disabled)".to_string(),
+    });
+    report("wallet", wallet);
+
+    if failed > 0 {
+        return Err(anyhow!("{} dependency checks failed", failed).into());
+    }
+    Ok(())
+}
+
+/// Runs a backfill over a block range
+pub async fn backfill(args: BackfillArgs) -> Result<()> {
+    let provider = eth::setup_provider(&CONFIG.eth_rpc_url).await?;
+    let repo = Repository::new_with_config(&CONFIG.database).await?;
+    let to_block = match args.to_block {
+        Some(to_block) => to_block,
+        None => provider.get_block_number().await?,
+    };
+
+    let backfill = Backfill::new(provider, repo, CONFIG.watcher.batch_size);
+    let recorded = match args.kind {
+        BackfillKind::Balances => {
+            backfill
+                .balances(args.from_block, to_block, args.step)
+                .await?
+        }
+        BackfillKind::Transfers => {
+            backfill
+                .transfers(args.from_block, to_block, args.chunk_size)
+                .await?
+        }
+    };
+    println!(
+        "Recorded {} balance changes from block {} to {}",
+        recorded, args.from_block, to_block
+    );
+    Ok(())
+}
+
+/// Deletes cached values, or every key of the cache database with `all`
+pub async fn flush_cache(all: bool) -> Result<()> {
+    let cache = DistCache::new(&CONFIG.cache);
+    if all {
+        cache.flush_all().await?;
+        println!("Flushed every key");
+        return Ok(());
+    }
+
+    let prices_pattern = format!("{}:*", prices::PRICE_KEY_PREFIX);
+    let patterns = [
+        utils::CURRENT_BLOCK_NUMBER_CACHE_KEY,
+        utils::GAS_PRICE_CACHE_KEY,
+        utils::MAX_PRIORITY_FEE_CACHE_KEY,
+        &prices_pattern,
+    ];
+    let mut deleted = 0;
+    for pattern in patterns {
+        deleted += cache.delete_matching(pattern).await?;
+    }
+    println!("Deleted {} cached values", deleted);
+    Ok(())
+}
@@ -1,9 +1,16 @@
 // Database module for handling PostgreSQL interactions and Ethereum account data
 use std::time::Duration;
 
-use crate::error::Result;
+use crate::error::{NotFoundError, Result};
+use crate::telemetry::{self, Instrumented};
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
+use sqlx::migrate::{Migrate, Migrator};
 use sqlx::{FromRow, PgPool, postgres::PgPoolOptions};
+use utoipa::ToSchema;
+
+/// Migrations of the `migrations` directory, embedded at build time
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
 /// Configuration for database connection settings
 #[derive(Debug, Deserialize)]
@@ -41,53 +48,1272 @@ impl Repository {
         Self { pool }
     }
 
+    // Helper function returning the pool as an executor recording query latency
+    fn executor(&self) -> Instrumented<&PgPool> {
+        Instrumented(&self.pool)
+    }
+
     /// Runs database migrations from the migrations directory
     /// Ensures database schema is up to date
     pub async fn run_migrations(&self) -> Result<()> {
-        sqlx::migrate!("./migrations").run(&self.pool).await?;
+        MIGRATOR.run(&self.pool).await?;
         Ok(())
     }
 
+    /// Reverts the latest `steps` applied migrations with their down scripts
+    /// Returns the versions reverted, newest first
+    pub async fn revert_migrations(&self, steps: usize) -> Result<Vec<i64>> {
+        let mut applied: Vec<i64> = self
+            .migration_status()
+            .await?
+            .into_iter()
+            .filter(|migration| migration.applied)
+            .map(|migration| migration.version)
+            .collect();
+        applied.sort_unstable_by(|a, b| b.cmp(a));
+
+        // Every migration newer than the target is reverted
+        let target = applied.get(steps).copied().unwrap_or(0);
+        MIGRATOR.undo(&self.pool, target).await?;
+
+        applied.truncate(steps);
+        Ok(applied)
+    }
+
+    /// Lists the migrations of the migrations directory, oldest first, and whether they
+    /// are applied to the database
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>> {
+        let mut conn = self.pool.acquire().await?;
+        conn.ensure_migrations_table().await?;
+        let applied = conn.list_applied_migrations().await?;
+
+        Ok(MIGRATOR
+            .iter()
+            .filter(|migration| migration.migration_type.is_up_migration())
+            .map(|migration| {
+                let applied = applied
+                    .iter()
+                    .find(|applied| applied.version == migration.version);
+                MigrationStatus {
+                    version: migration.version,
+                    description: migration.description.to_string(),
+                    applied: applied.is_some(),
+                    modified: applied.is_some_and(|applied| applied.checksum != migration.checksum),
+                }
+            })
+            .collect())
+    }
+
     /// Performs a health check on the database connection
     /// Returns Ok if the database is accessible
     pub async fn ping(&self) -> Result<()> {
-        let _ = sqlx::query("SELECT 1")
-            .execute(&self.pool)
-            .await
-            .map(|_| ())?;
+        sqlx::query("SELECT 1").execute(self.executor()).await?;
         Ok(())
     }
 
+    /// Records the connections of the pool, read on every scrape of `/metrics`
+    pub fn record_pool_metrics(&self) {
+        telemetry::record_pool(&self.pool);
+    }
+
+    /// Closes the connection pool, waiting for connections in use to be returned
+    /// Every clone shares the pool, so queries fail afterwards.
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
     /// Updates or inserts an Ethereum account balance in the database
-    /// Uses upsert operation to handle both new and existing records
-    /// 
+    /// Keeps `eth_account_balances` as the latest known value and appends a row to
+    /// `balance_history` whenever the balance changes
+    ///
+    /// Observations older than the stored block are ignored, so a slow request can't
+    /// overwrite a fresher balance. Returns the previously stored balance, if any, or
+    /// `Upsert::Stale` when the observation was ignored.
+    /// The token must be registered on the chain, balances reference the registry.
+    ///
     /// # Arguments
+    /// * `chain_id` - Chain the balance was read on
     /// * `address` - Ethereum account address
     /// * `token_address` - ERC20 token contract address
+    /// * `block_number` - Block at which the balance was read
     /// * `balance` - Current token balance
     pub async fn upsert_eth_account_balance(
         &self,
+        chain_id: u64,
         address: &str,
         token_address: &str,
+        block_number: u64,
         balance: rust_decimal::Decimal,
-    ) -> Result<()> {
-        sqlx::query_as!(
-            EthAccountBalance,
+    ) -> Result<Upsert> {
+        let address = address.to_lowercase();
+        let token_address = token_address.to_lowercase();
+        let block_number = i64::try_from(block_number)?;
+
+        let mut tx = self.pool.begin().await?;
+
+        // Serialize writers of the pair so they agree on whether the balance changed,
+        // the row may not exist yet so it can't be locked itself
+        sqlx::query!(
+            "SELECT pg_advisory_xact_lock(hashtextextended($1 || ':' || $2, 0))",
+            address,
+            token_address
+        )
+        .execute(Instrumented(&mut *tx))
+        .await?;
+
+        let previous = sqlx::query!(
+            r#"
+            SELECT balance, block_number FROM eth_account_balances
+            WHERE address = $1 AND token_address = $2
+            "#,
+            address,
+            token_address
+        )
+        .fetch_optional(Instrumented(&mut *tx))
+        .await?;
+
+        if let Some(prev) = &previous
+            && prev.block_number.is_some_and(|prev_block| prev_block > block_number)
+        {
+            tracing::debug!(
+                "Skip stale balance of {} for token {} at block {}",
+                address,
+                token_address,
+                block_number
+            );
+            return Ok(Upsert::Stale);
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO eth_account_balances (address, token_address, balance, block_number,
+                chain_id)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (address, token_address)
+            DO UPDATE SET balance = EXCLUDED.balance,
+                block_number = EXCLUDED.block_number,
+                chain_id = EXCLUDED.chain_id,
+                updated_at = NOW(),
+                changed_at = CASE
+                    WHEN eth_account_balances.balance = EXCLUDED.balance
+                    THEN eth_account_balances.changed_at
+                    ELSE NOW()
+                END
+            "#,
+            address,
+            token_address,
+            balance,
+            block_number,
+            chain_id as i64
+        )
+        .execute(Instrumented(&mut *tx))
+        .await?;
+
+        let previous_balance = previous.map(|prev| prev.balance);
+        if previous_balance != Some(balance) {
+            sqlx::query!(
+                r#"
+                INSERT INTO balance_history (address, token_address, block_number, balance)
+                VALUES ($1, $2, $3, $4)
+                "#,
+                address,
+                token_address,
+                block_number,
+                balance
+            )
+            .execute(Instrumented(&mut *tx))
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(Upsert::Written(previous_balance))
+    }
+
+    /// Lists balance changes of an account, newest first
+    ///
+    /// # Arguments
+    /// * `address` - Ethereum account address
+    /// * `filter` - Optional token, block range and time range filters
+    pub async fn get_balance_history(
+        &self,
+        address: &str,
+        filter: &BalanceHistoryFilter,
+    ) -> Result<Vec<BalanceHistory>> {
+        let records = sqlx::query_as!(
+            BalanceHistory,
             r#"
-            INSERT INTO eth_account_balances (address, token_address, balance)
+            SELECT address, token_address, block_number, balance, observed_at
+            FROM balance_history
+            WHERE address = $1
+                AND ($2::CHAR(42) IS NULL OR token_address = $2)
+                AND ($3::BIGINT IS NULL OR block_number >= $3)
+                AND ($4::BIGINT IS NULL OR block_number <= $4)
+                AND ($5::TIMESTAMP IS NULL OR observed_at >= $5)
+                AND ($6::TIMESTAMP IS NULL OR observed_at <= $6)
+            ORDER BY block_number DESC, id DESC
+            LIMIT $7
+            "#,
+            address.to_lowercase(),
+            filter.token_address.as_ref().map(|token| token.to_lowercase()),
+            filter.from_block.map(i64::try_from).transpose()?,
+            filter.to_block.map(i64::try_from).transpose()?,
+            filter.from_time,
+            filter.to_time,
+            filter.limit
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Records a backfilled balance in `balance_history`
+    /// The row is skipped when the block is already recorded or the balance equals the
+    /// latest one recorded before it, so a backfill can be rerun. The latest balance in
+    /// `eth_account_balances` is left untouched. Returns whether a row was inserted.
+    ///
+    /// # Arguments
+    /// * `address` - Ethereum account address
+    /// * `token_address` - ERC20 token contract address, `ZERO_ADDRESS` for the native balance
+    /// * `block_number` - Block at which the balance was read
+    /// * `balance` - Token balance at that block
+    /// * `observed_at` - Timestamp of the block
+    pub async fn insert_balance_history(
+        &self,
+        address: &str,
+        token_address: &str,
+        block_number: u64,
+        balance: rust_decimal::Decimal,
+        observed_at: NaiveDateTime,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO balance_history (address, token_address, block_number, balance, observed_at)
+            SELECT $1, $2, $3, $4, $5
+            WHERE NOT EXISTS (
+                SELECT 1 FROM balance_history
+                WHERE address = $1 AND token_address = $2 AND block_number = $3
+            )
+            AND $4 IS DISTINCT FROM (
+                SELECT balance FROM balance_history
+                WHERE address = $1 AND token_address = $2 AND block_number < $3
+                ORDER BY block_number DESC, id DESC
+                LIMIT 1
+            )
+            "#,
+            address.to_lowercase(),
+            token_address.to_lowercase(),
+            i64::try_from(block_number)?,
+            balance,
+            observed_at
+        )
+        .execute(self.executor())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Adds an (address, token) pair to the watchlist
+    /// Adding an already watched pair only updates its label
+    ///
+    /// # Arguments
+    /// * `address` - Ethereum account address
+    /// * `token_address` - ERC20 token contract address, `ZERO_ADDRESS` for the native balance
+    /// * `label` - Optional human readable label
+    pub async fn add_watched_balance(
+        &self,
+        address: &str,
+        token_address: &str,
+        label: Option<&str>,
+    ) -> Result<WatchedBalance> {
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO watched_balances (address, token_address, label)
             VALUES ($1, $2, $3)
             ON CONFLICT (address, token_address)
-            DO UPDATE SET balance = EXCLUDED.balance
+            DO UPDATE SET label = COALESCE(EXCLUDED.label, watched_balances.label)
+            RETURNING id
             "#,
             address.to_lowercase(),
             token_address.to_lowercase(),
-            balance
+            label
+        )
+        .fetch_one(self.executor())
+        .await?;
+
+        self.get_watched_balance(id)
+            .await?
+            .ok_or_else(|| NotFoundError(format!("Watched balance {} not found", id)).into())
+    }
+
+    /// Gets a watched pair together with its latest stored balance
+    pub async fn get_watched_balance(&self, id: i64) -> Result<Option<WatchedBalance>> {
+        let record = sqlx::query_as!(
+            WatchedBalance,
+            r#"
+            SELECT w.id, w.address, w.token_address, w.label, w.created_at,
+                b.balance AS "balance?", b.block_number AS "block_number?",
+                b.updated_at AS "updated_at?", b.changed_at AS "changed_at?"
+            FROM watched_balances w
+            LEFT JOIN eth_account_balances b
+                ON b.address = w.address AND b.token_address = w.token_address
+            WHERE w.id = $1
+            "#,
+            id
+        )
+        .fetch_optional(self.executor())
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Lists watched pairs together with their latest stored balances, ordered by id
+    ///
+    /// # Arguments
+    /// * `after_id` - Only return pairs with a greater id, for pagination
+    /// * `limit` - Maximum number of records to return
+    pub async fn list_watched_balances(
+        &self,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<WatchedBalance>> {
+        let records = sqlx::query_as!(
+            WatchedBalance,
+            r#"
+            SELECT w.id, w.address, w.token_address, w.label, w.created_at,
+                b.balance AS "balance?", b.block_number AS "block_number?",
+                b.updated_at AS "updated_at?", b.changed_at AS "changed_at?"
+            FROM watched_balances w
+            LEFT JOIN eth_account_balances b
+                ON b.address = w.address AND b.token_address = w.token_address
+            WHERE w.id > $1
+            ORDER BY w.id
+            LIMIT $2
+            "#,
+            after_id,
+            limit
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Updates the label of a watched pair
+    /// Returns false if the pair does not exist
+    pub async fn update_watched_balance_label(&self, id: i64, label: Option<&str>) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE watched_balances SET label = $2 WHERE id = $1
+            "#,
+            id,
+            label
+        )
+        .execute(self.executor())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns true if a watched pair references the token
+    pub async fn is_token_watched(&self, token_address: &str) -> Result<bool> {
+        let watched = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM watched_balances WHERE token_address = $1) AS "watched!"
+            "#,
+            token_address.to_lowercase()
+        )
+        .fetch_one(self.executor())
+        .await?;
+
+        Ok(watched)
+    }
+
+    /// Removes a pair from the watchlist, its stored balance and history are kept
+    /// Returns false if the pair does not exist
+    pub async fn delete_watched_balance(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM watched_balances WHERE id = $1
+            "#,
+            id
+        )
+        .execute(self.executor())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Registers a webhook that fires when a balance of `address` changes by more than `threshold`
+    ///
+    /// # Arguments
+    /// * `url` - Endpoint receiving the signed payloads
+    /// * `secret` - Key used to sign payloads with HMAC-SHA256
+    /// * `address` - Watched Ethereum account address
+    /// * `token_address` - Only fire for this token, `None` fires for every token
+    /// * `threshold` - Minimum absolute balance change, in the token's smallest unit
+    pub async fn create_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+        address: &str,
+        token_address: Option<&str>,
+        threshold: rust_decimal::Decimal,
+    ) -> Result<Webhook> {
+        let record = sqlx::query_as!(
+            Webhook,
+            r#"
+            INSERT INTO webhooks (url, secret, address, token_address, threshold)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, url, secret, address, token_address, threshold, active, created_at
+            "#,
+            url,
+            secret,
+            address.to_lowercase(),
+            token_address.map(str::to_lowercase),
+            threshold
+        )
+        .fetch_one(self.executor())
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Gets a webhook by id
+    pub async fn get_webhook(&self, id: i64) -> Result<Option<Webhook>> {
+        let record = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT id, url, secret, address, token_address, threshold, active, created_at
+            FROM webhooks
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(self.executor())
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Lists webhooks ordered by id
+    ///
+    /// # Arguments
+    /// * `after_id` - Only return webhooks with a greater id, for pagination
+    /// * `limit` - Maximum number of records to return
+    pub async fn list_webhooks(&self, after_id: i64, limit: i64) -> Result<Vec<Webhook>> {
+        let records = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT id, url, secret, address, token_address, threshold, active, created_at
+            FROM webhooks
+            WHERE id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+            after_id,
+            limit
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Enables or disables a webhook
+    /// Returns false if the webhook does not exist
+    pub async fn set_webhook_active(&self, id: i64, active: bool) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE webhooks SET active = $2 WHERE id = $1
+            "#,
+            id,
+            active
+        )
+        .execute(self.executor())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Deletes a webhook together with its deliveries
+    /// Returns false if the webhook does not exist
+    pub async fn delete_webhook(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM webhooks WHERE id = $1
+            "#,
+            id
+        )
+        .execute(self.executor())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Finds the active webhooks interested in a balance of an (address, token) pair
+    pub async fn find_webhooks_for_balance(
+        &self,
+        address: &str,
+        token_address: &str,
+    ) -> Result<Vec<Webhook>> {
+        let records = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT id, url, secret, address, token_address, threshold, active, created_at
+            FROM webhooks
+            WHERE active
+                AND address = $1
+                AND (token_address IS NULL OR token_address = $2)
+            ORDER BY id
+            "#,
+            address.to_lowercase(),
+            token_address.to_lowercase()
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Records a single webhook delivery attempt
+    pub async fn insert_webhook_delivery(&self, delivery: &NewWebhookDelivery<'_>) -> Result<i64> {
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO webhook_deliveries
+                (webhook_id, event_id, payload, attempt, status_code, error, succeeded)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#,
+            delivery.webhook_id,
+            delivery.event_id,
+            delivery.payload,
+            delivery.attempt,
+            delivery.status_code,
+            delivery.error,
+            delivery.succeeded
+        )
+        .fetch_one(self.executor())
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Gets a webhook delivery attempt by id
+    pub async fn get_webhook_delivery(&self, id: i64) -> Result<Option<WebhookDelivery>> {
+        let record = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT id, webhook_id, event_id, payload, attempt, status_code, error, succeeded,
+                created_at
+            FROM webhook_deliveries
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(self.executor())
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Lists the delivery attempts of a webhook, newest first
+    pub async fn list_webhook_deliveries(
+        &self,
+        webhook_id: i64,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let records = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT id, webhook_id, event_id, payload, attempt, status_code, error, succeeded,
+                created_at
+            FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ORDER BY id DESC
+            LIMIT $2
+            "#,
+            webhook_id,
+            limit
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Registers a token, or updates it if already registered
+    /// Fields left as none keep their stored value
+    pub async fn upsert_token(&self, token: &NewToken<'_>) -> Result<Token> {
+        upsert_token(self.executor(), token).await
+    }
+
+    /// Registers or updates many tokens in one transaction, returns the number of tokens written
+    pub async fn import_tokens(&self, tokens: &[NewToken<'_>]) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        for token in tokens {
+            upsert_token(Instrumented(&mut *tx), token).await?;
+        }
+        tx.commit().await?;
+
+        Ok(tokens.len())
+    }
+
+    /// Gets a registered token of a chain
+    pub async fn get_token(&self, chain_id: u64, address: &str) -> Result<Option<Token>> {
+        let record = sqlx::query_as!(
+            Token,
+            r#"
+            SELECT address, chain_id, symbol, name, decimals, logo_uri, tags, verified,
+                created_at, updated_at
+            FROM tokens
+            WHERE chain_id = $1 AND address = $2
+            "#,
+            chain_id as i64,
+            address.to_lowercase()
+        )
+        .fetch_optional(self.executor())
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Lists registered tokens of a chain, ordered by address
+    ///
+    /// # Arguments
+    /// * `chain_id` - Chain the tokens live on
+    /// * `after_address` - Only return tokens with a greater address, for pagination
+    /// * `verified` - Only return tokens with this verified flag
+    /// * `limit` - Maximum number of records to return
+    pub async fn list_tokens(
+        &self,
+        chain_id: u64,
+        after_address: Option<&str>,
+        verified: Option<bool>,
+        limit: i64,
+    ) -> Result<Vec<Token>> {
+        let records = sqlx::query_as!(
+            Token,
+            r#"
+            SELECT address, chain_id, symbol, name, decimals, logo_uri, tags, verified,
+                created_at, updated_at
+            FROM tokens
+            WHERE chain_id = $1
+                AND ($2::CHAR(42) IS NULL OR address > $2)
+                AND ($3::BOOLEAN IS NULL OR verified = $3)
+            ORDER BY address
+            LIMIT $4
+            "#,
+            chain_id as i64,
+            after_address.map(str::to_lowercase) as Option<String>,
+            verified,
+            limit
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Removes a token from the registry, stored balances of the token are kept
+    /// Returns false if the token does not exist
+    pub async fn delete_token(&self, chain_id: u64, address: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM tokens WHERE chain_id = $1 AND address = $2
+            "#,
+            chain_id as i64,
+            address.to_lowercase()
+        )
+        .execute(self.executor())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Gets the non-zero stored balances of an account on a chain with their token metadata
+    /// Balances stored before the registry existed have no chain and are left out
+    pub async fn get_account_holdings(
+        &self,
+        chain_id: u64,
+        address: &str,
+    ) -> Result<Vec<AccountHolding>> {
+        let records = sqlx::query_as!(
+            AccountHolding,
+            r#"
+            SELECT b.token_address, b.balance, b.block_number, b.updated_at,
+                t.symbol, t.name, t.decimals, t.logo_uri, t.verified
+            FROM eth_account_balances b
+            JOIN tokens t ON t.chain_id = b.chain_id AND t.address = b.token_address
+            WHERE b.chain_id = $1 AND b.address = $2 AND b.balance <> 0
+            ORDER BY b.token_address
+            "#,
+            chain_id as i64,
+            address.to_lowercase()
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Creates an API key, only the hash of the key is stored
+    pub async fn create_api_key(&self, key: &NewApiKey<'_>) -> Result<ApiKey> {
+        let record = sqlx::query_as!(
+            ApiKey,
+            r#"
+            INSERT INTO api_keys (key_hash, key_prefix, owner, scopes, rate_limit_per_second,
+                daily_quota)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, key_prefix, owner, scopes, rate_limit_per_second, daily_quota, active,
+                created_at
+            "#,
+            key.key_hash,
+            key.key_prefix,
+            key.owner,
+            key.scopes,
+            key.rate_limit_per_second,
+            key.daily_quota
+        )
+        .fetch_one(self.executor())
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Gets an API key by id
+    pub async fn get_api_key(&self, id: i64) -> Result<Option<ApiKey>> {
+        let record = sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT id, key_prefix, owner, scopes, rate_limit_per_second, daily_quota, active,
+                created_at
+            FROM api_keys
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(self.executor())
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Finds an API key by the SHA-256 hash of the key
+    pub async fn find_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>> {
+        let record = sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT id, key_prefix, owner, scopes, rate_limit_per_second, daily_quota, active,
+                created_at
+            FROM api_keys
+            WHERE key_hash = $1
+            "#,
+            key_hash
+        )
+        .fetch_optional(self.executor())
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Lists API keys ordered by id
+    ///
+    /// # Arguments
+    /// * `after_id` - Only return keys with a greater id, for pagination
+    /// * `limit` - Maximum number of records to return
+    pub async fn list_api_keys(&self, after_id: i64, limit: i64) -> Result<Vec<ApiKey>> {
+        let records = sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT id, key_prefix, owner, scopes, rate_limit_per_second, daily_quota, active,
+                created_at
+            FROM api_keys
+            WHERE id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+            after_id,
+            limit
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Updates an API key, none fields keep their stored value
+    /// Returns none if the key does not exist
+    pub async fn update_api_key(
+        &self,
+        id: i64,
+        update: &ApiKeyUpdate<'_>,
+    ) -> Result<Option<ApiKey>> {
+        let record = sqlx::query_as!(
+            ApiKey,
+            r#"
+            UPDATE api_keys SET
+                owner = COALESCE($2, owner),
+                scopes = COALESCE($3, scopes),
+                rate_limit_per_second = COALESCE($4, rate_limit_per_second),
+                daily_quota = COALESCE($5, daily_quota),
+                active = COALESCE($6, active)
+            WHERE id = $1
+            RETURNING id, key_prefix, owner, scopes, rate_limit_per_second, daily_quota, active,
+                created_at
+            "#,
+            id,
+            update.owner,
+            update.scopes as Option<&[String]>,
+            update.rate_limit_per_second,
+            update.daily_quota,
+            update.active
+        )
+        .fetch_optional(self.executor())
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Adds request counts to the usage of API keys per route and day, in one transaction
+    pub async fn record_api_key_usage(&self, usage: &[ApiKeyUsageDelta]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for delta in usage {
+            sqlx::query!(
+                r#"
+                INSERT INTO api_key_usage (api_key_id, route, day, request_count)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (api_key_id, route, day)
+                DO UPDATE SET request_count = api_key_usage.request_count + EXCLUDED.request_count
+                "#,
+                delta.api_key_id,
+                delta.route,
+                delta.day,
+                delta.request_count
+            )
+            .execute(Instrumented(&mut *tx))
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Lists the usage of API keys between two days, both included
+    /// Ordered by key, day and route, for billing exports
+    ///
+    /// # Arguments
+    /// * `from` - First day
+    /// * `to` - Last day
+    /// * `api_key_id` - Only return the usage of this key
+    pub async fn list_api_key_usage(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        api_key_id: Option<i64>,
+    ) -> Result<Vec<ApiKeyUsage>> {
+        let records = sqlx::query_as!(
+            ApiKeyUsage,
+            r#"
+            SELECT u.api_key_id, k.owner, u.route, u.day, u.request_count
+            FROM api_key_usage u
+            JOIN api_keys k ON k.id = u.api_key_id
+            WHERE u.day BETWEEN $1 AND $2
+                AND ($3::BIGINT IS NULL OR u.api_key_id = $3)
+            ORDER BY u.api_key_id, u.day, u.route
+            "#,
+            from,
+            to,
+            api_key_id
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Locks the nonce of a wallet until the returned lock is committed or dropped
+    /// Instances sending from the same wallet wait on the row lock, so a nonce is never
+    /// used twice. The nonce never goes below `chain_nonce`, in case the wallet sent
+    /// transactions the database doesn't know about.
+    ///
+    /// # Arguments
+    /// * `address` - Address of the wallet
+    /// * `chain_id` - Chain the transaction is sent on
+    /// * `chain_nonce` - Pending transaction count of the wallet on chain
+    pub async fn lock_wallet_nonce(
+        &self,
+        address: &str,
+        chain_id: u64,
+        chain_nonce: u64,
+    ) -> Result<NonceLock> {
+        let address = address.to_lowercase();
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO wallet_nonces (address, chain_id, next_nonce)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (address, chain_id) DO NOTHING
+            "#,
+            address,
+            chain_id as i64,
+            chain_nonce as i64
+        )
+        .execute(Instrumented(&mut *tx))
+        .await?;
+
+        let next_nonce = sqlx::query_scalar!(
+            r#"
+            SELECT next_nonce FROM wallet_nonces
+            WHERE address = $1 AND chain_id = $2
+            FOR UPDATE
+            "#,
+            address,
+            chain_id as i64
+        )
+        .fetch_one(Instrumented(&mut *tx))
+        .await?;
+
+        Ok(NonceLock {
+            tx,
+            address,
+            chain_id: chain_id as i64,
+            nonce: (next_nonce as u64).max(chain_nonce),
+        })
+    }
+
+    /// Records a resubmission of an outgoing transaction with the same nonce,
+    /// and marks the transaction as replaced by it, in one transaction
+    pub async fn replace_outgoing_transaction(
+        &self,
+        id: i64,
+        replacement: &NewOutgoingTransaction<'_>,
+    ) -> Result<OutgoingTransaction> {
+        let mut tx = self.pool.begin().await?;
+        let record = insert_outgoing_transaction(Instrumented(&mut *tx), replacement).await?;
+        sqlx::query!(
+            r#"
+            UPDATE outgoing_transactions
+            SET status = 'replaced', replaced_by = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            record.id
+        )
+        .execute(Instrumented(&mut *tx))
+        .await?;
+        tx.commit().await?;
+
+        Ok(record)
+    }
+
+    /// Gets an outgoing transaction by hash
+    pub async fn get_outgoing_transaction_by_hash(
+        &self,
+        tx_hash: &str,
+    ) -> Result<Option<OutgoingTransaction>> {
+        let record = sqlx::query_as!(
+            OutgoingTransaction,
+            r#"
+            SELECT id, from_address, chain_id, nonce, tx_hash, purpose, to_address, value,
+                input, gas_limit, max_fee_per_gas, max_priority_fee_per_gas,
+                status AS "status: OutgoingTransactionStatus", replaced_by, block_number,
+                succeeded, stuck_at, created_at, updated_at
+            FROM outgoing_transactions
+            WHERE tx_hash = $1
+            "#,
+            tx_hash.to_lowercase()
+        )
+        .fetch_optional(self.executor())
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Lists outgoing transactions, newest first
+    ///
+    /// # Arguments
+    /// * `before_id` - Only return transactions with a smaller id, for pagination
+    /// * `status` - Only return transactions in this state
+    /// * `limit` - Maximum number of records to return
+    pub async fn list_outgoing_transactions(
+        &self,
+        before_id: Option<i64>,
+        status: Option<OutgoingTransactionStatus>,
+        limit: i64,
+    ) -> Result<Vec<OutgoingTransaction>> {
+        let records = sqlx::query_as!(
+            OutgoingTransaction,
+            r#"
+            SELECT id, from_address, chain_id, nonce, tx_hash, purpose, to_address, value,
+                input, gas_limit, max_fee_per_gas, max_priority_fee_per_gas,
+                status AS "status: OutgoingTransactionStatus", replaced_by, block_number,
+                succeeded, stuck_at, created_at, updated_at
+            FROM outgoing_transactions
+            WHERE ($1::BIGINT IS NULL OR id < $1)
+                AND ($2::TEXT IS NULL OR status = $2)
+            ORDER BY id DESC
+            LIMIT $3
+            "#,
+            before_id,
+            status.map(OutgoingTransactionStatus::as_str),
+            limit
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Lists the pending and mined transactions of a wallet, by nonce
+    pub async fn list_open_outgoing_transactions(
+        &self,
+        from_address: &str,
+        chain_id: u64,
+    ) -> Result<Vec<OutgoingTransaction>> {
+        let records = sqlx::query_as!(
+            OutgoingTransaction,
+            r#"
+            SELECT id, from_address, chain_id, nonce, tx_hash, purpose, to_address, value,
+                input, gas_limit, max_fee_per_gas, max_priority_fee_per_gas,
+                status AS "status: OutgoingTransactionStatus", replaced_by, block_number,
+                succeeded, stuck_at, created_at, updated_at
+            FROM outgoing_transactions
+            WHERE status IN ('pending', 'mined') AND from_address = $1 AND chain_id = $2
+            ORDER BY nonce, id
+            "#,
+            from_address.to_lowercase(),
+            chain_id as i64
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Lists every transaction a wallet sent with a nonce, oldest first
+    pub async fn list_nonce_attempts(
+        &self,
+        from_address: &str,
+        chain_id: u64,
+        nonce: u64,
+    ) -> Result<Vec<OutgoingTransaction>> {
+        let records = sqlx::query_as!(
+            OutgoingTransaction,
+            r#"
+            SELECT id, from_address, chain_id, nonce, tx_hash, purpose, to_address, value,
+                input, gas_limit, max_fee_per_gas, max_priority_fee_per_gas,
+                status AS "status: OutgoingTransactionStatus", replaced_by, block_number,
+                succeeded, stuck_at, created_at, updated_at
+            FROM outgoing_transactions
+            WHERE from_address = $1 AND chain_id = $2 AND nonce = $3
+            ORDER BY id
+            "#,
+            from_address.to_lowercase(),
+            chain_id as i64,
+            nonce as i64
+        )
+        .fetch_all(self.executor())
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Moves an outgoing transaction to another state
+    ///
+    /// # Arguments
+    /// * `id` - Outgoing transaction id
+    /// * `status` - New state
+    /// * `block_number` - Block including the transaction, none unless mined or confirmed
+    /// * `succeeded` - Receipt status, none unless mined or confirmed
+    pub async fn update_outgoing_transaction_status(
+        &self,
+        id: i64,
+        status: OutgoingTransactionStatus,
+        block_number: Option<u64>,
+        succeeded: Option<bool>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE outgoing_transactions
+            SET status = $2, block_number = $3, succeeded = $4, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            status.as_str(),
+            block_number.map(|block_number| block_number as i64),
+            succeeded
         )
-        .execute(&self.pool)
+        .execute(self.executor())
         .await?;
 
         Ok(())
     }
+
+    /// Marks an outgoing transaction as stuck, it is no longer resubmitted
+    /// It stays open, so it is still followed if it gets mined after all
+    pub async fn mark_outgoing_transaction_stuck(&self, id: i64) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE outgoing_transactions
+            SET stuck_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND stuck_at IS NULL
+            "#,
+            id
+        )
+        .execute(self.executor())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Takes a lock shared by every instance of the service, without waiting for it
+    /// Returns None when another instance holds it. The lock is held by a transaction
+    /// until the returned lock is dropped, so it is released if the instance dies.
+    ///
+    /// # Arguments
+    /// * `key` - Name of the lock, e.g. `outgoing_transactions:<address>:<chain_id>`
+    pub async fn try_advisory_lock(&self, key: &str) -> Result<Option<AdvisoryLock>> {
+        let mut tx = self.pool.begin().await?;
+        let locked = sqlx::query_scalar!(
+            r#"SELECT pg_try_advisory_xact_lock(hashtextextended($1, 0)) AS "locked!""#,
+            key
+        )
+        .fetch_one(Instrumented(&mut *tx))
+        .await?;
+
+        Ok(locked.then_some(AdvisoryLock { _tx: tx }))
+    }
+}
+
+/// Registers or updates a token with the given executor, so it can run inside a transaction
+async fn upsert_token<'e, E>(executor: E, token: &NewToken<'_>) -> Result<Token>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let record = sqlx::query_as!(
+        Token,
+        r#"
+        INSERT INTO tokens (address, chain_id, symbol, name, decimals, logo_uri, tags, verified)
+        VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7::TEXT[], '{}'), COALESCE($8, FALSE))
+        ON CONFLICT (chain_id, address)
+        DO UPDATE SET
+            symbol = COALESCE($3, tokens.symbol),
+            name = COALESCE($4, tokens.name),
+            decimals = COALESCE($5, tokens.decimals),
+            logo_uri = COALESCE($6, tokens.logo_uri),
+            tags = COALESCE($7, tokens.tags),
+            verified = COALESCE($8, tokens.verified),
+            updated_at = NOW()
+        RETURNING address, chain_id, symbol, name, decimals, logo_uri, tags, verified,
+            created_at, updated_at
+        "#,
+        token.address.to_lowercase(),
+        token.chain_id as i64,
+        token.symbol,
+        token.name,
+        token.decimals,
+        token.logo_uri,
+        token.tags,
+        token.verified
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record)
+}
+
+/// Records an outgoing transaction with the given executor, so it can run inside a transaction
+async fn insert_outgoing_transaction<'e, E>(
+    executor: E,
+    record: &NewOutgoingTransaction<'_>,
+) -> Result<OutgoingTransaction>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let record = sqlx::query_as!(
+        OutgoingTransaction,
+        r#"
+        INSERT INTO outgoing_transactions (from_address, chain_id, nonce, tx_hash, purpose,
+            to_address, value, input, gas_limit, max_fee_per_gas, max_priority_fee_per_gas)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING id, from_address, chain_id, nonce, tx_hash, purpose, to_address, value,
+            input, gas_limit, max_fee_per_gas, max_priority_fee_per_gas,
+            status AS "status: OutgoingTransactionStatus", replaced_by, block_number,
+            succeeded, stuck_at, created_at, updated_at
+        "#,
+        record.from_address.to_lowercase(),
+        record.chain_id as i64,
+        record.nonce as i64,
+        record.tx_hash.to_lowercase(),
+        record.purpose,
+        record.to_address.map(str::to_lowercase),
+        record.value,
+        record.input,
+        record.gas_limit as i64,
+        record.max_fee_per_gas,
+        record.max_priority_fee_per_gas
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record)
+}
+
+/// A locked nonce of a wallet, see `Repository::lock_wallet_nonce`
+/// Dropping the lock without committing it releases the nonce unused
+pub struct NonceLock {
+    tx: sqlx::Transaction<'static, sqlx::Postgres>,
+    address: String,
+    chain_id: i64,
+    nonce: u64,
+}
+
+impl NonceLock {
+    /// Returns the nonce to send the transaction with
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    /// Records the transaction sent with the nonce, moves the wallet to the next nonce
+    /// and releases the lock
+    pub async fn commit(
+        mut self,
+        record: &NewOutgoingTransaction<'_>,
+    ) -> Result<OutgoingTransaction> {
+        let record = insert_outgoing_transaction(Instrumented(&mut *self.tx), record).await?;
+        sqlx::query!(
+            r#"
+            UPDATE wallet_nonces SET next_nonce = $3, updated_at = NOW()
+            WHERE address = $1 AND chain_id = $2
+            "#,
+            self.address,
+            self.chain_id,
+            self.nonce as i64 + 1
+        )
+        .execute(Instrumented(&mut *self.tx))
+        .await?;
+        self.tx.commit().await?;
+
+        Ok(record)
+    }
+}
+
+/// A lock shared by every instance, see `Repository::try_advisory_lock`
+/// Dropping it ends its transaction, which releases the lock
+pub struct AdvisoryLock {
+    _tx: sqlx::Transaction<'static, sqlx::Postgres>,
+}
+
+/// Status of a migration of the migrations directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+    /// The migration was changed after it was applied
+    pub modified: bool,
 }
 
 /// Represents an Ethereum account balance record in the database
@@ -100,4 +1326,326 @@ pub struct EthAccountBalance {
     pub token_address: String,
     /// Current token balance
     pub balance: rust_decimal::Decimal,
+    /// Block at which the balance was last read
+    pub block_number: Option<i64>,
+    /// Last time the balance was observed
+    pub updated_at: NaiveDateTime,
+    /// Last time the balance changed
+    pub changed_at: NaiveDateTime,
+}
+
+/// Outcome of `Repository::upsert_eth_account_balance`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Upsert {
+    /// The balance was stored, along with the previously stored balance if any
+    Written(Option<rust_decimal::Decimal>),
+    /// The balance was read at an older block than the stored one and was ignored
+    Stale,
+}
+
+/// Represents a balance change recorded in `balance_history`
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct BalanceHistory {
+    /// Ethereum account address
+    pub address: String,
+    /// ERC20 token contract address
+    pub token_address: String,
+    /// Block at which the balance was read
+    pub block_number: i64,
+    /// Token balance at that block
+    pub balance: rust_decimal::Decimal,
+    /// Time the change was observed
+    pub observed_at: NaiveDateTime,
+}
+
+/// Filters for querying `balance_history`, every bound is inclusive
+#[derive(Debug, Default)]
+pub struct BalanceHistoryFilter {
+    /// Only return changes of this token
+    pub token_address: Option<String>,
+    /// Lowest block number
+    pub from_block: Option<u64>,
+    /// Highest block number
+    pub to_block: Option<u64>,
+    /// Earliest observation time
+    pub from_time: Option<NaiveDateTime>,
+    /// Latest observation time
+    pub to_time: Option<NaiveDateTime>,
+    /// Maximum number of records to return
+    pub limit: i64,
+}
+
+/// Represents a watched (address, token) pair and its latest stored balance
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WatchedBalance {
+    /// Watchlist entry id
+    pub id: i64,
+    /// Ethereum account address
+    pub address: String,
+    /// ERC20 token contract address
+    pub token_address: String,
+    /// Human readable label
+    pub label: Option<String>,
+    /// Time the pair was added
+    pub created_at: NaiveDateTime,
+    /// Latest stored balance, none until the first refresh
+    pub balance: Option<rust_decimal::Decimal>,
+    /// Block at which the balance was last read
+    pub block_number: Option<i64>,
+    /// Last time the balance was observed
+    pub updated_at: Option<NaiveDateTime>,
+    /// Last time the balance changed
+    pub changed_at: Option<NaiveDateTime>,
+}
+
+/// Represents a webhook subscribed to balance changes of an address
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Webhook {
+    /// Webhook id
+    pub id: i64,
+    /// Endpoint receiving the signed payloads
+    pub url: String,
+    /// Key used to sign payloads with HMAC-SHA256
+    pub secret: String,
+    /// Watched Ethereum account address
+    pub address: String,
+    /// Only fire for this token, none fires for every token
+    pub token_address: Option<String>,
+    /// Minimum absolute balance change, in the token's smallest unit
+    pub threshold: rust_decimal::Decimal,
+    /// Whether the webhook fires
+    pub active: bool,
+    /// Time the webhook was registered
+    pub created_at: NaiveDateTime,
+}
+
+/// Represents a single webhook delivery attempt
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WebhookDelivery {
+    /// Delivery attempt id
+    pub id: i64,
+    /// Webhook the payload was sent to
+    pub webhook_id: i64,
+    /// Event id, shared by every attempt and replay of the same event
+    pub event_id: String,
+    /// JSON payload that was sent
+    pub payload: serde_json::Value,
+    /// Attempt number, starting at 1
+    pub attempt: i32,
+    /// HTTP status returned by the receiver, none if the request failed
+    pub status_code: Option<i32>,
+    /// Transport or HTTP error, none on success
+    pub error: Option<String>,
+    /// Whether the receiver acknowledged the payload
+    pub succeeded: bool,
+    /// Time of the attempt
+    pub created_at: NaiveDateTime,
+}
+
+/// A webhook delivery attempt to be recorded
+#[derive(Debug)]
+pub struct NewWebhookDelivery<'a> {
+    pub webhook_id: i64,
+    pub event_id: &'a str,
+    pub payload: &'a serde_json::Value,
+    pub attempt: i32,
+    pub status_code: Option<i32>,
+    pub error: Option<&'a str>,
+    pub succeeded: bool,
+}
+
+/// Represents a token in the registry
+/// Metadata is none until it is resolved from the chain or imported
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Token {
+    /// Token contract address, `ZERO_ADDRESS` for the native asset
+    pub address: String,
+    /// Chain the token lives on
+    pub chain_id: i64,
+    pub symbol: Option<String>,
+    pub name: Option<String>,
+    pub decimals: Option<i16>,
+    /// URL of the token logo
+    pub logo_uri: Option<String>,
+    /// Free form tags, e.g. from a token list
+    pub tags: Vec<String>,
+    /// Whether the token was reviewed by an admin or imported from a trusted list
+    pub verified: bool,
+    /// Time the token was registered
+    pub created_at: NaiveDateTime,
+    /// Last time the token was updated
+    pub updated_at: NaiveDateTime,
+}
+
+/// Represents a stored balance of an account together with its token metadata
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct AccountHolding {
+    /// Token contract address, `ZERO_ADDRESS` for the native asset
+    pub token_address: String,
+    /// Stored token balance, in the token's smallest unit
+    pub balance: rust_decimal::Decimal,
+    /// Block at which the balance was last read
+    pub block_number: Option<i64>,
+    /// Last time the balance was observed
+    pub updated_at: NaiveDateTime,
+    pub symbol: Option<String>,
+    pub name: Option<String>,
+    pub decimals: Option<i16>,
+    pub logo_uri: Option<String>,
+    pub verified: bool,
+}
+
+/// A token to be registered or updated, none fields keep their stored value
+#[derive(Debug, Default)]
+pub struct NewToken<'a> {
+    pub address: &'a str,
+    pub chain_id: u64,
+    pub symbol: Option<&'a str>,
+    pub name: Option<&'a str>,
+    pub decimals: Option<i16>,
+    pub logo_uri: Option<&'a str>,
+    pub tags: Option<&'a [String]>,
+    pub verified: Option<bool>,
+}
+
+/// Represents an API key of a partner, the key itself is never stored
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ApiKey {
+    /// API key id
+    pub id: i64,
+    /// First characters of the key, to tell keys apart
+    pub key_prefix: String,
+    /// Team or partner the key was issued to
+    pub owner: String,
+    /// Router groups the key may call, every group if empty
+    pub scopes: Vec<String>,
+    /// Maximum number of requests per second
+    pub rate_limit_per_second: i32,
+    /// Maximum number of requests per UTC day
+    pub daily_quota: i64,
+    /// Whether the key is accepted
+    pub active: bool,
+    /// Time the key was created
+    pub created_at: NaiveDateTime,
+}
+
+/// An API key to be created
+#[derive(Debug)]
+pub struct NewApiKey<'a> {
+    /// SHA-256 of the key, hex encoded
+    pub key_hash: &'a str,
+    pub key_prefix: &'a str,
+    pub owner: &'a str,
+    pub scopes: &'a [String],
+    pub rate_limit_per_second: i32,
+    pub daily_quota: i64,
+}
+
+/// Changes to an API key, none fields keep their stored value
+#[derive(Debug, Default)]
+pub struct ApiKeyUpdate<'a> {
+    pub owner: Option<&'a str>,
+    pub scopes: Option<&'a [String]>,
+    pub rate_limit_per_second: Option<i32>,
+    pub daily_quota: Option<i64>,
+    pub active: Option<bool>,
+}
+
+/// Requests of an API key to a route on a day, to be added to the stored usage
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiKeyUsageDelta {
+    pub api_key_id: i64,
+    /// Route template, e.g. `/v1/public/eth/accounts/{address}`
+    pub route: String,
+    pub day: NaiveDate,
+    pub request_count: i64,
+}
+
+/// Represents the requests of an API key to a route on a day
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ApiKeyUsage {
+    pub api_key_id: i64,
+    /// Team or partner the key was issued to
+    pub owner: String,
+    /// Route template, e.g. `/v1/public/eth/accounts/{address}`
+    pub route: String,
+    /// UTC day
+    pub day: NaiveDate,
+    pub request_count: i64,
+}
+
+/// State of an outgoing transaction
+/// pending → mined → confirmed, or dropped when its nonce was used by another transaction,
+/// or replaced by a resubmission with higher fees
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OutgoingTransactionStatus {
+    Pending,
+    Mined,
+    Confirmed,
+    Dropped,
+    Replaced,
+}
+
+impl OutgoingTransactionStatus {
+    /// Returns the stored name of the state
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Mined => "mined",
+            Self::Confirmed => "confirmed",
+            Self::Dropped => "dropped",
+            Self::Replaced => "replaced",
+        }
+    }
+}
+
+/// Represents a transaction sent by a hot wallet
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OutgoingTransaction {
+    /// Outgoing transaction id
+    pub id: i64,
+    pub from_address: String,
+    pub chain_id: i64,
+    pub nonce: i64,
+    pub tx_hash: String,
+    /// What the transaction is for, e.g. `faucet`, `mint` or `cancel`
+    pub purpose: String,
+    /// None for contract deployments
+    pub to_address: Option<String>,
+    /// Wei sent with the transaction
+    pub value: rust_decimal::Decimal,
+    /// Calldata
+    pub input: Vec<u8>,
+    pub gas_limit: i64,
+    pub max_fee_per_gas: rust_decimal::Decimal,
+    pub max_priority_fee_per_gas: rust_decimal::Decimal,
+    pub status: OutgoingTransactionStatus,
+    /// Resubmission that replaced this transaction
+    pub replaced_by: Option<i64>,
+    /// Block including the transaction, once mined
+    pub block_number: Option<i64>,
+    /// Receipt status, false if the transaction reverted
+    pub succeeded: Option<bool>,
+    /// Time the nonce was given up on, it is no longer resubmitted
+    pub stuck_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// A transaction sent by a hot wallet, to be recorded
+#[derive(Debug)]
+pub struct NewOutgoingTransaction<'a> {
+    pub from_address: &'a str,
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub tx_hash: &'a str,
+    pub purpose: &'a str,
+    pub to_address: Option<&'a str>,
+    pub value: rust_decimal::Decimal,
+    pub input: &'a [u8],
+    pub gas_limit: u64,
+    pub max_fee_per_gas: rust_decimal::Decimal,
+    pub max_priority_fee_per_gas: rust_decimal::Decimal,
 }
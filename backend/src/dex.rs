@@ -0,0 +1,505 @@
+// Token pricing from Uniswap V2 and V3 pools, for tokens without a Chainlink feed
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use alloy::primitives::{Address, Bytes, U160, aliases::U24};
+use alloy::providers::DynProvider;
+use alloy::sol_types::{SolCall, sol};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::db::Repository;
+use crate::error::{self, AppError, Result, ValidateError};
+use crate::eth::{self, IERC20, IERC20Instance};
+
+// Import the generated contract bindings for Uniswap factories and pools
+sol!(
+    #[sol(rpc)]
+    interface IUniswapV2Factory {
+        function getPair(address tokenA, address tokenB) external view returns (address pair);
+    }
+
+    #[sol(rpc)]
+    interface IUniswapV2Pair {
+        function getReserves() external view returns (
+            uint112 reserve0,
+            uint112 reserve1,
+            uint32 blockTimestampLast
+        );
+    }
+
+    #[sol(rpc)]
+    interface IUniswapV3Factory {
+        function getPool(address tokenA, address tokenB, uint24 fee) external view returns (address pool);
+    }
+
+    #[sol(rpc)]
+    interface IUniswapV3Pool {
+        function slot0() external view returns (
+            uint160 sqrtPriceX96,
+            int24 tick,
+            uint16 observationIndex,
+            uint16 observationCardinality,
+            uint16 observationCardinalityNext,
+            uint8 feeProtocol,
+            bool unlocked
+        );
+        function observe(uint32[] secondsAgos) external view returns (
+            int56[] tickCumulatives,
+            uint160[] secondsPerLiquidityCumulativeX128s
+        );
+    }
+);
+
+/// Configuration for DEX pricing
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Uniswap V2 factory address
+    pub v2_factory: String,
+    /// Uniswap V3 factory address
+    pub v3_factory: String,
+    /// V3 fee tiers searched for pools, in hundredths of a bip
+    pub v3_fee_tiers: Vec<u32>,
+    /// Wrapped native token, used to route tokens without a USDC pool
+    pub weth: String,
+    /// USDC, every price is quoted in it
+    pub usdc: String,
+    /// Default TWAP window, in seconds
+    pub twap_window: u32,
+    /// Maximum TWAP window a caller may request, in seconds
+    pub max_twap_window: u32,
+}
+
+/// Pool protocol
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    UniswapV2,
+    UniswapV3,
+}
+
+/// Price of `token_in` in `token_out` from a single pool
+#[derive(Debug, Clone)]
+pub struct PoolQuote {
+    pub pool_address: Address,
+    pub protocol: Protocol,
+    /// V3 fee tier, none for V2
+    pub fee: Option<u32>,
+    pub token_in: Address,
+    pub token_out: Address,
+    /// Current price of one whole `token_in`, in whole `token_out`
+    pub spot_price: f64,
+    /// Time weighted average price over the window, none for V2 pools
+    /// or when the pool doesn't have enough observations
+    pub twap_price: Option<f64>,
+    /// Pool balances, in whole tokens
+    pub reserve_in: f64,
+    pub reserve_out: f64,
+}
+
+/// Price of a token in USDC, through one or two pools
+#[derive(Debug, Clone)]
+pub struct PriceQuote {
+    pub token_address: Address,
+    pub quote_token: Address,
+    pub spot_price: f64,
+    /// None unless every pool of the route has a TWAP
+    pub twap_price: Option<f64>,
+    pub twap_window: u32,
+    /// Liquidity of the thinnest pool of the route, in USDC: twice its USDC-valued out reserve
+    /// None when pricing USDC itself
+    pub liquidity: Option<f64>,
+    pub route: Vec<PoolQuote>,
+}
+
+/// DexPricer prices tokens from their deepest Uniswap pools, quoting in USDC directly
+/// or through WETH when there is no direct pool
+#[derive(Clone)]
+pub struct DexPricer {
+    provider: DynProvider,
+    repo: Repository,
+    /// Chain of `provider`, the registry is read for it
+    chain_id: u64,
+    /// Decimals of the tokens priced so far, they never change
+    decimals: Arc<Mutex<HashMap<Address, u8>>>,
+    v2_factory: Address,
+    v3_factory: Address,
+    v3_fee_tiers: Vec<u32>,
+    weth: Address,
+    usdc: Address,
+    twap_window: u32,
+    max_twap_window: u32,
+}
+
+impl DexPricer {
+    /// Create a new instance of `DexPricer` with the provided configuration.
+    ///
+    /// # Arguments
+    /// * `provider` - Provider used to read the pools
+    /// * `repo` - Token registry, for decimals
+    /// * `chain_id` - Chain of `provider`
+    /// * `config` - Factories, fee tiers, quote tokens and TWAP windows
+    pub fn new(
+        provider: DynProvider,
+        repo: Repository,
+        chain_id: u64,
+        config: &Config,
+    ) -> Result<Self> {
+        Ok(Self {
+            provider,
+            repo,
+            chain_id,
+            decimals: Arc::new(Mutex::new(HashMap::new())),
+            v2_factory: config.v2_factory.parse()?,
+            v3_factory: config.v3_factory.parse()?,
+            v3_fee_tiers: config.v3_fee_tiers.clone(),
+            weth: config.weth.parse()?,
+            usdc: config.usdc.parse()?,
+            twap_window: config.twap_window,
+            max_twap_window: config.max_twap_window,
+        })
+    }
+
+    /// Validates a requested TWAP window, returns the default window if none is given
+    pub fn validate_twap_window(&self, twap_window: Option<u32>) -> Result<u32> {
+        let twap_window = twap_window.unwrap_or(self.twap_window);
+        if !(1..=self.max_twap_window).contains(&twap_window) {
+            return Err(ValidateError(format!(
+                "twap_window must be between 1 and {} seconds",
+                self.max_twap_window
+            ))
+            .into());
+        }
+        Ok(twap_window)
+    }
+
+    /// Prices a token in USDC, `ZERO_ADDRESS` prices the native token as WETH
+    /// Both the direct route and the route through WETH are tried, the more liquid one wins
+    /// Returns none if no route has pools
+    pub async fn get_price(
+        &self,
+        token_address: Address,
+        twap_window: u32,
+    ) -> Result<Option<PriceQuote>> {
+        let token = if token_address.is_zero() {
+            self.weth
+        } else {
+            token_address
+        };
+
+        let quote = |route: Vec<PoolQuote>, liquidity: Option<f64>| PriceQuote {
+            token_address,
+            quote_token: self.usdc,
+            spot_price: route.iter().map(|pool| pool.spot_price).product(),
+            twap_price: route.iter().map(|pool| pool.twap_price).product(),
+            twap_window,
+            liquidity,
+            route,
+        };
+
+        if token == self.usdc {
+            return Ok(Some(quote(Vec::new(), None)));
+        }
+
+        let through_weth = async {
+            if token == self.weth {
+                return Ok(None);
+            }
+            let (to_weth, weth_to_usdc) = tokio::try_join!(
+                self.best_pool(token, self.weth, twap_window),
+                self.best_pool(self.weth, self.usdc, twap_window),
+            )?;
+            Ok(to_weth.zip(weth_to_usdc))
+        };
+        let (direct, through_weth) =
+            tokio::try_join!(self.best_pool(token, self.usdc, twap_window), through_weth,)?;
+
+        let direct = direct.map(|pool| {
+            let liquidity = 2.0 * pool.reserve_out;
+            (vec![pool], liquidity)
+        });
+        let through_weth = through_weth.map(|(to_weth, weth_to_usdc)| {
+            let liquidity = f64::min(
+                2.0 * to_weth.reserve_out * weth_to_usdc.spot_price,
+                2.0 * weth_to_usdc.reserve_out,
+            );
+            (vec![to_weth, weth_to_usdc], liquidity)
+        });
+
+        let best = match (direct, through_weth) {
+            (Some(direct), Some(through_weth)) if through_weth.1 > direct.1 => Some(through_weth),
+            (Some(direct), _) => Some(direct),
+            (None, through_weth) => through_weth,
+        };
+
+        Ok(best.map(|(route, liquidity)| quote(route, Some(liquidity))))
+    }
+
+    /// Finds the pool of a pair with the largest `token_out` reserve across V2 and every V3 fee tier
+    /// The factories are asked in one Multicall, then every existing pool is read in another
+    async fn best_pool(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        twap_window: u32,
+    ) -> Result<Option<PoolQuote>> {
+        let (decimals_in, decimals_out) =
+            tokio::try_join!(self.decimals(token_in), self.decimals(token_out))?;
+        let pair = Pair {
+            token_in,
+            token_out,
+            decimals_in,
+            decimals_out,
+        };
+
+        // Both factories return a single address, so they share the `getPair` decoder
+        let mut lookups = vec![(
+            self.v2_factory,
+            IUniswapV2Factory::getPairCall {
+                tokenA: token_in,
+                tokenB: token_out,
+            }
+            .abi_encode()
+            .into(),
+        )];
+        lookups.extend(self.v3_fee_tiers.iter().map(|fee| {
+            let call = IUniswapV3Factory::getPoolCall {
+                tokenA: token_in,
+                tokenB: token_out,
+                fee: U24::from(*fee),
+            };
+            (self.v3_factory, call.abi_encode().into())
+        }));
+        let pool_addresses: Vec<Option<Address>> = eth::aggregate(&self.provider, lookups)
+            .await?
+            .into_iter()
+            .map(|data| {
+                data.and_then(|data| IUniswapV2Factory::getPairCall::abi_decode_returns(&data).ok())
+                    .filter(|address| !address.is_zero())
+            })
+            .collect();
+        let v2_address = pool_addresses[0];
+        let v3_pools: Vec<(u32, Address)> = self
+            .v3_fee_tiers
+            .iter()
+            .zip(&pool_addresses[1..])
+            .filter_map(|(fee, address)| Some((*fee, (*address)?)))
+            .collect();
+
+        // The V2 reserves, then the balances, slot0 and observations of every V3 pool
+        let mut reads: Vec<(Address, Bytes)> = Vec::new();
+        if let Some(pool_address) = v2_address {
+            reads.push((
+                pool_address,
+                IUniswapV2Pair::getReservesCall {}.abi_encode().into(),
+            ));
+        }
+        for (_, pool_address) in &v3_pools {
+            let balance = IERC20::balanceOfCall {
+                account: *pool_address,
+            }
+            .abi_encode();
+            let observe = IUniswapV3Pool::observeCall {
+                secondsAgos: vec![twap_window, 0],
+            };
+            reads.push((token_in, balance.clone().into()));
+            reads.push((token_out, balance.into()));
+            reads.push((
+                *pool_address,
+                IUniswapV3Pool::slot0Call {}.abi_encode().into(),
+            ));
+            reads.push((*pool_address, observe.abi_encode().into()));
+        }
+        let mut results = eth::aggregate(&self.provider, reads).await?.into_iter();
+
+        let v2 = v2_address
+            .and_then(|pool_address| v2_pool(&pair, pool_address, results.next().flatten()));
+        let v3 = v3_pools.iter().filter_map(|(fee, pool_address)| {
+            let reads: Vec<_> = results.by_ref().take(4).collect();
+            v3_pool(&pair, *fee, *pool_address, twap_window, &reads)
+        });
+
+        Ok(v2
+            .into_iter()
+            .chain(v3)
+            .max_by(|a, b| a.reserve_out.total_cmp(&b.reserve_out)))
+    }
+
+    /// Reads the decimals of a token from the registry, or from the token contract
+    /// Tokens missing from the registry are not registered, decimals are kept once read
+    async fn decimals(&self, token_address: Address) -> Result<u8> {
+        if let Some(decimals) = self.decimals.lock().unwrap().get(&token_address) {
+            return Ok(*decimals);
+        }
+
+        let registered = self
+            .repo
+            .get_token(self.chain_id, &token_address.to_string())
+            .await?
+            .and_then(|token| token.decimals)
+            .and_then(|decimals| u8::try_from(decimals).ok());
+        let decimals = match registered {
+            Some(decimals) => decimals,
+            None => IERC20Instance::new(token_address, self.provider.clone())
+                .decimals()
+                .call()
+                .await
+                .map_err(|err| -> AppError {
+                    // Failures of the node are not a token without decimals
+                    if !error::is_contract_revert(&err) {
+                        return err.into();
+                    }
+                    ValidateError(format!("Token {} has unknown decimals", token_address)).into()
+                })?,
+        };
+
+        self.decimals
+            .lock()
+            .unwrap()
+            .insert(token_address, decimals);
+        Ok(decimals)
+    }
+}
+
+/// Builds the quote of a Uniswap V2 pool from its reserves, none if it is empty
+/// or they couldn't be read
+fn v2_pool(pair: &Pair, pool_address: Address, reserves: Option<Bytes>) -> Option<PoolQuote> {
+    let reserves =
+        match reserves.map(|data| IUniswapV2Pair::getReservesCall::abi_decode_returns(&data)) {
+            Some(Ok(reserves)) => reserves,
+            _ => {
+                tracing::warn!("Failed to read reserves of pool {}", pool_address);
+                return None;
+            }
+        };
+    let (reserve_in, reserve_out) = if pair.token_in_is_token0() {
+        (reserves.reserve0, reserves.reserve1)
+    } else {
+        (reserves.reserve1, reserves.reserve0)
+    };
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return None;
+    }
+
+    let reserve_in = to_whole(reserve_in, pair.decimals_in);
+    let reserve_out = to_whole(reserve_out, pair.decimals_out);
+
+    Some(PoolQuote {
+        pool_address,
+        protocol: Protocol::UniswapV2,
+        fee: None,
+        token_in: pair.token_in,
+        token_out: pair.token_out,
+        spot_price: reserve_out / reserve_in,
+        twap_price: None,
+        reserve_in,
+        reserve_out,
+    })
+}
+
+/// Builds the quote of a Uniswap V3 pool from its token balances, slot0 and observations,
+/// none if it is empty or they couldn't be read
+fn v3_pool(
+    pair: &Pair,
+    fee: u32,
+    pool_address: Address,
+    twap_window: u32,
+    reads: &[Option<Bytes>],
+) -> Option<PoolQuote> {
+    let [reserve_in, reserve_out, slot0, observations] = reads else {
+        return None;
+    };
+    let balance =
+        |data: &Option<Bytes>| IERC20::balanceOfCall::abi_decode_returns(data.as_ref()?).ok();
+    let slot0 = slot0
+        .as_ref()
+        .and_then(|data| IUniswapV3Pool::slot0Call::abi_decode_returns(data).ok());
+    // V3 reserves are the pool's token balances, across every position
+    let (Some(reserve_in), Some(reserve_out), Some(slot0)) =
+        (balance(reserve_in), balance(reserve_out), slot0)
+    else {
+        tracing::warn!("Failed to read pool {}", pool_address);
+        return None;
+    };
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return None;
+    }
+    let spot_price = pair.adjust(sqrt_price_x96_to_price(slot0.sqrtPriceX96));
+
+    // Pools without enough observations for the window revert
+    let observations = observations
+        .as_ref()
+        .and_then(|data| IUniswapV3Pool::observeCall::abi_decode_returns(data).ok());
+    let twap_price = match observations {
+        Some(observations) => match observations.tickCumulatives.as_slice() {
+            [start, end] => {
+                let tick = average_tick(start.as_i64(), end.as_i64(), twap_window);
+                Some(pair.adjust(tick_to_price(tick)))
+            }
+            _ => None,
+        },
+        None => {
+            tracing::debug!("No TWAP for pool {}", pool_address);
+            None
+        }
+    };
+
+    Some(PoolQuote {
+        pool_address,
+        protocol: Protocol::UniswapV3,
+        fee: Some(fee),
+        token_in: pair.token_in,
+        token_out: pair.token_out,
+        spot_price,
+        twap_price,
+        reserve_in: to_whole(reserve_in, pair.decimals_in),
+        reserve_out: to_whole(reserve_out, pair.decimals_out),
+    })
+}
+
+/// A token pair being priced
+struct Pair {
+    token_in: Address,
+    token_out: Address,
+    decimals_in: u8,
+    decimals_out: u8,
+}
+
+impl Pair {
+    /// Uniswap sorts the tokens of a pool by address
+    fn token_in_is_token0(&self) -> bool {
+        self.token_in < self.token_out
+    }
+
+    /// Converts a raw price of token0 in token1 to the price of one whole `token_in` in `token_out`
+    fn adjust(&self, raw_price: f64) -> f64 {
+        let raw_price = if self.token_in_is_token0() {
+            raw_price
+        } else {
+            1.0 / raw_price
+        };
+        raw_price * 10f64.powi(i32::from(self.decimals_in) - i32::from(self.decimals_out))
+    }
+}
+
+/// Converts an amount in a token's smallest unit to whole tokens
+fn to_whole(amount: impl ToString, decimals: u8) -> f64 {
+    amount.to_string().parse::<f64>().unwrap_or_default() / 10f64.powi(decimals.into())
+}
+
+/// Converts a V3 `sqrtPriceX96` to the raw price of token0 in token1
+pub fn sqrt_price_x96_to_price(sqrt_price_x96: U160) -> f64 {
+    let sqrt_price = to_whole(sqrt_price_x96, 0) / 2f64.powi(96);
+    sqrt_price * sqrt_price
+}
+
+/// Converts a V3 tick to the raw price of token0 in token1
+pub fn tick_to_price(tick: i64) -> f64 {
+    1.0001f64.powf(tick as f64)
+}
+
+/// Arithmetic mean tick between two tick cumulatives `window` seconds apart,
+/// rounded towards negative infinity like Uniswap's `OracleLibrary.consult`
+pub fn average_tick(start: i64, end: i64, window: u32) -> i64 {
+    (end - start).div_euclid(i64::from(window))
+}
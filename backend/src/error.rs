@@ -1,11 +1,97 @@
 use std::fmt::Display;
 
+use alloy::providers::PendingTransactionError;
+use alloy::transports::{RpcError, TransportError, TransportErrorKind};
 use axum::{
-    http::StatusCode,
+    http::{HeaderValue, StatusCode, header::RETRY_AFTER},
     response::{self, IntoResponse},
 };
-use serde_json::json;
+use serde::Serialize;
 use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::rate_limit::RateLimitStatus;
+use crate::telemetry;
+
+/// Seconds clients are told to wait when the Ethereum node rate limits the service
+pub const UPSTREAM_RETRY_AFTER: u64 = 1;
+
+/// Body of every error response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub code: ErrorCode,
+    /// Details of client errors, a generic message for server errors
+    pub error_msg: String,
+    /// Id of the request, as in the `X-Request-Id` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Stable, machine-readable kind of an error, each answered with one status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// 400, invalid parameters or body
+    InvalidRequest,
+    /// 401, missing or invalid session or API key
+    Unauthorized,
+    /// 403, the session or API key is not allowed to call the endpoint
+    Forbidden,
+    /// 404
+    NotFound,
+    /// 409, the resource is still in use
+    Conflict,
+    /// 429, rate limit or quota of the client exceeded
+    RateLimited,
+    /// 500
+    Internal,
+    /// 502, the Ethereum node failed or answered with an invalid response
+    UpstreamError,
+    /// 503, the feature is not configured or the service is shutting down
+    Unavailable,
+    /// 503, the Ethereum node is rate limiting the service
+    UpstreamRateLimited,
+    /// 503
+    CacheUnavailable,
+    /// 503
+    DatabaseUnavailable,
+    /// 504, the Ethereum node didn't answer in time
+    UpstreamTimeout,
+}
+
+impl ErrorCode {
+    /// Status of the responses with this code
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidRequest => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Conflict => StatusCode::CONFLICT,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::UpstreamError => StatusCode::BAD_GATEWAY,
+            Self::Unavailable
+            | Self::UpstreamRateLimited
+            | Self::CacheUnavailable
+            | Self::DatabaseUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::UpstreamTimeout => StatusCode::GATEWAY_TIMEOUT,
+        }
+    }
+
+    /// Message replacing the details of server errors, none for errors the client may read
+    fn generic_message(&self) -> Option<&'static str> {
+        match self {
+            Self::Internal => Some("Internal server error"),
+            Self::UpstreamError => Some("The Ethereum node failed to answer"),
+            Self::UpstreamRateLimited => Some("The Ethereum node is rate limiting requests"),
+            Self::CacheUnavailable => Some("The cache is unavailable"),
+            Self::DatabaseUnavailable => Some("The database is unavailable"),
+            Self::UpstreamTimeout => Some("The Ethereum node timed out"),
+            _ => None,
+        }
+    }
+}
 
 /// Custom error type for the application, wrapping `anyhow::Error`.
 #[derive(Debug)]
@@ -18,28 +104,154 @@ impl Display for AppError {
 }
 
 macro_rules! match_error_response {
-    ($err:expr, $json:expr, $($ty:ty => $status:expr),* $(,)?) => {{
+    ($err:expr, $($ty:ty => $code:expr),* $(,)?) => {{
         $(
             if $err.is::<$ty>() {
-                return ($status, axum::Json($json)).into_response();
+                return $code;
             }
         )*
-        (axum::http::StatusCode::INTERNAL_SERVER_ERROR, axum::Json($json)).into_response()
     }};
 }
 
+impl AppError {
+    /// Returns true if the inner error is of type `E`
+    pub fn is<E>(&self) -> bool
+    where
+        E: std::fmt::Display + std::fmt::Debug + Send + Sync + 'static,
+    {
+        self.0.is::<E>()
+    }
+
+    /// Returns the code of the error, failures of the node, the cache and the database
+    /// are found anywhere in the chain of causes, anything else is internal
+    pub fn code(&self) -> ErrorCode {
+        match_error_response!(
+            self.0,
+            NotFoundError => ErrorCode::NotFound,
+            ConflictError => ErrorCode::Conflict,
+            ValidateError => ErrorCode::InvalidRequest,
+            UnauthorizedError => ErrorCode::Unauthorized,
+            ForbiddenError => ErrorCode::Forbidden,
+            UnavailableError => ErrorCode::Unavailable,
+            TooManyRequestsError => ErrorCode::RateLimited,
+        );
+        self.0
+            .chain()
+            .find_map(upstream_error_code)
+            .unwrap_or(ErrorCode::Internal)
+    }
+}
+
+/// Returns the code of a failure of the Ethereum node, the cache or the database
+fn upstream_error_code(err: &(dyn std::error::Error + 'static)) -> Option<ErrorCode> {
+    if let Some(err) = transport_error(err) {
+        return rpc_error_code(err);
+    }
+    if let Some(err) = err.downcast_ref::<redis::RedisError>()
+        && (err.is_io_error() || err.is_connection_refusal() || err.is_timeout())
+    {
+        return Some(ErrorCode::CacheUnavailable);
+    }
+    match err.downcast_ref::<sqlx::Error>() {
+        Some(sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_)) => {
+            Some(ErrorCode::DatabaseUnavailable)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the RPC error, contract and transaction errors forward their source
+fn transport_error<'a>(err: &'a (dyn std::error::Error + 'static)) -> Option<&'a TransportError> {
+    if let Some(err) = err.downcast_ref::<TransportError>() {
+        return Some(err);
+    }
+    match (
+        err.downcast_ref::<alloy::contract::Error>(),
+        err.downcast_ref::<PendingTransactionError>(),
+    ) {
+        (Some(alloy::contract::Error::TransportError(err)), _)
+        | (_, Some(PendingTransactionError::TransportError(err))) => Some(err),
+        _ => None,
+    }
+}
+
+/// Returns true if the node answered a call with an error response, e.g. a revert,
+/// rather than failing to answer or rate limiting the service
+pub fn is_revert(err: &TransportError) -> bool {
+    err.as_error_resp().is_some() && rpc_error_code(err).is_none()
+}
+
+/// Returns true if a contract call reverted, or returned nothing or data that doesn't
+/// decode, rather than failing on the node
+pub fn is_contract_revert(err: &alloy::contract::Error) -> bool {
+    match err {
+        alloy::contract::Error::TransportError(err) => is_revert(err),
+        _ => true,
+    }
+}
+
+/// Returns the code of an RPC error, none for error responses of the node, e.g. reverts
+fn rpc_error_code(err: &TransportError) -> Option<ErrorCode> {
+    match err {
+        RpcError::Transport(TransportErrorKind::HttpError(err)) => match err.status {
+            429 => Some(ErrorCode::UpstreamRateLimited),
+            408 | 504 => Some(ErrorCode::UpstreamTimeout),
+            _ => Some(ErrorCode::UpstreamError),
+        },
+        RpcError::Transport(TransportErrorKind::Custom(err))
+            if err
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(reqwest::Error::is_timeout) =>
+        {
+            Some(ErrorCode::UpstreamTimeout)
+        }
+        RpcError::Transport(_) | RpcError::NullResp | RpcError::DeserError { .. } => {
+            Some(ErrorCode::UpstreamError)
+        }
+        // Providers answer -32005 "limit exceeded" when requests are rate limited
+        RpcError::ErrorResp(payload) if matches!(payload.code, 429 | -32005) => {
+            Some(ErrorCode::UpstreamRateLimited)
+        }
+        _ => None,
+    }
+}
+
 // Tell axum how to convert `AppError` into a response.
 impl IntoResponse for AppError {
     fn into_response(self) -> response::Response {
-        // check inner error type and return appropriate response, use match
-        let json_response = json!({"error_msg": self.0.to_string()});
+        let code = self.code();
+        // Details of server errors are logged, never sent to the client
+        let error_msg = match code.generic_message() {
+            Some(message) => {
+                if code == ErrorCode::Internal {
+                    tracing::error!("Request failed: {:#}", self.0);
+                } else {
+                    tracing::warn!("Request failed with {:?}: {:#}", code, self.0);
+                }
+                message.to_string()
+            }
+            None => self.0.to_string(),
+        };
+        let json_response = ErrorResponse {
+            code,
+            error_msg,
+            request_id: telemetry::current_request_id(),
+        };
+        let mut response = (code.status(), axum::Json(json_response)).into_response();
 
-        match_error_response!(
-            self.0,
-            json_response,
-            NotFoundError => StatusCode::NOT_FOUND,
-            ValidateError => StatusCode::BAD_REQUEST,
-        )
+        // Rate limited clients are told when to retry
+        let retry_after = if let Some(err) = self.0.downcast_ref::<TooManyRequestsError>() {
+            err.status.insert_headers(response.headers_mut());
+            Some(err.retry_after)
+        } else {
+            (code == ErrorCode::UpstreamRateLimited).then_some(UPSTREAM_RETRY_AFTER)
+        };
+        if let Some(retry_after) = retry_after
+            && let Ok(retry_after) = HeaderValue::from_str(&retry_after.to_string())
+        {
+            response.headers_mut().insert(RETRY_AFTER, retry_after);
+        }
+        response
     }
 }
 
@@ -56,9 +268,35 @@ where
 #[error("Not found, details: {0}")]
 pub struct NotFoundError(pub String);
 
+#[derive(Debug, Error)]
+#[error("Conflict: {0}")]
+pub struct ConflictError(pub String);
+
 #[derive(Debug, Error)]
 #[error("Validate error: {0}")]
 pub struct ValidateError(pub String);
 
+#[derive(Debug, Error)]
+#[error("Unauthorized: {0}")]
+pub struct UnauthorizedError(pub String);
+
+#[derive(Debug, Error)]
+#[error("Forbidden: {0}")]
+pub struct ForbiddenError(pub String);
+
+#[derive(Debug, Error)]
+#[error("Service unavailable: {0}")]
+pub struct UnavailableError(pub String);
+
+/// A rate limit or quota was exceeded, answered with `Retry-After` and `X-RateLimit-*` headers
+#[derive(Debug, Error)]
+#[error("Too many requests: {message}")]
+pub struct TooManyRequestsError {
+    pub message: String,
+    /// Seconds until the request may be retried
+    pub retry_after: u64,
+    pub status: RateLimitStatus,
+}
+
 /// Result type for the application, aliasing `std::result::Result` with `AppError`.
 pub type Result<T> = std::result::Result<T, AppError>;
@@ -1,14 +1,25 @@
-use super::error::Result;
-use alloy::providers::{DynProvider, Provider, ProviderBuilder};
-use alloy::sol_types::sol;
+use super::error::{self, Result, ValidateError};
+use alloy::primitives::{Address, B256, Bytes, U256, utils::format_units};
+use alloy::providers::bindings::IMulticall3::{Call3, aggregate3Call};
+use alloy::providers::{CallItem, DynProvider, MULTICALL3_ADDRESS, Provider, ProviderBuilder};
+use alloy::rpc::client::ClientBuilder;
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol_types::{Eip712Domain, SolCall, sol};
+use reqwest::Url;
+
+use crate::telemetry::RpcTelemetryLayer;
 
 /// The zero address in Ethereum, used to represent an native token.
 pub const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
 
 /// Sets up an Ethereum provider using the given RPC URL.
 pub async fn setup_provider(rpc_url: &str) -> Result<DynProvider> {
-    let rpc_url = rpc_url.parse()?;
-    let provider = ProviderBuilder::new().connect_http(rpc_url);
+    let rpc_url: Url = rpc_url.parse()?;
+    // Every call of the provider is measured and traced
+    let client = ClientBuilder::default()
+        .layer(RpcTelemetryLayer::new(&rpc_url))
+        .http(rpc_url);
+    let provider = ProviderBuilder::new().connect_client(client);
 
     let chain_id = provider.get_chain_id().await?;
     tracing::info!("Success connect to chain_id {} network", chain_id);
@@ -19,8 +30,202 @@ pub async fn setup_provider(rpc_url: &str) -> Result<DynProvider> {
 // Import the generated contract bindings for IERC20
 sol!(
     #[sol(rpc)]
+    #[derive(Debug)]
     IERC20,
     "abi/IERC20.json"
 );
 
 pub use IERC20::IERC20Instance;
+
+// Import the generated contract bindings for the EIP-2612 permit extension of ERC20
+// The domain is read from EIP-5267 `eip712Domain`, or the `version` getter of older tokens
+sol!(
+    #[allow(clippy::too_many_arguments)]
+    #[sol(rpc)]
+    interface IERC20Permit {
+        function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external;
+        function nonces(address owner) external view returns (uint256);
+        function DOMAIN_SEPARATOR() external view returns (bytes32);
+        function name() external view returns (string);
+        function version() external view returns (string);
+        function eip712Domain() external view returns (bytes1 fields, string name, string version, uint256 chainId, address verifyingContract, bytes32 salt, uint256[] extensions);
+    }
+);
+
+/// Version of the EIP-712 domain of tokens exposing neither `eip712Domain` nor `version`
+const DEFAULT_PERMIT_VERSION: &str = "1";
+
+// Multicall3 helper to read an account's native balance inside a batch
+sol!(
+    #[sol(rpc)]
+    interface IMulticall3 {
+        function getEthBalance(address addr) external view returns (uint256 balance);
+    }
+);
+
+/// Reads the balances of many (account, token) pairs in one Multicall3 `aggregate3` call.
+/// A `ZERO_ADDRESS` token reads the native balance. Results are in input order,
+/// a pair whose call reverted (e.g. the token is not an ERC20) yields `None`.
+pub async fn get_balances(
+    provider: &DynProvider,
+    pairs: &[(Address, Address)],
+    block_number: u64,
+) -> Result<Vec<Option<U256>>> {
+    if pairs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Both calls return a single uint256, so they share the `balanceOf` decoder
+    let calls = pairs.iter().map(|(account, token)| {
+        let (target, input) = if token.is_zero() {
+            let call = IMulticall3::getEthBalanceCall { addr: *account };
+            (MULTICALL3_ADDRESS, call.abi_encode())
+        } else {
+            let call = IERC20::balanceOfCall { account: *account };
+            (*token, call.abi_encode())
+        };
+        CallItem::<IERC20::balanceOfCall>::new(target, input.into()).allow_failure(true)
+    });
+
+    let results = provider
+        .multicall()
+        .dynamic::<IERC20::balanceOfCall>()
+        .extend_calls(calls)
+        .block(block_number.into())
+        .aggregate3()
+        .await?;
+
+    Ok(results.into_iter().map(|result| result.ok()).collect())
+}
+
+/// Runs encoded calls in one Multicall3 `aggregate3` call, for batches whose calls return
+/// different types. Results are in input order, a call that reverted yields `None`.
+///
+/// # Arguments
+/// * `provider` - Provider used to call Multicall3
+/// * `calls` - Target and calldata of every call
+pub async fn aggregate(
+    provider: &DynProvider,
+    calls: Vec<(Address, Bytes)>,
+) -> Result<Vec<Option<Bytes>>> {
+    if calls.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let calls = calls
+        .into_iter()
+        .map(|(target, call_data)| Call3 {
+            target,
+            allowFailure: true,
+            callData: call_data,
+        })
+        .collect();
+    let request = TransactionRequest::default()
+        .to(MULTICALL3_ADDRESS)
+        .input(aggregate3Call { calls }.abi_encode().into());
+    let data = provider.call(request).await?;
+    let results = aggregate3Call::abi_decode_returns(&data)?;
+
+    Ok(results
+        .into_iter()
+        .map(|result| result.success.then_some(result.returnData))
+        .collect())
+}
+
+/// Formats an amount in the token's smallest unit as a decimal string, e.g. `1.5`
+pub fn format_balance(balance: U256, decimals: u8) -> Result<String> {
+    let formatted = format_units(balance, decimals)?;
+    if !formatted.contains('.') {
+        return Ok(formatted);
+    }
+    Ok(formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string())
+}
+
+/// EIP-712 domain of a token's permits, and the next permit nonce of an owner
+#[derive(Debug, Clone)]
+pub struct PermitDomain {
+    pub domain: Eip712Domain,
+    /// `DOMAIN_SEPARATOR` of the token
+    pub domain_separator: B256,
+    pub nonce: U256,
+}
+
+/// Reads the EIP-712 domain of an EIP-2612 token and the permit nonce of `owner`
+/// The domain is rebuilt from the token's name and version, and must hash to its
+/// `DOMAIN_SEPARATOR`, otherwise a signature over it would be rejected by the token.
+///
+/// # Arguments
+/// * `provider` - Provider used to call the token
+/// * `chain_id` - Chain the token lives on
+/// * `token_address` - Address of the token
+/// * `owner` - Account signing the permit
+pub async fn get_permit_domain(
+    provider: &DynProvider,
+    chain_id: u64,
+    token_address: Address,
+    owner: Address,
+) -> Result<PermitDomain> {
+    let token = IERC20Permit::new(token_address, provider.clone());
+    let domain_separator_call = token.DOMAIN_SEPARATOR();
+    let nonces_call = token.nonces(owner);
+    let name_call = token.name();
+    let eip712_domain_call = token.eip712Domain();
+    let (domain_separator, nonce, name, eip712_domain) = tokio::join!(
+        domain_separator_call.call(),
+        nonces_call.call(),
+        name_call.call(),
+        eip712_domain_call.call(),
+    );
+    let (domain_separator, nonce, name) = match (domain_separator, nonce, name) {
+        (Ok(domain_separator), Ok(nonce), Ok(name)) => (domain_separator, nonce, name),
+        (domain_separator, nonce, name) => {
+            // Tokens without permits revert or return nothing, failures of the node propagate
+            let errors = [domain_separator.err(), nonce.err(), name.err()];
+            if let Some(err) = errors
+                .into_iter()
+                .flatten()
+                .find(|err| !error::is_contract_revert(err))
+            {
+                return Err(err.into());
+            }
+            return Err(ValidateError(format!(
+                "Token {} does not support EIP-2612 permits",
+                token_address
+            ))
+            .into());
+        }
+    };
+
+    let version = match eip712_domain {
+        Ok(eip712_domain) => eip712_domain.version,
+        Err(err) if !error::is_contract_revert(&err) => return Err(err.into()),
+        Err(_) => match token.version().call().await {
+            Ok(version) => version,
+            Err(err) if !error::is_contract_revert(&err) => return Err(err.into()),
+            Err(_) => DEFAULT_PERMIT_VERSION.to_string(),
+        },
+    };
+    let domain = Eip712Domain::new(
+        Some(name.into()),
+        Some(version.into()),
+        Some(U256::from(chain_id)),
+        Some(token_address),
+        None,
+    );
+    if domain.separator() != domain_separator {
+        return Err(ValidateError(format!(
+            "EIP-712 domain of token {} does not match its DOMAIN_SEPARATOR",
+            token_address
+        ))
+        .into());
+    }
+
+    Ok(PermitDomain {
+        domain,
+        domain_separator,
+        nonce,
+    })
+}
@@ -0,0 +1,570 @@
+// Account event hub multiplexing every WebSocket subscription over shared upstream log queries
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicU64, Ordering},
+};
+
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::Filter;
+use alloy::sol_types::SolEvent;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, watch};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::Result;
+use crate::eth::{self, IERC20};
+use crate::heads::{HeadEvent, HeadTracker};
+
+/// Configuration for the account event hub
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Number of recent blocks kept to correct reorgs
+    pub max_reorg_depth: u64,
+    /// Number of events a client may have queued before it is dropped
+    pub client_buffer: usize,
+    /// Maximum number of addresses, and of tokens, in one subscription
+    pub max_subscription_size: usize,
+    /// Maximum number of blocks scanned for logs after a gap or a reorg
+    pub max_log_range: u64,
+}
+
+/// The addresses and tokens a client is interested in
+/// With no tokens, transfers of every token and the native balance are pushed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Subscription {
+    pub addresses: HashSet<Address>,
+    pub tokens: HashSet<Address>,
+}
+
+impl Subscription {
+    /// Whether the transfer moves one of the tokens from or to one of the addresses
+    pub fn matches_transfer(&self, transfer: &TransferEvent) -> bool {
+        (self.tokens.is_empty() || self.tokens.contains(&transfer.token_address))
+            && (self.addresses.contains(&transfer.from) || self.addresses.contains(&transfer.to))
+    }
+
+    /// The (address, token) pairs whose balances are pushed, `Address::ZERO` is the native balance
+    pub fn balance_pairs(&self) -> Vec<(Address, Address)> {
+        let tokens = if self.tokens.is_empty() {
+            vec![Address::ZERO]
+        } else {
+            self.tokens.iter().copied().collect()
+        };
+
+        self.addresses
+            .iter()
+            .flat_map(|address| tokens.iter().map(move |token| (*address, *token)))
+            .collect()
+    }
+}
+
+/// An ERC20 transfer seen at the head
+/// `removed` is set when a reorg dropped a previously pushed transfer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TransferEvent {
+    pub token_address: Address,
+    pub from: Address,
+    pub to: Address,
+    pub value: String,
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub transaction_hash: Option<B256>,
+    pub log_index: Option<u64>,
+    pub removed: bool,
+}
+
+/// Events pushed to subscribed clients
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AccountEvent {
+    /// A balance changed, `previous_balance` is none for the first read
+    Balance {
+        address: Address,
+        token_address: Address,
+        block_number: u64,
+        balance: String,
+        previous_balance: Option<String>,
+    },
+    Transfer(TransferEvent),
+    /// Blocks above `fork_block` were replaced, removed transfers follow as corrections
+    Reorg { fork_block: u64, depth: u64 },
+}
+
+/// Recent canonical blocks and the transfers pushed for them
+/// Only blocks with a known hash are kept, which is enough to find the fork point of a reorg
+#[derive(Debug, Default)]
+pub struct ChainWindow {
+    blocks: BTreeMap<u64, WindowBlock>,
+    max_depth: u64,
+}
+
+#[derive(Debug)]
+struct WindowBlock {
+    hash: B256,
+    transfers: Vec<TransferEvent>,
+}
+
+impl ChainWindow {
+    /// Create a new `ChainWindow` keeping at most `max_depth` blocks below the head.
+    pub fn new(max_depth: u64) -> Self {
+        Self {
+            blocks: BTreeMap::new(),
+            max_depth,
+        }
+    }
+
+    /// Returns the highest known block
+    pub fn head(&self) -> Option<u64> {
+        self.blocks.last_key_value().map(|(number, _)| *number)
+    }
+
+    /// Returns the lowest known block
+    pub fn tail(&self) -> Option<u64> {
+        self.blocks.first_key_value().map(|(number, _)| *number)
+    }
+
+    /// Returns the hash recorded for a block
+    pub fn hash_at(&self, block_number: u64) -> Option<B256> {
+        self.blocks.get(&block_number).map(|block| block.hash)
+    }
+
+    /// Records a canonical block and the transfers pushed for it
+    pub fn insert(&mut self, block_number: u64, hash: B256, transfers: Vec<TransferEvent>) {
+        self.blocks.insert(block_number, WindowBlock { hash, transfers });
+
+        let head = self.head().unwrap_or(block_number);
+        self.blocks = self
+            .blocks
+            .split_off(&head.saturating_sub(self.max_depth));
+    }
+
+    /// Forgets every block above `fork_block`
+    /// Returns their transfers flagged as removed, oldest first
+    pub fn rewind(&mut self, fork_block: u64) -> Vec<TransferEvent> {
+        self.blocks
+            .split_off(&(fork_block + 1))
+            .into_values()
+            .flat_map(|block| block.transfers)
+            .map(|transfer| TransferEvent {
+                removed: true,
+                ..transfer
+            })
+            .collect()
+    }
+
+    /// Forgets every block
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+}
+
+struct Client {
+    subscription: Subscription,
+    sender: mpsc::Sender<AccountEvent>,
+    /// Balances last sent to the client, by (address, token)
+    balances: HashMap<(Address, Address), U256>,
+}
+
+/// Sends a client the balances of its pairs that changed since the ones last sent to it,
+/// only those it never got one of if `new_only` is set
+/// Returns false if the client can't keep up or is gone.
+fn send_balances(
+    id: u64,
+    client: &mut Client,
+    block_number: u64,
+    balances: &HashMap<(Address, Address), U256>,
+    new_only: bool,
+) -> bool {
+    for pair in client.subscription.balance_pairs() {
+        let Some(balance) = balances.get(&pair).copied() else {
+            continue;
+        };
+        let previous = client.balances.get(&pair).copied();
+        if previous == Some(balance) || (new_only && previous.is_some()) {
+            continue;
+        }
+
+        let event = AccountEvent::Balance {
+            address: pair.0,
+            token_address: pair.1,
+            block_number,
+            balance: balance.to_string(),
+            previous_balance: previous.map(|previous| previous.to_string()),
+        };
+        match client.sender.try_send(event) {
+            Ok(()) => {
+                client.balances.insert(pair, balance);
+            }
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                tracing::warn!("Dropping account event client {} that can't keep up", id);
+                return false;
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => return false,
+        }
+    }
+    true
+}
+
+/// AccountEventHub tracks every client subscription and serves all of them from the same
+/// `Transfer` log queries and a single balance Multicall per new head
+/// A client whose queue is full is dropped, so a slow client never stalls the others
+#[derive(Clone)]
+pub struct AccountEventHub {
+    clients: Arc<Mutex<HashMap<u64, Client>>>,
+    next_client_id: Arc<AtomicU64>,
+    client_count: Arc<watch::Sender<usize>>,
+    config: Config,
+}
+
+impl AccountEventHub {
+    /// Create a new instance of `AccountEventHub` with the provided configuration.
+    pub fn new(config: &Config) -> Self {
+        let (client_count, _) = watch::channel(0);
+        Self {
+            clients: Arc::new(Mutex::new(HashMap::new())),
+            next_client_id: Arc::new(AtomicU64::new(1)),
+            client_count: Arc::new(client_count),
+            config: config.clone(),
+        }
+    }
+
+    /// Returns the hub configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Registers a client with an empty subscription
+    /// Returns its id and the receiver of its events, which closes if the client is dropped
+    pub fn register(&self) -> (u64, mpsc::Receiver<AccountEvent>) {
+        let id = self.next_client_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel(self.config.client_buffer);
+
+        let mut clients = self.clients.lock().expect("clients lock poisoned");
+        clients.insert(
+            id,
+            Client {
+                subscription: Subscription::default(),
+                sender,
+                balances: HashMap::new(),
+            },
+        );
+        self.client_count.send_replace(clients.len());
+
+        (id, receiver)
+    }
+
+    /// Replaces the subscription of a client
+    /// Balances of the pairs it keeps are not sent again until they change
+    pub fn subscribe(&self, id: u64, subscription: Subscription) {
+        let mut clients = self.clients.lock().expect("clients lock poisoned");
+        if let Some(client) = clients.get_mut(&id) {
+            let pairs: HashSet<_> = subscription.balance_pairs().into_iter().collect();
+            client.balances.retain(|pair, _| pairs.contains(pair));
+            client.subscription = subscription;
+        }
+    }
+
+    /// Removes a client
+    pub fn unregister(&self, id: u64) {
+        let mut clients = self.clients.lock().expect("clients lock poisoned");
+        clients.remove(&id);
+        self.client_count.send_replace(clients.len());
+    }
+
+    /// Sends an event to every client it concerns, dropping clients that can't keep up
+    pub fn dispatch(&self, event: &AccountEvent) {
+        let mut clients = self.clients.lock().expect("clients lock poisoned");
+        clients.retain(|id, client| {
+            let concerned = match event {
+                AccountEvent::Balance {
+                    address,
+                    token_address,
+                    ..
+                } => client
+                    .subscription
+                    .balance_pairs()
+                    .contains(&(*address, *token_address)),
+                AccountEvent::Transfer(transfer) => client.subscription.matches_transfer(transfer),
+                AccountEvent::Reorg { .. } => !client.subscription.addresses.is_empty(),
+            };
+            if !concerned {
+                return true;
+            }
+
+            match client.sender.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    tracing::warn!("Dropping account event client {} that can't keep up", id);
+                    false
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            }
+        });
+        self.client_count.send_replace(clients.len());
+    }
+
+    /// Sends every client the balances of its pairs that differ from the ones last sent to it,
+    /// so a new subscriber gets the current balances even of pairs other clients follow
+    /// Clients that can't keep up are dropped.
+    pub fn dispatch_balances(
+        &self,
+        block_number: u64,
+        balances: &HashMap<(Address, Address), U256>,
+    ) {
+        let mut clients = self.clients.lock().expect("clients lock poisoned");
+        clients.retain(|id, client| send_balances(*id, client, block_number, balances, false));
+        self.client_count.send_replace(clients.len());
+    }
+
+    /// Sends a client the balances read when it subscribed, so it doesn't wait for the next head
+    /// Pairs it already got a balance of are skipped, that one is at least as recent
+    pub fn dispatch_initial_balances(
+        &self,
+        id: u64,
+        block_number: u64,
+        balances: &HashMap<(Address, Address), U256>,
+    ) {
+        let mut clients = self.clients.lock().expect("clients lock poisoned");
+        let Some(client) = clients.get_mut(&id) else {
+            return;
+        };
+        if !send_balances(id, client, block_number, balances, true) {
+            clients.remove(&id);
+            self.client_count.send_replace(clients.len());
+        }
+    }
+
+    /// Returns the union of every subscription
+    /// Tokens are empty if any client wants transfers of every token
+    fn merged_subscription(&self) -> Subscription {
+        let clients = self.clients.lock().expect("clients lock poisoned");
+        let mut merged = Subscription::default();
+        let mut any_token = false;
+        for client in clients.values() {
+            merged
+                .addresses
+                .extend(client.subscription.addresses.iter().copied());
+            any_token |= client.subscription.tokens.is_empty();
+            merged.tokens.extend(client.subscription.tokens.iter().copied());
+        }
+        if any_token {
+            merged.tokens.clear();
+        }
+        merged
+    }
+
+    /// Returns every (address, token) balance pair some client subscribed to
+    fn balance_pairs(&self) -> HashSet<(Address, Address)> {
+        let clients = self.clients.lock().expect("clients lock poisoned");
+        clients
+            .values()
+            .flat_map(|client| client.subscription.balance_pairs())
+            .collect()
+    }
+
+    /// Follows new heads until `shutdown` is cancelled, only while at least one client is connected
+    pub async fn run(
+        self,
+        heads: HeadTracker,
+        provider: DynProvider,
+        shutdown: CancellationToken,
+    ) {
+        let mut client_count = self.client_count.subscribe();
+        let mut window = ChainWindow::new(self.config.max_reorg_depth);
+
+        loop {
+            tokio::select! {
+                count = client_count.wait_for(|count| *count > 0) => if count.is_err() {
+                    return;
+                },
+                _ = shutdown.cancelled() => return,
+            }
+
+            let mut stream = Box::pin(heads.subscribe().take_until(shutdown.cancelled()));
+            while let Some(head) = stream.next().await {
+                if *client_count.borrow() == 0 {
+                    break;
+                }
+                if let Err(err) = self.process_head(&provider, &head, &mut window).await {
+                    tracing::error!("Failed to process head {}: {}", head.block_number, err);
+                }
+            }
+
+            if shutdown.is_cancelled() {
+                return;
+            }
+
+            // Nobody was listening, or we lagged behind, so the window can't be trusted anymore
+            window.clear();
+        }
+    }
+
+    /// Pushes the transfers and balance changes of a new head, correcting a reorg first if needed
+    async fn process_head(
+        &self,
+        provider: &DynProvider,
+        head: &HeadEvent,
+        window: &mut ChainWindow,
+    ) -> Result<()> {
+        let head_hash: B256 = head.block_hash.parse()?;
+        let parent_hash: B256 = head.parent_hash.parse()?;
+
+        // Find the highest known block that is still canonical
+        let fork_block = match (window.head(), window.tail()) {
+            (Some(known_head), Some(tail)) => {
+                let mut candidate = known_head.min(head.block_number.saturating_sub(1));
+                loop {
+                    if let Some(known) = window.hash_at(candidate) {
+                        let canonical = if candidate + 1 == head.block_number {
+                            Some(parent_hash)
+                        } else {
+                            self.canonical_hash(provider, candidate).await?
+                        };
+                        if canonical == Some(known) {
+                            break candidate;
+                        }
+                    }
+                    if candidate <= tail {
+                        break tail.saturating_sub(1);
+                    }
+                    candidate -= 1;
+                }
+            }
+            _ => head.block_number.saturating_sub(1),
+        };
+
+        let known_head = window.head();
+        let removed = window.rewind(fork_block);
+        if let Some(known_head) = known_head
+            && known_head > fork_block
+        {
+            tracing::warn!(
+                "Reorg of {} blocks above block {}",
+                known_head - fork_block,
+                fork_block
+            );
+            self.dispatch(&AccountEvent::Reorg {
+                fork_block,
+                depth: known_head - fork_block,
+            });
+            for transfer in removed {
+                self.dispatch(&AccountEvent::Transfer(transfer));
+            }
+        }
+
+        let subscription = self.merged_subscription();
+        if !subscription.addresses.is_empty() {
+            let from_block = (fork_block + 1).max(
+                head.block_number
+                    .saturating_sub(self.config.max_log_range.saturating_sub(1)),
+            );
+            let transfers = self
+                .fetch_transfers(provider, &subscription, from_block, head.block_number)
+                .await?;
+
+            let mut by_block: BTreeMap<u64, (B256, Vec<TransferEvent>)> = BTreeMap::new();
+            for transfer in transfers {
+                self.dispatch(&AccountEvent::Transfer(transfer.clone()));
+                by_block
+                    .entry(transfer.block_number)
+                    .or_insert_with(|| (transfer.block_hash, Vec::new()))
+                    .1
+                    .push(transfer);
+            }
+            for (block_number, (hash, transfers)) in by_block {
+                window.insert(block_number, hash, transfers);
+            }
+
+            self.push_balances(provider, head.block_number).await?;
+        }
+        if window.hash_at(head.block_number).is_none() {
+            window.insert(head.block_number, head_hash, Vec::new());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the canonical hash of a block
+    async fn canonical_hash(&self, provider: &DynProvider, block_number: u64) -> Result<Option<B256>> {
+        let block = provider
+            .get_block_by_number(BlockNumberOrTag::Number(block_number))
+            .await?;
+        Ok(block.map(|block| block.header.hash))
+    }
+
+    /// Reads every `Transfer` of the subscribed tokens from or to a subscribed address in a
+    /// block range, with one log query on the senders and one on the recipients
+    async fn fetch_transfers(
+        &self,
+        provider: &DynProvider,
+        subscription: &Subscription,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<TransferEvent>> {
+        let mut filter = Filter::new()
+            .from_block(from_block)
+            .to_block(to_block)
+            .event_signature(IERC20::Transfer::SIGNATURE_HASH);
+        if !subscription.tokens.is_empty() {
+            filter = filter.address(subscription.tokens.iter().copied().collect::<Vec<_>>());
+        }
+        let addresses: Vec<B256> = subscription
+            .addresses
+            .iter()
+            .map(|address| address.into_word())
+            .collect();
+        let sent_filter = filter.clone().topic1(addresses.clone());
+        let received_filter = filter.topic2(addresses);
+
+        let (sent, received) = tokio::try_join!(
+            provider.get_logs(&sent_filter),
+            provider.get_logs(&received_filter),
+        )?;
+        let mut transfers: Vec<TransferEvent> = sent
+            .iter()
+            .chain(received.iter())
+            .filter_map(|log| {
+                // Tokens with a non standard Transfer event can't be decoded, skip them
+                let decoded = log.log_decode::<IERC20::Transfer>().ok()?;
+                Some(TransferEvent {
+                    token_address: log.address(),
+                    from: decoded.inner.data.from,
+                    to: decoded.inner.data.to,
+                    value: decoded.inner.data.value.to_string(),
+                    block_number: log.block_number?,
+                    block_hash: log.block_hash?,
+                    transaction_hash: log.transaction_hash,
+                    log_index: log.log_index,
+                    removed: false,
+                })
+            })
+            .filter(|transfer| subscription.matches_transfer(transfer))
+            .collect();
+
+        // Transfers between two subscribed addresses are returned by both queries
+        transfers.sort_by_key(|transfer| (transfer.block_number, transfer.log_index));
+        transfers.dedup_by_key(|transfer| (transfer.block_hash, transfer.log_index));
+
+        Ok(transfers)
+    }
+
+    /// Reads every subscribed balance in one Multicall and pushes those each client
+    /// hasn't received yet
+    async fn push_balances(&self, provider: &DynProvider, block_number: u64) -> Result<()> {
+        let pairs: Vec<_> = self.balance_pairs().into_iter().collect();
+        let results = eth::get_balances(provider, &pairs, block_number).await?;
+
+        let balances = pairs
+            .into_iter()
+            .zip(results)
+            .filter_map(|(pair, balance)| Some((pair, balance?)))
+            .collect();
+        self.dispatch_balances(block_number, &balances);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+// Testnet faucet sending native ETH or MyToken from the hot wallet
+use std::net::IpAddr;
+
+use alloy::primitives::{Address, B256, U256};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
+
+use crate::cache::DistCache;
+use crate::error::{Result, TooManyRequestsError, UnavailableError};
+use crate::rate_limit::RateLimitStatus;
+use crate::shutdown::Shutdown;
+use crate::wallet::HotWallet;
+
+/// Cache key prefix of the cooldowns
+const COOLDOWN_KEY_PREFIX: &str = "faucet:cooldown";
+
+/// Purpose recorded with the transactions of the faucet
+const FAUCET_PURPOSE: &str = "faucet";
+
+/// Configuration for the faucet
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Wei sent per native ETH claim
+    pub eth_amount: U256,
+    /// MyToken sent per claim, in its smallest unit
+    pub my_token_amount: U256,
+    /// Native balance the wallet keeps, it pays the gas of every claim
+    pub min_eth_balance: U256,
+    /// MyToken balance the wallet keeps
+    pub min_my_token_balance: U256,
+    /// Seconds before an address can claim again
+    pub address_cooldown: u64,
+    /// Seconds before a client address can claim again
+    pub ip_cooldown: u64,
+    /// Claims waiting to be sent before new claims are rejected
+    pub queue_capacity: usize,
+}
+
+/// Asset handed out by the faucet
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FaucetAsset {
+    Eth,
+    MyToken,
+}
+
+/// A claim sent by the faucet, the transaction is pending
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claim {
+    pub tx_hash: B256,
+    pub amount: U256,
+}
+
+/// A claim waiting for the sender task
+struct Job {
+    to: Address,
+    asset: FaucetAsset,
+    reply: oneshot::Sender<Result<Claim>>,
+}
+
+/// Faucet queues claims to a single task sending them one after the other,
+/// so a burst of claims doesn't pile up on the nonce lock of the hot wallet
+#[derive(Clone)]
+pub struct Faucet {
+    sender: mpsc::Sender<Job>,
+}
+
+impl Faucet {
+    /// Create a new instance of `Faucet` and start the task sending its claims.
+    ///
+    /// # Arguments
+    /// * `wallet` - Hot wallet paying the claims
+    /// * `my_token_address` - Address of the MyToken contract
+    /// * `config` - Amounts, balance floors and queue size
+    /// * `shutdown` - Stops the task once the claim being sent is done
+    pub fn spawn(
+        wallet: HotWallet,
+        my_token_address: Address,
+        config: &Config,
+        shutdown: &Shutdown,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(config.queue_capacity);
+        shutdown.spawn(run(
+            wallet,
+            my_token_address,
+            config.clone(),
+            receiver,
+            shutdown.token(),
+        ));
+        Self { sender }
+    }
+
+    /// Queues a claim and waits until its transaction is sent
+    /// Fails with 503 Service Unavailable when the queue is full or the faucet is empty,
+    /// only ever before anything is sent. Other errors may come after the broadcast.
+    pub async fn claim(&self, to: Address, asset: FaucetAsset) -> Result<Claim> {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .try_send(Job { to, asset, reply })
+            .map_err(|_| UnavailableError("Faucet is busy, try again later".to_string()))?;
+
+        response
+            .await
+            .map_err(|_| UnavailableError("Faucet stopped".to_string()))?
+    }
+}
+
+/// Sends queued claims until every `Faucet` handle is dropped or `shutdown` is cancelled
+/// Claims still queued then fail with 503 Service Unavailable.
+async fn run(
+    wallet: HotWallet,
+    my_token_address: Address,
+    config: Config,
+    mut receiver: mpsc::Receiver<Job>,
+    shutdown: CancellationToken,
+) {
+    while let Some(Some(job)) = shutdown.run_until_cancelled(receiver.recv()).await {
+        let result = send_claim(&wallet, my_token_address, &config, job.to, job.asset).await;
+        if let Err(err) = &result {
+            tracing::error!("Failed to send faucet claim to {}: {}", job.to, err);
+        }
+        // The claimant may have given up waiting
+        let _ = job.reply.send(result);
+    }
+}
+
+/// Sends a claim if the wallet stays above its balance floors afterwards
+/// The native floor is kept by MyToken claims too, the wallet pays their gas.
+async fn send_claim(
+    wallet: &HotWallet,
+    my_token_address: Address,
+    config: &Config,
+    to: Address,
+    asset: FaucetAsset,
+) -> Result<Claim> {
+    let (token_address, amount) = match asset {
+        FaucetAsset::Eth => (None, config.eth_amount),
+        FaucetAsset::MyToken => (Some(my_token_address), config.my_token_amount),
+    };
+
+    let eth_needed = match asset {
+        FaucetAsset::Eth => config.min_eth_balance.saturating_add(amount),
+        FaucetAsset::MyToken => config.min_eth_balance,
+    };
+    if wallet.balance(None).await? < eth_needed {
+        return Err(UnavailableError("Faucet is empty".to_string()).into());
+    }
+    if let Some(token_address) = token_address
+        && wallet.balance(Some(token_address)).await?
+            < config.min_my_token_balance.saturating_add(amount)
+    {
+        return Err(UnavailableError("Faucet is empty".to_string()).into());
+    }
+
+    let tx_hash = match token_address {
+        Some(token_address) => {
+            wallet
+                .transfer_token(token_address, to, amount, FAUCET_PURPOSE)
+                .await?
+        }
+        None => wallet.send_eth(to, amount, FAUCET_PURPOSE).await?,
+    };
+    tracing::info!(
+        "Faucet sent {} of {:?} to {} in {}",
+        amount,
+        asset,
+        to,
+        tx_hash
+    );
+
+    Ok(Claim { tx_hash, amount })
+}
+
+/// Returns the cache keys of the cooldowns of a claim
+fn cooldown_keys(address: Address, ip: Option<IpAddr>) -> Vec<String> {
+    let mut keys = vec![format!("{}:address:{}", COOLDOWN_KEY_PREFIX, address)];
+    if let Some(ip) = ip {
+        keys.push(format!("{}:ip:{}", COOLDOWN_KEY_PREFIX, ip));
+    }
+    keys
+}
+
+/// Starts the cooldowns of an address and a client address
+/// Fails with 429 Too Many Requests if either is still cooling down from a previous claim,
+/// in which case no cooldown is started.
+///
+/// # Arguments
+/// * `cache` - Cache holding the cooldowns
+/// * `config` - Cooldown durations
+/// * `address` - Address receiving the claim
+/// * `ip` - Address of the client, none if unknown
+pub async fn start_cooldowns(
+    cache: &DistCache,
+    config: &Config,
+    address: Address,
+    ip: Option<IpAddr>,
+) -> Result<()> {
+    let keys = cooldown_keys(address, ip);
+    let cooldowns = [config.address_cooldown, config.ip_cooldown];
+
+    for (index, (key, cooldown)) in keys.iter().zip(cooldowns).enumerate() {
+        if cache.set_nx_ex(key, 1, cooldown).await? {
+            continue;
+        }
+        // Undo the cooldowns started for this claim
+        let mut conn = cache.get_conn().await?;
+        if index > 0 {
+            let _: () = conn.del(&keys[..index]).await?;
+        }
+        let retry_after: i64 = conn.ttl(key).await?;
+        let retry_after = retry_after.max(1) as u64;
+        let subject = if index == 0 { "address" } else { "client" };
+        return Err(TooManyRequestsError {
+            message: format!("Faucet already claimed by this {}", subject),
+            retry_after,
+            status: RateLimitStatus {
+                limit: 1,
+                remaining: 0,
+                reset: retry_after,
+            },
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Ends the cooldowns of a claim that could not be sent, so it can be retried
+pub async fn clear_cooldowns(
+    cache: &DistCache,
+    address: Address,
+    ip: Option<IpAddr>,
+) -> Result<()> {
+    let mut conn = cache.get_conn().await?;
+    let _: () = conn.del(cooldown_keys(address, ip)).await?;
+    Ok(())
+}
@@ -1,24 +1,44 @@
+use alloy::primitives::Address;
 use alloy::providers::Provider;
 use axum::{
     Json,
     extract::{Path, State},
 };
 use serde::Serialize;
+use utoipa::ToSchema;
 
-use crate::error::{Result, ValidateError};
+use crate::error::{ErrorResponse, Result, ValidateError};
 use crate::eth::ZERO_ADDRESS;
+use crate::openapi::ApiKeyErrors;
+use crate::prices;
 use crate::state::AppState;
 
-use super::utils;
+use super::{misc, utils};
 
 /// Response structure for account information
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AccountResponse {
     address: String,
+    /// Balance in wei
     balance: String,
+    /// USD price of one ether, none without a usable price
+    usd_price: Option<String>,
+    usd_value: Option<String>,
 }
 
 /// Handler for getting account information
+#[utoipa::path(
+    get,
+    path = "/{address}",
+    tag = "accounts",
+    params(("address" = String, Path, description = "0x-prefixed account address")),
+    responses(
+        (status = 200, body = AccountResponse),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
 pub async fn get_account_info(
     Path(address): Path<String>,
     State(state): State<AppState>,
@@ -28,20 +48,37 @@ pub async fn get_account_info(
         return Err(ValidateError("Invalid Ethereum address format".to_string()).into());
     }
 
-    // Get account balance
+    // Get account balance, pinned to the current block so it can be recorded in history
     let eth_address = address.parse()?;
+    let block_number = misc::get_current_block_number(&state).await?;
     let balance = state
         .eth_provider
         .get_balance(eth_address)
-        .await?
-        .to_string();
+        .number(block_number)
+        .await?;
+
+    // Value the balance in USD, the native asset has 18 decimals
+    let usd_price = state.prices.get_usd_price(Address::ZERO).await;
+    let usd_value = usd_price.and_then(|price| prices::usd_value(balance, 18, price));
 
     // Update database with current balance
+    let balance = balance.to_string();
     let balance_decimal = balance.parse()?;
     state
         .repo
-        .upsert_eth_account_balance(&address, ZERO_ADDRESS, balance_decimal)
+        .upsert_eth_account_balance(
+            state.chain_id,
+            &address,
+            ZERO_ADDRESS,
+            block_number,
+            balance_decimal,
+        )
         .await?;
 
-    Ok(Json(AccountResponse { address, balance }))
+    Ok(Json(AccountResponse {
+        address,
+        balance,
+        usd_price: usd_price.map(|price| price.to_string()),
+        usd_value: usd_value.map(|value| value.to_string()),
+    }))
 }
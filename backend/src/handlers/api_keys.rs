@@ -0,0 +1,377 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::api_keys;
+use crate::auth::AdminUser;
+use crate::config::CONFIG;
+use crate::db::{ApiKey, ApiKeyUpdate, ApiKeyUsage, NewApiKey};
+use crate::error::{ErrorResponse, NotFoundError, Result, ValidateError};
+use crate::state::AppState;
+
+use super::utils;
+
+/// Router groups an API key can be scoped to
+const SCOPES: [&str; 2] = ["public", "private"];
+
+/// Longest period of a usage export, in days
+const MAX_USAGE_DAYS: i64 = 366;
+
+/// Request body for creating an API key
+#[derive(Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    /// Team or partner the key is issued to
+    owner: String,
+    /// Router groups the key may call, every group by default
+    #[serde(default)]
+    scopes: Vec<String>,
+    /// `api_keys.default_rate_limit_per_second` by default
+    rate_limit_per_second: Option<i32>,
+    /// `api_keys.default_daily_quota` by default
+    daily_quota: Option<i64>,
+}
+
+/// Request body for updating an API key, omitted fields are kept
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateApiKeyRequest {
+    owner: Option<String>,
+    scopes: Option<Vec<String>>,
+    rate_limit_per_second: Option<i32>,
+    daily_quota: Option<i64>,
+    /// Set to false to revoke the key
+    active: Option<bool>,
+}
+
+/// Query parameters for list endpoints
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// Only return entries with a greater id
+    after_id: Option<i64>,
+    limit: Option<i64>,
+}
+
+/// Query parameters for the usage export
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UsageQuery {
+    /// First day, the first day of the current month by default
+    from: Option<NaiveDate>,
+    /// Last day, today by default
+    to: Option<NaiveDate>,
+    /// Only export the usage of this key
+    api_key_id: Option<i64>,
+}
+
+/// Response structure for an API key, the key itself is only returned on creation
+#[derive(Serialize, ToSchema)]
+pub struct ApiKeyResponse {
+    id: i64,
+    key_prefix: String,
+    owner: String,
+    scopes: Vec<String>,
+    rate_limit_per_second: i32,
+    daily_quota: i64,
+    active: bool,
+    created_at: DateTime<Utc>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(record: ApiKey) -> Self {
+        Self {
+            id: record.id,
+            key_prefix: record.key_prefix,
+            owner: record.owner,
+            scopes: record.scopes,
+            rate_limit_per_second: record.rate_limit_per_second,
+            daily_quota: record.daily_quota,
+            active: record.active,
+            created_at: record.created_at.and_utc(),
+        }
+    }
+}
+
+/// Response structure for a new API key
+#[derive(Serialize, ToSchema)]
+pub struct CreateApiKeyResponse {
+    /// The key, to be sent in the `X-API-Key` header, it can't be retrieved again
+    key: String,
+    #[serde(flatten)]
+    api_key: ApiKeyResponse,
+}
+
+/// Response structure for listing API keys
+#[derive(Serialize, ToSchema)]
+pub struct ListApiKeysResponse {
+    items: Vec<ApiKeyResponse>,
+}
+
+/// Response structure for the requests of an API key to a route on a day
+#[derive(Serialize, ToSchema)]
+pub struct ApiKeyUsageResponse {
+    api_key_id: i64,
+    owner: String,
+    route: String,
+    day: NaiveDate,
+    request_count: i64,
+}
+
+impl From<ApiKeyUsage> for ApiKeyUsageResponse {
+    fn from(record: ApiKeyUsage) -> Self {
+        Self {
+            api_key_id: record.api_key_id,
+            owner: record.owner,
+            route: record.route,
+            day: record.day,
+            request_count: record.request_count,
+        }
+    }
+}
+
+/// Response structure for the usage export
+#[derive(Serialize, ToSchema)]
+pub struct ApiKeyUsageExportResponse {
+    from: NaiveDate,
+    to: NaiveDate,
+    items: Vec<ApiKeyUsageResponse>,
+}
+
+/// Validates the owner, scopes and limits of an API key
+fn validate_api_key(
+    owner: Option<&str>,
+    scopes: Option<&[String]>,
+    rate_limit_per_second: Option<i32>,
+    daily_quota: Option<i64>,
+) -> Result<()> {
+    if owner.is_some_and(|owner| owner.trim().is_empty()) {
+        return Err(ValidateError("owner must not be empty".to_string()).into());
+    }
+    if let Some(scope) = scopes
+        .unwrap_or_default()
+        .iter()
+        .find(|scope| !SCOPES.contains(&scope.as_str()))
+    {
+        return Err(ValidateError(format!(
+            "Unknown scope {}, expected one of {}",
+            scope,
+            SCOPES.join(", ")
+        ))
+        .into());
+    }
+    if rate_limit_per_second.is_some_and(|limit| limit < 1) {
+        return Err(ValidateError("rate_limit_per_second must be positive".to_string()).into());
+    }
+    if daily_quota.is_some_and(|quota| quota < 1) {
+        return Err(ValidateError("daily_quota must be positive".to_string()).into());
+    }
+    Ok(())
+}
+
+/// Handler for creating an API key
+#[utoipa::path(
+    post,
+    path = "/",
+    tag = "api-keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, body = CreateApiKeyResponse),
+        (status = 400, description = "Invalid owner, scopes or limits", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn create_api_key(
+    _admin: AdminUser,
+    State(state): State<AppState>,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreateApiKeyResponse>)> {
+    validate_api_key(
+        Some(&request.owner),
+        Some(&request.scopes),
+        request.rate_limit_per_second,
+        request.daily_quota,
+    )?;
+
+    let key = api_keys::generate_key();
+    let key_hash = api_keys::hash_key(&key);
+    let record = state
+        .repo
+        .create_api_key(&NewApiKey {
+            key_hash: &key_hash,
+            key_prefix: api_keys::key_prefix(&key),
+            owner: request.owner.trim(),
+            scopes: &request.scopes,
+            rate_limit_per_second: request
+                .rate_limit_per_second
+                .unwrap_or(CONFIG.api_keys.default_rate_limit_per_second),
+            daily_quota: request
+                .daily_quota
+                .unwrap_or(CONFIG.api_keys.default_daily_quota),
+        })
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateApiKeyResponse {
+            key,
+            api_key: record.into(),
+        }),
+    ))
+}
+
+/// Handler for listing API keys
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "api-keys",
+    params(ListQuery),
+    responses(
+        (status = 200, body = ListApiKeysResponse),
+        (status = 400, description = "Invalid limit", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn list_api_keys(
+    _admin: AdminUser,
+    Query(query): Query<ListQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ListApiKeysResponse>> {
+    let limit = utils::validate_limit(query.limit)?;
+
+    let items = state
+        .repo
+        .list_api_keys(query.after_id.unwrap_or(0), limit)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(ListApiKeysResponse { items }))
+}
+
+/// Handler for getting an API key
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "api-keys",
+    params(("id" = i64, Path, description = "Id of the API key")),
+    responses(
+        (status = 200, body = ApiKeyResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "No such API key", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn get_api_key(
+    _admin: AdminUser,
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiKeyResponse>> {
+    let record = state
+        .repo
+        .get_api_key(id)
+        .await?
+        .ok_or_else(|| NotFoundError(format!("API key {} not found", id)))?;
+
+    Ok(Json(record.into()))
+}
+
+/// Handler for updating the owner, scopes or limits of an API key, or revoking it
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    tag = "api-keys",
+    params(("id" = i64, Path, description = "Id of the API key")),
+    request_body = UpdateApiKeyRequest,
+    responses(
+        (status = 200, body = ApiKeyResponse),
+        (status = 400, description = "Invalid owner, scopes or limits", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "No such API key", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn update_api_key(
+    _admin: AdminUser,
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    Json(request): Json<UpdateApiKeyRequest>,
+) -> Result<Json<ApiKeyResponse>> {
+    validate_api_key(
+        request.owner.as_deref(),
+        request.scopes.as_deref(),
+        request.rate_limit_per_second,
+        request.daily_quota,
+    )?;
+
+    let record = state
+        .repo
+        .update_api_key(
+            id,
+            &ApiKeyUpdate {
+                owner: request.owner.as_deref().map(str::trim),
+                scopes: request.scopes.as_deref(),
+                rate_limit_per_second: request.rate_limit_per_second,
+                daily_quota: request.daily_quota,
+                active: request.active,
+            },
+        )
+        .await?
+        .ok_or_else(|| NotFoundError(format!("API key {} not found", id)))?;
+
+    Ok(Json(record.into()))
+}
+
+/// Handler for exporting the usage of API keys per route and day, for billing
+/// Usage is buffered for up to `api_keys.usage_flush_interval` before it shows up
+#[utoipa::path(
+    get,
+    path = "/usage",
+    tag = "api-keys",
+    params(UsageQuery),
+    responses(
+        (status = 200, body = ApiKeyUsageExportResponse),
+        (status = 400, description = "Invalid period", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn get_api_key_usage(
+    _admin: AdminUser,
+    Query(query): Query<UsageQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiKeyUsageExportResponse>> {
+    let to = query.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = query.from.unwrap_or_else(|| to.with_day(1).unwrap());
+    if from > to {
+        return Err(ValidateError("from must not be after to".to_string()).into());
+    }
+    if (to - from).num_days() >= MAX_USAGE_DAYS {
+        return Err(ValidateError(format!(
+            "Usage can be exported for at most {} days",
+            MAX_USAGE_DAYS
+        ))
+        .into());
+    }
+
+    let items = state
+        .repo
+        .list_api_key_usage(from, to, query.api_key_id)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(ApiKeyUsageExportResponse { from, to, items }))
+}
@@ -0,0 +1,149 @@
+use alloy::hex;
+use axum::{Json, extract::State, http::StatusCode};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::{self, AuthenticatedUser};
+use crate::config::CONFIG;
+use crate::error::{ErrorResponse, Result, ValidateError};
+use crate::openapi::ApiKeyErrors;
+use crate::state::AppState;
+
+/// Response structure for a SIWE nonce
+#[derive(Serialize, ToSchema)]
+pub struct NonceResponse {
+    nonce: String,
+    /// Seconds the nonce can be used for
+    expires_in: u64,
+}
+
+/// Request body for signing in
+#[derive(Deserialize, ToSchema)]
+pub struct SignInRequest {
+    /// EIP-4361 message text that was signed
+    message: String,
+    /// 0x-prefixed hex signature of the message
+    signature: String,
+}
+
+/// Response structure for a new session
+#[derive(Serialize, ToSchema)]
+pub struct SignInResponse {
+    /// Bearer token for `/v1/private` endpoints
+    token: String,
+    address: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Response structure for the signed-in account
+#[derive(Serialize, ToSchema)]
+pub struct MeResponse {
+    address: String,
+}
+
+/// Handler for issuing a single use SIWE nonce
+#[utoipa::path(
+    get,
+    path = "/v1/public/auth/nonce",
+    tag = "auth",
+    responses((status = 200, body = NonceResponse), ApiKeyErrors),
+    security((), ("api_key" = [])),
+)]
+pub async fn get_nonce(State(state): State<AppState>) -> Result<Json<NonceResponse>> {
+    let nonce = auth::issue_nonce(&state.cache, CONFIG.auth.nonce_ttl).await?;
+
+    Ok(Json(NonceResponse {
+        nonce,
+        expires_in: CONFIG.auth.nonce_ttl,
+    }))
+}
+
+/// Handler for signing in with a signed SIWE message
+#[utoipa::path(
+    post,
+    path = "/v1/public/auth/verify",
+    tag = "auth",
+    request_body = SignInRequest,
+    responses(
+        (status = 200, body = SignInResponse),
+        (status = 400, description = "Invalid message or signature format", body = ErrorResponse),
+        ApiKeyErrors,
+        (
+            status = 401,
+            description = "Invalid API key, or the message or signature was rejected",
+            body = ErrorResponse,
+        ),
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn sign_in(
+    State(state): State<AppState>,
+    Json(request): Json<SignInRequest>,
+) -> Result<Json<SignInResponse>> {
+    let signature = hex::decode(&request.signature)
+        .map_err(|_| ValidateError("Invalid signature format".to_string()))?;
+
+    let session = auth::sign_in(
+        &state.cache,
+        &state.eth_provider,
+        &CONFIG.auth,
+        state.chain_id,
+        &request.message,
+        &signature,
+    )
+    .await?;
+
+    Ok(Json(SignInResponse {
+        token: session.token,
+        address: session.address.to_string(),
+        expires_at: session.expires_at,
+    }))
+}
+
+/// Handler for signing out, ends the current session
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    tag = "auth",
+    responses(
+        (status = 204, description = "The session ended"),
+        ApiKeyErrors,
+        (
+            status = 401,
+            description = "Invalid API key, or missing or expired session",
+            body = ErrorResponse,
+        ),
+    ),
+    security(("session" = []), ("session" = [], "api_key" = [])),
+)]
+pub async fn sign_out(
+    user: AuthenticatedUser,
+    State(state): State<AppState>,
+) -> Result<StatusCode> {
+    auth::delete_session(&state.cache, &user.token).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for getting the signed-in account
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "auth",
+    responses(
+        (status = 200, body = MeResponse),
+        ApiKeyErrors,
+        (
+            status = 401,
+            description = "Invalid API key, or missing or expired session",
+            body = ErrorResponse,
+        ),
+    ),
+    security(("session" = []), ("session" = [], "api_key" = [])),
+)]
+pub async fn get_me(user: AuthenticatedUser) -> Result<Json<MeResponse>> {
+    Ok(Json(MeResponse {
+        address: user.address.to_string(),
+    }))
+}
@@ -3,22 +3,52 @@ use axum::{
     extract::{Path, State},
 };
 use serde::Serialize;
+use utoipa::ToSchema;
 
-use crate::error::ValidateError;
+use crate::db::NewToken;
+use crate::error::{ErrorResponse, ValidateError};
+use crate::openapi::ApiKeyErrors;
 use crate::state::AppState;
+use crate::prices;
 use crate::{error::Result, eth::IERC20Instance};
 
-use super::utils;
+use super::{misc, utils};
 
 /// Response structure for ERC20 token balance information
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct Erc20TokenResponse {
     address: String,
     token_address: String,
+    /// Balance in the token's smallest unit
     balance: String,
+    /// Token metadata from the registry, none for unregistered tokens
+    symbol: Option<String>,
+    name: Option<String>,
+    decimals: Option<i16>,
+    logo_uri: Option<String>,
+    verified: bool,
+    /// USD price of one whole token, none without a usable price
+    usd_price: Option<String>,
+    /// None without a price or known decimals
+    usd_value: Option<String>,
 }
 
 /// Handler for getting ERC20 token balance
+#[utoipa::path(
+    get,
+    path = "/{address}/erc20/{token_address}",
+    tag = "accounts",
+    params(
+        ("address" = String, Path, description = "0x-prefixed account address"),
+        ("token_address" = String, Path, description = "0x-prefixed token contract address"),
+    ),
+    responses(
+        (status = 200, body = Erc20TokenResponse),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
 pub async fn get_account_erc20(
     Path((address, token_address)): Path<(String, String)>,
     State(state): State<AppState>,
@@ -35,24 +65,73 @@ pub async fn get_account_erc20(
     let token_address = token_address.parse()?;
     let address = address.parse()?;
 
-    // Get token balance
-    let contract = IERC20Instance::new(token_address, state.eth_provider);
-    let erc20_balance = contract.balanceOf(address).call().await?;
+    // Get token balance, pinned to the current block so it can be recorded in history
+    let block_number = misc::get_current_block_number(&state).await?;
+    let contract = IERC20Instance::new(token_address, state.eth_provider.clone());
+    let erc20_balance = contract
+        .balanceOf(address)
+        .block(block_number.into())
+        .call()
+        .await?;
+
+    // Look up the token metadata, unregistered tokens have none
+    let token = state
+        .repo
+        .get_token(state.chain_id, &token_address.to_string())
+        .await?;
+
+    // Value the balance in USD
+    let usd_price = state.prices.get_usd_price(token_address).await;
+    let decimals = token.as_ref().and_then(|token| token.decimals);
+    let usd_value = usd_price.zip(decimals).and_then(|(price, decimals)| {
+        prices::usd_value(erc20_balance, u8::try_from(decimals).ok()?, price)
+    });
+
+    // Stored balances reference the registry, so an unknown token is registered first,
+    // unverified and without metadata
+    if token.is_none() {
+        state
+            .repo
+            .upsert_token(&NewToken {
+                address: &token_address.to_string(),
+                chain_id: state.chain_id,
+                ..Default::default()
+            })
+            .await?;
+    }
 
     // Update database with current balance
     let erc20_balance_decimal = erc20_balance.to_string().parse()?;
     state
         .repo
         .upsert_eth_account_balance(
+            state.chain_id,
             &address.to_string(),
             &token_address.to_string(),
+            block_number,
             erc20_balance_decimal,
         )
         .await?;
 
-    Ok(Json(Erc20TokenResponse {
+    let mut response = Erc20TokenResponse {
         address: address.to_string(),
         token_address: token_address.to_string(),
         balance: erc20_balance.to_string(),
-    }))
+        symbol: None,
+        name: None,
+        decimals: None,
+        logo_uri: None,
+        verified: false,
+        usd_price: usd_price.map(|price| price.to_string()),
+        usd_value: usd_value.map(|value| value.to_string()),
+    };
+    if let Some(token) = token {
+        response.symbol = token.symbol;
+        response.name = token.name;
+        response.decimals = token.decimals;
+        response.logo_uri = token.logo_uri;
+        response.verified = token.verified;
+    }
+
+    Ok(Json(response))
 }
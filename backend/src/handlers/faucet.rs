@@ -0,0 +1,98 @@
+use alloy::primitives::Address;
+use axum::{Json, extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config::CONFIG;
+use crate::error::{ErrorResponse, Result, UnavailableError, ValidateError};
+use crate::faucet::{self, FaucetAsset};
+use crate::openapi::ApiKeyErrors;
+use crate::rate_limit::ClientIp;
+use crate::state::AppState;
+
+use super::utils;
+
+/// Request body for claiming from the faucet
+#[derive(Deserialize, ToSchema)]
+pub struct FaucetRequest {
+    /// Account receiving the funds
+    address: String,
+    /// Native ETH by default
+    #[serde(default = "default_asset")]
+    #[schema(default = "eth")]
+    asset: FaucetAsset,
+}
+
+fn default_asset() -> FaucetAsset {
+    FaucetAsset::Eth
+}
+
+/// Response structure for a faucet claim, the transaction is pending
+#[derive(Serialize, ToSchema)]
+pub struct FaucetResponse {
+    address: String,
+    asset: FaucetAsset,
+    /// Amount in the asset's smallest unit
+    amount: String,
+    tx_hash: String,
+}
+
+/// Handler for claiming native ETH or MyToken from the faucet
+/// Every address, and every client address, can claim once per cooldown
+#[utoipa::path(
+    post,
+    path = "/v1/public/eth/faucet",
+    tag = "transactions",
+    request_body = FaucetRequest,
+    responses(
+        (status = 202, description = "The transfer was sent", body = FaucetResponse),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        ApiKeyErrors,
+        (
+            status = 429,
+            description = "Rate limited, or the address or client claimed within the cooldown",
+            body = ErrorResponse,
+        ),
+        (status = 503, description = "No hot wallet is configured", body = ErrorResponse),
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn claim_faucet(
+    ClientIp(ip): ClientIp,
+    State(state): State<AppState>,
+    Json(request): Json<FaucetRequest>,
+) -> Result<(StatusCode, Json<FaucetResponse>)> {
+    if !utils::is_valid_ethereum_address(&request.address) {
+        return Err(ValidateError("Invalid Ethereum address format".to_string()).into());
+    }
+    let address: Address = request.address.parse()?;
+    let faucet = state
+        .faucet
+        .as_ref()
+        .ok_or_else(|| UnavailableError("Faucet is not configured".to_string()))?;
+
+    faucet::start_cooldowns(&state.cache, &CONFIG.faucet, address, ip).await?;
+    let claim = match faucet.claim(address, request.asset).await {
+        Ok(claim) => claim,
+        Err(err) => {
+            // A busy or empty faucet sent nothing, so the claim can be retried right away.
+            // Any other error may come after the broadcast, the cooldowns are kept.
+            if err.is::<UnavailableError>()
+                && let Err(err) = faucet::clear_cooldowns(&state.cache, address, ip).await
+            {
+                tracing::error!("Failed to clear faucet cooldowns of {}: {}", address, err);
+            }
+            return Err(err);
+        }
+    };
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(FaucetResponse {
+            address: address.to_string(),
+            asset: request.asset,
+            amount: claim.amount.to_string(),
+            tx_hash: claim.tx_hash.to_string(),
+        }),
+    ))
+}
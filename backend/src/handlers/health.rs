@@ -2,15 +2,42 @@ use alloy::providers::Provider;
 use axum::extract::State;
 use redis::AsyncCommands;
 
-use crate::error::Result;
+use crate::error::{ErrorResponse, Result, UnavailableError};
 use crate::state::AppState;
 
+/// Answers as long as the service is running, without checking its dependencies
+#[utoipa::path(
+    get,
+    path = "/ping",
+    tag = "system",
+    responses((status = 200, description = "The service is running")),
+)]
+pub async fn ping() -> Result<()> {
+    Ok(())
+}
+
 /// Performs a health check on all system components
 /// Checks:
+/// - Readiness, failing as soon as shutdown started
 /// - Database connection
 /// - Ethereum provider connection
 /// - Redis cache connection
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "system",
+    responses(
+        (status = 200, description = "Every dependency is reachable"),
+        (status = 500, description = "A dependency is unreachable", body = ErrorResponse),
+        (status = 503, description = "Shutting down", body = ErrorResponse),
+    ),
+)]
 pub async fn healthcheck(State(state): State<AppState>) -> Result<()> {
+    // Fail first on shutdown, so load balancers stop routing requests here
+    if !state.shutdown.is_ready() {
+        return Err(UnavailableError("Shutting down".to_string()).into());
+    }
+
     // Check database connection
     state.repo.ping().await?;
     tracing::debug!("Database health check passed");
@@ -0,0 +1,111 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::db::BalanceHistoryFilter;
+use crate::error::{ErrorResponse, Result, ValidateError};
+use crate::openapi::ApiKeyErrors;
+use crate::state::AppState;
+
+use super::utils;
+
+/// Query parameters for filtering balance history
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BalanceHistoryQuery {
+    /// Token contract address, `ZERO_ADDRESS` for the native balance
+    token: Option<String>,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+    /// RFC 3339 timestamp
+    from: Option<DateTime<Utc>>,
+    /// RFC 3339 timestamp
+    to: Option<DateTime<Utc>>,
+    limit: Option<i64>,
+}
+
+/// A single balance change
+#[derive(Serialize, ToSchema)]
+pub struct BalanceHistoryItem {
+    token_address: String,
+    block_number: i64,
+    balance: String,
+    observed_at: DateTime<Utc>,
+}
+
+/// Response structure for balance history
+#[derive(Serialize, ToSchema)]
+pub struct BalanceHistoryResponse {
+    address: String,
+    history: Vec<BalanceHistoryItem>,
+}
+
+/// Handler for listing the recorded balance changes of an account
+#[utoipa::path(
+    get,
+    path = "/{address}/balances/history",
+    tag = "accounts",
+    params(
+        ("address" = String, Path, description = "0x-prefixed account address"),
+        BalanceHistoryQuery,
+    ),
+    responses(
+        (status = 200, body = BalanceHistoryResponse),
+        (status = 400, description = "Invalid address, range or limit", body = ErrorResponse),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn get_balance_history(
+    Path(address): Path<String>,
+    Query(query): Query<BalanceHistoryQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<BalanceHistoryResponse>> {
+    // Validate the Ethereum addresses and ranges
+    if !utils::is_valid_ethereum_address(&address) {
+        return Err(ValidateError("Invalid Ethereum address format".to_string()).into());
+    }
+    if let Some(token) = &query.token
+        && !utils::is_valid_ethereum_address(token)
+    {
+        return Err(ValidateError("Invalid token address format".to_string()).into());
+    }
+    if let (Some(from_block), Some(to_block)) = (query.from_block, query.to_block)
+        && from_block > to_block
+    {
+        return Err(ValidateError("from_block must not exceed to_block".to_string()).into());
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to)
+        && from > to
+    {
+        return Err(ValidateError("from must not be after to".to_string()).into());
+    }
+    let limit = utils::validate_limit(query.limit)?;
+
+    let filter = BalanceHistoryFilter {
+        token_address: query.token,
+        from_block: query.from_block,
+        to_block: query.to_block,
+        from_time: query.from.map(|from| from.naive_utc()),
+        to_time: query.to.map(|to| to.naive_utc()),
+        limit,
+    };
+    let history = state
+        .repo
+        .get_balance_history(&address, &filter)
+        .await?
+        .into_iter()
+        .map(|record| BalanceHistoryItem {
+            token_address: record.token_address,
+            block_number: record.block_number,
+            balance: record.balance.to_string(),
+            observed_at: record.observed_at.and_utc(),
+        })
+        .collect();
+
+    Ok(Json(BalanceHistoryResponse { address, history }))
+}
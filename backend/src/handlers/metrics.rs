@@ -0,0 +1,29 @@
+use axum::{extract::State, http::header, response::IntoResponse};
+
+use crate::state::AppState;
+use crate::telemetry;
+
+/// Handler for the Prometheus scrape endpoint
+/// Renders every metric in the text exposition format, with the database pool
+/// connections read at scrape time
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "system",
+    responses((
+        status = 200,
+        description = "Prometheus text exposition format",
+        body = String,
+        content_type = "text/plain",
+    )),
+)]
+pub async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    state.repo.record_pool_metrics();
+
+    let prometheus = telemetry::install_metrics();
+    prometheus.run_upkeep();
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        prometheus.render(),
+    )
+}
@@ -3,24 +3,20 @@ use std::time::SystemTime;
 
 use alloy::{consensus::BlockHeader, providers::Provider};
 use anyhow::anyhow;
-use axum::{
-    Json,
-    extract::{ State},
-};
-use redis::AsyncCommands;
+use axum::{Json, extract::State};
 use rust_decimal::Decimal;
 use serde::Serialize;
+use utoipa::ToSchema;
 
 use crate::error::{NotFoundError, Result};
+use crate::openapi::ApiKeyErrors;
 use crate::state::AppState;
 
 use super::utils;
 
 /// Fetches the current block number from cache or provider
-async fn get_current_block_number(state: &AppState) -> Result<u64> {
-    let mut conn = state.cache.get_conn().await?;
-
-    match conn.get(utils::CURRENT_BLOCK_NUMBER_CACHE_KEY).await {
+pub(crate) async fn get_current_block_number(state: &AppState) -> Result<u64> {
+    match state.cache.get(utils::CURRENT_BLOCK_NUMBER_CACHE_KEY).await {
         Ok(Some(block_number)) => {
             tracing::info!("Using cached block number: {}", block_number);
             Ok(block_number)
@@ -80,10 +76,9 @@ async fn fetch_and_cache_block_number(state: &AppState) -> Result<u64> {
 
 /// Fetches the current gas price from cache or provider
 async fn get_gas_price(state: &AppState) -> Result<u128> {
-    let mut conn = state.cache.get_conn().await?;
-
-    match conn
-        .get::<_, Option<Decimal>>(utils::GAS_PRICE_CACHE_KEY)
+    match state
+        .cache
+        .get::<Decimal>(utils::GAS_PRICE_CACHE_KEY)
         .await
     {
         Ok(Some(cached_gas_price)) => {
@@ -120,23 +115,101 @@ async fn fetch_and_cache_gas_price(state: &AppState) -> Result<u128> {
     Ok(gas_price)
 }
 
+/// Fetches the current max priority fee per gas from cache or provider
+async fn get_max_priority_fee(state: &AppState) -> Result<u128> {
+    match state
+        .cache
+        .get::<Decimal>(utils::MAX_PRIORITY_FEE_CACHE_KEY)
+        .await
+    {
+        Ok(Some(cached_fee)) => {
+            tracing::info!("Using cached max priority fee");
+            Ok(u128::try_from(cached_fee)?)
+        }
+        Ok(None) => {
+            tracing::info!("Cached max priority fee not found, fetching from provider.");
+            fetch_and_cache_max_priority_fee(state).await
+        }
+        Err(e) => {
+            tracing::error!("Failed to get cached max priority fee: {}", e);
+            fetch_and_cache_max_priority_fee(state).await
+        }
+    }
+}
+
+/// Fetches the current max priority fee per gas from provider and caches it
+async fn fetch_and_cache_max_priority_fee(state: &AppState) -> Result<u128> {
+    let fee = state.eth_provider.get_max_priority_fee_per_gas().await?;
+
+    if let Err(err) = state
+        .cache
+        .set_ex(
+            utils::MAX_PRIORITY_FEE_CACHE_KEY,
+            rust_decimal::Decimal::from(fee),
+            utils::GAS_PRICE_TTL,
+        )
+        .await
+    {
+        tracing::error!("Failed to cache max priority fee: {}", err);
+    }
+
+    Ok(fee)
+}
+
+/// EIP-1559 fees of a transaction, in wei per gas
+#[derive(Debug, Clone, Copy)]
+pub struct Eip1559Fees {
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+/// Derives EIP-1559 fees from the current gas price and priority fee
+/// The gas price is about the base fee plus the priority fee. The max fee leaves room
+/// for the base fee to double, so the transaction stays valid for a few full blocks.
+pub(crate) async fn get_eip1559_fees(state: &AppState) -> Result<Eip1559Fees> {
+    let gas_price = get_gas_price(state).await?;
+    let max_priority_fee_per_gas = get_max_priority_fee(state).await?.min(gas_price);
+    let base_fee = gas_price - max_priority_fee_per_gas;
+
+    Ok(Eip1559Fees {
+        max_fee_per_gas: base_fee * 2 + max_priority_fee_per_gas,
+        max_priority_fee_per_gas,
+    })
+}
+
 /// Response structure for blockchain misc information
-#[derive(Serialize)]
+/// Fees are integers in wei per gas, which may exceed the safe integer range of JavaScript
+#[derive(Serialize, ToSchema)]
 pub struct BlockchainMiscResponse {
     current_block: u64,
+    #[schema(format = "uint128")]
     gas_price: u128,
+    #[schema(format = "uint128")]
+    max_fee_per_gas: u128,
+    #[schema(format = "uint128")]
+    max_priority_fee_per_gas: u128,
 }
 
 /// Handler for getting blockchain misc information
+#[utoipa::path(
+    get,
+    path = "/v1/public/eth/misc",
+    tag = "chain",
+    responses((status = 200, body = BlockchainMiscResponse), ApiKeyErrors),
+    security((), ("api_key" = [])),
+)]
 pub async fn get_blockchain_misc(
     State(state): State<AppState>,
 ) -> Result<Json<BlockchainMiscResponse>> {
     // Get current block number and gas price
     let block_number = get_current_block_number(&state).await?;
     let gas_price = get_gas_price(&state).await?;
+    let fees = get_eip1559_fees(&state).await?;
 
     Ok(Json(BlockchainMiscResponse {
         current_block: block_number,
         gas_price,
+        max_fee_per_gas: fees.max_fee_per_gas,
+        max_priority_fee_per_gas: fees.max_priority_fee_per_gas,
     }))
 }
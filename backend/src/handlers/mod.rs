@@ -1,6 +1,22 @@
 pub mod account;
+pub mod api_keys;
+pub mod auth;
 pub mod misc;
+pub mod permit;
+pub mod portfolio;
+pub mod price;
+pub mod signatures;
 pub mod erc20;
+pub mod faucet;
 pub mod health;
+pub mod history;
+pub mod metrics;
+pub mod stream;
+pub mod tokens;
+pub mod transactions;
+pub mod wallet;
+pub mod watchlist;
+pub mod webhooks;
+pub mod ws;
 
-mod utils;
+pub mod utils;
@@ -0,0 +1,195 @@
+use alloy::dyn_abi::TypedData;
+use alloy::hex;
+use alloy::primitives::U256;
+use axum::{Json, extract::State};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::{ErrorResponse, Result, ValidateError};
+use crate::eth;
+use crate::openapi::ApiKeyErrors;
+use crate::permit::{self, Permit};
+use crate::state::AppState;
+
+use super::utils;
+
+/// Seconds a permit stays valid when no deadline is given
+const DEFAULT_PERMIT_TTL: u64 = 3600;
+
+/// Request body for building the typed data of a permit
+#[derive(Deserialize, ToSchema)]
+pub struct PermitTypedDataRequest {
+    token: String,
+    /// Account signing the permit
+    owner: String,
+    /// Account allowed to spend the tokens
+    spender: String,
+    /// Allowance in the token's smallest unit, as a decimal string
+    value: String,
+    /// Unix timestamp, an hour from now by default
+    deadline: Option<u64>,
+}
+
+/// Request body for verifying a signed permit
+#[derive(Deserialize, ToSchema)]
+pub struct VerifyPermitRequest {
+    token: String,
+    owner: String,
+    spender: String,
+    value: String,
+    deadline: u64,
+    /// Nonce the permit was signed with, the current nonce of the owner by default
+    nonce: Option<String>,
+    /// 0x-prefixed hex signature
+    signature: String,
+}
+
+/// Response structure for the typed data of a permit
+#[derive(Serialize, ToSchema)]
+pub struct PermitTypedDataResponse {
+    token: String,
+    owner: String,
+    spender: String,
+    value: String,
+    nonce: String,
+    deadline: u64,
+    domain_separator: String,
+    /// Hash the wallet signs
+    hash: String,
+    /// EIP-712 typed data to pass to `eth_signTypedData_v4`
+    #[schema(value_type = Object)]
+    typed_data: TypedData,
+}
+
+/// Response structure for a verified permit
+#[derive(Serialize, ToSchema)]
+pub struct VerifyPermitResponse {
+    /// Whether the token would accept the permit now
+    valid: bool,
+    /// Whether the owner signed the permit
+    signature_valid: bool,
+    expired: bool,
+    /// Next permit nonce of the owner, a permit signed with another nonce is rejected
+    current_nonce: String,
+    owner: String,
+    /// Address recovered from the signature
+    recovered: Option<String>,
+    hash: String,
+    v: u8,
+    r: String,
+    s: String,
+    /// Calldata of the `permit` call submitting the permit
+    permit_calldata: String,
+}
+
+/// Handler for building the EIP-712 typed data of an EIP-2612 permit
+/// The domain and the owner's nonce are read from the token
+#[utoipa::path(
+    post,
+    path = "/v1/public/eth/permits/typed-data",
+    tag = "signatures",
+    request_body = PermitTypedDataRequest,
+    responses(
+        (status = 200, body = PermitTypedDataResponse),
+        (status = 400, description = "Invalid request, or the token doesn't support EIP-2612", body = ErrorResponse),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn get_permit_typed_data(
+    State(state): State<AppState>,
+    Json(request): Json<PermitTypedDataRequest>,
+) -> Result<Json<PermitTypedDataResponse>> {
+    let token_address = utils::parse_address("token", &request.token)?;
+    let owner = utils::parse_address("owner", &request.owner)?;
+    let spender = utils::parse_address("spender", &request.spender)?;
+    let value = utils::parse_amount("value", &request.value)?;
+    let deadline = request
+        .deadline
+        .unwrap_or_else(|| Utc::now().timestamp() as u64 + DEFAULT_PERMIT_TTL);
+
+    let permit_domain =
+        eth::get_permit_domain(&state.eth_provider, state.chain_id, token_address, owner).await?;
+    let permit = Permit {
+        owner,
+        spender,
+        value,
+        nonce: permit_domain.nonce,
+        deadline: U256::from(deadline),
+    };
+
+    Ok(Json(PermitTypedDataResponse {
+        token: token_address.to_string(),
+        owner: owner.to_string(),
+        spender: spender.to_string(),
+        value: value.to_string(),
+        nonce: permit_domain.nonce.to_string(),
+        deadline,
+        domain_separator: permit_domain.domain_separator.to_string(),
+        hash: permit::permit_signing_hash(&permit_domain.domain, &permit).to_string(),
+        typed_data: permit::permit_typed_data(&permit_domain.domain, &permit),
+    }))
+}
+
+/// Handler for verifying a signed EIP-2612 permit and splitting it into v/r/s
+/// The permit is checked against the token's domain, the owner's current nonce
+/// and its deadline, as the token would
+#[utoipa::path(
+    post,
+    path = "/v1/public/eth/permits/verify",
+    tag = "signatures",
+    request_body = VerifyPermitRequest,
+    responses(
+        (status = 200, body = VerifyPermitResponse),
+        (status = 400, description = "Invalid request, or the token doesn't support EIP-2612", body = ErrorResponse),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn verify_permit(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyPermitRequest>,
+) -> Result<Json<VerifyPermitResponse>> {
+    let token_address = utils::parse_address("token", &request.token)?;
+    let owner = utils::parse_address("owner", &request.owner)?;
+    let spender = utils::parse_address("spender", &request.spender)?;
+    let value = utils::parse_amount("value", &request.value)?;
+    let nonce = request
+        .nonce
+        .as_deref()
+        .map(|nonce| utils::parse_amount("nonce", nonce))
+        .transpose()?;
+    let signature = hex::decode(&request.signature)
+        .map_err(|_| ValidateError("Invalid signature format".to_string()))?;
+
+    let permit_domain =
+        eth::get_permit_domain(&state.eth_provider, state.chain_id, token_address, owner).await?;
+    let permit = Permit {
+        owner,
+        spender,
+        value,
+        nonce: nonce.unwrap_or(permit_domain.nonce),
+        deadline: U256::from(request.deadline),
+    };
+    let hash = permit::permit_signing_hash(&permit_domain.domain, &permit);
+    let decoded = permit::decode_permit_signature(&hash, &signature)?;
+
+    let signature_valid = decoded.recovered == Some(owner);
+    let expired = permit::is_expired(permit.deadline, Utc::now().timestamp() as u64);
+    let valid = signature_valid && !expired && permit.nonce == permit_domain.nonce;
+
+    Ok(Json(VerifyPermitResponse {
+        valid,
+        signature_valid,
+        expired,
+        current_nonce: permit_domain.nonce.to_string(),
+        owner: owner.to_string(),
+        recovered: decoded.recovered.map(|address| address.to_string()),
+        hash: hash.to_string(),
+        v: decoded.v,
+        r: decoded.r.to_string(),
+        s: decoded.s.to_string(),
+        permit_calldata: permit::encode_permit_call(&permit, &decoded).to_string(),
+    }))
+}
@@ -0,0 +1,271 @@
+use std::cmp::Ordering;
+
+use alloy::primitives::{Address, U256};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::config::CONFIG;
+use crate::db::{AccountHolding, Token};
+use crate::error::{ErrorResponse, Result, ValidateError};
+use crate::openapi::ApiKeyErrors;
+use crate::{eth, prices};
+use crate::state::AppState;
+
+use super::{misc, utils};
+
+/// Configuration for on-chain portfolio reads
+/// Separate from `watcher.batch_size`: a portfolio reads the whole registry for one
+/// account while a client waits, the watcher refreshes watched pairs in the background
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Maximum number of tokens read in one Multicall
+    pub batch_size: usize,
+}
+
+/// Where portfolio balances are read from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PortfolioSource {
+    /// Read every registered token from the chain
+    #[default]
+    Rpc,
+    /// Serve the last stored balances
+    Db,
+}
+
+/// Query parameters for the portfolio endpoint
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PortfolioQuery {
+    #[serde(default)]
+    source: PortfolioSource,
+}
+
+/// Response structure for a single holding
+#[derive(Serialize, ToSchema)]
+pub struct HoldingResponse {
+    token_address: String,
+    symbol: Option<String>,
+    name: Option<String>,
+    decimals: Option<i16>,
+    logo_uri: Option<String>,
+    verified: bool,
+    /// Balance in the token's smallest unit
+    balance: String,
+    /// Balance in whole tokens, none if the token decimals are unknown
+    formatted_balance: Option<String>,
+    /// Last time the balance was observed, only for stored balances
+    updated_at: Option<DateTime<Utc>>,
+    /// USD price of one whole token, none without a usable price
+    usd_price: Option<String>,
+    /// None without a price or known decimals
+    usd_value: Option<String>,
+}
+
+/// Response structure for an account portfolio
+#[derive(Serialize, ToSchema)]
+pub struct PortfolioResponse {
+    address: String,
+    source: PortfolioSource,
+    /// Block the balances were read at, only for on-chain reads
+    block_number: Option<u64>,
+    /// Sum of the holdings with a USD value
+    total_usd_value: String,
+    holdings: Vec<HoldingResponse>,
+}
+
+impl HoldingResponse {
+    fn from_token(token: Token, balance: U256) -> Self {
+        Self {
+            formatted_balance: format_balance(balance, token.decimals),
+            token_address: token.address,
+            symbol: token.symbol,
+            name: token.name,
+            decimals: token.decimals,
+            logo_uri: token.logo_uri,
+            verified: token.verified,
+            balance: balance.to_string(),
+            updated_at: None,
+            usd_price: None,
+            usd_value: None,
+        }
+    }
+
+    fn from_holding(record: AccountHolding) -> Self {
+        let balance = record.balance.to_string();
+        Self {
+            formatted_balance: balance
+                .parse()
+                .ok()
+                .and_then(|balance| format_balance(balance, record.decimals)),
+            token_address: record.token_address,
+            symbol: record.symbol,
+            name: record.name,
+            decimals: record.decimals,
+            logo_uri: record.logo_uri,
+            verified: record.verified,
+            balance,
+            updated_at: Some(record.updated_at.and_utc()),
+            usd_price: None,
+            usd_value: None,
+        }
+    }
+
+    /// Amount in whole tokens used for sorting, none if the decimals are unknown
+    fn amount(&self) -> Option<f64> {
+        self.formatted_balance.as_ref()?.parse().ok()
+    }
+
+    /// USD value used for sorting and the total
+    fn usd(&self) -> Option<Decimal> {
+        self.usd_value.as_ref()?.parse().ok()
+    }
+
+    /// Sets the USD price and value of the holding
+    async fn price(mut self, state: &AppState) -> Self {
+        let Ok(token_address) = self.token_address.parse::<Address>() else {
+            return self;
+        };
+        let Some(usd_price) = state.prices.get_usd_price(token_address).await else {
+            return self;
+        };
+
+        self.usd_value = self
+            .balance
+            .parse::<U256>()
+            .ok()
+            .zip(self.decimals.and_then(|decimals| u8::try_from(decimals).ok()))
+            .and_then(|(balance, decimals)| prices::usd_value(balance, decimals, usd_price))
+            .map(|value| value.to_string());
+        self.usd_price = Some(usd_price.to_string());
+        self
+    }
+}
+
+/// Orders values largest first, missing values last
+fn descending<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Formats a balance with the registered decimals of its token
+fn format_balance(balance: U256, decimals: Option<i16>) -> Option<String> {
+    let decimals = u8::try_from(decimals?).ok()?;
+    eth::format_balance(balance, decimals).ok()
+}
+
+/// Handler for getting the non-zero balances of an account across the token registry
+/// Holdings are sorted by USD value, largest first, then holdings without a price by amount
+#[utoipa::path(
+    get,
+    path = "/{address}/portfolio",
+    tag = "accounts",
+    params(
+        ("address" = String, Path, description = "0x-prefixed account address"),
+        PortfolioQuery,
+    ),
+    responses(
+        (status = 200, body = PortfolioResponse),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn get_account_portfolio(
+    Path(address): Path<String>,
+    Query(query): Query<PortfolioQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<PortfolioResponse>> {
+    // Validate the Ethereum address format
+    if !utils::is_valid_ethereum_address(&address) {
+        return Err(ValidateError("Invalid Ethereum address format".to_string()).into());
+    }
+    let account: Address = address.parse()?;
+
+    let chain_id = state.chain_id;
+    let (block_number, holdings) = match query.source {
+        PortfolioSource::Db => {
+            let stored = state.repo.get_account_holdings(chain_id, &address).await?;
+            (
+                None,
+                stored.into_iter().map(HoldingResponse::from_holding).collect(),
+            )
+        }
+        PortfolioSource::Rpc => {
+            let (block_number, holdings) = read_holdings(&state, chain_id, account).await?;
+            (Some(block_number), holdings)
+        }
+    };
+
+    let mut holdings =
+        futures::future::join_all(holdings.into_iter().map(|holding| holding.price(&state))).await;
+    holdings.sort_by(|a, b| {
+        descending(a.usd(), b.usd())
+            .then_with(|| descending(a.amount(), b.amount()))
+            .then_with(|| a.token_address.cmp(&b.token_address))
+    });
+    let total_usd_value: Decimal = holdings.iter().filter_map(HoldingResponse::usd).sum();
+
+    Ok(Json(PortfolioResponse {
+        address,
+        source: query.source,
+        block_number,
+        total_usd_value: total_usd_value.to_string(),
+        holdings,
+    }))
+}
+
+/// Reads the balances of an account in every registered token at the current block
+/// The registry is read `portfolio.batch_size` tokens at a time, one Multicall per page,
+/// nothing is stored
+async fn read_holdings(
+    state: &AppState,
+    chain_id: u64,
+    account: Address,
+) -> Result<(u64, Vec<HoldingResponse>)> {
+    let block_number = misc::get_current_block_number(state).await?;
+    let batch_size = CONFIG.portfolio.batch_size.max(1);
+
+    let mut tokens = Vec::new();
+    let mut balances = Vec::new();
+    loop {
+        let after = tokens.last().map(|token: &Token| token.address.clone());
+        let page = state
+            .repo
+            .list_tokens(chain_id, after.as_deref(), None, batch_size as i64)
+            .await?;
+        let pairs = page
+            .iter()
+            .map(|token| Ok((account, token.address.parse()?)))
+            .collect::<Result<Vec<(Address, Address)>>>()?;
+        balances.extend(eth::get_balances(&state.eth_provider, &pairs, block_number).await?);
+
+        let last_page = page.len() < batch_size;
+        tokens.extend(page);
+        if last_page {
+            break;
+        }
+    }
+
+    // Calls to tokens that aren't ERC20 contracts fail, they hold nothing
+    let holdings = tokens
+        .into_iter()
+        .zip(balances)
+        .filter_map(|(token, balance)| {
+            let balance = balance.filter(|balance| !balance.is_zero())?;
+            Some(HoldingResponse::from_token(token, balance))
+        })
+        .collect();
+
+    Ok((block_number, holdings))
+}
@@ -0,0 +1,117 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::dex::{PoolQuote, PriceQuote, Protocol};
+use crate::error::{ErrorResponse, NotFoundError, Result, ValidateError};
+use crate::openapi::ApiKeyErrors;
+use crate::state::AppState;
+
+use super::utils;
+
+/// Query parameters for the token price endpoint
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TokenPriceQuery {
+    /// TWAP window in seconds, `dex.twap_window` by default
+    twap_window: Option<u32>,
+}
+
+/// Response structure for a pool of a price route
+/// Prices and reserves are in whole tokens
+#[derive(Serialize, ToSchema)]
+pub struct PoolResponse {
+    pool_address: String,
+    protocol: Protocol,
+    fee: Option<u32>,
+    token_in: String,
+    token_out: String,
+    spot_price: String,
+    twap_price: Option<String>,
+    reserve_in: String,
+    reserve_out: String,
+}
+
+impl From<PoolQuote> for PoolResponse {
+    fn from(pool: PoolQuote) -> Self {
+        Self {
+            pool_address: pool.pool_address.to_string(),
+            protocol: pool.protocol,
+            fee: pool.fee,
+            token_in: pool.token_in.to_string(),
+            token_out: pool.token_out.to_string(),
+            spot_price: pool.spot_price.to_string(),
+            twap_price: pool.twap_price.map(|price| price.to_string()),
+            reserve_in: pool.reserve_in.to_string(),
+            reserve_out: pool.reserve_out.to_string(),
+        }
+    }
+}
+
+/// Response structure for a token price in USDC
+#[derive(Serialize, ToSchema)]
+pub struct TokenPriceResponse {
+    token_address: String,
+    quote_token: String,
+    spot_price: String,
+    twap_price: Option<String>,
+    twap_window: u32,
+    /// Liquidity of the thinnest pool of the route, in USDC
+    liquidity: Option<String>,
+    route: Vec<PoolResponse>,
+}
+
+impl From<PriceQuote> for TokenPriceResponse {
+    fn from(quote: PriceQuote) -> Self {
+        Self {
+            token_address: quote.token_address.to_string(),
+            quote_token: quote.quote_token.to_string(),
+            spot_price: quote.spot_price.to_string(),
+            twap_price: quote.twap_price.map(|price| price.to_string()),
+            twap_window: quote.twap_window,
+            liquidity: quote.liquidity.map(|liquidity| liquidity.to_string()),
+            route: quote.route.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Handler for getting the price of a token in USDC from Uniswap V2 and V3 pools
+#[utoipa::path(
+    get,
+    path = "/v1/public/eth/tokens/{token_address}/price",
+    tag = "chain",
+    params(
+        ("token_address" = String, Path, description = "0x-prefixed token contract address"),
+        TokenPriceQuery,
+    ),
+    responses(
+        (status = 200, body = TokenPriceResponse),
+        (status = 400, description = "Invalid address or TWAP window", body = ErrorResponse),
+        (status = 404, description = "No pool prices the token", body = ErrorResponse),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn get_token_price(
+    Path(token_address): Path<String>,
+    Query(query): Query<TokenPriceQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<TokenPriceResponse>> {
+    if !utils::is_valid_ethereum_address(&token_address) {
+        return Err(ValidateError("Invalid token address format".to_string()).into());
+    }
+    let twap_window = state.dex.validate_twap_window(query.twap_window)?;
+
+    let quote = state
+        .dex
+        .get_price(token_address.parse()?, twap_window)
+        .await?
+        .ok_or_else(|| {
+            NotFoundError(format!("No Uniswap pool found for token {}", token_address))
+        })?;
+
+    Ok(Json(quote.into()))
+}
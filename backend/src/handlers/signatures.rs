@@ -0,0 +1,111 @@
+use alloy::dyn_abi::TypedData;
+use alloy::hex;
+use alloy::primitives::{Address, B256, eip191_hash_message};
+use axum::{Json, extract::State};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::{ErrorResponse, Result, ValidateError};
+use crate::openapi::ApiKeyErrors;
+use crate::signatures::{self, VerificationMethod};
+use crate::state::AppState;
+
+use super::utils;
+
+/// Encoding of a signed message
+#[derive(Debug, Default, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageEncoding {
+    /// The message is signed as its UTF-8 bytes
+    #[default]
+    Utf8,
+    /// The message is a 0x-prefixed hex string of the signed bytes
+    Hex,
+}
+
+/// Request body for verifying a signature
+/// Exactly one of `message` and `typed_data` must be given
+#[derive(Deserialize, ToSchema)]
+pub struct VerifySignatureRequest {
+    /// Claimed signer
+    signer: String,
+    /// 0x-prefixed hex signature
+    signature: String,
+    /// Message signed with EIP-191 `personal_sign`
+    message: Option<String>,
+    #[serde(default)]
+    encoding: MessageEncoding,
+    /// EIP-712 typed data signed with `eth_signTypedData_v4`
+    #[schema(value_type = Option<Object>)]
+    typed_data: Option<TypedData>,
+}
+
+/// Response structure for a signature verification
+#[derive(Serialize, ToSchema)]
+pub struct VerifySignatureResponse {
+    valid: bool,
+    signer: String,
+    method: VerificationMethod,
+    /// Hash that was signed
+    hash: String,
+    /// Address recovered from the signature, only for EOAs
+    recovered: Option<String>,
+}
+
+/// Computes the hash a wallet signs for the message or typed data of a request
+fn signing_hash(request: &VerifySignatureRequest) -> Result<B256> {
+    match (&request.message, &request.typed_data) {
+        (Some(message), None) => match request.encoding {
+            MessageEncoding::Utf8 => Ok(eip191_hash_message(message)),
+            MessageEncoding::Hex => {
+                let bytes = hex::decode(message)
+                    .map_err(|_| ValidateError("Invalid hex message".to_string()))?;
+                Ok(eip191_hash_message(bytes))
+            }
+        },
+        (None, Some(typed_data)) => Ok(typed_data
+            .eip712_signing_hash()
+            .map_err(|err| ValidateError(format!("Invalid typed data: {}", err)))?),
+        _ => Err(
+            ValidateError("Exactly one of message and typed_data is required".to_string()).into(),
+        ),
+    }
+}
+
+/// Handler for verifying an EIP-191 or EIP-712 signature
+/// EOAs are verified by recovering the signer, contracts through EIP-1271 `isValidSignature`
+#[utoipa::path(
+    post,
+    path = "/v1/public/eth/signatures/verify",
+    tag = "signatures",
+    request_body = VerifySignatureRequest,
+    responses(
+        (status = 200, body = VerifySignatureResponse),
+        (status = 400, description = "Invalid signer, signature, message or typed data", body = ErrorResponse),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn verify_signature(
+    State(state): State<AppState>,
+    Json(request): Json<VerifySignatureRequest>,
+) -> Result<Json<VerifySignatureResponse>> {
+    if !utils::is_valid_ethereum_address(&request.signer) {
+        return Err(ValidateError("Invalid signer address format".to_string()).into());
+    }
+    let signer: Address = request.signer.parse()?;
+    let signature = hex::decode(&request.signature)
+        .map_err(|_| ValidateError("Invalid signature format".to_string()))?;
+    let hash = signing_hash(&request)?;
+
+    let verification =
+        signatures::verify_signature(&state.eth_provider, signer, hash, &signature).await?;
+
+    Ok(Json(VerifySignatureResponse {
+        valid: verification.valid,
+        signer: signer.to_string(),
+        method: verification.method,
+        hash: hash.to_string(),
+        recovered: verification.recovered.map(|address| address.to_string()),
+    }))
+}
@@ -0,0 +1,39 @@
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::{Stream, StreamExt};
+
+use crate::heads::HeadEvent;
+use crate::openapi::ApiKeyErrors;
+use crate::state::AppState;
+
+/// Handler for streaming new blocks, base fees and gas prices as Server-Sent Events
+/// Every event is named `head` and carries a JSON encoded `HeadEvent`
+/// The stream ends on shutdown, so it doesn't hold up draining.
+#[utoipa::path(
+    get,
+    path = "/v1/public/eth/stream/heads",
+    tag = "chain",
+    responses(
+        (
+            status = 200,
+            description = "Server-Sent Events named `head`, the data of each is a `HeadEvent`",
+            body = HeadEvent,
+            content_type = "text/event-stream",
+        ),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn stream_heads(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let events = state
+        .heads
+        .subscribe()
+        .take_until(state.shutdown.token().cancelled_owned())
+        .map(|head| Event::default().event("head").json_data(head));
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,319 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::AdminUser;
+use crate::db::{NewToken, Token};
+use crate::error::{ConflictError, ErrorResponse, NotFoundError, Result, ValidateError};
+use crate::eth::ZERO_ADDRESS;
+use crate::state::AppState;
+use crate::tokens::{self, TokenList};
+
+use super::utils;
+
+/// Request body for registering or updating a token
+/// Omitted fields keep their stored value
+#[derive(Deserialize, ToSchema)]
+pub struct TokenRequest {
+    symbol: Option<String>,
+    name: Option<String>,
+    decimals: Option<u8>,
+    logo_uri: Option<String>,
+    tags: Option<Vec<String>>,
+    verified: Option<bool>,
+}
+
+/// Request body for registering a token
+#[derive(Deserialize, ToSchema)]
+pub struct CreateTokenRequest {
+    address: String,
+    #[serde(flatten)]
+    token: TokenRequest,
+}
+
+/// Query parameters for listing tokens
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListTokensQuery {
+    /// Only return tokens with a greater address
+    after: Option<String>,
+    verified: Option<bool>,
+    limit: Option<i64>,
+}
+
+/// Query parameters for importing a token list
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportTokensQuery {
+    /// Verified flag of the imported tokens, true by default
+    verified: Option<bool>,
+}
+
+/// Response structure for a registered token
+#[derive(Serialize, ToSchema)]
+pub struct TokenResponse {
+    address: String,
+    chain_id: i64,
+    symbol: Option<String>,
+    name: Option<String>,
+    decimals: Option<i16>,
+    logo_uri: Option<String>,
+    tags: Vec<String>,
+    verified: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<Token> for TokenResponse {
+    fn from(record: Token) -> Self {
+        Self {
+            address: record.address,
+            chain_id: record.chain_id,
+            symbol: record.symbol,
+            name: record.name,
+            decimals: record.decimals,
+            logo_uri: record.logo_uri,
+            tags: record.tags,
+            verified: record.verified,
+            created_at: record.created_at.and_utc(),
+            updated_at: record.updated_at.and_utc(),
+        }
+    }
+}
+
+/// Response structure for listing tokens
+#[derive(Serialize, ToSchema)]
+pub struct ListTokensResponse {
+    items: Vec<TokenResponse>,
+}
+
+/// Response structure for a token-list import
+#[derive(Serialize, ToSchema)]
+pub struct ImportTokensResponse {
+    imported: usize,
+    skipped: usize,
+}
+
+/// Writes a token request to the registry of the connected chain
+async fn save_token(
+    state: &AppState,
+    chain_id: u64,
+    address: &str,
+    request: &TokenRequest,
+) -> Result<Token> {
+    state
+        .repo
+        .upsert_token(&NewToken {
+            address,
+            chain_id,
+            symbol: request.symbol.as_deref(),
+            name: request.name.as_deref(),
+            decimals: request.decimals.map(Into::into),
+            logo_uri: request.logo_uri.as_deref(),
+            tags: request.tags.as_deref(),
+            verified: request.verified,
+        })
+        .await
+}
+
+/// Handler for registering a token, an existing token is updated
+#[utoipa::path(
+    post,
+    path = "/",
+    tag = "tokens",
+    request_body = CreateTokenRequest,
+    responses(
+        (status = 201, body = TokenResponse),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn create_token(
+    _admin: AdminUser,
+    State(state): State<AppState>,
+    Json(request): Json<CreateTokenRequest>,
+) -> Result<(StatusCode, Json<TokenResponse>)> {
+    if !utils::is_valid_ethereum_address(&request.address) {
+        return Err(ValidateError("Invalid token address format".to_string()).into());
+    }
+
+    let record = save_token(&state, state.chain_id, &request.address, &request.token).await?;
+
+    Ok((StatusCode::CREATED, Json(record.into())))
+}
+
+/// Handler for listing registered tokens
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "tokens",
+    params(ListTokensQuery),
+    responses(
+        (status = 200, body = ListTokensResponse),
+        (status = 400, description = "Invalid cursor or limit", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn list_tokens(
+    _admin: AdminUser,
+    Query(query): Query<ListTokensQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ListTokensResponse>> {
+    let limit = utils::validate_limit(query.limit)?;
+    if let Some(after) = &query.after
+        && !utils::is_valid_ethereum_address(after)
+    {
+        return Err(ValidateError("Invalid token address format".to_string()).into());
+    }
+
+    let items = state
+        .repo
+        .list_tokens(state.chain_id, query.after.as_deref(), query.verified, limit)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(ListTokensResponse { items }))
+}
+
+/// Handler for getting a registered token
+#[utoipa::path(
+    get,
+    path = "/{address}",
+    tag = "tokens",
+    params(("address" = String, Path, description = "0x-prefixed token contract address")),
+    responses(
+        (status = 200, body = TokenResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "Unknown token", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn get_token(
+    _admin: AdminUser,
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<TokenResponse>> {
+    let record = state
+        .repo
+        .get_token(state.chain_id, &address)
+        .await?
+        .ok_or_else(|| NotFoundError(format!("Token {} not found", address)))?;
+
+    Ok(Json(record.into()))
+}
+
+/// Handler for updating a registered token
+#[utoipa::path(
+    patch,
+    path = "/{address}",
+    tag = "tokens",
+    params(("address" = String, Path, description = "0x-prefixed token contract address")),
+    request_body = TokenRequest,
+    responses(
+        (status = 200, body = TokenResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "Unknown token", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn update_token(
+    _admin: AdminUser,
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+    Json(request): Json<TokenRequest>,
+) -> Result<Json<TokenResponse>> {
+    if state.repo.get_token(state.chain_id, &address).await?.is_none() {
+        return Err(NotFoundError(format!("Token {} not found", address)).into());
+    }
+
+    let record = save_token(&state, state.chain_id, &address, &request).await?;
+
+    Ok(Json(record.into()))
+}
+
+/// Handler for removing a token from the registry
+/// The native asset and watched tokens can't be removed, stored balances of the token are kept
+#[utoipa::path(
+    delete,
+    path = "/{address}",
+    tag = "tokens",
+    params(("address" = String, Path, description = "0x-prefixed token contract address")),
+    responses(
+        (status = 204, description = "The token was removed"),
+        (status = 400, description = "The native asset can't be removed", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "Unknown token", body = ErrorResponse),
+        (status = 409, description = "The token is on the watchlist", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn delete_token(
+    _admin: AdminUser,
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+) -> Result<StatusCode> {
+    if address.eq_ignore_ascii_case(ZERO_ADDRESS) {
+        return Err(ValidateError("The native asset can't be removed".to_string()).into());
+    }
+    if state.repo.is_token_watched(&address).await? {
+        return Err(ConflictError(format!(
+            "Token {} is on the watchlist, remove its watched pairs first",
+            address
+        ))
+        .into());
+    }
+    if !state.repo.delete_token(state.chain_id, &address).await? {
+        return Err(NotFoundError(format!("Token {} not found", address)).into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for importing a token list in the Uniswap token-list format
+/// Only tokens of the connected chain are imported
+#[utoipa::path(
+    post,
+    path = "/import",
+    tag = "tokens",
+    params(ImportTokensQuery),
+    request_body = TokenList,
+    responses(
+        (status = 200, body = ImportTokensResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn import_tokens(
+    _admin: AdminUser,
+    Query(query): Query<ImportTokensQuery>,
+    State(state): State<AppState>,
+    Json(list): Json<TokenList>,
+) -> Result<Json<ImportTokensResponse>> {
+    let summary = tokens::import_token_list(
+        &state.repo,
+        &list,
+        state.chain_id,
+        query.verified.unwrap_or(true),
+    )
+    .await?;
+
+    Ok(Json(ImportTokensResponse {
+        imported: summary.imported,
+        skipped: summary.skipped,
+    }))
+}
@@ -0,0 +1,365 @@
+use alloy::dyn_abi::DynSolType;
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::state::StateOverride;
+use alloy::rpc::types::{TransactionInput, TransactionRequest};
+use alloy::sol_types::SolCall;
+use axum::{Json, extract::State};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::error::{self, AppError, ErrorResponse, Result, ValidateError};
+use crate::eth::IERC20;
+use crate::openapi::ApiKeyErrors;
+use crate::simulation::{self, BalanceSource};
+use crate::state::AppState;
+
+use super::{misc, utils};
+
+/// EIP-2718 type of EIP-1559 transactions
+const EIP1559_TX_TYPE: u8 = 2;
+
+/// What a built transaction does, amounts are decimal strings in the smallest unit
+#[derive(Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransactionIntent {
+    /// Send native ETH
+    NativeTransfer { to: String, amount: String },
+    /// Transfer an ERC20 token
+    Erc20Transfer {
+        token: String,
+        to: String,
+        amount: String,
+    },
+    /// Allow `spender` to transfer an ERC20 token of the sender
+    Erc20Approve {
+        token: String,
+        spender: String,
+        amount: String,
+    },
+    /// Call a contract with raw calldata
+    Call {
+        to: String,
+        /// 0x-prefixed hex calldata
+        data: String,
+        /// Wei sent with the call, 0 by default
+        value: Option<String>,
+    },
+}
+
+/// Request body for building a transaction
+#[derive(Deserialize, ToSchema)]
+pub struct BuildTransactionRequest {
+    /// Account sending the transaction
+    from: String,
+    #[serde(flatten)]
+    intent: TransactionIntent,
+}
+
+/// Response structure for a built transaction, quantities are decimal strings
+#[derive(Serialize, ToSchema)]
+pub struct BuildTransactionResponse {
+    chain_id: u64,
+    from: String,
+    to: String,
+    value: String,
+    data: String,
+    nonce: u64,
+    gas_limit: u64,
+    max_fee_per_gas: String,
+    max_priority_fee_per_gas: String,
+    /// The transaction as the parameter of `eth_sendTransaction`, quantities hex encoded
+    #[schema(value_type = Object)]
+    eth_send_transaction: TransactionRequest,
+}
+
+/// Request body for simulating a transaction
+#[derive(Deserialize, ToSchema)]
+pub struct SimulateTransactionRequest {
+    /// Account sending the transaction
+    from: String,
+    #[serde(flatten)]
+    intent: TransactionIntent,
+    /// Geth-style state overrides keyed by address, e.g. `{ "0x...": { "balance": "0x..." } }`
+    #[schema(value_type = Option<Object>)]
+    state_overrides: Option<StateOverride>,
+    /// ABI types of the return value, e.g. `(uint256,bool)`, `bool` for ERC20 intents
+    returns: Option<String>,
+    /// Additional ERC20 tokens whose balances are read when the node can't trace calls,
+    /// at most 1000
+    tokens: Option<Vec<String>>,
+}
+
+/// Balance change of a simulated transaction, amounts are decimal strings
+#[derive(Serialize, ToSchema)]
+pub struct BalanceChangeResponse {
+    address: String,
+    /// The zero address for the native balance
+    token_address: String,
+    before: Option<String>,
+    after: Option<String>,
+    delta: String,
+}
+
+/// Response structure for a simulated transaction
+#[derive(Serialize, ToSchema)]
+pub struct SimulateTransactionResponse {
+    success: bool,
+    from: String,
+    to: String,
+    value: String,
+    data: String,
+    /// Return data, or revert data of a failed call
+    return_data: String,
+    /// Return data decoded with `returns`
+    return_value: Option<Value>,
+    revert_reason: Option<String>,
+    gas_used: Option<u64>,
+    /// How balance changes were derived, none if the node could not provide them
+    balance_source: Option<BalanceSource>,
+    balance_changes: Vec<BalanceChangeResponse>,
+}
+
+/// Returns the recipient, value and calldata of an intent
+/// ERC20 calldata is encoded with the `IERC20` bindings
+fn encode_intent(intent: &TransactionIntent) -> Result<(Address, U256, Bytes)> {
+    match intent {
+        TransactionIntent::NativeTransfer { to, amount } => Ok((
+            utils::parse_address("to", to)?,
+            utils::parse_amount("amount", amount)?,
+            Bytes::new(),
+        )),
+        TransactionIntent::Erc20Transfer { token, to, amount } => {
+            let call = IERC20::transferCall {
+                to: utils::parse_address("to", to)?,
+                value: utils::parse_amount("amount", amount)?,
+            };
+            Ok((
+                utils::parse_address("token", token)?,
+                U256::ZERO,
+                call.abi_encode().into(),
+            ))
+        }
+        TransactionIntent::Erc20Approve {
+            token,
+            spender,
+            amount,
+        } => {
+            let call = IERC20::approveCall {
+                spender: utils::parse_address("spender", spender)?,
+                value: utils::parse_amount("amount", amount)?,
+            };
+            Ok((
+                utils::parse_address("token", token)?,
+                U256::ZERO,
+                call.abi_encode().into(),
+            ))
+        }
+        TransactionIntent::Call { to, data, value } => {
+            let data = data
+                .parse::<Bytes>()
+                .map_err(|_| ValidateError("Invalid hex data".to_string()))?;
+            let value = match value {
+                Some(value) => utils::parse_amount("value", value)?,
+                None => U256::ZERO,
+            };
+            Ok((utils::parse_address("to", to)?, value, data))
+        }
+    }
+}
+
+/// Returns the accounts and ERC20 tokens whose balances an intent changes, besides the sender
+fn intent_balances(intent: &TransactionIntent) -> Result<(Vec<Address>, Vec<Address>)> {
+    match intent {
+        TransactionIntent::NativeTransfer { to, .. } | TransactionIntent::Call { to, .. } => {
+            Ok((vec![utils::parse_address("to", to)?], Vec::new()))
+        }
+        TransactionIntent::Erc20Transfer { token, to, .. } => Ok((
+            vec![utils::parse_address("to", to)?],
+            vec![utils::parse_address("token", token)?],
+        )),
+        TransactionIntent::Erc20Approve { token, .. } => {
+            Ok((Vec::new(), vec![utils::parse_address("token", token)?]))
+        }
+    }
+}
+
+/// Handler for building an unsigned EIP-1559 transaction for a wallet to sign
+/// The nonce is the pending transaction count of the sender, gas is estimated and
+/// fees are those of the misc endpoint. Fails with 400 when the estimation reverts.
+#[utoipa::path(
+    post,
+    path = "/v1/public/eth/transactions/build",
+    tag = "transactions",
+    request_body = BuildTransactionRequest,
+    responses(
+        (status = 200, body = BuildTransactionResponse),
+        (status = 400, description = "Invalid intent, or the estimation reverted", body = ErrorResponse),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn build_transaction(
+    State(state): State<AppState>,
+    Json(request): Json<BuildTransactionRequest>,
+) -> Result<Json<BuildTransactionResponse>> {
+    let from = utils::parse_address("from", &request.from)?;
+    let (to, value, data) = encode_intent(&request.intent)?;
+
+    let chain_id = state.chain_id;
+    let nonce = state
+        .eth_provider
+        .get_transaction_count(from)
+        .pending()
+        .await?;
+    let fees = misc::get_eip1559_fees(&state).await?;
+
+    let mut tx = TransactionRequest::default()
+        .from(from)
+        .to(to)
+        .value(value)
+        .input(TransactionInput::both(data.clone()))
+        .transaction_type(EIP1559_TX_TYPE)
+        .with_chain_id(chain_id)
+        .with_nonce(nonce)
+        .with_max_fee_per_gas(fees.max_fee_per_gas)
+        .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+    let gas_limit = state
+        .eth_provider
+        .estimate_gas(tx.clone())
+        .await
+        .map_err(|err| {
+            // A reverting intent is the caller's, failures of the node are not
+            if !error::is_revert(&err) {
+                return err.into();
+            }
+            AppError::from(ValidateError(format!("Gas estimation failed: {}", err)))
+        })?;
+    tx.set_gas_limit(gas_limit);
+
+    Ok(Json(BuildTransactionResponse {
+        chain_id,
+        from: from.to_string(),
+        to: to.to_string(),
+        value: value.to_string(),
+        data: data.to_string(),
+        nonce,
+        gas_limit,
+        max_fee_per_gas: fees.max_fee_per_gas.to_string(),
+        max_priority_fee_per_gas: fees.max_priority_fee_per_gas.to_string(),
+        eth_send_transaction: tx,
+    }))
+}
+
+/// Handler for simulating a transaction before it is signed
+/// The call runs with `eth_call` on the latest block with the given state overrides.
+/// Balance changes come from `debug_traceCall` when the node supports it, otherwise from
+/// `balanceOf` reads of the sender, the recipient and the tokens of the intent.
+#[utoipa::path(
+    post,
+    path = "/v1/public/eth/simulate",
+    tag = "transactions",
+    request_body = SimulateTransactionRequest,
+    responses(
+        (status = 200, description = "The call ran, successfully or not", body = SimulateTransactionResponse),
+        (status = 400, description = "Invalid intent, overrides, return types or too many tokens", body = ErrorResponse),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn simulate_transaction(
+    State(state): State<AppState>,
+    Json(request): Json<SimulateTransactionRequest>,
+) -> Result<Json<SimulateTransactionResponse>> {
+    let from = utils::parse_address("from", &request.from)?;
+    let (to, value, data) = encode_intent(&request.intent)?;
+    let return_type = match (&request.returns, &request.intent) {
+        (Some(returns), _) => Some(simulation::parse_return_type(returns)?),
+        (
+            None,
+            TransactionIntent::Erc20Transfer { .. } | TransactionIntent::Erc20Approve { .. },
+        ) => Some(DynSolType::Bool),
+        (None, _) => None,
+    };
+
+    // Native balances are always read, tokens are those of the intent and the request
+    let (recipients, intent_tokens) = intent_balances(&request.intent)?;
+    let mut accounts = vec![from];
+    for account in recipients {
+        if !accounts.contains(&account) {
+            accounts.push(account);
+        }
+    }
+    let mut tokens = vec![Address::ZERO];
+    for token in intent_tokens {
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+    // Every token is read for every account, twice
+    let extra_tokens = request.tokens.as_deref().unwrap_or_default();
+    if extra_tokens.len() > utils::MAX_PAGE_LIMIT as usize {
+        return Err(ValidateError(format!(
+            "tokens must have at most {} items",
+            utils::MAX_PAGE_LIMIT
+        ))
+        .into());
+    }
+    for token in extra_tokens {
+        let token = utils::parse_address("tokens", token)?;
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+    let watched: Vec<_> = accounts
+        .iter()
+        .flat_map(|account| tokens.iter().map(|token| (*account, *token)))
+        .collect();
+
+    let tx = TransactionRequest::default()
+        .from(from)
+        .to(to)
+        .value(value)
+        .input(TransactionInput::both(data.clone()));
+    let simulation = simulation::simulate(
+        &state.eth_provider,
+        tx,
+        request.state_overrides.unwrap_or_default(),
+        &watched,
+    )
+    .await?;
+
+    let return_value = match &return_type {
+        Some(ty) if simulation.success => {
+            simulation::decode_return_value(ty, &simulation.return_data)
+        }
+        _ => None,
+    };
+    let balance_changes = simulation
+        .balance_changes
+        .iter()
+        .map(|change| BalanceChangeResponse {
+            address: change.address.to_string(),
+            token_address: change.token_address.to_string(),
+            before: change.before.map(|before| before.to_string()),
+            after: change.after.map(|after| after.to_string()),
+            delta: change.delta.to_string(),
+        })
+        .collect();
+
+    Ok(Json(SimulateTransactionResponse {
+        success: simulation.success,
+        from: from.to_string(),
+        to: to.to_string(),
+        value: value.to_string(),
+        data: data.to_string(),
+        return_data: simulation.return_data.to_string(),
+        return_value,
+        revert_reason: simulation.revert_reason,
+        gas_used: simulation.gas_used,
+        balance_source: simulation.balance_source,
+        balance_changes,
+    }))
+}
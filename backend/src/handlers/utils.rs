@@ -1,16 +1,49 @@
 // Utility module for common functions and constants
 
-use alloy::primitives::Address;
-use anyhow::Result;
+use alloy::primitives::{Address, U256};
+
+use crate::error::{Result, ValidateError};
 
 /// Cache keys and TTLs
 pub const CURRENT_BLOCK_NUMBER_CACHE_KEY: &str = "current_block:number";
 pub const GAS_PRICE_CACHE_KEY: &str = "gas_price";
+pub const MAX_PRIORITY_FEE_CACHE_KEY: &str = "max_priority_fee";
 pub const BLOCK_MINE_DURATION: u64 = 12;
 pub const GAS_PRICE_TTL: u64 = 1; // 1 second
 
+/// Pagination limits for list endpoints
+pub const DEFAULT_PAGE_LIMIT: i64 = 100;
+pub const MAX_PAGE_LIMIT: i64 = 1000;
+
 /// Validates an Ethereum address format
 /// Returns true if the address is valid, false otherwise
 pub fn is_valid_ethereum_address(address: &str) -> bool {
     address.starts_with("0x") && address.len() == 42
 }
+
+/// Parses an address field of a request body, e.g. `to`
+pub fn parse_address(field: &str, value: &str) -> Result<Address> {
+    if !is_valid_ethereum_address(value) {
+        return Err(ValidateError(format!("Invalid {} address format", field)).into());
+    }
+    value
+        .parse()
+        .map_err(|_| ValidateError(format!("Invalid {} address format", field)).into())
+}
+
+/// Parses an amount field of a request body, a decimal string in the smallest unit
+pub fn parse_amount(field: &str, value: &str) -> Result<U256> {
+    value
+        .parse()
+        .map_err(|_| ValidateError(format!("Invalid {}", field)).into())
+}
+
+/// Validates the `limit` query parameter of list endpoints
+/// Returns the default limit if none is given
+pub fn validate_limit(limit: Option<i64>) -> Result<i64> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+    if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+        return Err(ValidateError(format!("limit must be between 1 and {}", MAX_PAGE_LIMIT)).into());
+    }
+    Ok(limit)
+}
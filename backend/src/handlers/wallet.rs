@@ -0,0 +1,271 @@
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::Provider;
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::AdminUser;
+use crate::config::CONFIG;
+use crate::db::{OutgoingTransaction, OutgoingTransactionStatus};
+use crate::error::{ErrorResponse, Result, UnavailableError, ValidateError};
+use crate::state::AppState;
+use crate::wallet::HotWallet;
+
+use super::utils;
+
+/// Request body for minting MyToken
+#[derive(Deserialize, ToSchema)]
+pub struct MintRequest {
+    /// Account receiving the tokens
+    to: String,
+    /// Amount in the token's smallest unit, as a decimal string
+    amount: String,
+}
+
+/// Response structure for a mint, the transactions are pending
+#[derive(Serialize, ToSchema)]
+pub struct MintResponse {
+    wallet: String,
+    token_address: String,
+    to: String,
+    amount: String,
+    mint_tx_hash: String,
+    /// None when the tokens were minted to the wallet itself
+    transfer_tx_hash: Option<String>,
+}
+
+/// Status of a sent transaction
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    /// Not mined yet, or unknown to the node
+    Pending,
+    Success,
+    Reverted,
+}
+
+/// Response structure for the receipt of a transaction
+/// Receipt fields are none while the transaction is pending
+#[derive(Serialize, ToSchema)]
+pub struct TransactionReceiptResponse {
+    tx_hash: String,
+    status: TransactionStatus,
+    block_number: Option<u64>,
+    gas_used: Option<u64>,
+    effective_gas_price: Option<String>,
+}
+
+/// Query parameters for listing outgoing transactions
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListOutgoingTransactionsQuery {
+    /// Only return transactions with a smaller id
+    before_id: Option<i64>,
+    /// Only return transactions in this state
+    status: Option<OutgoingTransactionStatus>,
+    limit: Option<i64>,
+}
+
+/// Response structure for a transaction sent by the hot wallet
+#[derive(Serialize, ToSchema)]
+pub struct OutgoingTransactionResponse {
+    id: i64,
+    from: String,
+    chain_id: i64,
+    nonce: i64,
+    tx_hash: String,
+    purpose: String,
+    to: Option<String>,
+    value: String,
+    gas_limit: i64,
+    max_fee_per_gas: String,
+    max_priority_fee_per_gas: String,
+    status: OutgoingTransactionStatus,
+    /// Id of the resubmission that replaced the transaction
+    replaced_by: Option<i64>,
+    block_number: Option<i64>,
+    succeeded: Option<bool>,
+    /// Set when the nonce was given up on and needs an operator
+    stuck_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<OutgoingTransaction> for OutgoingTransactionResponse {
+    fn from(record: OutgoingTransaction) -> Self {
+        Self {
+            id: record.id,
+            from: record.from_address,
+            chain_id: record.chain_id,
+            nonce: record.nonce,
+            tx_hash: record.tx_hash,
+            purpose: record.purpose,
+            to: record.to_address,
+            value: record.value.to_string(),
+            gas_limit: record.gas_limit,
+            max_fee_per_gas: record.max_fee_per_gas.to_string(),
+            max_priority_fee_per_gas: record.max_priority_fee_per_gas.to_string(),
+            status: record.status,
+            replaced_by: record.replaced_by,
+            block_number: record.block_number,
+            succeeded: record.succeeded,
+            stuck_at: record.stuck_at.map(|stuck_at| stuck_at.and_utc()),
+            created_at: record.created_at.and_utc(),
+            updated_at: record.updated_at.and_utc(),
+        }
+    }
+}
+
+/// Response structure for listing outgoing transactions
+#[derive(Serialize, ToSchema)]
+pub struct ListOutgoingTransactionsResponse {
+    items: Vec<OutgoingTransactionResponse>,
+}
+
+/// Returns the hot wallet, or fails if none is configured
+fn hot_wallet(state: &AppState) -> Result<&HotWallet> {
+    state
+        .wallet
+        .as_ref()
+        .ok_or_else(|| UnavailableError("Hot wallet is not configured".to_string()).into())
+}
+
+/// Handler for minting MyToken to an account with the hot wallet
+/// Returns once the transactions are sent, their receipts are fetched separately
+#[utoipa::path(
+    post,
+    path = "/my-token/mint",
+    tag = "wallet",
+    request_body = MintRequest,
+    responses(
+        (status = 202, description = "The transactions were sent", body = MintResponse),
+        (status = 400, description = "Invalid address or amount", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 503, description = "No hot wallet is configured", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn mint_my_token(
+    admin: AdminUser,
+    State(state): State<AppState>,
+    Json(request): Json<MintRequest>,
+) -> Result<(StatusCode, Json<MintResponse>)> {
+    if !utils::is_valid_ethereum_address(&request.to) {
+        return Err(ValidateError("Invalid Ethereum address format".to_string()).into());
+    }
+    let to: Address = request.to.parse()?;
+    let amount = request
+        .amount
+        .parse::<U256>()
+        .ok()
+        .filter(|amount| !amount.is_zero())
+        .ok_or_else(|| ValidateError("Invalid amount".to_string()))?;
+    let wallet = hot_wallet(&state)?;
+    let token_address = CONFIG.wallet.my_token_address;
+
+    let transactions = wallet.mint_my_token(token_address, to, amount).await?;
+    tracing::info!(
+        "{} minted {} MyToken to {} in {}",
+        admin.address,
+        amount,
+        to,
+        transactions.mint_tx_hash
+    );
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(MintResponse {
+            wallet: wallet.address().to_string(),
+            token_address: token_address.to_string(),
+            to: to.to_string(),
+            amount: amount.to_string(),
+            mint_tx_hash: transactions.mint_tx_hash.to_string(),
+            transfer_tx_hash: transactions.transfer_tx_hash.map(|hash| hash.to_string()),
+        }),
+    ))
+}
+
+/// Handler for getting the receipt of a transaction
+#[utoipa::path(
+    get,
+    path = "/transactions/{tx_hash}",
+    tag = "wallet",
+    params(("tx_hash" = String, Path, description = "0x-prefixed transaction hash")),
+    responses(
+        (status = 200, body = TransactionReceiptResponse),
+        (status = 400, description = "Invalid transaction hash", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn get_transaction_receipt(
+    _admin: AdminUser,
+    Path(tx_hash): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<TransactionReceiptResponse>> {
+    let tx_hash: B256 = tx_hash
+        .parse()
+        .map_err(|_| ValidateError("Invalid transaction hash format".to_string()))?;
+
+    let receipt = state.eth_provider.get_transaction_receipt(tx_hash).await?;
+
+    Ok(Json(match receipt {
+        Some(receipt) => TransactionReceiptResponse {
+            tx_hash: tx_hash.to_string(),
+            status: if receipt.status() {
+                TransactionStatus::Success
+            } else {
+                TransactionStatus::Reverted
+            },
+            block_number: receipt.block_number,
+            gas_used: Some(receipt.gas_used),
+            effective_gas_price: Some(receipt.effective_gas_price.to_string()),
+        },
+        None => TransactionReceiptResponse {
+            tx_hash: tx_hash.to_string(),
+            status: TransactionStatus::Pending,
+            block_number: None,
+            gas_used: None,
+            effective_gas_price: None,
+        },
+    }))
+}
+
+/// Handler for listing the transactions sent by the hot wallet, newest first
+#[utoipa::path(
+    get,
+    path = "/transactions",
+    tag = "wallet",
+    params(ListOutgoingTransactionsQuery),
+    responses(
+        (status = 200, body = ListOutgoingTransactionsResponse),
+        (status = 400, description = "Invalid limit", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn list_outgoing_transactions(
+    _admin: AdminUser,
+    Query(query): Query<ListOutgoingTransactionsQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ListOutgoingTransactionsResponse>> {
+    let limit = utils::validate_limit(query.limit)?;
+
+    let items = state
+        .repo
+        .list_outgoing_transactions(query.before_id, query.status, limit)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(ListOutgoingTransactionsResponse { items }))
+}
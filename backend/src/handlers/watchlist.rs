@@ -0,0 +1,240 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::AdminUser;
+use crate::db::WatchedBalance;
+use crate::error::{ErrorResponse, NotFoundError, Result, ValidateError};
+use crate::eth::ZERO_ADDRESS;
+use crate::state::AppState;
+use crate::tokens;
+
+use super::utils;
+
+/// Request body for adding a pair to the watchlist
+#[derive(Deserialize, ToSchema)]
+pub struct CreateWatchedBalanceRequest {
+    address: String,
+    /// Token contract address, defaults to the native balance
+    token_address: Option<String>,
+    label: Option<String>,
+}
+
+/// Request body for updating a watched pair
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateWatchedBalanceRequest {
+    label: Option<String>,
+}
+
+/// Query parameters for listing the watchlist
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListWatchedBalancesQuery {
+    /// Only return entries with a greater id
+    after_id: Option<i64>,
+    limit: Option<i64>,
+}
+
+/// Response structure for a watched pair
+#[derive(Serialize, ToSchema)]
+pub struct WatchedBalanceResponse {
+    id: i64,
+    address: String,
+    token_address: String,
+    label: Option<String>,
+    created_at: DateTime<Utc>,
+    balance: Option<String>,
+    block_number: Option<i64>,
+    updated_at: Option<DateTime<Utc>>,
+    changed_at: Option<DateTime<Utc>>,
+}
+
+impl From<WatchedBalance> for WatchedBalanceResponse {
+    fn from(record: WatchedBalance) -> Self {
+        Self {
+            id: record.id,
+            address: record.address,
+            token_address: record.token_address,
+            label: record.label,
+            created_at: record.created_at.and_utc(),
+            balance: record.balance.map(|balance| balance.to_string()),
+            block_number: record.block_number,
+            updated_at: record.updated_at.map(|updated_at| updated_at.and_utc()),
+            changed_at: record.changed_at.map(|changed_at| changed_at.and_utc()),
+        }
+    }
+}
+
+/// Response structure for listing the watchlist
+#[derive(Serialize, ToSchema)]
+pub struct ListWatchedBalancesResponse {
+    items: Vec<WatchedBalanceResponse>,
+}
+
+/// Handler for adding a pair to the watchlist
+#[utoipa::path(
+    post,
+    path = "/",
+    tag = "watchlist",
+    request_body = CreateWatchedBalanceRequest,
+    responses(
+        (status = 201, body = WatchedBalanceResponse),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn create_watched_balance(
+    _admin: AdminUser,
+    State(state): State<AppState>,
+    Json(request): Json<CreateWatchedBalanceRequest>,
+) -> Result<(StatusCode, Json<WatchedBalanceResponse>)> {
+    // Validate Ethereum addresses
+    if !utils::is_valid_ethereum_address(&request.address) {
+        return Err(ValidateError("Invalid Ethereum address format".to_string()).into());
+    }
+    let token_address = request.token_address.as_deref().unwrap_or(ZERO_ADDRESS);
+    if !utils::is_valid_ethereum_address(token_address) {
+        return Err(ValidateError("Invalid token address format".to_string()).into());
+    }
+
+    // Register the token, so its stored balances show up with their metadata
+    tokens::ensure_registered(
+        &state.repo,
+        &state.eth_provider,
+        state.chain_id,
+        token_address.parse()?,
+    )
+    .await?;
+
+    let record = state
+        .repo
+        .add_watched_balance(&request.address, token_address, request.label.as_deref())
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(record.into())))
+}
+
+/// Handler for listing the watchlist
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "watchlist",
+    params(ListWatchedBalancesQuery),
+    responses(
+        (status = 200, body = ListWatchedBalancesResponse),
+        (status = 400, description = "Invalid limit", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn list_watched_balances(
+    _admin: AdminUser,
+    Query(query): Query<ListWatchedBalancesQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ListWatchedBalancesResponse>> {
+    let limit = utils::validate_limit(query.limit)?;
+
+    let items = state
+        .repo
+        .list_watched_balances(query.after_id.unwrap_or(0), limit)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(ListWatchedBalancesResponse { items }))
+}
+
+/// Handler for getting a watched pair
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "watchlist",
+    params(("id" = i64, Path, description = "Id of the entry")),
+    responses(
+        (status = 200, body = WatchedBalanceResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "No such entry", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn get_watched_balance(
+    _admin: AdminUser,
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<WatchedBalanceResponse>> {
+    let record = state
+        .repo
+        .get_watched_balance(id)
+        .await?
+        .ok_or_else(|| NotFoundError(format!("Watched balance {} not found", id)))?;
+
+    Ok(Json(record.into()))
+}
+
+/// Handler for updating the label of a watched pair
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    tag = "watchlist",
+    params(("id" = i64, Path, description = "Id of the entry")),
+    request_body = UpdateWatchedBalanceRequest,
+    responses(
+        (status = 200, body = WatchedBalanceResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "No such entry", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn update_watched_balance(
+    admin: AdminUser,
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    Json(request): Json<UpdateWatchedBalanceRequest>,
+) -> Result<Json<WatchedBalanceResponse>> {
+    if !state
+        .repo
+        .update_watched_balance_label(id, request.label.as_deref())
+        .await?
+    {
+        return Err(NotFoundError(format!("Watched balance {} not found", id)).into());
+    }
+
+    get_watched_balance(admin, Path(id), State(state)).await
+}
+
+/// Handler for removing a pair from the watchlist
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "watchlist",
+    params(("id" = i64, Path, description = "Id of the entry")),
+    responses(
+        (status = 204, description = "The entry was removed"),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "No such entry", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn delete_watched_balance(
+    _admin: AdminUser,
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<StatusCode> {
+    if !state.repo.delete_watched_balance(id).await? {
+        return Err(NotFoundError(format!("Watched balance {} not found", id)).into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
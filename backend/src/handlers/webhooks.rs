@@ -0,0 +1,369 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::AdminUser;
+use crate::db::{Webhook, WebhookDelivery};
+use crate::error::{ErrorResponse, NotFoundError, Result, ValidateError};
+use crate::state::AppState;
+
+use super::utils;
+
+/// Minimum length of a webhook signing secret
+const MIN_SECRET_LENGTH: usize = 16;
+
+/// Request body for registering a webhook
+#[derive(Deserialize, ToSchema)]
+pub struct CreateWebhookRequest {
+    url: String,
+    /// Key used to sign payloads with HMAC-SHA256
+    secret: String,
+    address: String,
+    /// Only fire for this token, every token and the native balance by default
+    token_address: Option<String>,
+    /// Minimum absolute balance change in the token's smallest unit, as a decimal string
+    threshold: Option<String>,
+}
+
+/// Request body for updating a webhook
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateWebhookRequest {
+    active: bool,
+}
+
+/// Query parameters for list endpoints
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// Only return entries with a greater id
+    after_id: Option<i64>,
+    limit: Option<i64>,
+}
+
+/// Response structure for a webhook, the secret is never returned
+#[derive(Serialize, ToSchema)]
+pub struct WebhookResponse {
+    id: i64,
+    url: String,
+    address: String,
+    token_address: Option<String>,
+    threshold: String,
+    active: bool,
+    created_at: DateTime<Utc>,
+}
+
+impl From<Webhook> for WebhookResponse {
+    fn from(record: Webhook) -> Self {
+        Self {
+            id: record.id,
+            url: record.url,
+            address: record.address,
+            token_address: record.token_address,
+            threshold: record.threshold.to_string(),
+            active: record.active,
+            created_at: record.created_at.and_utc(),
+        }
+    }
+}
+
+/// Response structure for listing webhooks
+#[derive(Serialize, ToSchema)]
+pub struct ListWebhooksResponse {
+    items: Vec<WebhookResponse>,
+}
+
+/// Response structure for a webhook delivery attempt
+#[derive(Serialize, ToSchema)]
+pub struct WebhookDeliveryResponse {
+    id: i64,
+    webhook_id: i64,
+    event_id: String,
+    payload: serde_json::Value,
+    attempt: i32,
+    status_code: Option<i32>,
+    error: Option<String>,
+    succeeded: bool,
+    created_at: DateTime<Utc>,
+}
+
+impl From<WebhookDelivery> for WebhookDeliveryResponse {
+    fn from(record: WebhookDelivery) -> Self {
+        Self {
+            id: record.id,
+            webhook_id: record.webhook_id,
+            event_id: record.event_id,
+            payload: record.payload,
+            attempt: record.attempt,
+            status_code: record.status_code,
+            error: record.error,
+            succeeded: record.succeeded,
+            created_at: record.created_at.and_utc(),
+        }
+    }
+}
+
+/// Response structure for listing webhook deliveries
+#[derive(Serialize, ToSchema)]
+pub struct ListWebhookDeliveriesResponse {
+    items: Vec<WebhookDeliveryResponse>,
+}
+
+/// Handler for registering a webhook
+#[utoipa::path(
+    post,
+    path = "/",
+    tag = "webhooks",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 201, body = WebhookResponse),
+        (status = 400, description = "Invalid url, secret, address or threshold", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn create_webhook(
+    _admin: AdminUser,
+    State(state): State<AppState>,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Result<(StatusCode, Json<WebhookResponse>)> {
+    // Validate the receiver, secret, addresses and threshold
+    let url: reqwest::Url = request
+        .url
+        .parse()
+        .map_err(|_| ValidateError("Invalid webhook url".to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ValidateError("Webhook url must use http or https".to_string()).into());
+    }
+    if request.secret.len() < MIN_SECRET_LENGTH {
+        return Err(ValidateError(format!(
+            "secret must be at least {} characters",
+            MIN_SECRET_LENGTH
+        ))
+        .into());
+    }
+    if !utils::is_valid_ethereum_address(&request.address) {
+        return Err(ValidateError("Invalid Ethereum address format".to_string()).into());
+    }
+    if let Some(token_address) = &request.token_address
+        && !utils::is_valid_ethereum_address(token_address)
+    {
+        return Err(ValidateError("Invalid token address format".to_string()).into());
+    }
+    let threshold = match &request.threshold {
+        Some(threshold) => threshold
+            .parse::<Decimal>()
+            .ok()
+            .filter(|threshold| !threshold.is_sign_negative())
+            .ok_or_else(|| ValidateError("Invalid threshold".to_string()))?,
+        None => Decimal::ZERO,
+    };
+
+    let record = state
+        .repo
+        .create_webhook(
+            url.as_str(),
+            &request.secret,
+            &request.address,
+            request.token_address.as_deref(),
+            threshold,
+        )
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(record.into())))
+}
+
+/// Handler for listing webhooks
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "webhooks",
+    params(ListQuery),
+    responses(
+        (status = 200, body = ListWebhooksResponse),
+        (status = 400, description = "Invalid limit", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn list_webhooks(
+    _admin: AdminUser,
+    Query(query): Query<ListQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ListWebhooksResponse>> {
+    let limit = utils::validate_limit(query.limit)?;
+
+    let items = state
+        .repo
+        .list_webhooks(query.after_id.unwrap_or(0), limit)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(ListWebhooksResponse { items }))
+}
+
+/// Handler for getting a webhook
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "webhooks",
+    params(("id" = i64, Path, description = "Id of the webhook")),
+    responses(
+        (status = 200, body = WebhookResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "No such webhook", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn get_webhook(
+    _admin: AdminUser,
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<WebhookResponse>> {
+    let record = state
+        .repo
+        .get_webhook(id)
+        .await?
+        .ok_or_else(|| NotFoundError(format!("Webhook {} not found", id)))?;
+
+    Ok(Json(record.into()))
+}
+
+/// Handler for enabling or disabling a webhook
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    tag = "webhooks",
+    params(("id" = i64, Path, description = "Id of the webhook")),
+    request_body = UpdateWebhookRequest,
+    responses(
+        (status = 200, body = WebhookResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "No such webhook", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn update_webhook(
+    admin: AdminUser,
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    Json(request): Json<UpdateWebhookRequest>,
+) -> Result<Json<WebhookResponse>> {
+    if !state.repo.set_webhook_active(id, request.active).await? {
+        return Err(NotFoundError(format!("Webhook {} not found", id)).into());
+    }
+
+    get_webhook(admin, Path(id), State(state)).await
+}
+
+/// Handler for deleting a webhook and its deliveries
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "webhooks",
+    params(("id" = i64, Path, description = "Id of the webhook")),
+    responses(
+        (status = 204, description = "The webhook was removed"),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "No such webhook", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn delete_webhook(
+    _admin: AdminUser,
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<StatusCode> {
+    if !state.repo.delete_webhook(id).await? {
+        return Err(NotFoundError(format!("Webhook {} not found", id)).into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for listing the delivery attempts of a webhook, newest first
+#[utoipa::path(
+    get,
+    path = "/{id}/deliveries",
+    tag = "webhooks",
+    params(
+        ("id" = i64, Path, description = "Id of the webhook"),
+        ListQuery,
+    ),
+    responses(
+        (status = 200, body = ListWebhookDeliveriesResponse),
+        (status = 400, description = "Invalid limit", body = ErrorResponse),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn list_webhook_deliveries(
+    _admin: AdminUser,
+    Path(id): Path<i64>,
+    Query(query): Query<ListQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ListWebhookDeliveriesResponse>> {
+    let limit = utils::validate_limit(query.limit)?;
+
+    let items = state
+        .repo
+        .list_webhook_deliveries(id, limit)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(ListWebhookDeliveriesResponse { items }))
+}
+
+/// Handler for sending the payload of a past delivery again
+/// The replay runs in the background with the usual retries
+#[utoipa::path(
+    post,
+    path = "/deliveries/{id}/replay",
+    tag = "webhooks",
+    params(("id" = i64, Path, description = "Id of the delivery")),
+    responses(
+        (status = 202, description = "The payload is being delivered again"),
+        (status = 401, description = "Missing or expired session", body = ErrorResponse),
+        (status = 403, description = "The session is not an admin", body = ErrorResponse),
+        (status = 404, description = "No such delivery or webhook", body = ErrorResponse),
+    ),
+    security(("session" = [])),
+)]
+pub async fn replay_webhook_delivery(
+    _admin: AdminUser,
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<StatusCode> {
+    let delivery = state
+        .repo
+        .get_webhook_delivery(id)
+        .await?
+        .ok_or_else(|| NotFoundError(format!("Webhook delivery {} not found", id)))?;
+    let webhook = state
+        .repo
+        .get_webhook(delivery.webhook_id)
+        .await?
+        .ok_or_else(|| NotFoundError(format!("Webhook {} not found", delivery.webhook_id)))?;
+
+    tracing::info!("Replaying webhook delivery {}", delivery.id);
+    state
+        .notifier
+        .spawn_delivery(webhook, delivery.event_id, delivery.payload);
+
+    Ok(StatusCode::ACCEPTED)
+}
@@ -0,0 +1,212 @@
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use axum::{
+    extract::{
+        State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
+    },
+    response::Response,
+};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, ValidateError};
+use crate::eth;
+use crate::events::Subscription;
+use crate::openapi::ApiKeyErrors;
+use crate::state::AppState;
+
+use super::utils;
+
+/// Messages sent by clients
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// Replaces the current subscription
+    Subscribe {
+        addresses: Vec<String>,
+        /// Token contract addresses, every token if empty
+        #[serde(default)]
+        tokens: Vec<String>,
+    },
+    Unsubscribe,
+}
+
+/// Control messages sent to clients, next to the `AccountEvent`s
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ControlMessage {
+    Subscribed {
+        addresses: Vec<Address>,
+        tokens: Vec<Address>,
+    },
+    Unsubscribed,
+    Error {
+        error_msg: String,
+    },
+}
+
+/// Handler for the account events WebSocket
+/// Clients send `subscribe` messages and receive balance changes and ERC20 transfers
+/// of the subscribed addresses as they are seen at the head, with corrections on reorgs
+#[utoipa::path(
+    get,
+    path = "/v1/public/eth/stream/accounts",
+    tag = "accounts",
+    responses(
+        (status = 101, description = "Switched to the WebSocket protocol"),
+        ApiKeyErrors,
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn stream_accounts(ws: WebSocketUpgrade, State(state): State<AppState>) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, state))
+}
+
+async fn handle_socket(socket: WebSocket, state: AppState) {
+    let (client_id, mut events) = state.events.register();
+    let (mut sink, mut stream) = socket.split();
+
+    loop {
+        let outgoing = tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => vec![serde_json::to_string(&event)],
+                // The hub dropped us for being too slow
+                None => break,
+            },
+            message = stream.next() => match message {
+                Some(Ok(Message::Text(text))) => handle_message(&state, client_id, &text).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            // Close the socket on shutdown, so it doesn't hold up draining
+            _ = state.shutdown.cancelled() => {
+                let _ = sink
+                    .send(Message::Close(Some(CloseFrame {
+                        code: close_code::AWAY,
+                        reason: "Server shutting down".into(),
+                    })))
+                    .await;
+                break;
+            }
+        };
+
+        for message in outgoing {
+            let sent = match message {
+                Ok(text) => sink.send(Message::Text(text.into())).await,
+                Err(err) => {
+                    tracing::error!("Failed to encode account event: {}", err);
+                    continue;
+                }
+            };
+            if sent.is_err() {
+                state.events.unregister(client_id);
+                return;
+            }
+        }
+    }
+
+    state.events.unregister(client_id);
+}
+
+/// Applies a client message, returns the messages to send back
+async fn handle_message(
+    state: &AppState,
+    client_id: u64,
+    text: &str,
+) -> Vec<serde_json::Result<String>> {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(err) => {
+            return vec![serde_json::to_string(&ControlMessage::Error {
+                error_msg: format!("Invalid message: {}", err),
+            })];
+        }
+    };
+
+    match message {
+        ClientMessage::Subscribe { addresses, tokens } => {
+            let subscription = match parse_subscription(state, &addresses, &tokens) {
+                Ok(subscription) => subscription,
+                Err(err) => {
+                    return vec![serde_json::to_string(&ControlMessage::Error {
+                        error_msg: err.to_string(),
+                    })];
+                }
+            };
+            state.events.subscribe(client_id, subscription.clone());
+
+            // Start with the current balances, queued behind the reply, later events only
+            // carry changes
+            if let Err(err) = send_initial_balances(state, client_id, &subscription).await {
+                tracing::error!("Failed to read initial balances: {}", err);
+            }
+            vec![serde_json::to_string(&ControlMessage::Subscribed {
+                addresses: subscription.addresses.iter().copied().collect(),
+                tokens: subscription.tokens.iter().copied().collect(),
+            })]
+        }
+        ClientMessage::Unsubscribe => {
+            state.events.subscribe(client_id, Subscription::default());
+            vec![serde_json::to_string(&ControlMessage::Unsubscribed)]
+        }
+    }
+}
+
+/// Validates the addresses and tokens of a subscribe message
+fn parse_subscription(
+    state: &AppState,
+    addresses: &[String],
+    tokens: &[String],
+) -> Result<Subscription> {
+    let max_size = state.events.config().max_subscription_size;
+    if addresses.is_empty() || addresses.len() > max_size || tokens.len() > max_size {
+        return Err(ValidateError(format!(
+            "A subscription needs between 1 and {} addresses, and at most {} tokens",
+            max_size, max_size
+        ))
+        .into());
+    }
+
+    let parse = |values: &[String], message: &str| {
+        values
+            .iter()
+            .map(|value| {
+                if !utils::is_valid_ethereum_address(value) {
+                    return Err(ValidateError(message.to_string()).into());
+                }
+                Ok(value.parse::<Address>()?)
+            })
+            .collect::<Result<_>>()
+    };
+
+    Ok(Subscription {
+        addresses: parse(addresses, "Invalid Ethereum address format")?,
+        tokens: parse(tokens, "Invalid token address format")?,
+    })
+}
+
+/// Reads the current balances of a subscription in one Multicall and queues them for the client
+async fn send_initial_balances(
+    state: &AppState,
+    client_id: u64,
+    subscription: &Subscription,
+) -> Result<()> {
+    let block_number = match state.heads.latest() {
+        Some(head) => head.block_number,
+        None => state.eth_provider.get_block_number().await?,
+    };
+    let pairs = subscription.balance_pairs();
+    let results = eth::get_balances(&state.eth_provider, &pairs, block_number).await?;
+
+    let balances = pairs
+        .into_iter()
+        .zip(results)
+        .filter_map(|(pair, balance)| Some((pair, balance?)))
+        .collect();
+    state
+        .events
+        .dispatch_initial_balances(client_id, block_number, &balances);
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+// Shared head tracker that fans new blocks out to every stream subscriber
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::{DynProvider, Provider};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
+
+use crate::error::{NotFoundError, Result};
+
+/// Configuration for the head tracker
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// How often to poll the provider for a new block, in milliseconds
+    pub poll_interval_ms: u64,
+    /// Number of events a subscriber may fall behind before it is dropped
+    pub channel_capacity: usize,
+}
+
+/// A new block at the head of the chain
+/// Fee values are strings, since they may not fit in a JavaScript number
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct HeadEvent {
+    pub block_number: u64,
+    pub block_hash: String,
+    pub parent_hash: String,
+    pub timestamp: u64,
+    pub base_fee_per_gas: Option<String>,
+    pub gas_price: String,
+}
+
+/// HeadTracker polls the provider once for all subscribers and broadcasts every new head
+/// A subscriber that falls more than `channel_capacity` events behind is dropped,
+/// so a slow client never stalls the others
+#[derive(Clone)]
+pub struct HeadTracker {
+    sender: broadcast::Sender<HeadEvent>,
+    latest: Arc<watch::Sender<Option<HeadEvent>>>,
+}
+
+impl HeadTracker {
+    /// Create a new instance of `HeadTracker` with the provided configuration.
+    pub fn new(config: &Config) -> Self {
+        let (sender, _) = broadcast::channel(config.channel_capacity);
+        let (latest, _) = watch::channel(None);
+        Self {
+            sender,
+            latest: Arc::new(latest),
+        }
+    }
+
+    /// Returns the latest head seen by the tracker
+    pub fn latest(&self) -> Option<HeadEvent> {
+        self.latest.borrow().clone()
+    }
+
+    /// Publishes a head to every subscriber
+    /// Ignored if it is older than the latest one, or the same block
+    pub fn publish(&self, event: HeadEvent) {
+        let is_new = self.latest.send_if_modified(|latest| {
+            if latest.as_ref().is_some_and(|latest| {
+                latest.block_number > event.block_number || latest.block_hash == event.block_hash
+            }) {
+                return false;
+            }
+            *latest = Some(event.clone());
+            true
+        });
+
+        // Sending only fails when nobody is subscribed
+        if is_new {
+            let _ = self.sender.send(event);
+        }
+    }
+
+    /// Subscribes to new heads, starting with the latest one if any
+    /// The stream ends when the subscriber lags behind or the tracker stops
+    pub fn subscribe(&self) -> impl Stream<Item = HeadEvent> + Send + use<> {
+        let receiver = self.sender.subscribe();
+        let latest = self.latest();
+
+        futures::stream::unfold(
+            (receiver, latest),
+            |(mut receiver, pending)| async move {
+                if let Some(event) = pending {
+                    return Some((event, (receiver, None)));
+                }
+                match receiver.recv().await {
+                    Ok(event) => Some((event, (receiver, None))),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Dropping head subscriber lagging {} events behind", skipped);
+                        None
+                    }
+                    Err(broadcast::error::RecvError::Closed) => None,
+                }
+            },
+        )
+    }
+
+    /// Polls the provider for new heads until `shutdown` is cancelled
+    /// Polling pauses while nobody is subscribed, to avoid paying for unused RPC calls,
+    /// and the latest head is forgotten meanwhile
+    pub async fn run(self, provider: DynProvider, config: Config, shutdown: CancellationToken) {
+        let mut ticker = tokio::time::interval(Duration::from_millis(config.poll_interval_ms));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            if self.sender.receiver_count() == 0 {
+                // The latest head goes stale while paused, the next subscriber waits for a fresh one
+                self.latest.send_replace(None);
+                continue;
+            }
+
+            match self.poll(&provider).await {
+                Ok(Some(event)) => self.publish(event),
+                Ok(None) => {}
+                Err(err) => tracing::error!("Failed to poll head: {}", err),
+            }
+        }
+    }
+
+    /// Fetches the latest head, returns none if it was already published
+    /// A head at the same height with a different hash is new, it replaced the previous one
+    async fn poll(&self, provider: &DynProvider) -> Result<Option<HeadEvent>> {
+        let block = provider
+            .get_block_by_number(BlockNumberOrTag::Latest)
+            .await?
+            .ok_or_else(|| NotFoundError("Latest block not found".to_string()))?;
+        let block_hash = block.header.hash.to_string();
+        if self.latest().is_some_and(|latest| {
+            latest.block_number > block.header.number || latest.block_hash == block_hash
+        }) {
+            return Ok(None);
+        }
+
+        let gas_price = provider.get_gas_price().await?;
+
+        Ok(Some(HeadEvent {
+            block_number: block.header.number,
+            block_hash,
+            parent_hash: block.header.parent_hash.to_string(),
+            timestamp: block.header.timestamp,
+            base_fee_per_gas: block.header.base_fee_per_gas.map(|fee| fee.to_string()),
+            gas_price: gas_price.to_string(),
+        }))
+    }
+}
@@ -3,5 +3,27 @@ pub mod config;
 pub mod eth;
 pub mod handlers;
 pub mod state;
+pub mod api_keys;
+pub mod auth;
+pub mod backfill;
 pub mod cache;
+pub mod cli;
 pub mod db;
+pub mod dex;
+pub mod events;
+pub mod faucet;
+pub mod heads;
+pub mod openapi;
+pub mod permit;
+pub mod prices;
+pub mod rate_limit;
+pub mod routes;
+pub mod shutdown;
+pub mod signatures;
+pub mod simulation;
+pub mod telemetry;
+pub mod tokens;
+pub mod transactions;
+pub mod wallet;
+pub mod watcher;
+pub mod webhook;
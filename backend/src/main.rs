@@ -1,12 +1,16 @@
 // Main application entry point for the Ethereum account information service
-use axum::{Router, routing::get};
+use std::net::SocketAddr;
+
+use alloy::providers::Provider;
+use axum::{Router, middleware};
+use clap::Parser;
+use cli::{CacheCommand, Cli, Command, ServeArgs};
+use shutdown::Shutdown;
 use state::AppState;
 use tokio::net::TcpListener;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 // Module imports for error handling, configuration, and core functionality
 mod error;
-use error::Result;
 
 mod config;
 use config::CONFIG;
@@ -16,12 +20,35 @@ mod eth;
 mod handlers;
 mod state;
 
+mod api_keys;
+mod auth;
+mod backfill;
 mod cache;
+mod cli;
 pub mod db;
+mod dex;
+mod events;
+mod faucet;
+mod heads;
+mod openapi;
+mod permit;
+mod prices;
+mod rate_limit;
+mod routes;
+mod shutdown;
+mod signatures;
+mod simulation;
+mod telemetry;
+mod tokens;
+mod transactions;
+mod wallet;
+mod watcher;
+mod webhook;
 
 /// Sets up the application router with all necessary routes and middleware
 /// Initializes the Ethereum provider, database repository, and cache
-async fn setup_app() -> Router {
+/// Background tasks are spawned on `shutdown`, which stops them.
+async fn setup_app(args: &ServeArgs, shutdown: &Shutdown) -> (Router, AppState) {
     // Initialize Ethereum provider with configured RPC URL
     let eth_provider = eth::setup_provider(&CONFIG.eth_rpc_url)
         .await
@@ -32,54 +59,204 @@ async fn setup_app() -> Router {
         .await
         .expect("setup repository failed");
 
-    // Run database migrations
-    repo.run_migrations().await.expect("run migrations failed");
+    // Run database migrations, unless the deploy runs `migrate up` as its own step
+    if !args.skip_migrations {
+        repo.run_migrations().await.expect("run migrations failed");
+    }
+
+    // Register the native asset of the chain, balances at `ZERO_ADDRESS` refer to it
+    let chain_id = eth_provider
+        .get_chain_id()
+        .await
+        .expect("get chain_id failed");
+    tokens::register_native_token(&repo, chain_id)
+        .await
+        .expect("register native token failed");
 
     // Initialize distributed cache
     let dist_cache = cache::DistCache::new(&CONFIG.cache);
-    
+
+    // Initialize webhook notifier
+    let notifier =
+        webhook::WebhookNotifier::new(repo.clone(), &CONFIG.webhook, shutdown.clone())
+            .expect("setup webhook notifier failed");
+
+    // Initialize USD pricing
+    let prices = prices::PriceOracle::new(eth_provider.clone(), dist_cache.clone(), &CONFIG.prices)
+        .expect("setup price oracle failed");
+
+    // Initialize DEX pricing
+    let dex = dex::DexPricer::new(eth_provider.clone(), repo.clone(), chain_id, &CONFIG.dex)
+        .expect("setup dex pricer failed");
+
+    // Start tracking new heads for stream subscribers
+    let heads = heads::HeadTracker::new(&CONFIG.heads);
+    shutdown.spawn(heads.clone().run(
+        eth_provider.clone(),
+        CONFIG.heads.clone(),
+        shutdown.token(),
+    ));
+
+    // Start serving account event subscriptions from the head feed
+    let events = events::AccountEventHub::new(&CONFIG.events);
+    shutdown.spawn(
+        events
+            .clone()
+            .run(heads.clone(), eth_provider.clone(), shutdown.token()),
+    );
+
+    // Load the hot wallet, if a keystore is configured
+    let wallet = wallet::HotWallet::from_config(
+        eth_provider.clone(),
+        repo.clone(),
+        chain_id,
+        &CONFIG.wallet,
+    )
+    .expect("load hot wallet failed");
+    if let Some(wallet) = &wallet {
+        tracing::info!("Hot wallet {} loaded", wallet.address());
+        // Start following the transactions of the wallet, resubmitting stuck ones
+        shutdown.spawn(
+            wallet
+                .transactions()
+                .clone()
+                .run(CONFIG.transactions.clone(), shutdown.token()),
+        );
+    }
+
+    // Start the faucet, it pays claims from the hot wallet
+    let faucet = wallet.as_ref().map(|wallet| {
+        faucet::Faucet::spawn(
+            wallet.clone(),
+            CONFIG.wallet.my_token_address,
+            &CONFIG.faucet,
+            shutdown,
+        )
+    });
+
     // Create application state with all dependencies
     let app_state = AppState {
         repo,
         eth_provider,
+        chain_id,
         cache: dist_cache,
+        notifier,
+        heads,
+        events,
+        prices,
+        dex,
+        usage: api_keys::UsageMeter::new(),
+        wallet,
+        faucet,
+        shutdown: shutdown.clone(),
     };
 
-    // Set up Ethereum accounts router with endpoints
-    let eth_accounts_router = Router::new()
-        .route("/{address}", get(handlers::account::get_account_info))
-        .route(
-            "/{address}/erc20/{token_address}",
-            get(handlers::erc20::get_account_erc20),
-        );
+    // Start writing API key usage to the database
+    shutdown.spawn(app_state.usage.clone().run(
+        app_state.repo.clone(),
+        CONFIG.api_keys.clone(),
+        shutdown.token(),
+    ));
+
+    // Start refreshing watched balances in the background
+    let balance_watcher = watcher::BalanceWatcher::new(app_state.clone(), CONFIG.watcher.clone());
+    shutdown.spawn(balance_watcher.run(shutdown.token()));
 
-    // Create main router with all routes and middleware
-    Router::new()
-        .route("/ping", get(async || -> Result<()> { Ok(()) }))
-        .route("/health", get(handlers::health::healthcheck))
-        .nest("/v1/public/eth/accounts", eth_accounts_router)
-        .route("/v1/public/eth/misc", get(handlers::misc::get_blockchain_misc))
-        .with_state(app_state)
+    // Create main router with all routes and middleware, the document is served with them
+    let (router, openapi) = routes::router(&app_state).split_for_parts();
+    let router = router
+        .merge(openapi::docs(openapi))
+        .layer(middleware::from_fn(telemetry::track_requests))
+        .layer(middleware::from_fn(telemetry::trace_requests))
+        .with_state(app_state.clone());
+
+    (router, app_state)
 }
 
-/// Main entry point of the application
-#[tokio::main]
-async fn main() {
-    // Initialize logging with environment-based configuration
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| format!("{}=debug", env!("CARGO_CRATE_NAME")).into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+/// Serves the HTTP API until SIGTERM or SIGINT, then shuts down gracefully:
+/// readiness fails first, then in-flight requests and background tasks drain
+/// until the drain deadline, and the database pool is closed last
+async fn serve(args: ServeArgs) {
+    // Install the metrics recorder first, metrics recorded before it are dropped
+    telemetry::install_metrics();
 
     // Set up the application router
-    let app = setup_app().await;
+    let shutdown = Shutdown::new();
+    let (app, state) = setup_app(&args, &shutdown).await;
 
     // Configure and start the HTTP server
     let serve_addr = format!("{}:{}", CONFIG.host, CONFIG.port);
     let listener = TcpListener::bind(serve_addr).await.unwrap();
     tracing::debug!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap();
+    // The peer address identifies anonymous clients for rate limiting
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown::signal().await;
+            shutdown.start(&CONFIG.shutdown).await;
+        }
+    });
+
+    // Requests and background tasks share the drain deadline
+    let drain = async {
+        server.await.expect("server failed");
+        shutdown.wait_tasks().await;
+    };
+    tokio::select! {
+        _ = drain => tracing::info!("Drained requests and background tasks"),
+        _ = shutdown.drain_deadline(&CONFIG.shutdown) => tracing::warn!(
+            "Drain deadline of {} seconds passed, dropping what is still running",
+            CONFIG.shutdown.drain_timeout
+        ),
+    }
+
+    // Redis connections are opened per operation, they closed with the requests and tasks
+    state.repo.close().await;
+    tracing::info!("Shutdown complete");
+}
+
+/// Main entry point of the application
+#[tokio::main]
+async fn main() {
+    // Without a subcommand the binary serves, as it always did
+    let command = Cli::parse()
+        .command
+        .unwrap_or(Command::Serve(ServeArgs::default()));
+
+    // Initialize logging with environment-based configuration
+    // Only the server exports traces, other commands may run without a valid configuration
+    let tracer_provider = match &command {
+        Command::Serve(_) => {
+            telemetry::tracer_provider(&CONFIG.telemetry).expect("setup trace export failed")
+        }
+        _ => None,
+    };
+    telemetry::init_tracing(tracer_provider.as_ref());
+
+    let result = match command {
+        Command::Serve(args) => {
+            serve(args).await;
+            Ok(())
+        }
+        Command::Migrate(command) => cli::migrate(command).await,
+        Command::CheckConfig => cli::check_config().await,
+        Command::Backfill(args) => cli::backfill(args).await,
+        Command::Cache(CacheCommand::Flush { all }) => cli::flush_cache(all).await,
+    };
+
+    // Export the spans still buffered, the exporter blocks on its own thread
+    if let Some(provider) = tracer_provider {
+        let flushed = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+        if let Ok(Err(err)) = flushed {
+            eprintln!("Failed to export remaining spans: {}", err);
+        }
+    }
+    if let Err(err) = result {
+        tracing::error!("{}", err);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,108 @@
+// OpenAPI document of the HTTP API, derived from the handlers and served with a Swagger UI
+use std::collections::BTreeMap;
+
+use axum::Router;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{Content, Ref, RefOr, Response, ResponseBuilder, ResponsesBuilder};
+use utoipa::{IntoResponses, Modify, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::api_keys::API_KEY_HEADER;
+use crate::error::ErrorResponse;
+
+/// Path of the OpenAPI document
+pub const OPENAPI_PATH: &str = "/openapi.json";
+/// Path of the Swagger UI
+pub const SWAGGER_UI_PATH: &str = "/docs";
+
+/// Base of the document, paths are added by `routes::router` as the routes are registered
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Ethereum account service",
+        description = "Balances, prices, transactions and signatures of Ethereum accounts. \
+            Amounts and balances are decimal strings in the smallest unit of their asset. \
+            Errors are answered with an `ErrorResponse`, its `code` is stable, and failures \
+            of the Ethereum node are answered with 502, 503 or 504."
+    ),
+    modifiers(&SecuritySchemes),
+    components(schemas(ErrorResponse)),
+    tags(
+        (name = "system", description = "Liveness, readiness and metrics"),
+        (name = "accounts", description = "Balances and history of accounts"),
+        (name = "auth", description = "Sign-In with Ethereum sessions"),
+        (name = "chain", description = "Blocks, fees and token prices"),
+        (name = "transactions", description = "Transaction building, simulation and the faucet"),
+        (name = "signatures", description = "Signature verification and EIP-2612 permits"),
+        (name = "watchlist", description = "Balances refreshed in the background"),
+        (name = "tokens", description = "Token registry"),
+        (name = "wallet", description = "Hot wallet, admin sessions only"),
+        (name = "webhooks", description = "Balance change notifications"),
+        (name = "api-keys", description = "API keys and their usage"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Adds the `api_key` and `session` security schemes operations refer to
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                API_KEY_HEADER,
+                "API key, optional unless `api_keys.require_api_key` is set",
+            ))),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("Session token from `/v1/public/auth/verify`"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Errors of the API key and rate limit middleware of `/v1/public` and `/v1/private` endpoints
+pub struct ApiKeyErrors;
+
+impl IntoResponses for ApiKeyErrors {
+    fn responses() -> BTreeMap<String, RefOr<Response>> {
+        let error = |description: &str| {
+            ResponseBuilder::new()
+                .description(description)
+                .content(
+                    "application/json",
+                    Content::new(Some(Ref::from_schema_name(ErrorResponse::name()))),
+                )
+                .build()
+        };
+        ResponsesBuilder::new()
+            .response("401", error("Missing, invalid or inactive API key"))
+            .response(
+                "403",
+                error("The API key is not allowed to call this group of endpoints"),
+            )
+            .response(
+                "429",
+                error("Rate limit or daily quota exceeded, retry after `Retry-After` seconds"),
+            )
+            .build()
+            .into()
+    }
+}
+
+/// Routes serving the OpenAPI document and the Swagger UI
+pub fn docs<S>(openapi: utoipa::openapi::OpenApi) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    SwaggerUi::new(SWAGGER_UI_PATH)
+        .url(OPENAPI_PATH, openapi)
+        .into()
+}
@@ -0,0 +1,73 @@
+// EIP-2612 permits: the typed data wallets sign for gasless approvals, and its verification
+use alloy::dyn_abi::TypedData;
+use alloy::primitives::{Address, B256, Bytes, U256};
+use alloy::sol_types::{Eip712Domain, SolCall, SolStruct, sol};
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::eth::IERC20Permit;
+use crate::signatures;
+
+sol!(
+    /// The struct signed for an EIP-2612 permit
+    #[derive(Debug, Serialize)]
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
+);
+
+/// Builds the EIP-712 typed data of a permit, as passed to `eth_signTypedData_v4`
+pub fn permit_typed_data(domain: &Eip712Domain, permit: &Permit) -> TypedData {
+    TypedData::from_struct(permit, Some(domain.clone()))
+}
+
+/// Returns the hash signed for a permit
+pub fn permit_signing_hash(domain: &Eip712Domain, permit: &Permit) -> B256 {
+    permit.eip712_signing_hash(domain)
+}
+
+/// A permit signature split into the parameters of `permit`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermitSignature {
+    pub v: u8,
+    pub r: B256,
+    pub s: B256,
+    /// Address recovered from the signature, none if it doesn't recover
+    pub recovered: Option<Address>,
+}
+
+/// Splits a permit signature into v/r/s and recovers its signer
+/// Tokens check permits with `ecrecover`, so only EOA signatures are accepted.
+pub fn decode_permit_signature(hash: &B256, signature: &[u8]) -> Result<PermitSignature> {
+    let parsed = signatures::parse_signature(signature)?;
+    Ok(PermitSignature {
+        v: 27 + parsed.v() as u8,
+        r: parsed.r().into(),
+        s: parsed.s().into(),
+        recovered: signatures::recover_signer(hash, signature)?,
+    })
+}
+
+/// Encodes the `permit` call submitting a signed permit
+pub fn encode_permit_call(permit: &Permit, signature: &PermitSignature) -> Bytes {
+    IERC20Permit::permitCall {
+        owner: permit.owner,
+        spender: permit.spender,
+        value: permit.value,
+        deadline: permit.deadline,
+        v: signature.v,
+        r: signature.r,
+        s: signature.s,
+    }
+    .abi_encode()
+    .into()
+}
+
+/// Returns whether a permit deadline, a unix timestamp, has passed
+pub fn is_expired(deadline: U256, now: u64) -> bool {
+    deadline < U256::from(now)
+}
@@ -0,0 +1,177 @@
+// USD pricing of tokens from Chainlink price feeds, cached in Redis
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use alloy::primitives::{Address, U256};
+use alloy::providers::DynProvider;
+use alloy::sol_types::sol;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::cache::DistCache;
+use crate::error::Result;
+use crate::eth;
+
+// Import the generated contract bindings for Chainlink price feeds
+sol!(
+    #[sol(rpc)]
+    interface AggregatorV3Interface {
+        function decimals() external view returns (uint8);
+        function latestRoundData() external view returns (
+            uint80 roundId,
+            int256 answer,
+            uint256 startedAt,
+            uint256 updatedAt,
+            uint80 answeredInRound
+        );
+    }
+);
+
+pub use AggregatorV3Interface::AggregatorV3InterfaceInstance;
+
+/// Cache key prefix of feed answers, they expire with the heartbeat of their feed
+pub const PRICE_KEY_PREFIX: &str = "price:usd";
+
+/// Configuration for USD pricing
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Chainlink USD feeds, by token
+    #[serde(default)]
+    pub feeds: Vec<FeedConfig>,
+    /// Fixed USD prices by token address, for tokens without a feed (e.g. on testnets)
+    #[serde(default)]
+    pub static_prices: HashMap<String, Decimal>,
+}
+
+/// A Chainlink feed quoting a token in USD
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedConfig {
+    /// Token contract address, `ZERO_ADDRESS` for the native asset
+    pub token_address: String,
+    /// Address of the feed's `AggregatorV3Interface` proxy
+    pub feed_address: String,
+    /// Maximum time between two answers of the feed, in seconds
+    pub heartbeat: u64,
+}
+
+/// A parsed feed
+#[derive(Debug, Clone)]
+struct Feed {
+    address: Address,
+    heartbeat: u64,
+}
+
+/// PriceOracle resolves USD prices from Chainlink feeds, falling back to the static price table
+/// Feed answers are cached until the feed's heartbeat runs out, and answers older than
+/// the heartbeat are rejected
+#[derive(Clone)]
+pub struct PriceOracle {
+    provider: DynProvider,
+    cache: DistCache,
+    feeds: Arc<HashMap<Address, Feed>>,
+    static_prices: Arc<HashMap<Address, Decimal>>,
+}
+
+impl PriceOracle {
+    /// Create a new instance of `PriceOracle` with the provided configuration.
+    pub fn new(provider: DynProvider, cache: DistCache, config: &Config) -> Result<Self> {
+        let feeds = config
+            .feeds
+            .iter()
+            .map(|feed| {
+                Ok((
+                    feed.token_address.parse()?,
+                    Feed {
+                        address: feed.feed_address.parse()?,
+                        heartbeat: feed.heartbeat,
+                    },
+                ))
+            })
+            .collect::<Result<_>>()?;
+        let static_prices = config
+            .static_prices
+            .iter()
+            .map(|(token_address, price)| Ok((token_address.parse()?, *price)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            provider,
+            cache,
+            feeds: Arc::new(feeds),
+            static_prices: Arc::new(static_prices),
+        })
+    }
+
+    /// Returns the USD price of one whole token, none if the token has no usable price
+    /// A feed that fails or answers a stale price yields none rather than an error,
+    /// so one broken feed doesn't fail a whole portfolio
+    pub async fn get_usd_price(&self, token_address: Address) -> Option<Decimal> {
+        let Some(feed) = self.feeds.get(&token_address) else {
+            return self.static_prices.get(&token_address).copied();
+        };
+
+        match self.get_feed_price(feed).await {
+            Ok(price) => price,
+            Err(err) => {
+                tracing::error!("Failed to read price feed {}: {}", feed.address, err);
+                None
+            }
+        }
+    }
+
+    /// Reads a feed answer from the cache, or from the chain
+    async fn get_feed_price(&self, feed: &Feed) -> Result<Option<Decimal>> {
+        let key = format!("{}:{}", PRICE_KEY_PREFIX, feed.address);
+        match self.get_cached_price(&key).await {
+            Ok(Some(price)) => return Ok(Some(price)),
+            Ok(None) => {}
+            Err(err) => tracing::error!("Failed to get cached price: {}", err),
+        }
+
+        let contract = AggregatorV3InterfaceInstance::new(feed.address, self.provider.clone());
+        let decimals = contract.decimals().call().await?;
+        let round = contract.latestRoundData().call().await?;
+
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        let updated_at = u64::try_from(round.updatedAt).unwrap_or(u64::MAX);
+        let age = now.saturating_sub(updated_at);
+        if updated_at == 0 || age >= feed.heartbeat {
+            tracing::warn!(
+                "Rejecting stale answer of price feed {}, updated {}s ago",
+                feed.address,
+                age
+            );
+            return Ok(None);
+        }
+
+        let Some(price) = parse_answer(round.answer.try_into().ok(), decimals) else {
+            tracing::warn!("Rejecting invalid answer of price feed {}", feed.address);
+            return Ok(None);
+        };
+
+        // Cache the answer until the feed is due for a new one
+        if let Err(err) = self.cache.set_ex(&key, price, feed.heartbeat - age).await {
+            tracing::error!("Failed to cache price: {}", err);
+        }
+
+        Ok(Some(price))
+    }
+
+    async fn get_cached_price(&self, key: &str) -> Result<Option<Decimal>> {
+        self.cache.get(key).await
+    }
+}
+
+/// Converts a feed answer with `decimals` decimals to a price, none unless it is positive
+fn parse_answer(answer: Option<i128>, decimals: u8) -> Option<Decimal> {
+    let answer = answer.filter(|answer| *answer > 0)?;
+    Decimal::try_from_i128_with_scale(answer, decimals.into()).ok()
+}
+
+/// Returns the USD value of a balance in the token's smallest unit, rounded to cents
+/// None if the value doesn't fit in a `Decimal`
+pub fn usd_value(balance: U256, decimals: u8, price: Decimal) -> Option<Decimal> {
+    let amount = Decimal::from_str(&eth::format_balance(balance, decimals).ok()?).ok()?;
+    Some(amount.checked_mul(price)?.round_dp(2))
+}
@@ -0,0 +1,312 @@
+// Token bucket rate limiting of anonymous clients, shared by every instance through Redis
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::LazyLock;
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, MatchedPath, Request, State},
+    http::{Extensions, HeaderMap, HeaderName, HeaderValue, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use ipnet::IpNet;
+use serde::Deserialize;
+
+use crate::api_keys;
+use crate::cache::DistCache;
+use crate::config::CONFIG;
+use crate::db::ApiKey;
+use crate::error::{Result, TooManyRequestsError};
+use crate::state::AppState;
+
+/// Cache key prefix of the buckets
+const BUCKET_KEY_PREFIX: &str = "rate_limit";
+
+/// Headers describing the bucket of a client
+pub const RATE_LIMIT_LIMIT_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+pub const RATE_LIMIT_REMAINING_HEADER: HeaderName =
+    HeaderName::from_static("x-ratelimit-remaining");
+pub const RATE_LIMIT_RESET_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// Refills a bucket for the time elapsed since its last request, then takes a token if any
+/// The clock of Redis is used, so instances with skewed clocks agree on the refill.
+/// Returns whether the token was taken, the whole tokens left, and the milliseconds until
+/// a token is available and until the bucket is full again.
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_per_ms = tonumber(ARGV[2]) / 1000
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or capacity
+local updated_at = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated_at) * refill_per_ms)
+
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+local retry_after = 0
+if allowed == 0 then
+    retry_after = math.ceil((1 - tokens) / refill_per_ms)
+end
+local reset = math.ceil((capacity - tokens) / refill_per_ms)
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+redis.call('PEXPIRE', KEYS[1], reset + 1000)
+return {allowed, math.floor(tokens), retry_after, reset}
+"#;
+
+static TOKEN_BUCKET: LazyLock<redis::Script> =
+    LazyLock::new(|| redis::Script::new(TOKEN_BUCKET_SCRIPT));
+
+/// Configuration for rate limiting anonymous clients
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Proxies allowed to forward the client address, e.g. `10.0.0.0/8`
+    pub trusted_proxies: Vec<IpNet>,
+    /// Header trusted proxies append the client address to, e.g. `x-forwarded-for`
+    pub client_ip_header: String,
+    /// Bucket of every route group, e.g. `public`, groups without a bucket are not limited
+    pub groups: HashMap<String, BucketConfig>,
+}
+
+/// Size and refill rate of a token bucket, every request takes one token
+#[derive(Debug, Clone, Deserialize)]
+pub struct BucketConfig {
+    /// Maximum number of tokens, i.e. the largest burst
+    pub capacity: u64,
+    /// Tokens added per second, i.e. the sustained rate
+    pub refill_per_second: f64,
+}
+
+impl Config {
+    /// Checks the bucket of every route group, see `BucketConfig::validate`
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        for (group, bucket) in &self.groups {
+            bucket
+                .validate()
+                .map_err(|err| format!("rate_limit.groups.{}: {}", group, err))?;
+        }
+        Ok(())
+    }
+}
+
+impl BucketConfig {
+    /// Checks that the bucket lets requests through
+    /// An empty bucket rejects every request, and one that never refills can't compute a reset
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.capacity < 1 {
+            return Err("capacity must be at least 1".to_string());
+        }
+        if !(self.refill_per_second.is_finite() && self.refill_per_second > 0.0) {
+            return Err("refill_per_second must be a positive number".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// State of the limit of a client, sent as `X-RateLimit-*` headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitStatus {
+    /// Maximum number of requests
+    pub limit: u64,
+    /// Requests left
+    pub remaining: u64,
+    /// Seconds until every request is available again
+    pub reset: u64,
+}
+
+impl RateLimitStatus {
+    /// Adds the `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset` headers
+    pub fn insert_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(RATE_LIMIT_LIMIT_HEADER, HeaderValue::from(self.limit));
+        headers.insert(RATE_LIMIT_REMAINING_HEADER, HeaderValue::from(self.remaining));
+        headers.insert(RATE_LIMIT_RESET_HEADER, HeaderValue::from(self.reset));
+    }
+}
+
+/// Outcome of taking a token from a bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    /// Seconds until a token is available, 0 if the request was allowed
+    pub retry_after: u64,
+    pub status: RateLimitStatus,
+}
+
+/// Takes a token from the bucket stored at `key`, atomically for every instance
+///
+/// # Arguments
+/// * `cache` - Cache holding the buckets
+/// * `key` - Cache key of the bucket
+/// * `bucket` - Size and refill rate of the bucket
+pub async fn take_token(cache: &DistCache, key: &str, bucket: &BucketConfig) -> Result<Decision> {
+    let mut conn = cache.get_conn().await?;
+    let (allowed, remaining, retry_after_ms, reset_ms): (u8, u64, u64, u64) = TOKEN_BUCKET
+        .key(key)
+        .arg(bucket.capacity)
+        .arg(bucket.refill_per_second)
+        .invoke_async(&mut conn)
+        .await?;
+
+    Ok(Decision {
+        allowed: allowed == 1,
+        retry_after: retry_after_ms.div_ceil(1000),
+        status: RateLimitStatus {
+            limit: bucket.capacity,
+            remaining,
+            reset: reset_ms.div_ceil(1000),
+        },
+    })
+}
+
+/// Resolves the address of a client
+/// The forwarded header is only read when the peer is a trusted proxy. It is walked from
+/// the right, the closest hop, and the first address that isn't a trusted proxy is the
+/// client, so a client can't spoof its address by sending the header itself.
+///
+/// # Arguments
+/// * `peer` - Address of the connection
+/// * `forwarded` - Value of the client IP header, e.g. `203.0.113.7, 10.0.0.2`
+/// * `trusted_proxies` - Networks of the trusted proxies
+pub fn client_ip(
+    peer: Option<IpAddr>,
+    forwarded: Option<&str>,
+    trusted_proxies: &[IpNet],
+) -> Option<IpAddr> {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+
+    let mut client = peer?;
+    if !is_trusted(&client) {
+        return Some(client);
+    }
+    for hop in forwarded.unwrap_or_default().rsplit(',') {
+        let hop = hop.trim();
+        let Some(ip) = hop
+            .parse::<IpAddr>()
+            .ok()
+            .or_else(|| hop.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        else {
+            break;
+        };
+        client = ip;
+        if !is_trusted(&client) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+/// Resolves the address of the client of a request with the configured trusted proxies
+/// None when the peer address is unknown, i.e. the server was not started with connect info
+fn request_client_ip(extensions: &Extensions, headers: &HeaderMap) -> Option<IpAddr> {
+    let config = &CONFIG.rate_limit;
+    let peer = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let forwarded = headers
+        .get(&config.client_ip_header)
+        .and_then(|value| value.to_str().ok());
+    client_ip(peer, forwarded, &config.trusted_proxies)
+}
+
+/// Extractor for the address of the client, see `client_ip`
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Infallible> {
+        Ok(Self(request_client_ip(&parts.extensions, &parts.headers)))
+    }
+}
+
+/// Takes a token from the bucket of the client of a request in its route group
+/// Rejects the request once the bucket is empty. Limits fail open, an unavailable cache
+/// must not take the service down, so None is returned when the route group is not
+/// limited or the bucket could not be read.
+async fn take_anonymous_token(
+    state: &AppState,
+    extensions: &Extensions,
+    headers: &HeaderMap,
+) -> Result<Option<Decision>> {
+    let config = &CONFIG.rate_limit;
+    let Some((group, bucket)) = extensions
+        .get::<MatchedPath>()
+        .and_then(|path| api_keys::route_scope(path.as_str()))
+        .and_then(|group| config.groups.get_key_value(group))
+    else {
+        return Ok(None);
+    };
+
+    let client = request_client_ip(extensions, headers)
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let key = format!("{}:{}:{}", BUCKET_KEY_PREFIX, group, client);
+
+    let decision = match take_token(&state.cache, &key, bucket).await {
+        Ok(decision) => decision,
+        Err(err) => {
+            tracing::error!("Failed to rate limit {}: {}", client, err);
+            return Ok(None);
+        }
+    };
+    if !decision.allowed {
+        return Err(TooManyRequestsError {
+            message: format!("Rate limit exceeded for {} endpoints", group),
+            retry_after: decision.retry_after,
+            status: decision.status,
+        }
+        .into());
+    }
+    Ok(Some(decision))
+}
+
+/// Charges a request carrying an invalid API key to the bucket of its client
+/// Invalid keys are anonymous requests, so a junk key can't bypass the limit.
+///
+/// # Arguments
+/// * `state` - Application state holding the buckets
+/// * `extensions` - Extensions of the request, with its matched path and connect info
+/// * `headers` - Headers of the request
+pub async fn charge_invalid_key(
+    state: &AppState,
+    extensions: &Extensions,
+    headers: &HeaderMap,
+) -> Result<()> {
+    take_anonymous_token(state, extensions, headers).await?;
+    Ok(())
+}
+
+/// Middleware limiting anonymous requests per client address and route group
+/// Runs after `api_keys::authenticate`, requests with a valid API key are limited by the
+/// key's own quotas instead.
+pub async fn limit_anonymous(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    if request.extensions().get::<ApiKey>().is_some() {
+        return Ok(next.run(request).await);
+    }
+    let Some(decision) =
+        take_anonymous_token(&state, request.extensions(), request.headers()).await?
+    else {
+        return Ok(next.run(request).await);
+    };
+
+    let mut response = next.run(request).await;
+    decision.status.insert_headers(response.headers_mut());
+    Ok(response)
+}
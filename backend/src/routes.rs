@@ -0,0 +1,122 @@
+// Routes of the HTTP API, registered together with their OpenAPI documentation
+use axum::middleware;
+use utoipa::OpenApi;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::handlers;
+use crate::openapi::ApiDoc;
+use crate::state::AppState;
+use crate::{api_keys, rate_limit};
+
+/// Builds the router of every API endpoint along with its OpenAPI document
+/// Routes are only registered with `routes!`, which documents them from the
+/// `#[utoipa::path]` attribute of their handlers.
+pub fn router(state: &AppState) -> OpenApiRouter<AppState> {
+    // Set up Ethereum accounts router with endpoints
+    let eth_accounts_router = OpenApiRouter::new()
+        .routes(routes!(handlers::account::get_account_info))
+        .routes(routes!(handlers::erc20::get_account_erc20))
+        .routes(routes!(handlers::history::get_balance_history))
+        .routes(routes!(handlers::portfolio::get_account_portfolio));
+
+    // Set up private router, every endpoint requires a SIWE session
+    let private_router = OpenApiRouter::new()
+        .routes(routes!(handlers::auth::get_me))
+        .routes(routes!(handlers::auth::sign_out));
+
+    // Set up admin watchlist router with endpoints
+    let watchlist_router = OpenApiRouter::new()
+        .routes(routes!(
+            handlers::watchlist::list_watched_balances,
+            handlers::watchlist::create_watched_balance
+        ))
+        .routes(routes!(
+            handlers::watchlist::get_watched_balance,
+            handlers::watchlist::update_watched_balance,
+            handlers::watchlist::delete_watched_balance
+        ));
+
+    // Set up admin token registry router with endpoints
+    let tokens_router = OpenApiRouter::new()
+        .routes(routes!(
+            handlers::tokens::list_tokens,
+            handlers::tokens::create_token
+        ))
+        .routes(routes!(handlers::tokens::import_tokens))
+        .routes(routes!(
+            handlers::tokens::get_token,
+            handlers::tokens::update_token,
+            handlers::tokens::delete_token
+        ));
+
+    // Set up admin webhooks router with endpoints
+    let webhooks_router = OpenApiRouter::new()
+        .routes(routes!(
+            handlers::webhooks::list_webhooks,
+            handlers::webhooks::create_webhook
+        ))
+        .routes(routes!(
+            handlers::webhooks::get_webhook,
+            handlers::webhooks::update_webhook,
+            handlers::webhooks::delete_webhook
+        ))
+        .routes(routes!(handlers::webhooks::list_webhook_deliveries))
+        .routes(routes!(handlers::webhooks::replay_webhook_delivery));
+
+    // Set up admin API keys router with endpoints
+    let api_keys_router = OpenApiRouter::new()
+        .routes(routes!(
+            handlers::api_keys::list_api_keys,
+            handlers::api_keys::create_api_key
+        ))
+        .routes(routes!(handlers::api_keys::get_api_key_usage))
+        .routes(routes!(
+            handlers::api_keys::get_api_key,
+            handlers::api_keys::update_api_key
+        ));
+
+    // Public and private endpoints are authenticated, limited and metered by API key,
+    // anonymous requests and invalid keys are limited per client address.
+    // Authentication runs first, so only validated keys skip the anonymous limit.
+    let api_router = OpenApiRouter::new()
+        .nest("/v1/public/eth/accounts", eth_accounts_router)
+        .routes(routes!(handlers::auth::get_nonce))
+        .routes(routes!(handlers::auth::sign_in))
+        .routes(routes!(handlers::misc::get_blockchain_misc))
+        .routes(routes!(handlers::price::get_token_price))
+        .routes(routes!(handlers::faucet::claim_faucet))
+        .routes(routes!(handlers::transactions::build_transaction))
+        .routes(routes!(handlers::transactions::simulate_transaction))
+        .routes(routes!(handlers::permit::get_permit_typed_data))
+        .routes(routes!(handlers::permit::verify_permit))
+        .routes(routes!(handlers::signatures::verify_signature))
+        .routes(routes!(handlers::stream::stream_heads))
+        .routes(routes!(handlers::ws::stream_accounts))
+        .nest("/v1/private", private_router)
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::limit_anonymous,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            api_keys::authenticate,
+        ));
+
+    // Set up admin hot wallet router with endpoints
+    let wallet_router = OpenApiRouter::new()
+        .routes(routes!(handlers::wallet::mint_my_token))
+        .routes(routes!(handlers::wallet::list_outgoing_transactions))
+        .routes(routes!(handlers::wallet::get_transaction_receipt));
+
+    OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .routes(routes!(handlers::health::ping))
+        .routes(routes!(handlers::health::healthcheck))
+        .routes(routes!(handlers::metrics::get_metrics))
+        .merge(api_router)
+        // Every admin endpoint requires a SIWE session of an account in `auth.admin_addresses`
+        .nest("/v1/admin/eth/watchlist", watchlist_router)
+        .nest("/v1/admin/eth/tokens", tokens_router)
+        .nest("/v1/admin/eth", wallet_router)
+        .nest("/v1/admin/webhooks", webhooks_router)
+        .nest("/v1/admin/api-keys", api_keys_router)
+}
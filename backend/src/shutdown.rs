@@ -0,0 +1,116 @@
+// Graceful shutdown: readiness, draining of in-flight requests and stop of background tasks
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+use tokio_util::task::TaskTracker;
+
+/// Configuration for the graceful shutdown
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Seconds between failing readiness and closing the listener,
+    /// so load balancers stop routing new requests here first
+    pub readiness_delay: u64,
+    /// Seconds in-flight requests and background tasks get to finish once draining started
+    pub drain_timeout: u64,
+}
+
+/// Shutdown is shared by the server, the handlers and the background tasks.
+/// Cancelling it ends streams and stops the tasks after their current iteration.
+#[derive(Clone)]
+pub struct Shutdown {
+    ready: Arc<AtomicBool>,
+    token: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    /// Create a new instance of `Shutdown`, ready to serve
+    pub fn new() -> Self {
+        Self {
+            ready: Arc::new(AtomicBool::new(true)),
+            token: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+        }
+    }
+
+    /// Whether the instance accepts new traffic, false once shutdown started
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    /// Resolves once draining started
+    pub fn cancelled(&self) -> WaitForCancellationFuture<'_> {
+        self.token.cancelled()
+    }
+
+    /// Returns a token cancelled once draining started, for tasks outliving a borrow
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Spawns a background task, shutdown waits for it to return
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(task);
+    }
+
+    /// Fails readiness, waits `readiness_delay` and starts draining
+    pub async fn start(&self, config: &Config) {
+        self.ready.store(false, Ordering::Relaxed);
+        tracing::info!(
+            "Shutting down, draining in {} seconds",
+            config.readiness_delay
+        );
+        tokio::time::sleep(Duration::from_secs(config.readiness_delay)).await;
+        self.token.cancel();
+    }
+
+    /// Waits until every spawned background task returned
+    /// Tasks only stop once draining started.
+    pub async fn wait_tasks(&self) {
+        self.tasks.close();
+        self.tasks.wait().await;
+    }
+
+    /// Resolves `drain_timeout` after draining started
+    pub async fn drain_deadline(&self, config: &Config) {
+        self.cancelled().await;
+        tokio::time::sleep(Duration::from_secs(config.drain_timeout)).await;
+    }
+}
+
+/// Resolves on SIGTERM or SIGINT
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("install SIGINT handler failed");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("install SIGTERM handler failed")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received SIGINT"),
+        _ = terminate => tracing::info!("Received SIGTERM"),
+    }
+}
@@ -0,0 +1,104 @@
+// Signature verification for EOAs (ECDSA) and smart contract wallets (EIP-1271)
+use alloy::primitives::{Address, B256, Bytes, FixedBytes, Signature};
+use alloy::providers::{DynProvider, Provider};
+use alloy::sol_types::sol;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::error::{self, Result, ValidateError};
+
+// Import the generated contract bindings for EIP-1271 wallets
+sol!(
+    #[sol(rpc)]
+    interface IERC1271 {
+        function isValidSignature(bytes32 hash, bytes signature) external view returns (bytes4 magicValue);
+    }
+);
+
+/// Value returned by `isValidSignature` for a valid signature
+pub const EIP1271_MAGIC_VALUE: FixedBytes<4> = FixedBytes([0x16, 0x26, 0xba, 0x7e]);
+
+/// How a signature was verified
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationMethod {
+    /// The signer is an EOA, the signature was recovered with ECDSA
+    Ecdsa,
+    /// The signer is a contract, the signature was checked with `isValidSignature`
+    Eip1271,
+}
+
+/// Result of a signature verification
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification {
+    pub valid: bool,
+    pub method: VerificationMethod,
+    /// Address recovered from the signature, only for ECDSA
+    pub recovered: Option<Address>,
+}
+
+/// Parses a 65-byte signature, or a 64-byte ERC-2098 compact one
+pub fn parse_signature(signature: &[u8]) -> Result<Signature> {
+    match signature.len() {
+        65 => Ok(Signature::from_raw(signature)
+            .map_err(|err| ValidateError(format!("Invalid signature: {}", err)))?),
+        64 => Ok(Signature::from_erc2098(signature)),
+        _ => Err(ValidateError("Signature must be 64 or 65 bytes".to_string()).into()),
+    }
+}
+
+/// Recovers the signer of a hash, none if the signature doesn't recover to any address
+pub fn recover_signer(hash: &B256, signature: &[u8]) -> Result<Option<Address>> {
+    let signature = parse_signature(signature)?;
+    Ok(signature.recover_address_from_prehash(hash).ok())
+}
+
+/// Verifies that `signer` signed `hash`
+/// Contracts are asked through EIP-1271 `isValidSignature`, so smart contract wallets
+/// such as Safe are supported. Any other address must match the ECDSA recovered signer.
+///
+/// # Arguments
+/// * `provider` - Provider used to read the signer code and call the wallet
+/// * `signer` - Claimed signer
+/// * `hash` - EIP-191 or EIP-712 hash that was signed
+/// * `signature` - Raw signature bytes
+pub async fn verify_signature(
+    provider: &DynProvider,
+    signer: Address,
+    hash: B256,
+    signature: &[u8],
+) -> Result<Verification> {
+    let code = provider.get_code_at(signer).await?;
+    if code.is_empty() {
+        let recovered = recover_signer(&hash, signature)?;
+        return Ok(Verification {
+            valid: recovered == Some(signer),
+            method: VerificationMethod::Ecdsa,
+            recovered,
+        });
+    }
+
+    // A wallet that rejects the signature may revert or return nothing instead of
+    // another value, failures of the node are not a rejection
+    let wallet = IERC1271::new(signer, provider.clone());
+    let valid = match wallet
+        .isValidSignature(hash, Bytes::copy_from_slice(signature))
+        .call()
+        .await
+    {
+        Ok(magic_value) => magic_value == EIP1271_MAGIC_VALUE,
+        Err(alloy::contract::Error::TransportError(err)) if !error::is_revert(&err) => {
+            return Err(err.into());
+        }
+        Err(err) => {
+            tracing::debug!("isValidSignature of {} failed: {}", signer, err);
+            false
+        }
+    };
+
+    Ok(Verification {
+        valid,
+        method: VerificationMethod::Eip1271,
+        recovered: None,
+    })
+}
@@ -0,0 +1,388 @@
+// Transaction simulation: runs a call against the latest state with optional overrides,
+// and reports its outcome and the balance changes it would cause
+use std::collections::BTreeMap;
+
+use alloy::dyn_abi::{DynSolType, DynSolValue};
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{Address, B256, Bytes, I256, U64, U256};
+use alloy::providers::{DynProvider, MULTICALL3_ADDRESS, Provider};
+use alloy::rpc::types::simulate::{SimBlock, SimulatePayload};
+use alloy::rpc::types::state::StateOverride;
+use alloy::rpc::types::{TransactionInput, TransactionRequest};
+use alloy::sol_types::{SolCall, SolEvent, SolInterface, decode_revert_reason};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::ToSchema;
+
+use crate::error::{Result, ValidateError};
+use crate::eth::{IERC20, IMulticall3};
+
+/// Where the balance changes of a simulation were derived from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceSource {
+    /// `debug_traceCall` call tracer: value transfers and ERC20 `Transfer` logs
+    Trace,
+    /// `balanceOf` reads before and after the call, in the same `eth_simulateV1` block
+    BalanceOf,
+}
+
+/// Change of the native or ERC20 balance of an account
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceChange {
+    pub address: Address,
+    /// Token of the balance, the zero address for the native balance
+    pub token_address: Address,
+    /// Balance before the call, only known from `balanceOf` reads
+    pub before: Option<U256>,
+    /// Balance after the call, only known from `balanceOf` reads
+    pub after: Option<U256>,
+    pub delta: I256,
+}
+
+/// Outcome of a simulated call
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub success: bool,
+    /// Return data of the call, or its revert data
+    pub return_data: Bytes,
+    pub revert_reason: Option<String>,
+    pub gas_used: Option<u64>,
+    /// None when the node can neither trace nor simulate calls
+    pub balance_source: Option<BalanceSource>,
+    pub balance_changes: Vec<BalanceChange>,
+}
+
+/// Frame of the `callTracer` output of `debug_traceCall`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from: Address,
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(default)]
+    pub value: Option<U256>,
+    pub gas_used: U64,
+    /// Set when the frame reverted
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub calls: Vec<CallFrame>,
+    #[serde(default)]
+    pub logs: Vec<CallLog>,
+}
+
+/// Log emitted by a call frame
+#[derive(Debug, Clone, Deserialize)]
+pub struct CallLog {
+    pub address: Address,
+    #[serde(default)]
+    pub topics: Vec<B256>,
+    #[serde(default)]
+    pub data: Bytes,
+}
+
+/// Simulates `tx` on top of the latest block with `overrides` applied
+/// The outcome comes from `eth_call`. Balance changes come from `debug_traceCall` when the
+/// node supports it, otherwise from `balanceOf` reads of the `watched` (account, token)
+/// pairs around the call. A `ZERO_ADDRESS` token reads the native balance.
+///
+/// # Arguments
+/// * `provider` - Provider used to run the call
+/// * `tx` - Call to simulate, `from` should be set
+/// * `overrides` - Geth-style state overrides applied before the call
+/// * `watched` - (account, token) pairs read when the call can't be traced
+pub async fn simulate(
+    provider: &DynProvider,
+    tx: TransactionRequest,
+    overrides: StateOverride,
+    watched: &[(Address, Address)],
+) -> Result<Simulation> {
+    // Every request runs on the same block, so reads and the call see the same state
+    let block_number = provider.get_block_number().await?;
+
+    let (success, return_data, revert_reason) = match provider
+        .call(tx.clone())
+        .overrides(overrides.clone())
+        .block(block_number.into())
+        .await
+    {
+        Ok(data) => (true, data, None),
+        Err(err) => {
+            // Only errors of the node executing the call are reverts
+            let Some(payload) = err.as_error_resp() else {
+                return Err(err.into());
+            };
+            let data = payload.as_revert_data().unwrap_or_default();
+            let reason = decode_revert(&data).unwrap_or_else(|| payload.message.to_string());
+            (false, data, Some(reason))
+        }
+    };
+
+    if let Some(frame) = trace_call(provider, &tx, &overrides, block_number).await {
+        return Ok(Simulation {
+            success,
+            return_data,
+            revert_reason,
+            gas_used: Some(frame.gas_used.to()),
+            balance_source: Some(BalanceSource::Trace),
+            balance_changes: trace_balance_changes(&frame),
+        });
+    }
+
+    let (balance_source, balance_changes, simulated_gas) =
+        match read_balance_changes(provider, &tx, overrides.clone(), watched, block_number).await {
+            Ok((changes, gas_used)) => (Some(BalanceSource::BalanceOf), changes, Some(gas_used)),
+            Err(err) => {
+                tracing::warn!("Failed to read balance changes of a simulation: {}", err);
+                (None, Vec::new(), None)
+            }
+        };
+    let gas_used = match simulated_gas {
+        Some(gas_used) => Some(gas_used),
+        None if success => provider
+            .estimate_gas(tx)
+            .overrides(overrides)
+            .block(block_number.into())
+            .await
+            .ok(),
+        None => None,
+    };
+
+    Ok(Simulation {
+        success,
+        return_data,
+        revert_reason,
+        gas_used,
+        balance_source,
+        balance_changes,
+    })
+}
+
+/// Decodes a revert reason: `Error(string)`, `Panic(uint256)` or an ERC20 custom error
+pub fn decode_revert(data: &[u8]) -> Option<String> {
+    if data.is_empty() {
+        return None;
+    }
+    decode_revert_reason(data).or_else(|| {
+        IERC20::IERC20Errors::abi_decode(data)
+            .ok()
+            .map(|err| format!("{:?}", err))
+    })
+}
+
+/// Parses the ABI types of a return value, e.g. `uint256` or `(uint256,bool)`
+pub fn parse_return_type(types: &str) -> Result<DynSolType> {
+    DynSolType::parse(types)
+        .map_err(|err| ValidateError(format!("Invalid return type {}: {}", types, err)).into())
+}
+
+/// Decodes return data as JSON, none if it doesn't match the type
+/// Integers are decimal strings and bytes are 0x-prefixed hex.
+pub fn decode_return_value(ty: &DynSolType, data: &[u8]) -> Option<Value> {
+    ty.abi_decode_params(data)
+        .ok()
+        .map(|value| sol_value_to_json(&value))
+}
+
+// Helper function to convert a decoded ABI value to JSON
+fn sol_value_to_json(value: &DynSolValue) -> Value {
+    match value {
+        DynSolValue::Bool(value) => json!(value),
+        DynSolValue::Int(value, _) => json!(value.to_string()),
+        DynSolValue::Uint(value, _) => json!(value.to_string()),
+        DynSolValue::FixedBytes(value, size) => json!(Bytes::copy_from_slice(&value[..*size])),
+        DynSolValue::Address(value) => json!(value.to_string()),
+        DynSolValue::Function(value) => json!(value.to_string()),
+        DynSolValue::Bytes(value) => json!(Bytes::copy_from_slice(value)),
+        DynSolValue::String(value) => json!(value),
+        DynSolValue::Array(values)
+        | DynSolValue::FixedArray(values)
+        | DynSolValue::Tuple(values)
+        | DynSolValue::CustomStruct { tuple: values, .. } => {
+            Value::Array(values.iter().map(sol_value_to_json).collect())
+        }
+    }
+}
+
+// Helper function to trace a call, none if the node doesn't support `debug_traceCall`
+async fn trace_call(
+    provider: &DynProvider,
+    tx: &TransactionRequest,
+    overrides: &StateOverride,
+    block_number: u64,
+) -> Option<CallFrame> {
+    let options = json!({
+        "tracer": "callTracer",
+        "tracerConfig": { "withLog": true },
+        "stateOverrides": overrides,
+    });
+    match provider
+        .raw_request::<_, CallFrame>(
+            "debug_traceCall".into(),
+            (tx, BlockNumberOrTag::Number(block_number), options),
+        )
+        .await
+    {
+        Ok(frame) => Some(frame),
+        Err(err) => {
+            tracing::debug!("debug_traceCall is not available: {}", err);
+            None
+        }
+    }
+}
+
+/// Derives balance changes from a call trace
+/// Value moved by calls and ERC20 `Transfer` logs are summed per account, frames that
+/// reverted are skipped along with their subcalls. Mints and burns don't change the
+/// balance of the zero address.
+pub fn trace_balance_changes(frame: &CallFrame) -> Vec<BalanceChange> {
+    let mut deltas = BTreeMap::new();
+    collect_trace_deltas(frame, &mut deltas);
+
+    deltas
+        .into_iter()
+        .filter(|((address, _), delta)| !address.is_zero() && !delta.is_zero())
+        .map(|((address, token_address), delta)| BalanceChange {
+            address,
+            token_address,
+            before: None,
+            after: None,
+            delta,
+        })
+        .collect()
+}
+
+// Helper function to sum the balance deltas of a frame and its subcalls
+fn collect_trace_deltas(frame: &CallFrame, deltas: &mut BTreeMap<(Address, Address), I256>) {
+    if frame.error.is_some() {
+        return;
+    }
+
+    // Delegate and static calls run in the caller's context and can't move value
+    let moves_value = !matches!(frame.call_type.as_str(), "DELEGATECALL" | "STATICCALL");
+    if moves_value
+        && let (Some(to), Some(value)) = (frame.to, frame.value)
+        && !value.is_zero()
+    {
+        add_transfer(deltas, Address::ZERO, frame.from, to, value);
+    }
+
+    for log in &frame.logs {
+        if let Ok(transfer) =
+            IERC20::Transfer::decode_raw_log(log.topics.iter().copied(), &log.data)
+        {
+            add_transfer(
+                deltas,
+                log.address,
+                transfer.from,
+                transfer.to,
+                transfer.value,
+            );
+        }
+    }
+
+    for call in &frame.calls {
+        collect_trace_deltas(call, deltas);
+    }
+}
+
+// Helper function to move `value` of `token` from one account to another
+fn add_transfer(
+    deltas: &mut BTreeMap<(Address, Address), I256>,
+    token: Address,
+    from: Address,
+    to: Address,
+    value: U256,
+) {
+    // Deltas saturate, a token may log transfers no balance could hold
+    let value = I256::try_from(value).unwrap_or(I256::MAX);
+    let sent = deltas.entry((from, token)).or_insert(I256::ZERO);
+    *sent = sent.saturating_sub(value);
+    let received = deltas.entry((to, token)).or_insert(I256::ZERO);
+    *received = received.saturating_add(value);
+}
+
+// Helper function to build the read of a native or ERC20 balance
+fn balance_call(account: Address, token: Address) -> TransactionRequest {
+    let (target, input) = if token.is_zero() {
+        let call = IMulticall3::getEthBalanceCall { addr: account };
+        (MULTICALL3_ADDRESS, call.abi_encode())
+    } else {
+        let call = IERC20::balanceOfCall { account };
+        (token, call.abi_encode())
+    };
+    TransactionRequest::default()
+        .to(target)
+        .input(TransactionInput::new(input.into()))
+}
+
+// Helper function to read balances before and after the call in one simulated block,
+// returns the changes and the gas used by the call
+async fn read_balance_changes(
+    provider: &DynProvider,
+    tx: &TransactionRequest,
+    overrides: StateOverride,
+    watched: &[(Address, Address)],
+    block_number: u64,
+) -> Result<(Vec<BalanceChange>, u64)> {
+    let reads: Vec<_> = watched
+        .iter()
+        .map(|(account, token)| balance_call(*account, *token))
+        .collect();
+    let calls = reads
+        .iter()
+        .cloned()
+        .chain(std::iter::once(tx.clone()))
+        .chain(reads.iter().cloned())
+        .collect();
+    let payload = SimulatePayload {
+        block_state_calls: vec![SimBlock {
+            block_overrides: None,
+            state_overrides: Some(overrides),
+            calls,
+        }],
+        trace_transfers: false,
+        validation: false,
+        return_full_transactions: false,
+    };
+
+    let blocks = provider.simulate(&payload).number(block_number).await?;
+    let results = &blocks
+        .first()
+        .ok_or_else(|| anyhow!("eth_simulateV1 returned no block"))?
+        .calls;
+    if results.len() != 2 * reads.len() + 1 {
+        return Err(anyhow!("eth_simulateV1 returned {} calls", results.len()).into());
+    }
+
+    // Reads that reverted, e.g. of a contract that is not an ERC20, are skipped
+    let decode = |index: usize| {
+        let result = &results[index];
+        if !result.status {
+            return None;
+        }
+        IERC20::balanceOfCall::abi_decode_returns(&result.return_data).ok()
+    };
+    let changes = watched
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (account, token))| {
+            let before = decode(index)?;
+            let after = decode(reads.len() + 1 + index)?;
+            let delta = I256::try_from(after).ok()? - I256::try_from(before).ok()?;
+            (!delta.is_zero()).then_some(BalanceChange {
+                address: *account,
+                token_address: *token,
+                before: Some(before),
+                after: Some(after),
+                delta,
+            })
+        })
+        .collect();
+
+    Ok((changes, results[reads.len()].gas_used))
+}
@@ -1,11 +1,29 @@
 use alloy::providers::DynProvider;
 
-use crate::{cache::DistCache, db::Repository};
+use crate::{
+    api_keys::UsageMeter, cache::DistCache, db::Repository, dex::DexPricer,
+    events::AccountEventHub, faucet::Faucet, heads::HeadTracker, prices::PriceOracle,
+    shutdown::Shutdown, wallet::HotWallet, webhook::WebhookNotifier,
+};
 
 // the application state
 #[derive(Clone)]
 pub struct AppState {
     pub repo: Repository,
     pub eth_provider: DynProvider,
+    /// Chain of `eth_provider`, resolved once at startup
+    pub chain_id: u64,
     pub cache: DistCache,
+    pub notifier: WebhookNotifier,
+    pub heads: HeadTracker,
+    pub events: AccountEventHub,
+    pub prices: PriceOracle,
+    pub dex: DexPricer,
+    pub usage: UsageMeter,
+    /// None when no keystore is configured
+    pub wallet: Option<HotWallet>,
+    /// None without a hot wallet
+    pub faucet: Option<Faucet>,
+    /// Readiness and the cancellation of streams and background tasks
+    pub shutdown: Shutdown,
 }
@@ -0,0 +1,638 @@
+// Telemetry: Prometheus metrics and OpenTelemetry traces of HTTP requests, RPC calls,
+// Redis commands and database queries
+use std::collections::{BTreeSet, HashMap};
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use alloy::hex;
+use alloy::primitives::B128;
+use alloy::rpc::json_rpc::{Id, RequestPacket, ResponsePacket};
+use alloy::transports::{Transport, TransportError, TransportFut};
+use axum::{
+    extract::{MatchedPath, Request},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_otlp::{Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use redis::aio::{ConnectionLike, MultiplexedConnection};
+use redis::{Arg, Cmd, Pipeline, RedisFuture, Value};
+use reqwest::Url;
+use serde::Deserialize;
+use sqlx::postgres::{PgQueryResult, PgRow, PgStatement, PgTypeInfo};
+use sqlx::{Describe, Either, Execute, Executor, PgPool, Postgres};
+use tower::{Layer, Service};
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::error;
+
+/// Header carrying the id of a request, taken from the client or generated
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request id accepted from a client, longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Configuration for trace export
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// OTLP/HTTP traces endpoint, e.g. `http://localhost:4318/v1/traces`
+    /// Spans are only logged without it.
+    pub otlp_endpoint: Option<String>,
+    /// Encoding of exported spans
+    pub otlp_protocol: OtlpProtocol,
+    /// `service.name` of exported spans
+    pub service_name: String,
+}
+
+/// Encoding of OTLP/HTTP exports
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum OtlpProtocol {
+    #[serde(rename = "http/protobuf")]
+    HttpProtobuf,
+    #[serde(rename = "http/json")]
+    HttpJson,
+}
+
+tokio::task_local! {
+    /// Id of the request being handled, set by `trace_requests`
+    static REQUEST_ID: String;
+}
+
+/// Returns the id of the request being handled, none outside of a request
+/// Tasks spawned by a handler don't inherit it.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Builds the tracer provider exporting spans to the OTLP endpoint, none without one
+/// Spans are exported in batches from a background thread.
+pub fn tracer_provider(config: &Config) -> error::Result<Option<SdkTracerProvider>> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+    let protocol = match config.otlp_protocol {
+        OtlpProtocol::HttpProtobuf => Protocol::HttpBinary,
+        OtlpProtocol::HttpJson => Protocol::HttpJson,
+    };
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_protocol(protocol)
+        .with_endpoint(endpoint)
+        .build()?;
+    let resource = Resource::builder()
+        .with_service_name(config.service_name.clone())
+        .build();
+
+    Ok(Some(
+        SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource)
+            .build(),
+    ))
+}
+
+/// Initializes logging to stdout filtered by `RUST_LOG`, and span export through
+/// `provider` when given. Incoming `traceparent` headers continue the caller's trace.
+pub fn init_tracing(provider: Option<&SdkTracerProvider>) {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let otel = provider.map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+    });
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| format!("{}=debug", env!("CARGO_CRATE_NAME")).into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .with(otel)
+        .init();
+}
+
+/// Middleware giving every request an id and a server span
+/// The id comes from the `X-Request-Id` header, or is generated, and is echoed in the
+/// response. Spans of RPC calls, queries and Redis commands of the request are its
+/// children and carry the id too.
+pub async fn trace_requests(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| hex::encode(B128::random()));
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let span = tracing::info_span!(
+        "http_request",
+        otel.name = format!("{} {}", method, route),
+        otel.kind = "server",
+        otel.status_code = Empty,
+        http.request.method = %method,
+        http.route = %route,
+        http.response.status_code = Empty,
+        request_id = %request_id,
+    );
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    // Without a valid `traceparent` the span starts a new trace
+    let _ = span.set_parent(parent);
+
+    let mut response = REQUEST_ID
+        .scope(
+            request_id.clone(),
+            next.run(request).instrument(span.clone()),
+        )
+        .await;
+
+    span.record("http.response.status_code", response.status().as_u16());
+    if response.status().is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Whether a client supplied request id can be used as is: printable ASCII, not too long
+pub fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// Buckets of every `*_seconds` histogram, from 1ms to 10s
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static PROMETHEUS: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Installs the Prometheus recorder, once per process, and returns its handle
+/// Metrics recorded before the recorder is installed are dropped.
+pub fn install_metrics() -> &'static PrometheusHandle {
+    PROMETHEUS.get_or_init(|| {
+        PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Suffix("seconds".to_string()), LATENCY_BUCKETS)
+            .expect("invalid latency buckets")
+            .install_recorder()
+            .expect("install metrics recorder failed")
+    })
+}
+
+/// Middleware counting requests and recording their latency by route and status
+/// Routes are their matched pattern, e.g. `/v1/public/eth/accounts/{address}`,
+/// so addresses don't blow up the number of series.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    counter!("http_requests_total", &labels).increment(1);
+    histogram!("http_request_duration_seconds", &labels).record(started.elapsed().as_secs_f64());
+    response
+}
+
+/// Records a cache lookup as a `hit`, `miss` or `error`
+/// The cache is named by the first segment of the key, e.g. `price` for `price:usd:0x..`
+pub fn record_cache_lookup(key: &str, result: &'static str) {
+    let cache = key.split(':').next().unwrap_or(key).to_string();
+    counter!("cache_requests_total", "cache" => cache, "result" => result).increment(1);
+}
+
+/// Records the connections of a pool, in use and idle, and its maximum
+pub fn record_pool(pool: &PgPool) {
+    let size = pool.size() as usize;
+    let idle = pool.num_idle();
+    gauge!("db_pool_connections", "state" => "idle").set(idle as f64);
+    gauge!("db_pool_connections", "state" => "in_use").set(size.saturating_sub(idle) as f64);
+    gauge!("db_pool_max_connections").set(pool.options().get_max_connections() as f64);
+}
+
+/// Layer of the RPC client counting the calls, errors and latency of every JSON-RPC method,
+/// and tracing each call in an `rpc` span
+#[derive(Debug, Clone)]
+pub struct RpcTelemetryLayer {
+    endpoint: String,
+}
+
+impl RpcTelemetryLayer {
+    /// Create a new instance of `RpcTelemetryLayer` labelling calls with the host of `url`
+    /// Only the host is kept, RPC URLs often carry an API key in their path.
+    pub fn new(url: &Url) -> Self {
+        Self {
+            endpoint: url.host_str().unwrap_or("unknown").to_string(),
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcTelemetryLayer {
+    type Service = RpcTelemetry<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcTelemetry {
+            inner,
+            endpoint: self.endpoint.clone(),
+        }
+    }
+}
+
+/// Transport recording metrics and spans around an inner transport
+#[derive(Debug, Clone)]
+pub struct RpcTelemetry<S> {
+    inner: S,
+    endpoint: String,
+}
+
+impl<S> Service<RequestPacket> for RpcTelemetry<S>
+where
+    S: Transport + Clone,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let methods: HashMap<Id, String> = match &request {
+            RequestPacket::Single(request) => vec![request],
+            RequestPacket::Batch(requests) => requests.iter().collect(),
+        }
+        .into_iter()
+        .map(|request| (request.id().clone(), request.method().to_string()))
+        .collect();
+        let endpoint = self.endpoint.clone();
+        // Batches are named by their distinct methods
+        let name = methods
+            .values()
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>()
+            .join(",");
+        let span = tracing::info_span!(
+            "rpc",
+            otel.name = %name,
+            otel.kind = "client",
+            otel.status_code = Empty,
+            rpc.system = "jsonrpc",
+            rpc.method = %name,
+            server.address = %endpoint,
+            error.type = Empty,
+            request_id = current_request_id(),
+        );
+        let started = Instant::now();
+        let response = self.inner.call(request);
+
+        let record_span = span.clone();
+        let recorded = async move {
+            let response = response.await;
+            let elapsed = started.elapsed().as_secs_f64();
+
+            for method in methods.values() {
+                let labels = [("method", method.clone()), ("endpoint", endpoint.clone())];
+                counter!("rpc_requests_total", &labels).increment(1);
+                histogram!("rpc_request_duration_seconds", &labels).record(elapsed);
+            }
+            let failed: Vec<(&String, &'static str)> = match &response {
+                // The whole packet failed, timeouts and HTTP errors included
+                Err(_) => methods
+                    .values()
+                    .map(|method| (method, "transport"))
+                    .collect(),
+                Ok(response) => response
+                    .responses()
+                    .iter()
+                    .filter(|response| response.payload.is_error())
+                    .filter_map(|response| methods.get(&response.id))
+                    .map(|method| (method, "rpc"))
+                    .collect(),
+            };
+            for (method, kind) in failed {
+                record_span.record("otel.status_code", "ERROR");
+                record_span.record("error.type", kind);
+                counter!(
+                    "rpc_errors_total",
+                    "method" => method.clone(),
+                    "endpoint" => endpoint.clone(),
+                    "kind" => kind
+                )
+                .increment(1);
+            }
+
+            response
+        };
+        Box::pin(recorded.instrument(span))
+    }
+}
+
+/// Executor recording the latency of every query it runs, by `query_label`, and
+/// tracing it in a `db.query` span with the rows it returned or affected.
+/// Wraps a pool or a connection, e.g. `Instrumented(&mut *tx)` in a transaction.
+#[derive(Debug)]
+pub struct Instrumented<E>(pub E);
+
+impl<'c, E> Executor<'c> for Instrumented<E>
+where
+    E: Executor<'c, Database = Postgres>,
+{
+    type Database = Postgres;
+
+    fn fetch_many<'e, 'q: 'e, Q>(
+        self,
+        query: Q,
+    ) -> BoxStream<'e, Result<Either<PgQueryResult, PgRow>, sqlx::Error>>
+    where
+        'c: 'e,
+        Q: 'q + Execute<'q, Postgres>,
+    {
+        let mut timer = QueryTimer::start(query.sql());
+        self.0
+            .fetch_many(query)
+            .map(move |item| {
+                // Borrow the whole timer, so the stream owns it and records on drop
+                let timer = &mut timer;
+                match &item {
+                    Ok(Either::Left(result)) => timer.rows_affected += result.rows_affected(),
+                    Ok(Either::Right(_)) => timer.rows_returned += 1,
+                    Err(_) => timer.failed = true,
+                }
+                item
+            })
+            .boxed()
+    }
+
+    fn fetch_optional<'e, 'q: 'e, Q>(
+        self,
+        query: Q,
+    ) -> BoxFuture<'e, Result<Option<PgRow>, sqlx::Error>>
+    where
+        'c: 'e,
+        Q: 'q + Execute<'q, Postgres>,
+    {
+        let timer = QueryTimer::start(query.sql());
+        let row = self.0.fetch_optional(query);
+        Box::pin(async move {
+            let mut timer = timer;
+            let row = row.await;
+            match &row {
+                Ok(row) => timer.rows_returned = row.is_some() as u64,
+                Err(_) => timer.failed = true,
+            }
+            row
+        })
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [PgTypeInfo],
+    ) -> BoxFuture<'e, Result<PgStatement<'q>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        self.0.prepare_with(sql, parameters)
+    }
+
+    fn describe<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+    ) -> BoxFuture<'e, Result<Describe<Postgres>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        self.0.describe(sql)
+    }
+}
+
+/// Records the latency of a query and closes its span when dropped,
+/// so abandoned streams are recorded too
+struct QueryTimer {
+    query: String,
+    span: Span,
+    started: Instant,
+    rows_returned: u64,
+    rows_affected: u64,
+    failed: bool,
+}
+
+impl QueryTimer {
+    fn start(sql: &str) -> Self {
+        let query = query_label(sql);
+        // Parameters are bound separately, so the statement carries no values
+        let span = tracing::info_span!(
+            "db.query",
+            otel.name = %query,
+            otel.kind = "client",
+            otel.status_code = Empty,
+            db.system.name = "postgresql",
+            db.query.summary = %query,
+            db.query.text = sql,
+            db.response.returned_rows = Empty,
+            db.response.affected_rows = Empty,
+            request_id = current_request_id(),
+        );
+        Self {
+            query,
+            span,
+            started: Instant::now(),
+            rows_returned: 0,
+            rows_affected: 0,
+            failed: false,
+        }
+    }
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        self.span
+            .record("db.response.returned_rows", self.rows_returned);
+        self.span
+            .record("db.response.affected_rows", self.rows_affected);
+        if self.failed {
+            self.span.record("otel.status_code", "ERROR");
+        }
+
+        let status = if self.failed { "error" } else { "ok" };
+        histogram!(
+            "db_query_duration_seconds",
+            "query" => std::mem::take(&mut self.query),
+            "status" => status
+        )
+        .record(self.started.elapsed().as_secs_f64());
+    }
+}
+
+/// Labels a query by its statement and main table, e.g. `select eth_accounts`
+/// Values never show up in labels, so every query of the repository maps to a few series.
+pub fn query_label(sql: &str) -> String {
+    let words: Vec<String> = sql
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect();
+    let Some(statement) = words.first() else {
+        return "unknown".to_string();
+    };
+
+    let keyword = match statement.as_str() {
+        "insert" => Some("into"),
+        "delete" | "select" | "with" => Some("from"),
+        _ => None,
+    };
+    let table = match statement.as_str() {
+        "update" => words.get(1),
+        _ => keyword.and_then(|keyword| {
+            let position = words.iter().position(|word| word == keyword)?;
+            words.get(position + 1)
+        }),
+    };
+    let table = table.map(|table| {
+        table
+            .split(['(', ')', ',', ';'])
+            .next()
+            .unwrap_or_default()
+            .trim_matches('"')
+    });
+
+    match table {
+        Some(table) if !table.is_empty() => format!("{} {}", statement, table),
+        _ => statement.clone(),
+    }
+}
+
+/// Redis connection tracing every command in a `redis` span
+/// Every connection of `DistCache` is wrapped, so raw commands are traced too.
+#[derive(Clone)]
+pub struct InstrumentedConnection(pub MultiplexedConnection);
+
+impl ConnectionLike for InstrumentedConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        let (command, key) = command_name_and_key(cmd);
+        let span = redis_span(&command, key.as_deref(), 1);
+        let record_span = span.clone();
+        Box::pin(
+            async move {
+                let reply = self.0.req_packed_command(cmd).await;
+                if reply.is_err() {
+                    record_span.record("otel.status_code", "ERROR");
+                }
+                reply
+            }
+            .instrument(span),
+        )
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        let commands: Vec<_> = cmd.cmd_iter().map(command_name_and_key).collect();
+        let name = commands
+            .iter()
+            .map(|(command, _)| command.as_str())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>()
+            .join(",");
+        let key = commands.iter().find_map(|(_, key)| key.clone());
+        let span = redis_span(&name, key.as_deref(), commands.len());
+        let record_span = span.clone();
+        Box::pin(
+            async move {
+                let replies = self.0.req_packed_commands(cmd, offset, count).await;
+                if replies.is_err() {
+                    record_span.record("otel.status_code", "ERROR");
+                }
+                replies
+            }
+            .instrument(span),
+        )
+    }
+
+    fn get_db(&self) -> i64 {
+        self.0.get_db()
+    }
+}
+
+// Helper function to create the span of a Redis command
+fn redis_span(command: &str, key: Option<&str>, batch_size: usize) -> Span {
+    tracing::info_span!(
+        "redis",
+        otel.name = %command,
+        otel.kind = "client",
+        otel.status_code = Empty,
+        db.system.name = "redis",
+        db.operation.name = %command,
+        db.operation.batch.size = batch_size,
+        cache.key = key,
+        request_id = current_request_id(),
+    )
+}
+
+/// Returns the name and the key of a command as they appear in traces, credentials redacted
+pub fn command_name_and_key(cmd: &Cmd) -> (String, Option<String>) {
+    let mut args = cmd.args_iter().filter_map(|arg| match arg {
+        Arg::Simple(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        Arg::Cursor => None,
+    });
+    let command = args
+        .next()
+        .map(|command| command.to_uppercase())
+        .unwrap_or_default();
+    // Scripts take their keys after the hash and the number of keys
+    let key = match command.as_str() {
+        "EVALSHA" | "EVAL" => args.nth(2),
+        _ => args.next(),
+    };
+    (command, key.map(|key| redact_key(&key)))
+}
+
+/// Returns a cache key as it may appear in traces
+/// Session tokens and SIWE nonces are credentials, only their prefix is kept.
+pub fn redact_key(key: &str) -> String {
+    match key.strip_prefix("siwe:") {
+        Some(rest) => match rest.split_once(':') {
+            Some((kind, _)) => format!("siwe:{}:*", kind),
+            None => key.to_string(),
+        },
+        None => key.to_string(),
+    }
+}
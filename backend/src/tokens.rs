@@ -0,0 +1,131 @@
+// Token registry helpers: on-chain metadata resolution and token-list import
+use alloy::primitives::Address;
+use alloy::providers::DynProvider;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::db::{NewToken, Repository, Token};
+use crate::error::Result;
+use crate::eth::{IERC20Instance, ZERO_ADDRESS};
+
+/// A token list in the Uniswap token-list format, see https://tokenlists.org
+/// Only the fields stored in the registry are read
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TokenList {
+    pub name: String,
+    pub tokens: Vec<TokenInfo>,
+}
+
+/// A token entry of a token list
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TokenInfo {
+    #[serde(rename = "chainId")]
+    pub chain_id: u64,
+    pub address: String,
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+    #[serde(rename = "logoURI")]
+    pub logo_uri: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Result of a token-list import
+#[derive(Debug, PartialEq)]
+pub struct ImportSummary {
+    /// Tokens registered or updated
+    pub imported: usize,
+    /// Tokens of other chains or with an invalid address
+    pub skipped: usize,
+}
+
+/// Registers the tokens of a list that live on `chain_id`
+/// Existing tokens are updated with the list's metadata
+///
+/// # Arguments
+/// * `repo` - Repository to write to
+/// * `list` - Parsed token list
+/// * `chain_id` - Chain the service is connected to
+/// * `verified` - Verified flag to set on every imported token
+pub async fn import_token_list(
+    repo: &Repository,
+    list: &TokenList,
+    chain_id: u64,
+    verified: bool,
+) -> Result<ImportSummary> {
+    let tokens: Vec<NewToken> = list
+        .tokens
+        .iter()
+        .filter(|token| token.chain_id == chain_id && token.address.parse::<Address>().is_ok())
+        .map(|token| NewToken {
+            address: &token.address,
+            chain_id: token.chain_id,
+            symbol: Some(&token.symbol),
+            name: Some(&token.name),
+            decimals: Some(token.decimals.into()),
+            logo_uri: token.logo_uri.as_deref(),
+            tags: Some(&token.tags),
+            verified: Some(verified),
+        })
+        .collect();
+
+    let imported = repo.import_tokens(&tokens).await?;
+    tracing::info!("Imported {} tokens from list {}", imported, list.name);
+
+    Ok(ImportSummary {
+        imported,
+        skipped: list.tokens.len() - imported,
+    })
+}
+
+/// Returns the registry entry of a token on the connected chain, registering it first if needed
+/// Missing metadata is read from the token contract, the token stays unverified
+pub async fn ensure_registered(
+    repo: &Repository,
+    provider: &DynProvider,
+    chain_id: u64,
+    token_address: Address,
+) -> Result<Token> {
+    let address = token_address.to_string();
+    if let Some(token) = repo.get_token(chain_id, &address).await?
+        && token.decimals.is_some()
+    {
+        return Ok(token);
+    }
+
+    // Not every contract implements the optional metadata methods
+    let contract = IERC20Instance::new(token_address, provider.clone());
+    let symbol = contract.symbol().call().await.ok();
+    let name = contract.name().call().await.ok();
+    let decimals = contract.decimals().call().await.ok();
+
+    repo.upsert_token(&NewToken {
+        address: &address,
+        chain_id,
+        symbol: symbol.as_deref(),
+        name: name.as_deref(),
+        decimals: decimals.map(Into::into),
+        ..Default::default()
+    })
+    .await
+}
+
+/// Registers the native asset of a chain at `ZERO_ADDRESS`, unless it is registered already
+pub async fn register_native_token(repo: &Repository, chain_id: u64) -> Result<Token> {
+    if let Some(token) = repo.get_token(chain_id, ZERO_ADDRESS).await? {
+        return Ok(token);
+    }
+
+    repo.upsert_token(&NewToken {
+        address: ZERO_ADDRESS,
+        chain_id,
+        symbol: Some("ETH"),
+        name: Some("Ether"),
+        decimals: Some(18),
+        tags: Some(&["native".to_string()]),
+        verified: Some(true),
+        ..Default::default()
+    })
+    .await
+}
@@ -0,0 +1,440 @@
+// Nonce management and lifecycle tracking of the transactions sent by the hot wallet
+use std::str::FromStr;
+use std::time::Duration;
+
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, B256, Bytes, U256};
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::TransactionRequest;
+use chrono::Utc;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde::Deserialize;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+use crate::db::{
+    NewOutgoingTransaction, OutgoingTransaction, OutgoingTransactionStatus, Repository,
+};
+use crate::error::{Result, ValidateError};
+
+/// Purpose of the transactions cancelling a stuck nonce
+pub const CANCEL_PURPOSE: &str = "cancel";
+
+/// Gas of a plain native transfer, used by cancellations
+const CANCEL_GAS_LIMIT: u64 = 21_000;
+
+/// Configuration for tracking outgoing transactions
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// How often open transactions are checked, in seconds
+    pub poll_interval: u64,
+    /// Blocks on top of the including block before a transaction is confirmed
+    pub confirmations: u64,
+    /// Seconds a transaction may stay pending before it is resubmitted
+    pub stuck_after: u64,
+    /// Fee increase of a resubmission, nodes require at least 10 percent
+    pub fee_bump_percent: u64,
+    /// Resubmissions with the same payload before the nonce is cancelled instead
+    pub max_resubmissions: usize,
+    /// Cancellations of a nonce before it is marked stuck and left to an operator
+    pub max_cancellations: usize,
+    /// Highest fee per gas a resubmission may pay, in wei, the nonce is marked stuck instead
+    pub max_fee_per_gas: u64,
+}
+
+/// A transaction sent by the wallet, in the shape it is recorded
+struct SentTransaction {
+    from_address: String,
+    to_address: Option<String>,
+    tx_hash: String,
+    nonce: u64,
+    value: Decimal,
+    input: Bytes,
+    gas_limit: u64,
+    max_fee_per_gas: Decimal,
+    max_priority_fee_per_gas: Decimal,
+}
+
+impl SentTransaction {
+    fn record<'a>(&'a self, chain_id: u64, purpose: &'a str) -> NewOutgoingTransaction<'a> {
+        NewOutgoingTransaction {
+            from_address: &self.from_address,
+            chain_id,
+            nonce: self.nonce,
+            tx_hash: &self.tx_hash,
+            purpose,
+            to_address: self.to_address.as_deref(),
+            value: self.value,
+            input: &self.input,
+            gas_limit: self.gas_limit,
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+        }
+    }
+}
+
+/// Converts an amount of wei to its stored form
+/// Decimals hold up to about 7.9e28, larger amounts are rejected
+fn to_decimal(amount: U256) -> Result<Decimal> {
+    Decimal::from_str(&amount.to_string())
+        .map_err(|_| ValidateError(format!("Amount {} is too large to record", amount)).into())
+}
+
+/// Raises a stored fee by `percent`, rounding up
+fn bump_fee(fee: Decimal, percent: u64) -> Result<u128> {
+    let fee = fee
+        .to_u128()
+        .ok_or_else(|| anyhow::anyhow!("Invalid stored fee {}", fee))?;
+    Ok((fee * (100 + percent as u128)).div_ceil(100))
+}
+
+/// TransactionManager sends the transactions of a wallet with nonces handed out under a
+/// Postgres row lock, so several instances of the service never reuse a nonce. Every
+/// transaction is recorded in `outgoing_transactions` and followed through its lifecycle
+/// by `run`.
+#[derive(Clone)]
+pub struct TransactionManager {
+    address: Address,
+    chain_id: u64,
+    /// Provider signing with the wallet
+    provider: DynProvider,
+    repo: Repository,
+}
+
+impl TransactionManager {
+    /// Create a new instance of `TransactionManager`.
+    ///
+    /// # Arguments
+    /// * `address` - Address of the wallet
+    /// * `chain_id` - Chain the wallet sends on
+    /// * `provider` - Provider signing with the wallet
+    /// * `repo` - Repository holding the nonces and the transactions
+    pub fn new(address: Address, chain_id: u64, provider: DynProvider, repo: Repository) -> Self {
+        Self {
+            address,
+            chain_id,
+            provider,
+            repo,
+        }
+    }
+
+    /// Sends a transaction from the wallet and records it, returns its hash without
+    /// waiting for it. Gas is estimated unless the request sets it, fees are always
+    /// estimated. The nonce stays locked until the transaction is recorded.
+    ///
+    /// # Arguments
+    /// * `request` - Recipient, value and calldata of the transaction
+    /// * `purpose` - What the transaction is for, e.g. `faucet`
+    pub async fn send(&self, request: TransactionRequest, purpose: &str) -> Result<B256> {
+        let mut request = request.with_from(self.address).with_chain_id(self.chain_id);
+        if request.gas.is_none() {
+            let gas_limit = self.provider.estimate_gas(request.clone()).await?;
+            request.set_gas_limit(gas_limit);
+        }
+        let fees = self.provider.estimate_eip1559_fees().await?;
+        request.set_max_fee_per_gas(fees.max_fee_per_gas);
+        request.set_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+
+        let chain_nonce = self
+            .provider
+            .get_transaction_count(self.address)
+            .pending()
+            .await?;
+        let lock = self
+            .repo
+            .lock_wallet_nonce(&self.address.to_string(), self.chain_id, chain_nonce)
+            .await?;
+        request.set_nonce(lock.nonce());
+
+        let sent = self.send_request(request).await?;
+        lock.commit(&sent.record(self.chain_id, purpose)).await?;
+        Ok(sent.tx_hash.parse()?)
+    }
+
+    /// Sends a fully filled request
+    /// Amounts are converted before sending, a transaction that can't be recorded is not sent
+    async fn send_request(&self, request: TransactionRequest) -> Result<SentTransaction> {
+        let value = to_decimal(request.value.unwrap_or_default())?;
+        let max_fee_per_gas = to_decimal(U256::from(request.max_fee_per_gas.unwrap_or_default()))?;
+        let max_priority_fee_per_gas = to_decimal(U256::from(
+            request.max_priority_fee_per_gas.unwrap_or_default(),
+        ))?;
+
+        let pending = self.provider.send_transaction(request.clone()).await?;
+
+        Ok(SentTransaction {
+            from_address: self.address.to_string(),
+            to_address: request.to.and_then(|to| to.to().map(|to| to.to_string())),
+            tx_hash: pending.tx_hash().to_string(),
+            nonce: request.nonce.unwrap_or_default(),
+            value,
+            input: request.input.input().cloned().unwrap_or_default(),
+            gas_limit: request.gas.unwrap_or_default(),
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        })
+    }
+
+    /// Sends a transaction again with the same nonce and bumped fees, the old one is
+    /// recorded as replaced. A cancellation sends nothing to the wallet itself instead.
+    /// Nothing is sent when the bumped fee is above `max_fee_per_gas`, the nonce is
+    /// marked stuck instead and None is returned.
+    ///
+    /// # Arguments
+    /// * `tx` - Stuck transaction
+    /// * `cancel` - Whether to cancel the nonce instead of resending the payload
+    /// * `config` - Fee bump and cap
+    pub async fn resubmit(
+        &self,
+        tx: &OutgoingTransaction,
+        cancel: bool,
+        config: &Config,
+    ) -> Result<Option<OutgoingTransaction>> {
+        // Nodes only accept a replacement raising both fees, it must also keep up
+        // with the current base fee
+        let fees = self.provider.estimate_eip1559_fees().await?;
+        let max_priority_fee_per_gas =
+            bump_fee(tx.max_priority_fee_per_gas, config.fee_bump_percent)?
+                .max(fees.max_priority_fee_per_gas);
+        let max_fee_per_gas = bump_fee(tx.max_fee_per_gas, config.fee_bump_percent)?
+            .max(fees.max_fee_per_gas)
+            .max(max_priority_fee_per_gas);
+        if max_fee_per_gas > u128::from(config.max_fee_per_gas) {
+            self.mark_stuck(
+                tx,
+                &format!("its fee would exceed {} wei per gas", config.max_fee_per_gas),
+            )
+            .await?;
+            return Ok(None);
+        }
+
+        let request = if cancel {
+            TransactionRequest::default()
+                .with_to(self.address)
+                .with_value(U256::ZERO)
+                .with_gas_limit(CANCEL_GAS_LIMIT)
+        } else {
+            let mut request = TransactionRequest::default()
+                .with_value(U256::from_str(&tx.value.to_string())?)
+                .with_input(tx.input.clone())
+                .with_gas_limit(tx.gas_limit as u64);
+            if let Some(to) = &tx.to_address {
+                request.set_to(to.parse::<Address>()?);
+            }
+            request
+        };
+        let request = request
+            .with_from(self.address)
+            .with_chain_id(self.chain_id)
+            .with_nonce(tx.nonce as u64)
+            .with_max_fee_per_gas(max_fee_per_gas)
+            .with_max_priority_fee_per_gas(max_priority_fee_per_gas);
+
+        let sent = self.send_request(request).await?;
+        let purpose = if cancel { CANCEL_PURPOSE } else { &tx.purpose };
+        let replacement = self
+            .repo
+            .replace_outgoing_transaction(tx.id, &sent.record(self.chain_id, purpose))
+            .await?;
+        tracing::info!(
+            "Resubmitted transaction {} with nonce {} as {}",
+            tx.tx_hash,
+            tx.nonce,
+            replacement.tx_hash
+        );
+
+        Ok(Some(replacement))
+    }
+
+    /// Gives up on the nonce of a transaction, it is left to an operator
+    async fn mark_stuck(&self, tx: &OutgoingTransaction, reason: &str) -> Result<()> {
+        tracing::error!(
+            "Transaction {} with nonce {} is stuck, {}",
+            tx.tx_hash,
+            tx.nonce,
+            reason
+        );
+        self.repo.mark_outgoing_transaction_stuck(tx.id).await
+    }
+
+    /// Moves every open transaction of the wallet forward in its lifecycle:
+    /// pending transactions are mined, dropped or resubmitted when stuck, and mined
+    /// transactions are confirmed, or pending again when their block was reorged out.
+    /// Instances sending from the same wallet take turns, one that finds another
+    /// instance polling skips the round, so a nonce is never resubmitted twice.
+    pub async fn poll(&self, config: &Config) -> Result<()> {
+        let lock_key = format!("outgoing_transactions:{}:{}", self.address, self.chain_id);
+        let Some(_lock) = self.repo.try_advisory_lock(&lock_key).await? else {
+            tracing::debug!("Outgoing transactions of {} are polled elsewhere", self.address);
+            return Ok(());
+        };
+
+        let open = self
+            .repo
+            .list_open_outgoing_transactions(&self.address.to_string(), self.chain_id)
+            .await?;
+        if open.is_empty() {
+            return Ok(());
+        }
+        let latest_block = self.provider.get_block_number().await?;
+        let mined_nonce = self.provider.get_transaction_count(self.address).await?;
+
+        for tx in open {
+            let result = match tx.status {
+                OutgoingTransactionStatus::Pending => {
+                    self.check_pending(&tx, mined_nonce, config).await
+                }
+                OutgoingTransactionStatus::Mined => {
+                    self.check_mined(&tx, latest_block, config).await
+                }
+                _ => Ok(()),
+            };
+            if let Err(err) = result {
+                tracing::error!("Failed to track transaction {}: {}", tx.tx_hash, err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a pending transaction
+    async fn check_pending(
+        &self,
+        tx: &OutgoingTransaction,
+        mined_nonce: u64,
+        config: &Config,
+    ) -> Result<()> {
+        if let Some(receipt) = self
+            .provider
+            .get_transaction_receipt(tx.tx_hash.parse()?)
+            .await?
+        {
+            return self
+                .repo
+                .update_outgoing_transaction_status(
+                    tx.id,
+                    OutgoingTransactionStatus::Mined,
+                    receipt.block_number,
+                    Some(receipt.status()),
+                )
+                .await;
+        }
+
+        let nonce = tx.nonce as u64;
+        if nonce < mined_nonce {
+            // The nonce was used by an earlier attempt, or by a transaction sent elsewhere
+            let attempts = self
+                .repo
+                .list_nonce_attempts(&tx.from_address, self.chain_id, nonce)
+                .await?;
+            for attempt in attempts.iter().filter(|attempt| attempt.id != tx.id) {
+                if let Some(receipt) = self
+                    .provider
+                    .get_transaction_receipt(attempt.tx_hash.parse()?)
+                    .await?
+                {
+                    self.repo
+                        .update_outgoing_transaction_status(
+                            attempt.id,
+                            OutgoingTransactionStatus::Mined,
+                            receipt.block_number,
+                            Some(receipt.status()),
+                        )
+                        .await?;
+                    break;
+                }
+            }
+            tracing::warn!("Transaction {} was dropped", tx.tx_hash);
+            return self
+                .repo
+                .update_outgoing_transaction_status(
+                    tx.id,
+                    OutgoingTransactionStatus::Dropped,
+                    None,
+                    None,
+                )
+                .await;
+        }
+
+        let pending_for = (Utc::now().naive_utc() - tx.created_at).num_seconds();
+        if tx.stuck_at.is_some() || pending_for < config.stuck_after as i64 {
+            return Ok(());
+        }
+        let attempts = self
+            .repo
+            .list_nonce_attempts(&tx.from_address, self.chain_id, nonce)
+            .await?;
+        // Past the resubmissions the nonce is cancelled, cancellations stay cancellations
+        let resubmissions = attempts.len() - 1;
+        let cancel = tx.purpose == CANCEL_PURPOSE || resubmissions >= config.max_resubmissions;
+        let cancellations = attempts
+            .iter()
+            .filter(|attempt| attempt.purpose == CANCEL_PURPOSE)
+            .count();
+        if cancel && cancellations >= config.max_cancellations {
+            return self
+                .mark_stuck(tx, &format!("{} cancellations were not mined", cancellations))
+                .await;
+        }
+        self.resubmit(tx, cancel, config).await?;
+        Ok(())
+    }
+
+    /// Checks a mined transaction
+    async fn check_mined(
+        &self,
+        tx: &OutgoingTransaction,
+        latest_block: u64,
+        config: &Config,
+    ) -> Result<()> {
+        let Some(receipt) = self
+            .provider
+            .get_transaction_receipt(tx.tx_hash.parse()?)
+            .await?
+        else {
+            tracing::warn!("Transaction {} was reorged out", tx.tx_hash);
+            return self
+                .repo
+                .update_outgoing_transaction_status(
+                    tx.id,
+                    OutgoingTransactionStatus::Pending,
+                    None,
+                    None,
+                )
+                .await;
+        };
+
+        let block_number = receipt.block_number.unwrap_or_default();
+        let status = if latest_block >= block_number + config.confirmations {
+            OutgoingTransactionStatus::Confirmed
+        } else if tx.block_number != Some(block_number as i64) {
+            // Included again in another block
+            OutgoingTransactionStatus::Mined
+        } else {
+            return Ok(());
+        };
+        self.repo
+            .update_outgoing_transaction_status(
+                tx.id,
+                status,
+                Some(block_number),
+                Some(receipt.status()),
+            )
+            .await
+    }
+
+    /// Checks the open transactions every `poll_interval` seconds until `shutdown` is cancelled
+    pub async fn run(self, config: Config, shutdown: CancellationToken) {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.poll_interval));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            if let Err(err) = self.poll(&config).await {
+                tracing::error!("Failed to track outgoing transactions: {}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+// Hot wallet signing the transactions of the service, loaded from an encrypted keystore
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol_types::sol;
+use serde::Deserialize;
+
+use crate::db::Repository;
+use crate::error::Result;
+use crate::transactions::TransactionManager;
+
+// Import the generated contract bindings for MyToken, `abi/MyToken.json` is a copy of
+// the Ignition artifact in `contracts/ignition/deployments` and carries the bytecode
+sol!(
+    #[sol(rpc)]
+    MyToken,
+    "abi/MyToken.json"
+);
+
+/// Gas limit of an ERC20 transfer to a new holder, with some headroom
+const TRANSFER_GAS_LIMIT: u64 = 100_000;
+
+/// Configuration for the hot wallet
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Encrypted JSON keystore of the wallet, the wallet is disabled without it
+    pub keystore_path: Option<String>,
+    /// Password of the keystore, better set through `APP__WALLET__KEYSTORE_PASSWORD`
+    pub keystore_password: Option<String>,
+    /// Address of the deployed MyToken contract
+    pub my_token_address: Address,
+}
+
+/// Decrypts the signer of the configured keystore
+/// Returns none if no keystore is configured
+pub fn load_signer(config: &Config) -> Result<Option<PrivateKeySigner>> {
+    let Some(keystore_path) = &config.keystore_path else {
+        return Ok(None);
+    };
+    let password = config.keystore_password.as_deref().unwrap_or_default();
+    Ok(Some(PrivateKeySigner::decrypt_keystore(
+        keystore_path,
+        password,
+    )?))
+}
+
+/// Transactions sent to mint MyToken to an account
+#[derive(Debug, Clone, PartialEq)]
+pub struct MintTransactions {
+    pub mint_tx_hash: B256,
+    /// None when the tokens were minted to the wallet itself
+    pub transfer_tx_hash: Option<B256>,
+}
+
+/// HotWallet signs and sends transactions with a key held by the service
+/// Transactions are sent through a `TransactionManager`, which hands out the nonces
+/// and records every transaction
+#[derive(Clone)]
+pub struct HotWallet {
+    address: Address,
+    provider: DynProvider,
+    transactions: TransactionManager,
+}
+
+impl HotWallet {
+    /// Create a new instance of `HotWallet` signing with `signer` on top of `provider`.
+    ///
+    /// # Arguments
+    /// * `provider` - Provider of the chain
+    /// * `signer` - Key of the wallet
+    /// * `repo` - Repository recording the transactions of the wallet
+    /// * `chain_id` - Chain id of the provider
+    pub fn new(
+        provider: DynProvider,
+        signer: PrivateKeySigner,
+        repo: Repository,
+        chain_id: u64,
+    ) -> Self {
+        let address = signer.address();
+        let provider = ProviderBuilder::new()
+            .wallet(EthereumWallet::from(signer))
+            .connect_provider(provider)
+            .erased();
+        let transactions = TransactionManager::new(address, chain_id, provider.clone(), repo);
+        Self {
+            address,
+            provider,
+            transactions,
+        }
+    }
+
+    /// Loads the wallet from the configured keystore
+    /// Returns none if no keystore is configured
+    pub fn from_config(
+        provider: DynProvider,
+        repo: Repository,
+        chain_id: u64,
+        config: &Config,
+    ) -> Result<Option<Self>> {
+        let Some(signer) = load_signer(config)? else {
+            return Ok(None);
+        };
+        Ok(Some(Self::new(provider, signer, repo, chain_id)))
+    }
+
+    /// Returns the address of the wallet
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Returns the manager sending and tracking the transactions of the wallet
+    pub fn transactions(&self) -> &TransactionManager {
+        &self.transactions
+    }
+
+    /// Returns the native balance of the wallet, or its balance of an ERC20 token
+    pub async fn balance(&self, token_address: Option<Address>) -> Result<U256> {
+        match token_address {
+            Some(token_address) => {
+                let token = MyToken::new(token_address, self.provider.clone());
+                Ok(token.balanceOf(self.address).call().await?)
+            }
+            None => Ok(self.provider.get_balance(self.address).await?),
+        }
+    }
+
+    /// Sends native ETH to `to`, returns the transaction hash without waiting for it
+    ///
+    /// # Arguments
+    /// * `to` - Recipient
+    /// * `amount` - Amount in wei
+    /// * `purpose` - What the transaction is for, recorded with it
+    pub async fn send_eth(&self, to: Address, amount: U256, purpose: &str) -> Result<B256> {
+        let tx = TransactionRequest::default().with_to(to).with_value(amount);
+        self.transactions.send(tx, purpose).await
+    }
+
+    /// Transfers an ERC20 token to `to`, returns the transaction hash without waiting for it
+    ///
+    /// # Arguments
+    /// * `token_address` - Address of the token contract
+    /// * `to` - Recipient
+    /// * `amount` - Amount in the token's smallest unit
+    /// * `purpose` - What the transaction is for, recorded with it
+    pub async fn transfer_token(
+        &self,
+        token_address: Address,
+        to: Address,
+        amount: U256,
+        purpose: &str,
+    ) -> Result<B256> {
+        let token = MyToken::new(token_address, self.provider.clone());
+        let tx = token.transfer(to, amount).into_transaction_request();
+        self.transactions.send(tx, purpose).await
+    }
+
+    /// Mints MyToken and transfers it to `to`
+    /// `mint` credits the caller, so the wallet mints to itself first. Both transactions
+    /// are sent without waiting, the transfer follows the mint by nonce.
+    ///
+    /// # Arguments
+    /// * `token_address` - Address of the MyToken contract
+    /// * `to` - Account receiving the tokens
+    /// * `amount` - Amount in the token's smallest unit
+    pub async fn mint_my_token(
+        &self,
+        token_address: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<MintTransactions> {
+        let token = MyToken::new(token_address, self.provider.clone());
+
+        let mint = token.mint(amount).into_transaction_request();
+        let mint_tx_hash = self.transactions.send(mint, "mint").await?;
+
+        let transfer_tx_hash = if to == self.address {
+            None
+        } else {
+            // The transfer is estimated before the mint is mined, so its gas is set explicitly
+            let transfer = token
+                .transfer(to, amount)
+                .gas(TRANSFER_GAS_LIMIT)
+                .into_transaction_request();
+            Some(self.transactions.send(transfer, "mint").await?)
+        };
+
+        Ok(MintTransactions {
+            mint_tx_hash,
+            transfer_tx_hash,
+        })
+    }
+}
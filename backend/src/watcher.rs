@@ -0,0 +1,199 @@
+// Background scheduler that keeps the balances of watched (address, token) pairs fresh
+use std::time::Duration;
+
+use alloy::providers::Provider;
+use serde::Deserialize;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+use crate::db::Upsert;
+use crate::error::Result;
+use crate::eth;
+use crate::state::AppState;
+
+/// Cache key prefix and TTL of the lock that lets a single instance refresh a block range
+const REFRESH_LOCK_KEY_PREFIX: &str = "watcher:refresh";
+const REFRESH_LOCK_TTL: u64 = 60;
+
+/// Configuration for the balance watcher
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Refresh watched balances every N blocks
+    pub refresh_interval_blocks: u64,
+    /// How often to poll the provider for a new block, in seconds
+    pub poll_interval: u64,
+    /// Maximum number of pairs read in one Multicall
+    pub batch_size: usize,
+}
+
+/// BalanceWatcher periodically reads every watched balance with Multicall batching
+/// and writes it through `Repository`, which records each change in history
+pub struct BalanceWatcher {
+    state: AppState,
+    config: Config,
+}
+
+impl BalanceWatcher {
+    /// Create a new instance of `BalanceWatcher` with the provided state and configuration.
+    pub fn new(state: AppState, config: Config) -> Self {
+        Self { state, config }
+    }
+
+    /// Runs the refresh loop until `shutdown` is cancelled
+    /// Failures are logged and the refresh is retried on the next poll.
+    /// A refresh in progress completes, so no balance write is cut off.
+    pub async fn run(self, shutdown: CancellationToken) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(self.config.poll_interval));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut last_refreshed_block: Option<u64> = None;
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+
+            let block_number = match self.state.eth_provider.get_block_number().await {
+                Ok(block_number) => block_number,
+                Err(err) => {
+                    tracing::error!("Watcher failed to get block number: {}", err);
+                    continue;
+                }
+            };
+            if last_refreshed_block
+                .is_some_and(|last| block_number < last + self.config.refresh_interval_blocks)
+            {
+                continue;
+            }
+
+            match self.refresh(block_number).await {
+                Ok(changed) => {
+                    tracing::info!(
+                        "Refreshed watched balances at block {}, {} changed",
+                        block_number,
+                        changed
+                    );
+                    last_refreshed_block = Some(block_number);
+                }
+                Err(err) => tracing::error!("Failed to refresh watched balances: {}", err),
+            }
+        }
+    }
+
+    /// Refreshes every watched balance at `block_number`
+    /// Returns the number of balances that changed since their last stored value
+    pub async fn refresh(&self, block_number: u64) -> Result<usize> {
+        // Instances sharing the cache only need one of them to refresh each interval
+        let lock_key = format!(
+            "{}:{}",
+            REFRESH_LOCK_KEY_PREFIX,
+            block_number / self.config.refresh_interval_blocks.max(1)
+        );
+        match self.state.cache.set_nx_ex(&lock_key, block_number, REFRESH_LOCK_TTL).await {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::debug!("Watched balances already refreshed by another instance");
+                return Ok(0);
+            }
+            Err(err) => tracing::warn!("Failed to acquire watcher lock, refresh anyway: {}", err),
+        }
+
+        let refreshed = self.refresh_watched(block_number).await;
+        // Release the interval so the next poll, of any instance, retries the refresh
+        if refreshed.is_err()
+            && let Err(err) = self.state.cache.delete(&lock_key).await
+        {
+            tracing::warn!("Failed to release watcher lock: {}", err);
+        }
+        refreshed
+    }
+
+    /// Reads every watched balance at `block_number` in batches and stores it
+    async fn refresh_watched(&self, block_number: u64) -> Result<usize> {
+        let mut after_id = 0;
+        let mut changed = 0;
+        loop {
+            let watched = self
+                .state
+                .repo
+                .list_watched_balances(after_id, self.config.batch_size as i64)
+                .await?;
+            let Some(last) = watched.last() else {
+                break;
+            };
+            after_id = last.id;
+
+            let pairs = watched
+                .iter()
+                .map(|item| Ok((item.address.parse()?, item.token_address.parse()?)))
+                .collect::<Result<Vec<_>>>()?;
+            let balances = eth::get_balances(&self.state.eth_provider, &pairs, block_number).await?;
+
+            for (item, balance) in watched.iter().zip(balances) {
+                let Some(balance) = balance else {
+                    tracing::warn!(
+                        "Failed to read balance of {} for token {}",
+                        item.address,
+                        item.token_address
+                    );
+                    continue;
+                };
+
+                let balance = balance.to_string().parse()?;
+                // A failing pair, e.g. of a token removed from the registry, doesn't stop the others
+                let previous = match self
+                    .state
+                    .repo
+                    .upsert_eth_account_balance(
+                        self.state.chain_id,
+                        &item.address,
+                        &item.token_address,
+                        block_number,
+                        balance,
+                    )
+                    .await
+                {
+                    Ok(previous) => previous,
+                    Err(err) => {
+                        tracing::error!(
+                            "Failed to store balance of {} for token {}: {}",
+                            item.address,
+                            item.token_address,
+                            err
+                        );
+                        continue;
+                    }
+                };
+                // Stale reads stored nothing, and a first read has nothing to compare to
+                let Upsert::Written(Some(previous)) = previous else {
+                    continue;
+                };
+                if previous == balance {
+                    continue;
+                }
+                changed += 1;
+
+                if let Err(err) = self
+                    .state
+                    .notifier
+                    .notify_balance_change(
+                        &item.address,
+                        &item.token_address,
+                        block_number,
+                        previous,
+                        balance,
+                    )
+                    .await
+                {
+                    tracing::error!("Failed to notify balance change of {}: {}", item.address, err);
+                }
+            }
+
+            if watched.len() < self.config.batch_size {
+                break;
+            }
+        }
+
+        Ok(changed)
+    }
+}
@@ -0,0 +1,198 @@
+// Webhook notifications for balance changes of watched addresses
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+
+use crate::db::{NewWebhookDelivery, Repository, Webhook};
+use crate::error::Result;
+use crate::shutdown::Shutdown;
+
+/// Header carrying the `sha256=<hex>` HMAC of the request body
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+/// Header carrying the event id, identical across retries and replays
+pub const EVENT_ID_HEADER: &str = "X-Webhook-Event-Id";
+
+/// Configuration for webhook deliveries
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Maximum number of attempts per delivery
+    pub max_attempts: u32,
+    /// Delay before the first retry in milliseconds, doubled after every attempt
+    pub initial_backoff_ms: u64,
+    /// Timeout of a single request in seconds
+    pub request_timeout: u64,
+}
+
+/// Signs a payload with HMAC-SHA256, in the `sha256=<hex>` format of `SIGNATURE_HEADER`
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", alloy::hex::encode(mac.finalize().into_bytes()))
+}
+
+/// WebhookNotifier sends signed balance change payloads to registered webhooks
+/// Every attempt is recorded in `webhook_deliveries`
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    repo: Repository,
+    client: reqwest::Client,
+    config: Config,
+    /// Deliveries run as background tasks of the shutdown, which stops their retries
+    shutdown: Shutdown,
+}
+
+impl WebhookNotifier {
+    /// Create a new instance of `WebhookNotifier` with the provided repository and configuration.
+    /// Deliveries are spawned on `shutdown`, which waits for them when draining.
+    pub fn new(repo: Repository, config: &Config, shutdown: Shutdown) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout))
+            .build()?;
+
+        Ok(Self {
+            repo,
+            client,
+            config: config.clone(),
+            shutdown,
+        })
+    }
+
+    /// Notifies the webhooks of an (address, token) pair whose threshold the change exceeds
+    /// Deliveries run in the background, returns the number of notified webhooks
+    pub async fn notify_balance_change(
+        &self,
+        address: &str,
+        token_address: &str,
+        block_number: u64,
+        previous_balance: Decimal,
+        balance: Decimal,
+    ) -> Result<usize> {
+        let delta = balance - previous_balance;
+        let webhooks = self
+            .repo
+            .find_webhooks_for_balance(address, token_address)
+            .await?;
+
+        let mut notified = 0;
+        for webhook in webhooks {
+            if delta.abs() <= webhook.threshold {
+                continue;
+            }
+
+            let event_id = format!(
+                "{}:{}:{}:{}",
+                webhook.id,
+                address.to_lowercase(),
+                token_address.to_lowercase(),
+                block_number
+            );
+            let payload = json!({
+                "event": "balance.changed",
+                "event_id": event_id,
+                "webhook_id": webhook.id,
+                "address": address.to_lowercase(),
+                "token_address": token_address.to_lowercase(),
+                "block_number": block_number,
+                "previous_balance": previous_balance.to_string(),
+                "balance": balance.to_string(),
+                "delta": delta.to_string(),
+            });
+
+            self.spawn_delivery(webhook, event_id, payload);
+            notified += 1;
+        }
+
+        Ok(notified)
+    }
+
+    /// Delivers a payload to a webhook in the background, see `deliver`
+    pub fn spawn_delivery(&self, webhook: Webhook, event_id: String, payload: serde_json::Value) {
+        let notifier = self.clone();
+        self.shutdown.spawn(async move {
+            if let Err(err) = notifier.deliver(&webhook, &event_id, &payload).await {
+                tracing::error!("Failed to deliver webhook {}: {}", webhook.id, err);
+            }
+        });
+    }
+
+    /// Delivers a payload to a webhook, retrying with exponential backoff
+    /// Retries stop once draining started, the delivery can be replayed later
+    /// Returns whether the receiver acknowledged it with a 2xx status
+    pub async fn deliver(
+        &self,
+        webhook: &Webhook,
+        event_id: &str,
+        payload: &serde_json::Value,
+    ) -> Result<bool> {
+        let body = serde_json::to_vec(payload)?;
+        let signature = sign_payload(&webhook.secret, &body);
+        let mut backoff = Duration::from_millis(self.config.initial_backoff_ms);
+
+        for attempt in 1..=self.config.max_attempts {
+            let response = self
+                .client
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, &signature)
+                .header(EVENT_ID_HEADER, event_id)
+                .body(body.clone())
+                .send()
+                .await;
+
+            let (status_code, error) = match response {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status().as_u16()), None)
+                }
+                Ok(response) => (
+                    Some(response.status().as_u16()),
+                    Some(format!("Receiver returned {}", response.status())),
+                ),
+                Err(err) => (None, Some(err.to_string())),
+            };
+            let succeeded = error.is_none();
+
+            self.repo
+                .insert_webhook_delivery(&NewWebhookDelivery {
+                    webhook_id: webhook.id,
+                    event_id,
+                    payload,
+                    attempt: attempt as i32,
+                    status_code: status_code.map(i32::from),
+                    error: error.as_deref(),
+                    succeeded,
+                })
+                .await?;
+
+            if succeeded {
+                return Ok(true);
+            }
+            tracing::warn!(
+                "Webhook {} attempt {} failed: {}",
+                webhook.id,
+                attempt,
+                error.unwrap_or_default()
+            );
+
+            if attempt < self.config.max_attempts {
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => backoff *= 2,
+                    _ = self.shutdown.cancelled() => {
+                        tracing::warn!(
+                            "Webhook {} retries of event {} stopped by shutdown",
+                            webhook.id,
+                            event_id
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(false)
+    }
+}
@@ -0,0 +1,69 @@
+use backend::api_keys::{UsageMeter, generate_key, hash_key, key_prefix, route_scope};
+use backend::db::Repository;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+#[test]
+fn test_generate_and_hash_key() {
+    let key = generate_key();
+    assert!(key.starts_with("ak_"));
+    assert_eq!(key.len(), 67);
+    assert_ne!(key, generate_key());
+
+    assert_eq!(key_prefix(&key), &key[..11]);
+    assert_eq!(key_prefix("ak_1"), "ak_1");
+
+    let hash = hash_key(&key);
+    assert_eq!(hash.len(), 64);
+    assert_eq!(hash, hash_key(&key));
+    assert_eq!(
+        hash_key("abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn test_route_scope() {
+    assert_eq!(route_scope("/v1/public/eth/misc"), Some("public"));
+    assert_eq!(route_scope("/v1/private/me"), Some("private"));
+    assert_eq!(route_scope("/health"), None);
+}
+
+#[test]
+fn test_usage_meter_aggregates() {
+    let meter = UsageMeter::new();
+    let day = NaiveDate::from_ymd_opt(2025, 7, 22).unwrap();
+    meter.record(2, "/v1/public/eth/misc", day);
+    meter.record(1, "/v1/public/eth/misc", day);
+    meter.record(1, "/v1/public/eth/misc", day);
+    meter.record(1, "/v1/private/me", day);
+
+    let usage = meter.take();
+    let counts: Vec<_> = usage
+        .iter()
+        .map(|delta| (delta.api_key_id, delta.route.as_str(), delta.request_count))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![
+            (1, "/v1/private/me", 1),
+            (1, "/v1/public/eth/misc", 2),
+            (2, "/v1/public/eth/misc", 1),
+        ]
+    );
+    assert!(meter.take().is_empty());
+}
+
+#[sqlx::test()]
+async fn test_usage_meter_keeps_usage_on_failure(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let meter = UsageMeter::new();
+    let day = NaiveDate::from_ymd_opt(2025, 7, 22).unwrap();
+
+    // The key doesn't exist, so the write fails and the usage is kept for a retry
+    meter.record(42, "/v1/public/eth/misc", day);
+    assert!(meter.flush(&repo).await.is_err());
+    assert_eq!(meter.take().len(), 1);
+
+    assert_eq!(meter.flush(&repo).await.unwrap(), 0);
+}
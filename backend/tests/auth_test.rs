@@ -0,0 +1,76 @@
+use alloy::primitives::address;
+use backend::auth::SiweMessage;
+use chrono::{DateTime, Duration, Utc};
+
+const MESSAGE: &str = "localhost:3000 wants you to sign in with your Ethereum account:
+0xEA921FB6d4CF7F5CeD3E5A774dea51496D1ed2bF
+
+Sign in to the dapp
+
+URI: http://localhost:3000/login
+Version: 1
+Chain ID: 11155111
+Nonce: 32891756
+Issued At: 2025-07-15T09:00:00Z
+Expiration Time: 2025-07-15T10:00:00Z
+Request ID: request-1
+Resources:
+- https://example.com/terms
+- ipfs://bafybeiemxf5abjwjbikoz4mc3a3dla6ual3jsgpdr4cjr3oz3evfyavhwq";
+
+// Helper function to parse an RFC 3339 timestamp
+fn time(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+}
+
+#[test]
+fn test_parse_siwe_message() {
+    let message: SiweMessage = MESSAGE.parse().unwrap();
+    assert_eq!(message.domain, "localhost:3000");
+    assert_eq!(message.address, address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf"));
+    assert_eq!(message.statement.as_deref(), Some("Sign in to the dapp"));
+    assert_eq!(message.uri, "http://localhost:3000/login");
+    assert_eq!(message.version, "1");
+    assert_eq!(message.chain_id, 11155111);
+    assert_eq!(message.nonce, "32891756");
+    assert_eq!(message.issued_at, time("2025-07-15T09:00:00Z"));
+    assert_eq!(message.expiration_time, Some(time("2025-07-15T10:00:00Z")));
+    assert_eq!(message.not_before, None);
+    assert_eq!(message.request_id.as_deref(), Some("request-1"));
+    assert_eq!(message.resources.len(), 2);
+}
+
+#[test]
+fn test_parse_siwe_message_without_statement() {
+    let message = MESSAGE.replace("Sign in to the dapp\n\n", "");
+    let message: SiweMessage = message.parse().unwrap();
+    assert_eq!(message.statement, None);
+    assert_eq!(message.uri, "http://localhost:3000/login");
+}
+
+#[test]
+fn test_parse_invalid_siwe_message() {
+    // Wrong header, bad address, short nonce, missing field and trailing content
+    assert!(MESSAGE.replace("wants you to", "asks you to").parse::<SiweMessage>().is_err());
+    assert!(MESSAGE.replace("0xEA921FB6", "0xZZ921FB6").parse::<SiweMessage>().is_err());
+    assert!(MESSAGE.replace("Nonce: 32891756", "Nonce: 123").parse::<SiweMessage>().is_err());
+    assert!(MESSAGE.replace("Version: 1\n", "").parse::<SiweMessage>().is_err());
+    assert!(format!("{}\nextra", MESSAGE).parse::<SiweMessage>().is_err());
+}
+
+#[test]
+fn test_validate_siwe_message() {
+    let message: SiweMessage = MESSAGE.parse().unwrap();
+    let now = time("2025-07-15T09:30:00Z");
+
+    assert!(message.validate("localhost:3000", 11155111, now).is_ok());
+    assert!(message.validate("evil.example.com", 11155111, now).is_err());
+    assert!(message.validate("localhost:3000", 1, now).is_err());
+    assert!(message.validate("localhost:3000", 11155111, now + Duration::hours(1)).is_err());
+
+    let message: SiweMessage = MESSAGE
+        .replace("Request ID", "Not Before: 2025-07-15T09:45:00Z\nRequest ID")
+        .parse()
+        .unwrap();
+    assert!(message.validate("localhost:3000", 11155111, now).is_err());
+}
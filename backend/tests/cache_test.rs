@@ -1,5 +1,4 @@
 use std::time::Duration;
-use tokio;
 use redis::AsyncCommands;
 
 use backend::cache::{Config, DistCache};
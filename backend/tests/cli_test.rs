@@ -0,0 +1,89 @@
+use backend::backfill::sample_blocks;
+use backend::cli::{BackfillKind, CacheCommand, Cli, Command, MigrateCommand};
+use clap::{CommandFactory, Parser};
+
+#[test]
+fn test_cli_definition() {
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn test_parse_commands() {
+    // The binary serves without a subcommand
+    let cli = Cli::try_parse_from(["backend"]).unwrap();
+    assert!(cli.command.is_none());
+
+    let cli = Cli::try_parse_from(["backend", "serve", "--skip-migrations"]).unwrap();
+    let Some(Command::Serve(args)) = cli.command else {
+        panic!("expected serve");
+    };
+    assert!(args.skip_migrations);
+
+    let cli = Cli::try_parse_from(["backend", "migrate", "down"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Migrate(MigrateCommand::Down { steps: 1 }))
+    ));
+    let cli = Cli::try_parse_from(["backend", "migrate", "down", "--steps", "3"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Migrate(MigrateCommand::Down { steps: 3 }))
+    ));
+    let cli = Cli::try_parse_from(["backend", "migrate", "status"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Migrate(MigrateCommand::Status))
+    ));
+
+    let cli = Cli::try_parse_from(["backend", "check-config"]).unwrap();
+    assert!(matches!(cli.command, Some(Command::CheckConfig)));
+
+    let cli = Cli::try_parse_from(["backend", "cache", "flush"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Cache(CacheCommand::Flush { all: false }))
+    ));
+    let cli = Cli::try_parse_from(["backend", "cache", "flush", "--all"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Cache(CacheCommand::Flush { all: true }))
+    ));
+}
+
+#[test]
+fn test_parse_backfill() {
+    let cli = Cli::try_parse_from([
+        "backend",
+        "backfill",
+        "transfers",
+        "--from-block",
+        "100",
+        "--chunk-size",
+        "10",
+    ])
+    .unwrap();
+    let Some(Command::Backfill(args)) = cli.command else {
+        panic!("expected backfill");
+    };
+    assert_eq!(args.kind, BackfillKind::Transfers);
+    assert_eq!(args.from_block, 100);
+    assert_eq!(args.to_block, None);
+    assert_eq!(args.chunk_size, 10);
+
+    // The kind and the first block are required
+    assert!(Cli::try_parse_from(["backend", "backfill", "--from-block", "1"]).is_err());
+    assert!(Cli::try_parse_from(["backend", "backfill", "balances"]).is_err());
+    assert!(Cli::try_parse_from(["backend", "backfill", "logs", "--from-block", "1"]).is_err());
+    assert!(Cli::try_parse_from(["backend", "migrate", "sideways"]).is_err());
+}
+
+#[test]
+fn test_sample_blocks() {
+    assert_eq!(sample_blocks(10, 30, 10), vec![10, 20, 30]);
+    // The last block is always read
+    assert_eq!(sample_blocks(10, 35, 10), vec![10, 20, 30, 35]);
+    assert_eq!(sample_blocks(10, 10, 100), vec![10]);
+    // A zero step reads every block
+    assert_eq!(sample_blocks(1, 3, 0), vec![1, 2, 3]);
+    assert!(sample_blocks(10, 5, 1).is_empty());
+}
@@ -0,0 +1,36 @@
+// Fixtures shared by the integration tests
+use alloy::providers::{Provider, ProviderBuilder};
+use sqlx::PgPool;
+
+use backend::{
+    api_keys::UsageMeter, cache::DistCache, config::CONFIG, db::Repository, dex::DexPricer,
+    events::AccountEventHub, heads::HeadTracker, prices::PriceOracle, shutdown::Shutdown,
+    state::AppState, webhook::WebhookNotifier,
+};
+
+// Helper function to create an application state, nothing is connected until it is used
+pub async fn create_state(pool: PgPool) -> AppState {
+    let eth_provider = ProviderBuilder::new()
+        .connect_http(CONFIG.eth_rpc_url.parse().unwrap())
+        .erased();
+    let repo = Repository::new(pool).await;
+    let cache = DistCache::new(&CONFIG.cache);
+    let shutdown = Shutdown::new();
+
+    AppState {
+        notifier: WebhookNotifier::new(repo.clone(), &CONFIG.webhook, shutdown.clone()).unwrap(),
+        heads: HeadTracker::new(&CONFIG.heads),
+        events: AccountEventHub::new(&CONFIG.events),
+        prices: PriceOracle::new(eth_provider.clone(), cache.clone(), &CONFIG.prices).unwrap(),
+        dex: DexPricer::new(eth_provider.clone(), repo.clone(), 11155111, &CONFIG.dex).unwrap(),
+        usage: UsageMeter::new(),
+        wallet: None,
+        faucet: None,
+        shutdown,
+        repo,
+        eth_provider,
+        // Sepolia, the chain of the configured RPC URL
+        chain_id: 11155111,
+        cache,
+    }
+}
@@ -1,7 +1,24 @@
 use alloy::primitives::address;
 use backend::db::*;
+use backend::eth::ZERO_ADDRESS;
+use backend::tokens::register_native_token;
+use rust_decimal::Decimal;
 use sqlx::PgPool;
 
+// Chain of the tokens registered by the tests
+const CHAIN_ID: u64 = 11155111;
+
+// Helper function to register a token without metadata
+async fn register_token(repo: &Repository, token_address: &str) {
+    repo.upsert_token(&NewToken {
+        address: token_address,
+        chain_id: CHAIN_ID,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+}
+
 #[sqlx::test()]
 async fn test_upsert_eth_account_balance(pool: PgPool) {
     let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf");
@@ -9,10 +26,17 @@ async fn test_upsert_eth_account_balance(pool: PgPool) {
     let balance = rust_decimal::Decimal::new(100, 0);
 
     let repo = Repository::new(pool.clone()).await;
+    register_token(&repo, &token_address.to_string()).await;
 
-    repo.upsert_eth_account_balance(&address.to_string(), &token_address.to_string(), balance)
-        .await
-        .unwrap();
+    repo.upsert_eth_account_balance(
+        CHAIN_ID,
+        &address.to_string(),
+        &token_address.to_string(),
+        1,
+        balance,
+    )
+    .await
+    .unwrap();
 
     let data = sqlx::query!(
         r#"
@@ -30,3 +54,813 @@ async fn test_ping(pool: PgPool) {
     let repo = Repository::new(pool.clone()).await;
     assert!(repo.ping().await.is_ok());
 }
+
+#[sqlx::test()]
+async fn test_upsert_eth_account_balance_records_history(pool: PgPool) {
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+    let token_address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3").to_string();
+
+    let repo = Repository::new(pool.clone()).await;
+    register_token(&repo, &token_address).await;
+
+    // First observation, then an unchanged one, then a change
+    let previous = repo
+        .upsert_eth_account_balance(CHAIN_ID, &address, &token_address, 10, Decimal::new(100, 0))
+        .await
+        .unwrap();
+    assert_eq!(previous, Upsert::Written(None));
+    repo.upsert_eth_account_balance(CHAIN_ID, &address, &token_address, 11, Decimal::new(100, 0))
+        .await
+        .unwrap();
+    let previous = repo
+        .upsert_eth_account_balance(CHAIN_ID, &address, &token_address, 12, Decimal::new(250, 0))
+        .await
+        .unwrap();
+    assert_eq!(previous, Upsert::Written(Some(Decimal::new(100, 0))));
+
+    let history = repo
+        .get_balance_history(&address, &BalanceHistoryFilter {
+            limit: 10,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].block_number, 12);
+    assert_eq!(history[0].balance, Decimal::new(250, 0));
+    assert_eq!(history[1].block_number, 10);
+
+    let latest = sqlx::query!(
+        r#"
+        SELECT balance, block_number FROM eth_account_balances
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(latest.balance, Decimal::new(250, 0));
+    assert_eq!(latest.block_number, Some(12));
+}
+
+#[sqlx::test()]
+async fn test_upsert_eth_account_balance_ignores_stale_block(pool: PgPool) {
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+
+    let repo = Repository::new(pool.clone()).await;
+    register_native_token(&repo, CHAIN_ID).await.unwrap();
+
+    repo.upsert_eth_account_balance(CHAIN_ID, &address, ZERO_ADDRESS, 20, Decimal::new(5, 0))
+        .await
+        .unwrap();
+    let outcome = repo
+        .upsert_eth_account_balance(CHAIN_ID, &address, ZERO_ADDRESS, 19, Decimal::new(7, 0))
+        .await
+        .unwrap();
+    assert_eq!(outcome, Upsert::Stale);
+
+    // The stale balance was not stored, the next write compares to the fresher one
+    let outcome = repo
+        .upsert_eth_account_balance(CHAIN_ID, &address, ZERO_ADDRESS, 21, Decimal::new(5, 0))
+        .await
+        .unwrap();
+    assert_eq!(outcome, Upsert::Written(Some(Decimal::new(5, 0))));
+
+    let history = repo
+        .get_balance_history(&address, &BalanceHistoryFilter {
+            limit: 10,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].balance, Decimal::new(5, 0));
+}
+
+#[sqlx::test()]
+async fn test_upsert_eth_account_balance_concurrent_first_writes(pool: PgPool) {
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+
+    let repo = Repository::new(pool.clone()).await;
+    register_native_token(&repo, CHAIN_ID).await.unwrap();
+
+    // Writers racing on a pair without a stored balance record a single change
+    let writers: Vec<_> = (0..8)
+        .map(|_| {
+            let repo = repo.clone();
+            let address = address.clone();
+            tokio::spawn(async move {
+                repo.upsert_eth_account_balance(
+                    CHAIN_ID,
+                    &address,
+                    ZERO_ADDRESS,
+                    30,
+                    Decimal::new(9, 0),
+                )
+                .await
+                .unwrap()
+            })
+        })
+        .collect();
+    let mut previous = Vec::new();
+    for writer in writers {
+        previous.push(writer.await.unwrap());
+    }
+    let first_writes = previous
+        .iter()
+        .filter(|outcome| **outcome == Upsert::Written(None))
+        .count();
+    assert_eq!(first_writes, 1);
+
+    let history = repo
+        .get_balance_history(&address, &BalanceHistoryFilter {
+            limit: 10,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 1);
+}
+
+#[sqlx::test()]
+async fn test_get_balance_history_filters(pool: PgPool) {
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+    let token_address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3").to_string();
+
+    let repo = Repository::new(pool.clone()).await;
+    register_native_token(&repo, CHAIN_ID).await.unwrap();
+    register_token(&repo, &token_address).await;
+
+    for block in 1..=5u64 {
+        repo.upsert_eth_account_balance(
+            CHAIN_ID,
+            &address,
+            &token_address,
+            block,
+            Decimal::from(block),
+        )
+        .await
+        .unwrap();
+        repo.upsert_eth_account_balance(
+            CHAIN_ID,
+            &address,
+            ZERO_ADDRESS,
+            block,
+            Decimal::from(block * 10),
+        )
+        .await
+        .unwrap();
+    }
+
+    let history = repo
+        .get_balance_history(&address, &BalanceHistoryFilter {
+            token_address: Some(token_address.clone()),
+            from_block: Some(2),
+            to_block: Some(4),
+            limit: 10,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let blocks: Vec<i64> = history.iter().map(|record| record.block_number).collect();
+    assert_eq!(blocks, vec![4, 3, 2]);
+    assert!(history
+        .iter()
+        .all(|record| record.token_address == token_address.to_lowercase()));
+
+    let history = repo
+        .get_balance_history(&address, &BalanceHistoryFilter {
+            to_time: chrono::NaiveDate::from_ymd_opt(2000, 1, 1)
+                .and_then(|date| date.and_hms_opt(0, 0, 0)),
+            limit: 10,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(history.is_empty());
+}
+
+#[sqlx::test()]
+async fn test_watched_balances_crud(pool: PgPool) {
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+    let token_address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3").to_string();
+
+    let repo = Repository::new(pool.clone()).await;
+    register_token(&repo, &token_address).await;
+
+    let native = repo
+        .add_watched_balance(&address, ZERO_ADDRESS, Some("treasury"))
+        .await
+        .unwrap();
+    assert_eq!(native.address, address.to_lowercase());
+    assert_eq!(native.label.as_deref(), Some("treasury"));
+    assert!(native.balance.is_none());
+
+    // Adding the same pair again keeps the entry and its label
+    let again = repo
+        .add_watched_balance(&address, ZERO_ADDRESS, None)
+        .await
+        .unwrap();
+    assert_eq!(again.id, native.id);
+    assert_eq!(again.label.as_deref(), Some("treasury"));
+
+    let token = repo
+        .add_watched_balance(&address, &token_address, None)
+        .await
+        .unwrap();
+
+    // The latest stored balance is joined in
+    repo.upsert_eth_account_balance(CHAIN_ID, &address, &token_address, 7, Decimal::new(42, 0))
+        .await
+        .unwrap();
+
+    let items = repo.list_watched_balances(0, 10).await.unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[1].id, token.id);
+    assert_eq!(items[1].balance, Some(Decimal::new(42, 0)));
+    assert_eq!(items[1].block_number, Some(7));
+    assert!(items[1].changed_at.is_some());
+
+    let items = repo.list_watched_balances(native.id, 10).await.unwrap();
+    assert_eq!(items.len(), 1);
+
+    assert!(repo.update_watched_balance_label(token.id, Some("ops")).await.unwrap());
+    let updated = repo.get_watched_balance(token.id).await.unwrap().unwrap();
+    assert_eq!(updated.label.as_deref(), Some("ops"));
+
+    assert!(repo.is_token_watched(&token_address).await.unwrap());
+    assert!(repo.delete_watched_balance(token.id).await.unwrap());
+    assert!(!repo.delete_watched_balance(token.id).await.unwrap());
+    assert!(!repo.is_token_watched(&token_address).await.unwrap());
+    assert!(repo.get_watched_balance(token.id).await.unwrap().is_none());
+}
+
+#[sqlx::test()]
+async fn test_upsert_eth_account_balance_tracks_changed_at(pool: PgPool) {
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+
+    let repo = Repository::new(pool.clone()).await;
+    register_native_token(&repo, CHAIN_ID).await.unwrap();
+
+    repo.upsert_eth_account_balance(CHAIN_ID, &address, ZERO_ADDRESS, 1, Decimal::new(5, 0))
+        .await
+        .unwrap();
+    let first = sqlx::query!(
+        r#"
+        SELECT updated_at, changed_at FROM eth_account_balances
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    // Same balance at a later block only refreshes updated_at
+    repo.upsert_eth_account_balance(CHAIN_ID, &address, ZERO_ADDRESS, 2, Decimal::new(5, 0))
+        .await
+        .unwrap();
+    let second = sqlx::query!(
+        r#"
+        SELECT updated_at, changed_at FROM eth_account_balances
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(second.changed_at, first.changed_at);
+    assert!(second.updated_at >= first.updated_at);
+}
+
+#[sqlx::test()]
+async fn test_tokens_registry(pool: PgPool) {
+    let token_address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3").to_string();
+    let tags = vec!["stablecoin".to_string()];
+
+    let repo = Repository::new(pool.clone()).await;
+    register_native_token(&repo, CHAIN_ID).await.unwrap();
+
+    let token = repo
+        .upsert_token(&NewToken {
+            address: &token_address,
+            chain_id: CHAIN_ID,
+            symbol: Some("MTK"),
+            name: Some("MyToken"),
+            decimals: Some(18),
+            tags: Some(&tags),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(token.address, token_address.to_lowercase());
+    assert_eq!(token.chain_id, CHAIN_ID as i64);
+    assert_eq!(token.tags, tags);
+    assert!(!token.verified);
+
+    // Omitted fields keep their stored value
+    let token = repo
+        .upsert_token(&NewToken {
+            address: &token_address,
+            chain_id: CHAIN_ID,
+            verified: Some(true),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(token.symbol.as_deref(), Some("MTK"));
+    assert!(token.verified);
+
+    let verified = repo.list_tokens(CHAIN_ID, None, Some(true), 10).await.unwrap();
+    assert_eq!(verified.len(), 2);
+    let after_native = repo
+        .list_tokens(CHAIN_ID, Some(ZERO_ADDRESS), None, 10)
+        .await
+        .unwrap();
+    assert_eq!(after_native.len(), 1);
+    assert_eq!(after_native[0].address, token_address.to_lowercase());
+
+    assert!(repo.delete_token(CHAIN_ID, &token_address).await.unwrap());
+    assert!(!repo.delete_token(CHAIN_ID, &token_address).await.unwrap());
+}
+
+#[sqlx::test()]
+async fn test_tokens_are_keyed_by_chain(pool: PgPool) {
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+    let token_address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3").to_string();
+
+    let repo = Repository::new(pool.clone()).await;
+
+    // The same address is a different token on another chain
+    for (chain_id, symbol) in [(CHAIN_ID, "MTK"), (1, "OTHER")] {
+        repo.upsert_token(&NewToken {
+            address: &token_address,
+            chain_id,
+            symbol: Some(symbol),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    }
+    let token = repo.get_token(CHAIN_ID, &token_address).await.unwrap().unwrap();
+    assert_eq!(token.symbol.as_deref(), Some("MTK"));
+    let token = repo.get_token(1, &token_address).await.unwrap().unwrap();
+    assert_eq!(token.symbol.as_deref(), Some("OTHER"));
+    assert!(repo.list_tokens(1, None, Some(true), 10).await.unwrap().is_empty());
+    assert!(repo.get_token(10, &token_address).await.unwrap().is_none());
+
+    // Removing a token keeps its stored balances, detached from the registry
+    repo.upsert_eth_account_balance(CHAIN_ID, &address, &token_address, 1, Decimal::new(1, 0))
+        .await
+        .unwrap();
+    assert!(repo.delete_token(CHAIN_ID, &token_address).await.unwrap());
+    assert!(repo.get_token(1, &token_address).await.unwrap().is_some());
+    let data = sqlx::query!(
+        r#"
+        SELECT balance, chain_id FROM eth_account_balances
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(data.balance, Decimal::new(1, 0));
+    assert_eq!(data.chain_id, None);
+    assert!(repo.get_account_holdings(CHAIN_ID, &address).await.unwrap().is_empty());
+}
+
+#[sqlx::test()]
+async fn test_balances_reference_registered_tokens(pool: PgPool) {
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+    let token_address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3").to_string();
+
+    let repo = Repository::new(pool.clone()).await;
+
+    // A balance of a token missing from the registry of the chain is rejected
+    let result = repo
+        .upsert_eth_account_balance(CHAIN_ID, &address, &token_address, 1, Decimal::new(1, 0))
+        .await;
+    assert!(result.is_err());
+
+    // Registered on another chain only is not enough
+    repo.upsert_token(&NewToken {
+        address: &token_address,
+        chain_id: 1,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let result = repo
+        .upsert_eth_account_balance(CHAIN_ID, &address, &token_address, 1, Decimal::new(1, 0))
+        .await;
+    assert!(result.is_err());
+
+    register_token(&repo, &token_address).await;
+    let outcome = repo
+        .upsert_eth_account_balance(CHAIN_ID, &address, &token_address, 1, Decimal::new(1, 0))
+        .await
+        .unwrap();
+    assert_eq!(outcome, Upsert::Written(None));
+}
+
+#[sqlx::test()]
+async fn test_get_account_holdings(pool: PgPool) {
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+    let token_address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3").to_string();
+    let empty_token_address = address!("0x1c7d4b196cb0c7b01d743fbc6116a902379c7238").to_string();
+
+    let repo = Repository::new(pool.clone()).await;
+    register_native_token(&repo, CHAIN_ID).await.unwrap();
+    repo.upsert_token(&NewToken {
+        address: &token_address,
+        chain_id: CHAIN_ID,
+        symbol: Some("MTK"),
+        decimals: Some(18),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    register_token(&repo, &empty_token_address).await;
+
+    repo.upsert_eth_account_balance(CHAIN_ID, &address, ZERO_ADDRESS, 1, Decimal::new(5, 0))
+        .await
+        .unwrap();
+    repo.upsert_eth_account_balance(CHAIN_ID, &address, &token_address, 1, Decimal::new(7, 0))
+        .await
+        .unwrap();
+    repo.upsert_eth_account_balance(CHAIN_ID, &address, &empty_token_address, 1, Decimal::ZERO)
+        .await
+        .unwrap();
+
+    // Zero balances are left out, metadata comes from the registry
+    let holdings = repo.get_account_holdings(CHAIN_ID, &address).await.unwrap();
+    assert_eq!(holdings.len(), 2);
+    assert_eq!(holdings[0].token_address, ZERO_ADDRESS);
+    assert_eq!(holdings[0].symbol.as_deref(), Some("ETH"));
+    assert_eq!(holdings[1].token_address, token_address.to_lowercase());
+    assert_eq!(holdings[1].balance, Decimal::new(7, 0));
+    assert_eq!(holdings[1].block_number, Some(1));
+}
+
+#[sqlx::test()]
+async fn test_api_keys_and_usage(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let scopes = vec!["public".to_string()];
+
+    let key = repo
+        .create_api_key(&NewApiKey {
+            key_hash: &"a".repeat(64),
+            key_prefix: "ak_aaaaaaaa",
+            owner: "partner",
+            scopes: &scopes,
+            rate_limit_per_second: 5,
+            daily_quota: 1000,
+        })
+        .await
+        .unwrap();
+    assert!(key.active);
+    assert_eq!(key.scopes, scopes);
+
+    // Keys are only found by their hash
+    let found = repo.find_api_key_by_hash(&"a".repeat(64)).await.unwrap();
+    assert_eq!(found.unwrap().id, key.id);
+    assert!(repo.find_api_key_by_hash(&"b".repeat(64)).await.unwrap().is_none());
+
+    // Omitted fields are kept
+    let updated = repo
+        .update_api_key(
+            key.id,
+            &ApiKeyUpdate {
+                daily_quota: Some(2000),
+                active: Some(false),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(updated.owner, "partner");
+    assert_eq!(updated.rate_limit_per_second, 5);
+    assert_eq!(updated.daily_quota, 2000);
+    assert!(!updated.active);
+    assert!(
+        repo.update_api_key(key.id + 1, &ApiKeyUpdate::default())
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(repo.list_api_keys(0, 10).await.unwrap().len(), 1);
+
+    // Usage adds up per key, route and day
+    let day = chrono::NaiveDate::from_ymd_opt(2025, 7, 22).unwrap();
+    let delta = ApiKeyUsageDelta {
+        api_key_id: key.id,
+        route: "/v1/public/eth/misc".to_string(),
+        day,
+        request_count: 3,
+    };
+    repo.record_api_key_usage(std::slice::from_ref(&delta))
+        .await
+        .unwrap();
+    repo.record_api_key_usage(&[
+        delta.clone(),
+        ApiKeyUsageDelta {
+            day: day.succ_opt().unwrap(),
+            ..delta.clone()
+        },
+    ])
+    .await
+    .unwrap();
+
+    let usage = repo.list_api_key_usage(day, day, None).await.unwrap();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].owner, "partner");
+    assert_eq!(usage[0].request_count, 6);
+
+    let usage = repo
+        .list_api_key_usage(day, day.succ_opt().unwrap(), Some(key.id))
+        .await
+        .unwrap();
+    assert_eq!(usage.len(), 2);
+    assert!(
+        repo.list_api_key_usage(day, day, Some(key.id + 1))
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+// Helper function to build an outgoing transaction of the test wallet
+fn outgoing_transaction<'a>(nonce: u64, tx_hash: &'a str) -> NewOutgoingTransaction<'a> {
+    NewOutgoingTransaction {
+        from_address: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        chain_id: 1,
+        nonce,
+        tx_hash,
+        purpose: "faucet",
+        to_address: Some("0x000000000000000000000000000000000000dEaD"),
+        value: Decimal::new(1000, 0),
+        input: &[],
+        gas_limit: 21000,
+        max_fee_per_gas: Decimal::new(2_000_000_000, 0),
+        max_priority_fee_per_gas: Decimal::new(1_000_000_000, 0),
+    }
+}
+
+#[sqlx::test()]
+async fn test_wallet_nonces(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let wallet = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    let hash = |byte: u8| format!("0x{}", format!("{:02x}", byte).repeat(32));
+
+    // The first nonce comes from the chain
+    let lock = repo.lock_wallet_nonce(wallet, 1, 5).await.unwrap();
+    assert_eq!(lock.nonce(), 5);
+    let record = lock
+        .commit(&outgoing_transaction(5, &hash(1)))
+        .await
+        .unwrap();
+    assert_eq!(record.status, OutgoingTransactionStatus::Pending);
+    assert_eq!(record.from_address, wallet.to_lowercase());
+
+    // A second instance waits for the lock and gets the next nonce
+    let lock = repo.lock_wallet_nonce(wallet, 1, 5).await.unwrap();
+    assert_eq!(lock.nonce(), 6);
+    let waiting = tokio::spawn({
+        let repo = repo.clone();
+        async move { repo.lock_wallet_nonce(wallet, 1, 5).await.unwrap().nonce() }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    assert!(!waiting.is_finished());
+    lock.commit(&outgoing_transaction(6, &hash(2)))
+        .await
+        .unwrap();
+    assert_eq!(waiting.await.unwrap(), 7);
+
+    // A dropped lock leaves its nonce unused, and the chain nonce wins when ahead
+    let lock = repo.lock_wallet_nonce(wallet, 1, 5).await.unwrap();
+    assert_eq!(lock.nonce(), 7);
+    drop(lock);
+    assert_eq!(
+        repo.lock_wallet_nonce(wallet, 1, 7).await.unwrap().nonce(),
+        7
+    );
+    assert_eq!(
+        repo.lock_wallet_nonce(wallet, 1, 9).await.unwrap().nonce(),
+        9
+    );
+
+    // Nonces are per chain
+    assert_eq!(
+        repo.lock_wallet_nonce(wallet, 2, 0).await.unwrap().nonce(),
+        0
+    );
+}
+
+#[sqlx::test()]
+async fn test_outgoing_transaction_lifecycle(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let wallet = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    let hash = |byte: u8| format!("0x{}", format!("{:02x}", byte).repeat(32));
+
+    let first = repo
+        .lock_wallet_nonce(wallet, 1, 0)
+        .await
+        .unwrap()
+        .commit(&outgoing_transaction(0, &hash(1)))
+        .await
+        .unwrap();
+    let second = repo
+        .lock_wallet_nonce(wallet, 1, 0)
+        .await
+        .unwrap()
+        .commit(&outgoing_transaction(1, &hash(2)))
+        .await
+        .unwrap();
+
+    // Resubmitting the second transaction replaces it
+    let replacement = repo
+        .replace_outgoing_transaction(second.id, &outgoing_transaction(1, &hash(3)))
+        .await
+        .unwrap();
+    let second = repo
+        .get_outgoing_transaction_by_hash(&hash(2))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(second.status, OutgoingTransactionStatus::Replaced);
+    assert_eq!(second.replaced_by, Some(replacement.id));
+    let attempts = repo.list_nonce_attempts(wallet, 1, 1).await.unwrap();
+    assert_eq!(
+        attempts.iter().map(|tx| tx.id).collect::<Vec<_>>(),
+        vec![second.id, replacement.id]
+    );
+
+    // Only pending and mined transactions are open
+    repo.update_outgoing_transaction_status(
+        first.id,
+        OutgoingTransactionStatus::Mined,
+        Some(100),
+        Some(true),
+    )
+    .await
+    .unwrap();
+    let open = repo
+        .list_open_outgoing_transactions(wallet, 1)
+        .await
+        .unwrap();
+    assert_eq!(
+        open.iter().map(|tx| (tx.id, tx.status)).collect::<Vec<_>>(),
+        vec![
+            (first.id, OutgoingTransactionStatus::Mined),
+            (replacement.id, OutgoingTransactionStatus::Pending)
+        ]
+    );
+    assert_eq!(open[0].block_number, Some(100));
+    assert_eq!(open[0].succeeded, Some(true));
+
+    repo.update_outgoing_transaction_status(
+        first.id,
+        OutgoingTransactionStatus::Confirmed,
+        Some(100),
+        Some(true),
+    )
+    .await
+    .unwrap();
+    repo.update_outgoing_transaction_status(
+        replacement.id,
+        OutgoingTransactionStatus::Dropped,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(
+        repo.list_open_outgoing_transactions(wallet, 1)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // Listing is newest first, optionally by state
+    let all = repo
+        .list_outgoing_transactions(None, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        all.iter().map(|tx| tx.id).collect::<Vec<_>>(),
+        vec![replacement.id, second.id, first.id]
+    );
+    let page = repo
+        .list_outgoing_transactions(Some(replacement.id), None, 1)
+        .await
+        .unwrap();
+    assert_eq!(page[0].id, second.id);
+    let confirmed = repo
+        .list_outgoing_transactions(None, Some(OutgoingTransactionStatus::Confirmed), 10)
+        .await
+        .unwrap();
+    assert_eq!(confirmed.len(), 1);
+    assert_eq!(confirmed[0].id, first.id);
+}
+
+#[sqlx::test()]
+async fn test_insert_balance_history(pool: PgPool) {
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+    let token_address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3").to_string();
+    let observed_at = chrono::DateTime::from_timestamp(1_700_000_000, 0)
+        .unwrap()
+        .naive_utc();
+
+    let repo = Repository::new(pool.clone()).await;
+    register_token(&repo, &token_address).await;
+    repo.upsert_eth_account_balance(CHAIN_ID, &address, &token_address, 100, Decimal::new(300, 0))
+        .await
+        .unwrap();
+
+    // Backfilled blocks are recorded when the balance changed since the previous one
+    let insert = |block_number, balance| {
+        repo.insert_balance_history(
+            &address,
+            &token_address,
+            block_number,
+            Decimal::new(balance, 0),
+            observed_at,
+        )
+    };
+    assert!(insert(10, 100).await.unwrap());
+    assert!(!insert(20, 100).await.unwrap());
+    assert!(insert(30, 200).await.unwrap());
+    // Rerunning a backfill records nothing new
+    assert!(!insert(30, 200).await.unwrap());
+    assert!(!insert(10, 100).await.unwrap());
+
+    let history = repo
+        .get_balance_history(&address, &BalanceHistoryFilter {
+            limit: 10,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|item| (item.block_number, item.balance))
+            .collect::<Vec<_>>(),
+        vec![
+            (100, Decimal::new(300, 0)),
+            (30, Decimal::new(200, 0)),
+            (10, Decimal::new(100, 0)),
+        ]
+    );
+    assert_eq!(history[1].observed_at, observed_at);
+
+    // The latest balance is left untouched
+    let latest = sqlx::query!(
+        r#"
+        SELECT balance, block_number FROM eth_account_balances
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(latest.balance, Decimal::new(300, 0));
+    assert_eq!(latest.block_number, Some(100));
+}
+
+#[sqlx::test()]
+async fn test_migrations(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+
+    let status = repo.migration_status().await.unwrap();
+    assert!(!status.is_empty());
+    assert!(status.iter().all(|migration| migration.applied && !migration.modified));
+    assert!(status.windows(2).all(|pair| pair[0].version < pair[1].version));
+
+    // The latest migrations are reverted, then applied again
+    let reverted = repo.revert_migrations(2).await.unwrap();
+    let latest: Vec<_> = status.iter().rev().take(2).map(|migration| migration.version).collect();
+    assert_eq!(reverted, latest);
+
+    let after_revert = repo.migration_status().await.unwrap();
+    assert_eq!(
+        after_revert
+            .iter()
+            .filter(|migration| !migration.applied)
+            .map(|migration| migration.version)
+            .collect::<Vec<_>>(),
+        latest.iter().rev().copied().collect::<Vec<_>>()
+    );
+
+    repo.run_migrations().await.unwrap();
+    let after_run = repo.migration_status().await.unwrap();
+    assert!(after_run.iter().all(|migration| migration.applied));
+}
+
+#[sqlx::test()]
+async fn test_advisory_lock(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+
+    // Only one holder at a time, dropping the lock releases it
+    let lock = repo.try_advisory_lock("test:job").await.unwrap();
+    assert!(lock.is_some());
+    assert!(repo.try_advisory_lock("test:job").await.unwrap().is_none());
+    assert!(repo.try_advisory_lock("test:other").await.unwrap().is_some());
+    drop(lock);
+    assert!(repo.try_advisory_lock("test:job").await.unwrap().is_some());
+}
@@ -0,0 +1,28 @@
+use alloy::primitives::U160;
+use backend::dex::{average_tick, sqrt_price_x96_to_price, tick_to_price};
+
+#[test]
+fn test_sqrt_price_x96_to_price() {
+    let one = U160::from(1u8) << 96;
+    assert_eq!(sqrt_price_x96_to_price(one), 1.0);
+    assert_eq!(sqrt_price_x96_to_price(one << 1), 4.0);
+    assert_eq!(sqrt_price_x96_to_price(one >> 1), 0.25);
+}
+
+#[test]
+fn test_tick_to_price() {
+    assert_eq!(tick_to_price(0), 1.0);
+    assert!((tick_to_price(1) - 1.0001).abs() < 1e-12);
+    // Tick -887272 is the lowest price a pool supports
+    assert!(tick_to_price(-887272) > 0.0);
+    assert!((tick_to_price(69082) - 1000.0).abs() < 1.0);
+}
+
+#[test]
+fn test_average_tick() {
+    assert_eq!(average_tick(0, 3600, 1800), 2);
+    assert_eq!(average_tick(1000, 1000, 1800), 0);
+    // Negative means round towards negative infinity
+    assert_eq!(average_tick(0, -3601, 1800), -3);
+    assert_eq!(average_tick(0, -3600, 1800), -2);
+}
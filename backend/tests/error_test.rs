@@ -0,0 +1,280 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use alloy::primitives::Address;
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::rpc::json_rpc::ErrorPayload;
+use alloy::transports::{RpcError, TransportErrorKind};
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::{
+    Json, Router, middleware,
+    routing::{get, post},
+};
+use axum_test::TestServer;
+use serde_json::{Value, json};
+use tracing_subscriber::fmt::MakeWriter;
+
+use backend::error::{self, AppError, ConflictError, ErrorCode, Result, ValidateError};
+use backend::eth;
+use backend::telemetry;
+
+/// Log output kept in memory
+#[derive(Clone, Default)]
+struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Logs {
+    type Writer = Logs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+// Helper function to get the code of an error
+fn code_of(err: impl Into<AppError>) -> ErrorCode {
+    err.into().code()
+}
+
+// Helper function to start a node answering `eth_chainId`, every call then either
+// reverts or fails with 502 Bad Gateway
+async fn start_node(calls_fail: bool) -> DynProvider {
+    let app = Router::new().route(
+        "/",
+        post(async move |Json(request): Json<Value>| {
+            if request["method"] == "eth_chainId" {
+                return Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": "0x1" }))
+                    .into_response();
+            }
+            if calls_fail {
+                return StatusCode::BAD_GATEWAY.into_response();
+            }
+            Json(json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": 3, "message": "execution reverted" },
+            }))
+            .into_response()
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    let url = format!("http://{}", addr).parse().unwrap();
+    ProviderBuilder::new().connect_http(url).erased()
+}
+
+#[test]
+fn test_error_codes() {
+    assert_eq!(
+        code_of(ValidateError("Invalid amount".to_string())),
+        ErrorCode::InvalidRequest
+    );
+    assert_eq!(
+        code_of(ConflictError("Token is watched".to_string())),
+        ErrorCode::Conflict
+    );
+    assert_eq!(code_of(anyhow!("unexpected")), ErrorCode::Internal);
+
+    // Transport failures of the node
+    assert_eq!(
+        code_of(TransportErrorKind::custom_str("connection refused")),
+        ErrorCode::UpstreamError
+    );
+    assert_eq!(
+        code_of(TransportErrorKind::http_error(502, String::new())),
+        ErrorCode::UpstreamError
+    );
+    assert_eq!(
+        code_of(TransportErrorKind::http_error(504, String::new())),
+        ErrorCode::UpstreamTimeout
+    );
+    assert_eq!(
+        code_of(TransportErrorKind::http_error(429, String::new())),
+        ErrorCode::UpstreamRateLimited
+    );
+
+    // Causes are found behind context and contract errors
+    let err: anyhow::Error = TransportErrorKind::backend_gone().into();
+    assert_eq!(
+        code_of(err.context("Failed to get balance")),
+        ErrorCode::UpstreamError
+    );
+    let err =
+        alloy::contract::Error::TransportError(TransportErrorKind::http_error(503, String::new()));
+    assert_eq!(code_of(err), ErrorCode::UpstreamError);
+
+    // Error responses of the node are only upstream failures when they rate limit
+    let revert = RpcError::<TransportErrorKind>::ErrorResp(ErrorPayload {
+        code: 3,
+        message: "execution reverted".into(),
+        data: None,
+    });
+    assert_eq!(code_of(revert), ErrorCode::Internal);
+    let limited = RpcError::<TransportErrorKind>::ErrorResp(ErrorPayload {
+        code: -32005,
+        message: "limit exceeded".into(),
+        data: None,
+    });
+    assert_eq!(code_of(limited), ErrorCode::UpstreamRateLimited);
+
+    assert_eq!(
+        code_of(sqlx::Error::PoolTimedOut),
+        ErrorCode::DatabaseUnavailable
+    );
+    assert_eq!(code_of(sqlx::Error::RowNotFound), ErrorCode::Internal);
+}
+
+#[test]
+fn test_is_revert() {
+    // Only error responses the node answered a call with, rate limits aside
+    let revert = RpcError::<TransportErrorKind>::ErrorResp(ErrorPayload {
+        code: 3,
+        message: "execution reverted".into(),
+        data: None,
+    });
+    assert!(error::is_revert(&revert));
+    let limited = RpcError::<TransportErrorKind>::ErrorResp(ErrorPayload {
+        code: 429,
+        message: "too many requests".into(),
+        data: None,
+    });
+    assert!(!error::is_revert(&limited));
+    assert!(!error::is_revert(&TransportErrorKind::backend_gone()));
+    assert!(!error::is_revert(&TransportErrorKind::http_error(
+        502,
+        String::new()
+    )));
+}
+
+#[tokio::test]
+async fn test_contract_call_errors() {
+    // A token that reverts doesn't support permits
+    let provider = start_node(false).await;
+    let err = eth::get_permit_domain(&provider, 1, Address::ZERO, Address::ZERO)
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidRequest);
+
+    // A failing node is not the token's fault
+    let provider = start_node(true).await;
+    let err = eth::get_permit_domain(&provider, 1, Address::ZERO, Address::ZERO)
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::UpstreamError);
+}
+
+#[tokio::test]
+async fn test_unreachable_upstream_codes() {
+    // A node accepting connections without ever answering
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            sockets.push(socket);
+        }
+    });
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let err = client
+        .post(format!("http://{}", addr))
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        code_of(TransportErrorKind::custom(err)),
+        ErrorCode::UpstreamTimeout
+    );
+
+    // Nothing listens on port 1
+    let err = client.post("http://127.0.0.1:1").send().await.unwrap_err();
+    assert_eq!(
+        code_of(TransportErrorKind::custom(err)),
+        ErrorCode::UpstreamError
+    );
+    let err = redis::Client::open("redis://127.0.0.1:1")
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap_err();
+    assert_eq!(code_of(err), ErrorCode::CacheUnavailable);
+}
+
+#[tokio::test]
+async fn test_error_responses() {
+    let logs = Logs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(logs.clone())
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = Router::new()
+        .route(
+            "/internal",
+            get(async || -> Result<()> {
+                Err(anyhow!("password authentication failed for user admin")
+                    .context("Failed to load account"))?
+            }),
+        )
+        .route(
+            "/invalid",
+            get(async || -> Result<()> { Err(ValidateError("Invalid amount".to_string()))? }),
+        )
+        .route(
+            "/limited",
+            get(async || -> Result<()> {
+                Err(TransportErrorKind::http_error(429, "slow down".to_string()))?
+            }),
+        )
+        .layer(middleware::from_fn(telemetry::trace_requests));
+    let server = TestServer::new(app).unwrap();
+
+    // Internal details are logged, not sent
+    let response = server
+        .get("/internal")
+        .add_header("x-request-id", "req-7")
+        .await;
+    response.assert_status_internal_server_error();
+    let body: Value = response.json();
+    assert_eq!(body["code"], "internal");
+    assert_eq!(body["error_msg"], "Internal server error");
+    assert_eq!(body["request_id"], "req-7");
+    let logged = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(logged.contains("Failed to load account: password authentication failed"));
+
+    // Client errors keep their message
+    let response = server.get("/invalid").await;
+    response.assert_status_bad_request();
+    let body: Value = response.json();
+    assert_eq!(body["code"], "invalid_request");
+    assert_eq!(body["error_msg"], "Validate error: Invalid amount");
+    assert_eq!(
+        body["request_id"],
+        response.header("x-request-id").to_str().unwrap()
+    );
+
+    // A rate limited node makes the service unavailable for a moment
+    let response = server.get("/limited").await;
+    response.assert_status_service_unavailable();
+    assert_eq!(response.header("retry-after"), "1");
+    let body: Value = response.json();
+    assert_eq!(body["code"], "upstream_rate_limited");
+    assert!(!body["error_msg"].as_str().unwrap().contains("slow down"));
+}
@@ -1,6 +1,7 @@
 use alloy::{
-    primitives::{Address, address},
-    providers::Provider,
+    primitives::{Address, U256, address},
+    providers::{MULTICALL3_ADDRESS, Provider},
+    sol_types::SolCall,
 };
 use backend::eth::*;
 
@@ -34,3 +35,63 @@ async fn test_get_erc20_balance() {
     let balance = contract.balanceOf(address).call().await.unwrap();
     assert!(!balance.is_zero(), "Balance should be zero");
 }
+
+#[tokio::test]
+async fn test_get_balances_multicall() {
+    let rpc_url = "https://1rpc.io/sepolia";
+    let provider = setup_provider(rpc_url).await.unwrap();
+    let address: Address = address!("0xd27de11aaacd14c62fe689d214a67e9385e6f60c");
+    let token_address = address!("0xab809CB0aB6669d51f6189432f751f1a916a10cd");
+    let block_number = provider.get_block_number().await.unwrap();
+
+    let balances = get_balances(
+        &provider,
+        &[(address, Address::ZERO), (address, token_address), (address, address)],
+        block_number,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(balances.len(), 3);
+    assert!(balances[0].is_some_and(|balance| !balance.is_zero()));
+    assert!(balances[1].is_some_and(|balance| !balance.is_zero()));
+    // An EOA is not a token, so its call fails
+    assert!(balances[2].is_none());
+}
+
+#[tokio::test]
+async fn test_aggregate_multicall() {
+    let rpc_url = "https://1rpc.io/sepolia";
+    let provider = setup_provider(rpc_url).await.unwrap();
+    let address: Address = address!("0xd27de11aaacd14c62fe689d214a67e9385e6f60c");
+    let token_address = address!("0xab809CB0aB6669d51f6189432f751f1a916a10cd");
+
+    // Calls returning different types, and one that reverts
+    let balance_of = IERC20::balanceOfCall { account: address };
+    let results = aggregate(
+        &provider,
+        vec![
+            (token_address, balance_of.abi_encode().into()),
+            (token_address, IERC20::decimalsCall {}.abi_encode().into()),
+            (MULTICALL3_ADDRESS, IERC20::decimalsCall {}.abi_encode().into()),
+        ],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(results.len(), 3);
+    let balance = IERC20::balanceOfCall::abi_decode_returns(results[0].as_ref().unwrap()).unwrap();
+    assert!(!balance.is_zero());
+    assert!(IERC20::decimalsCall::abi_decode_returns(results[1].as_ref().unwrap()).is_ok());
+    assert!(results[2].is_none());
+}
+
+#[test]
+fn test_format_balance() {
+    let balance = U256::from(1_500_000_000_000_000_000u64);
+    assert_eq!(format_balance(balance, 18).unwrap(), "1.5");
+    assert_eq!(format_balance(U256::from(2_000_000u64), 6).unwrap(), "2");
+    assert_eq!(format_balance(U256::from(1u64), 6).unwrap(), "0.000001");
+    assert_eq!(format_balance(U256::from(42u64), 0).unwrap(), "42");
+    assert_eq!(format_balance(U256::ZERO, 18).unwrap(), "0");
+}
@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use alloy::primitives::{Address, B256, U256, address};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use axum::{Json, Router, routing::post};
+use serde_json::{Value, json};
+use tokio_util::sync::CancellationToken;
+
+use backend::events::{
+    AccountEvent, AccountEventHub, ChainWindow, Config, Subscription, TransferEvent,
+};
+use backend::heads::{self, HeadEvent, HeadTracker};
+
+const ACCOUNT: Address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf");
+const OTHER: Address = address!("0xd27de11aaacd14c62fe689d214a67e9385e6f60c");
+const TOKEN: Address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3");
+
+// Helper function to create a test hub with a small client buffer
+fn create_test_hub() -> AccountEventHub {
+    AccountEventHub::new(&Config {
+        max_reorg_depth: 4,
+        client_buffer: 2,
+        max_subscription_size: 10,
+        max_log_range: 100,
+    })
+}
+
+fn transfer(block_number: u64, from: Address, to: Address) -> TransferEvent {
+    TransferEvent {
+        token_address: TOKEN,
+        from,
+        to,
+        value: "100".to_string(),
+        block_number,
+        block_hash: B256::with_last_byte(block_number as u8),
+        transaction_hash: Some(B256::repeat_byte(0xaa)),
+        log_index: Some(0),
+        removed: false,
+    }
+}
+
+fn subscription(addresses: &[Address], tokens: &[Address]) -> Subscription {
+    Subscription {
+        addresses: addresses.iter().copied().collect(),
+        tokens: tokens.iter().copied().collect(),
+    }
+}
+
+#[test]
+fn test_subscription_matches_transfer() {
+    let any_token = subscription(&[ACCOUNT], &[]);
+    assert!(any_token.matches_transfer(&transfer(1, ACCOUNT, OTHER)));
+    assert!(any_token.matches_transfer(&transfer(1, OTHER, ACCOUNT)));
+    assert!(!any_token.matches_transfer(&transfer(1, OTHER, OTHER)));
+
+    let other_token = subscription(&[ACCOUNT], &[OTHER]);
+    assert!(!other_token.matches_transfer(&transfer(1, ACCOUNT, OTHER)));
+}
+
+#[test]
+fn test_subscription_balance_pairs() {
+    let native = subscription(&[ACCOUNT], &[]);
+    assert_eq!(native.balance_pairs(), vec![(ACCOUNT, Address::ZERO)]);
+
+    let tokens = subscription(&[ACCOUNT, OTHER], &[TOKEN]);
+    let pairs: HashSet<_> = tokens.balance_pairs().into_iter().collect();
+    assert_eq!(pairs, HashSet::from([(ACCOUNT, TOKEN), (OTHER, TOKEN)]));
+}
+
+#[test]
+fn test_chain_window_rewind_returns_removed_transfers() {
+    let mut window = ChainWindow::new(64);
+    window.insert(10, B256::with_last_byte(10), vec![transfer(10, ACCOUNT, OTHER)]);
+    window.insert(11, B256::with_last_byte(11), vec![]);
+    window.insert(12, B256::with_last_byte(12), vec![transfer(12, OTHER, ACCOUNT)]);
+
+    let removed = window.rewind(10);
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].block_number, 12);
+    assert!(removed[0].removed);
+
+    assert_eq!(window.head(), Some(10));
+    assert_eq!(window.hash_at(11), None);
+    assert_eq!(window.hash_at(10), Some(B256::with_last_byte(10)));
+}
+
+#[test]
+fn test_chain_window_prunes_old_blocks() {
+    let mut window = ChainWindow::new(2);
+    for block_number in 1..=5 {
+        window.insert(block_number, B256::with_last_byte(block_number as u8), vec![]);
+    }
+
+    assert_eq!(window.tail(), Some(3));
+    assert_eq!(window.head(), Some(5));
+}
+
+#[tokio::test]
+async fn test_dispatch_only_to_concerned_clients() {
+    let hub = create_test_hub();
+    let (first, mut first_events) = hub.register();
+    let (second, mut second_events) = hub.register();
+    hub.subscribe(first, subscription(&[ACCOUNT], &[]));
+    hub.subscribe(second, subscription(&[OTHER], &[OTHER]));
+
+    let event = AccountEvent::Transfer(transfer(1, ACCOUNT, OTHER));
+    hub.dispatch(&event);
+
+    assert_eq!(first_events.recv().await, Some(event));
+    assert!(second_events.try_recv().is_err());
+
+    hub.unregister(first);
+    hub.unregister(second);
+}
+
+#[tokio::test]
+async fn test_slow_client_is_dropped() {
+    let hub = create_test_hub();
+    let (slow, mut slow_events) = hub.register();
+    let (fast, mut fast_events) = hub.register();
+    hub.subscribe(slow, subscription(&[ACCOUNT], &[]));
+    hub.subscribe(fast, subscription(&[ACCOUNT], &[]));
+
+    // The fast client keeps up, the slow one overflows its buffer
+    for block_number in 1..=3 {
+        let event = AccountEvent::Transfer(transfer(block_number, ACCOUNT, OTHER));
+        hub.dispatch(&event);
+        assert_eq!(fast_events.recv().await, Some(event));
+    }
+
+    assert!(slow_events.recv().await.is_some());
+    assert!(slow_events.recv().await.is_some());
+    assert_eq!(slow_events.recv().await, None);
+
+    let event = AccountEvent::Reorg {
+        fork_block: 2,
+        depth: 1,
+    };
+    hub.dispatch(&event);
+    assert_eq!(fast_events.recv().await, Some(event));
+}
+
+// Helper function to build the balance event of (ACCOUNT, native asset)
+fn balance(block_number: u64, balance: u64, previous_balance: Option<u64>) -> AccountEvent {
+    AccountEvent::Balance {
+        address: ACCOUNT,
+        token_address: Address::ZERO,
+        block_number,
+        balance: balance.to_string(),
+        previous_balance: previous_balance.map(|previous| previous.to_string()),
+    }
+}
+
+#[tokio::test]
+async fn test_balances_are_tracked_per_client() {
+    let hub = create_test_hub();
+    let (first, mut first_events) = hub.register();
+    let (second, mut second_events) = hub.register();
+    hub.subscribe(first, subscription(&[ACCOUNT], &[]));
+    let balances = |value: u64| HashMap::from([((ACCOUNT, Address::ZERO), U256::from(value))]);
+
+    hub.dispatch_balances(1, &balances(100));
+    assert_eq!(first_events.recv().await, Some(balance(1, 100, None)));
+
+    // A later subscriber to the same pair gets its current balance, unchanged or not
+    hub.subscribe(second, subscription(&[ACCOUNT], &[]));
+    hub.dispatch_balances(2, &balances(100));
+    assert_eq!(second_events.recv().await, Some(balance(2, 100, None)));
+    assert!(first_events.try_recv().is_err());
+
+    hub.dispatch_balances(3, &balances(150));
+    assert_eq!(first_events.recv().await, Some(balance(3, 150, Some(100))));
+    assert_eq!(second_events.recv().await, Some(balance(3, 150, Some(100))));
+
+    // Keeping the pair in a new subscription doesn't send it again
+    hub.subscribe(first, subscription(&[ACCOUNT, OTHER], &[]));
+    hub.dispatch_balances(4, &balances(150));
+    assert!(first_events.try_recv().is_err());
+
+    hub.unregister(first);
+    hub.unregister(second);
+}
+
+#[tokio::test]
+async fn test_initial_balances_are_sent_once() {
+    let hub = create_test_hub();
+    let (id, mut events) = hub.register();
+    hub.subscribe(id, subscription(&[ACCOUNT, OTHER], &[]));
+    let balances = HashMap::from([
+        ((ACCOUNT, Address::ZERO), U256::from(100u64)),
+        ((OTHER, Address::ZERO), U256::from(200u64)),
+    ]);
+
+    // The balances read on subscribe are not sent again by the next head
+    hub.dispatch_initial_balances(id, 1, &balances);
+    hub.dispatch_balances(2, &balances);
+    let mut received = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        received.push(event);
+    }
+    assert_eq!(received.len(), 2);
+    let mut pairs: Vec<_> = received
+        .iter()
+        .map(|event| match event {
+            AccountEvent::Balance {
+                address,
+                token_address,
+                previous_balance,
+                ..
+            } => {
+                assert!(previous_balance.is_none());
+                (*address, *token_address)
+            }
+            event => panic!("unexpected event {:?}", event),
+        })
+        .collect();
+    pairs.sort();
+    pairs.dedup();
+    assert_eq!(pairs.len(), 2);
+
+    // A late initial read doesn't replace a balance the hub already sent
+    hub.subscribe(id, subscription(&[ACCOUNT], &[]));
+    hub.dispatch_balances(3, &HashMap::from([((ACCOUNT, Address::ZERO), U256::from(150u64))]));
+    assert_eq!(events.recv().await, Some(balance(3, 150, Some(100))));
+    hub.dispatch_initial_balances(id, 2, &balances);
+    assert!(events.try_recv().is_err());
+
+    hub.unregister(id);
+}
+
+// Helper function to start a node recording the filters of `eth_getLogs`, which finds no logs
+async fn start_node(filters: Arc<Mutex<Vec<Value>>>) -> DynProvider {
+    let app = Router::new().route(
+        "/",
+        post(async move |Json(request): Json<Value>| {
+            let result = if request["method"] == "eth_getLogs" {
+                filters.lock().unwrap().push(request["params"][0].clone());
+                json!([])
+            } else {
+                json!("0x")
+            };
+            Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    let url = format!("http://{}", addr).parse().unwrap();
+    ProviderBuilder::new().connect_http(url).erased()
+}
+
+#[tokio::test]
+async fn test_transfer_logs_are_filtered_on_subscribed_addresses() {
+    let filters = Arc::new(Mutex::new(Vec::new()));
+    let provider = start_node(filters.clone()).await;
+    let heads = HeadTracker::new(&heads::Config {
+        poll_interval_ms: 100,
+        channel_capacity: 2,
+    });
+    let hub = create_test_hub();
+    let shutdown = CancellationToken::new();
+    tokio::spawn(hub.clone().run(heads.clone(), provider, shutdown.clone()));
+
+    // A subscription without tokens still only asks the node for its own transfers
+    let (id, _events) = hub.register();
+    hub.subscribe(id, subscription(&[ACCOUNT], &[]));
+    let mut recorded = Vec::new();
+    for _ in 0..50 {
+        heads.publish(HeadEvent {
+            block_number: 10,
+            block_hash: format!("0x{:064x}", 10),
+            parent_hash: format!("0x{:064x}", 9),
+            timestamp: 1_700_000_000,
+            base_fee_per_gas: None,
+            gas_price: "1".to_string(),
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        recorded = filters.lock().unwrap().clone();
+        if recorded.len() >= 2 {
+            break;
+        }
+    }
+    shutdown.cancel();
+    assert!(recorded.len() >= 2, "the hub never queried transfer logs");
+
+    let account = json!(ACCOUNT.into_word());
+    recorded.sort_by_key(|filter| filter["topics"][1].is_null());
+    assert!(recorded.iter().all(|filter| filter.get("address").is_none()));
+    assert_eq!(recorded[0]["topics"][1], account);
+    assert!(recorded[0]["topics"][2].is_null());
+    assert!(recorded[1]["topics"][1].is_null());
+    assert_eq!(recorded[1]["topics"][2], account);
+}
+
+#[test]
+fn test_account_event_serialization() {
+    let event = AccountEvent::Transfer(transfer(7, ACCOUNT, OTHER));
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["type"], "transfer");
+    assert_eq!(json["block_number"], 7);
+    assert_eq!(json["removed"], false);
+
+    let event = AccountEvent::Reorg {
+        fork_block: 5,
+        depth: 2,
+    };
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["type"], "reorg");
+    assert_eq!(json["fork_block"], 5);
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use alloy::network::EthereumWallet;
+use alloy::node_bindings::Anvil;
+use alloy::primitives::{Address, B256, U256, address};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::signers::local::PrivateKeySigner;
+use backend::db::Repository;
+use backend::eth::setup_provider;
+use backend::faucet::{Config, Faucet, FaucetAsset};
+use backend::shutdown::Shutdown;
+use backend::wallet::{HotWallet, MyToken};
+use futures::future::join_all;
+use sqlx::PgPool;
+
+// Helper function to build a faucet config
+fn config(min_eth_balance: U256) -> Config {
+    Config {
+        eth_amount: U256::from(1_000_000_000u64),
+        my_token_amount: U256::from(100u64),
+        min_eth_balance,
+        min_my_token_balance: U256::ZERO,
+        address_cooldown: 60,
+        ip_cooldown: 60,
+        queue_capacity: 16,
+    }
+}
+
+// Helper function to wait for a transaction to be mined by the dev node
+async fn wait_for_receipt(provider: &DynProvider, tx_hash: B256) -> bool {
+    for _ in 0..50 {
+        if let Some(receipt) = provider.get_transaction_receipt(tx_hash).await.unwrap() {
+            return receipt.status();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("transaction {} was not mined", tx_hash);
+}
+
+#[sqlx::test()]
+async fn test_faucet_claims(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let anvil = Anvil::new().spawn();
+    let provider = setup_provider(&anvil.endpoint()).await.unwrap();
+    let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let wallet = HotWallet::new(provider.clone(), signer, repo, anvil.chain_id());
+
+    // Deploy MyToken from another account and fund the faucet with it
+    let deployer: PrivateKeySigner = anvil.keys()[1].clone().into();
+    let deployer_provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::from(deployer))
+        .connect_provider(provider.clone());
+    let token_address = *MyToken::deploy(deployer_provider).await.unwrap().address();
+    let transactions = wallet
+        .mint_my_token(token_address, wallet.address(), U256::from(1000u64))
+        .await
+        .unwrap();
+    assert!(wait_for_receipt(&provider, transactions.mint_tx_hash).await);
+
+    let faucet = Faucet::spawn(
+        wallet.clone(),
+        token_address,
+        &config(U256::ZERO),
+        &Shutdown::new(),
+    );
+
+    // Concurrent claims are sent one after the other, with distinct nonces
+    let recipients: Vec<Address> = (1..=5u8).map(Address::with_last_byte).collect();
+    let claims = join_all(
+        recipients
+            .iter()
+            .map(|recipient| faucet.claim(*recipient, FaucetAsset::Eth)),
+    )
+    .await;
+    for claim in claims {
+        assert!(wait_for_receipt(&provider, claim.unwrap().tx_hash).await);
+    }
+    for recipient in &recipients {
+        let balance = provider.get_balance(*recipient).await.unwrap();
+        assert_eq!(balance, U256::from(1_000_000_000u64));
+    }
+
+    let recipient = address!("0x000000000000000000000000000000000000dEaD");
+    let claim = faucet.claim(recipient, FaucetAsset::MyToken).await.unwrap();
+    assert_eq!(claim.amount, U256::from(100u64));
+    assert!(wait_for_receipt(&provider, claim.tx_hash).await);
+    let token = MyToken::new(token_address, provider.clone());
+    assert_eq!(
+        token.balanceOf(recipient).call().await.unwrap(),
+        U256::from(100u64)
+    );
+}
+
+#[sqlx::test()]
+async fn test_faucet_keeps_balance_floor(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let anvil = Anvil::new().spawn();
+    let provider = setup_provider(&anvil.endpoint()).await.unwrap();
+    let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let wallet = HotWallet::new(provider.clone(), signer, repo, anvil.chain_id());
+
+    // The floor is above the whole balance
+    let balance = provider.get_balance(wallet.address()).await.unwrap();
+    let min_eth_balance = balance + U256::from(1u64);
+    let faucet = Faucet::spawn(wallet, Address::ZERO, &config(min_eth_balance), &Shutdown::new());
+
+    let recipient = address!("0x000000000000000000000000000000000000dEaD");
+    let err = faucet.claim(recipient, FaucetAsset::Eth).await.unwrap_err();
+    assert!(err.to_string().contains("Faucet is empty"));
+
+    // MyToken claims keep the native floor too, the wallet pays their gas
+    let err = faucet.claim(recipient, FaucetAsset::MyToken).await.unwrap_err();
+    assert!(err.to_string().contains("Faucet is empty"));
+}
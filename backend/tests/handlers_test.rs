@@ -1,42 +1,171 @@
 use axum::{
-    http::StatusCode, routing::get, Router
+    extract::{Path, Query, State}, http::StatusCode, middleware, response::IntoResponse, routing::{get, post}, Json, Router
 };
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use alloy::signers::{SignerSync, local::PrivateKeySigner};
 use axum_test::TestServer;
-use serde_json::Value;
+use serde_json::{Value, json};
 
 use backend::{
-    cache::DistCache, config::CONFIG, db::Repository, eth::setup_provider, handlers::{
+    api_keys::{self, UsageMeter}, auth::AdminUser, cache::DistCache, config::CONFIG, db::{NewToken, Repository}, dex::DexPricer, eth::setup_provider, handlers::{
         account::get_account_info,
+        api_keys::{create_api_key, get_api_key, get_api_key_usage, list_api_keys, update_api_key},
+        auth::{get_me, get_nonce, sign_in, sign_out},
         erc20::get_account_erc20,
-        health::healthcheck, misc::get_blockchain_misc,
-    }, state::AppState
+        faucet::claim_faucet,
+        permit::{get_permit_typed_data, verify_permit},
+        health::healthcheck, history::get_balance_history, portfolio::get_account_portfolio,
+        price::get_token_price, signatures::verify_signature,
+        transactions::{build_transaction, simulate_transaction},
+        wallet::{get_transaction_receipt, list_outgoing_transactions, mint_my_token},
+        tokens::{create_token, delete_token, get_token, import_tokens, list_tokens, update_token},
+        webhooks::{list_webhooks, create_webhook, replay_webhook_delivery},
+        watchlist::{
+            create_watched_balance, delete_watched_balance, get_watched_balance,
+            list_watched_balances, update_watched_balance,
+        }, misc::get_blockchain_misc,
+    }, events::AccountEventHub, heads::HeadTracker, prices::PriceOracle, rate_limit, shutdown::Shutdown, state::AppState, tokens, webhook::WebhookNotifier,
 };
 
-// Helper function to create a test router
-async fn create_test_router() -> Router {
+// Helper function to create the application state of the test router
+async fn create_test_state() -> AppState {
     let eth_provider = setup_provider(&CONFIG.eth_rpc_url)
         .await
         .expect("Failed to setup eth provider");
+    let chain_id = eth_provider
+        .get_chain_id()
+        .await
+        .expect("Failed to get chain id");
 
     let repo = Repository::new_with_config(&CONFIG.database)
         .await
         .expect("Failed to setup repository");
+    tokens::register_native_token(&repo, chain_id)
+        .await
+        .expect("Failed to register native token");
 
     let cache = DistCache::new(&CONFIG.cache);
-    let app_state = AppState {
+    let shutdown = Shutdown::new();
+    let notifier = WebhookNotifier::new(repo.clone(), &CONFIG.webhook, shutdown.clone())
+        .expect("Failed to setup webhook notifier");
+    let heads = HeadTracker::new(&CONFIG.heads);
+    let events = AccountEventHub::new(&CONFIG.events);
+    let prices = PriceOracle::new(eth_provider.clone(), cache.clone(), &CONFIG.prices)
+        .expect("Failed to setup price oracle");
+    let dex = DexPricer::new(eth_provider.clone(), repo.clone(), chain_id, &CONFIG.dex)
+        .expect("Failed to setup dex pricer");
+
+    AppState {
         repo,
         eth_provider,
+        chain_id,
         cache,
-    };
+        notifier,
+        heads,
+        events,
+        prices,
+        dex,
+        usage: UsageMeter::new(),
+        wallet: None,
+        faucet: None,
+        shutdown,
+    }
+}
 
-    Router::new()
-        .route("/health", get(healthcheck))
+// Helper function to get an admin, admin sessions need an account listed in
+// `auth.admin_addresses`, so admin handlers are called directly with it
+fn admin() -> AdminUser {
+    AdminUser {
+        address: Address::ZERO,
+    }
+}
+
+// Helper function to read the status and JSON body of a handler response
+async fn read_response(response: impl IntoResponse) -> (StatusCode, Value) {
+    let response = response.into_response();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+// Helper function to create a test router
+async fn create_test_router() -> Router {
+    let app_state = create_test_state().await;
+
+    let api_router = Router::new()
         .route("/v1/public/eth/accounts/{address}", get(get_account_info))
         .route(
             "/v1/public/eth/accounts/{address}/erc20/{token_address}",
             get(get_account_erc20),
         )
+        .route(
+            "/v1/public/eth/accounts/{address}/balances/history",
+            get(get_balance_history),
+        )
+        .route(
+            "/v1/public/eth/accounts/{address}/portfolio",
+            get(get_account_portfolio),
+        )
         .route("/v1/public/eth/misc", get(get_blockchain_misc))
+        .route("/v1/public/eth/tokens/{token_address}/price", get(get_token_price))
+        .route("/v1/public/eth/signatures/verify", post(verify_signature))
+        .route("/v1/public/eth/faucet", post(claim_faucet))
+        .route("/v1/public/eth/transactions/build", post(build_transaction))
+        .route("/v1/public/eth/simulate", post(simulate_transaction))
+        .route("/v1/public/eth/permits/typed-data", post(get_permit_typed_data))
+        .route("/v1/public/eth/permits/verify", post(verify_permit))
+        .route("/v1/public/auth/nonce", get(get_nonce))
+        .route("/v1/public/auth/verify", post(sign_in))
+        .route("/v1/private/me", get(get_me))
+        .route("/v1/private/auth/logout", post(sign_out))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            api_keys::authenticate,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            rate_limit::limit_anonymous,
+        ));
+
+    Router::new()
+        .route("/health", get(healthcheck))
+        .merge(api_router)
+        .route(
+            "/v1/admin/eth/watchlist",
+            get(list_watched_balances).post(create_watched_balance),
+        )
+        .route(
+            "/v1/admin/eth/watchlist/{id}",
+            get(get_watched_balance)
+                .patch(update_watched_balance)
+                .delete(delete_watched_balance),
+        )
+        .route("/v1/admin/eth/tokens", get(list_tokens).post(create_token))
+        .route("/v1/admin/eth/tokens/import", post(import_tokens))
+        .route(
+            "/v1/admin/eth/tokens/{address}",
+            get(get_token).patch(update_token).delete(delete_token),
+        )
+        .route("/v1/admin/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/v1/admin/api-keys/usage", get(get_api_key_usage))
+        .route("/v1/admin/webhooks", get(list_webhooks).post(create_webhook))
+        .route(
+            "/v1/admin/webhooks/deliveries/{id}/replay",
+            post(replay_webhook_delivery),
+        )
+        .route("/v1/admin/eth/my-token/mint", post(mint_my_token))
+        .route("/v1/admin/eth/transactions", get(list_outgoing_transactions))
+        .route(
+            "/v1/admin/eth/transactions/{tx_hash}",
+            get(get_transaction_receipt),
+        )
+        .route(
+            "/v1/admin/api-keys/{id}",
+            get(get_api_key).patch(update_api_key),
+        )
         .with_state(app_state)
 }
 
@@ -119,6 +248,22 @@ async fn test_get_erc20_balance_valid_addresses() {
     assert_eq!(body["address"].as_str().unwrap().to_lowercase(), account_address);
     assert_eq!(body["token_address"], token_address);
     assert!(body["balance"].is_string());
+    assert!(body["decimals"].is_number());
+    assert!(body.get("usd_value").is_some());
+    assert!(body["verified"].is_boolean());
+
+    // The balance is stored, registering the token if it was unknown
+    let state = create_test_state().await;
+    let holdings = state
+        .repo
+        .get_account_holdings(state.chain_id, account_address)
+        .await
+        .unwrap();
+    assert!(
+        holdings
+            .iter()
+            .any(|holding| holding.token_address == token_address.to_lowercase())
+    );
 }
 
 #[tokio::test]
@@ -156,4 +301,896 @@ async fn test_get_blockchain_misc_endpoint() {
     let body: Value = response.json();
     assert!(body.get("current_block").is_some());
     assert!(body.get("gas_price").is_some());
+    assert!(body.get("max_fee_per_gas").is_some());
+    assert!(body.get("max_priority_fee_per_gas").is_some());
+}
+
+#[tokio::test]
+async fn test_get_balance_history_invalid_range() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    let response = server
+        .get("/v1/public/eth/accounts/0x742d35Cc6634C0532925a3b844Bc454e4438f44e/balances/history?from_block=10&to_block=1")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    let response = server
+        .get("/v1/public/eth/accounts/0x742d35Cc6634C0532925a3b844Bc454e4438f44e/balances/history?token=0x123")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_get_balance_history_endpoint() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    let response = server
+        .get("/v1/public/eth/accounts/0x742d35Cc6634C0532925a3b844Bc454e4438f44e/balances/history?from=2024-01-01T00:00:00Z")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert!(body["history"].is_array());
+}
+
+#[tokio::test]
+async fn test_get_account_portfolio_endpoint() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    // Test with invalid address
+    let response = server.get("/v1/public/eth/accounts/0xinvalid/portfolio").await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    // Test with valid address, read from the chain
+    let response = server
+        .get("/v1/public/eth/accounts/0xd27de11aaacd14c62fe689d214a67e9385e6f60c/portfolio")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["source"], "rpc");
+    assert!(body["total_usd_value"].is_string());
+    assert!(body["block_number"].is_number());
+    let holdings = body["holdings"].as_array().unwrap();
+    assert!(holdings.iter().all(|holding| holding["balance"] != "0"));
+
+    // Test that unverified tokens are read too, MyToken is held by the account
+    let state = create_test_state().await;
+    state
+        .repo
+        .upsert_token(&NewToken {
+            address: "0xab809CB0aB6669d51f6189432f751f1a916a10cd",
+            chain_id: state.chain_id,
+            symbol: Some("MYTK"),
+            name: Some("MyToken"),
+            decimals: Some(18),
+            logo_uri: None,
+            tags: None,
+            verified: Some(false),
+        })
+        .await
+        .unwrap();
+    let response = server
+        .get("/v1/public/eth/accounts/0xd27de11aaacd14c62fe689d214a67e9385e6f60c/portfolio")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body: Value = response.json();
+    assert!(body["holdings"].as_array().unwrap().iter().any(|holding| {
+        holding["token_address"] == "0xab809cb0ab6669d51f6189432f751f1a916a10cd"
+            && holding["verified"] == false
+    }));
+
+    // Test with stored balances
+    let response = server
+        .get("/v1/public/eth/accounts/0xd27de11aaacd14c62fe689d214a67e9385e6f60c/portfolio?source=db")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["source"], "db");
+    assert!(body["holdings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|holding| holding["updated_at"].is_string()));
+}
+
+#[tokio::test]
+async fn test_get_token_price_endpoint() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    // Test with invalid address and window
+    let response = server.get("/v1/public/eth/tokens/0xinvalid/price").await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let response = server
+        .get("/v1/public/eth/tokens/0xfFf9976782d46CC05630D1f6eBAb18b2324d6B14/price?twap_window=0")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    // Test WETH, which has a direct USDC pool
+    let response = server
+        .get("/v1/public/eth/tokens/0xfFf9976782d46CC05630D1f6eBAb18b2324d6B14/price")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body: Value = response.json();
+    assert!(body["spot_price"].is_string());
+    assert!(body["liquidity"].is_string());
+    assert!(!body["route"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_verify_signature_endpoint() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+    let signer = PrivateKeySigner::random();
+    let signature = signer.sign_message_sync(b"hello").unwrap();
+
+    // Test without message or typed data
+    let response = server
+        .post("/v1/public/eth/signatures/verify")
+        .json(&json!({ "signer": signer.address(), "signature": signature.to_string() }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    // Test an EOA signature, valid for its signer only
+    let response = server
+        .post("/v1/public/eth/signatures/verify")
+        .json(&json!({ "signer": signer.address(), "signature": signature.to_string(), "message": "hello" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["valid"], true);
+    assert_eq!(body["method"], "ecdsa");
+
+    let response = server
+        .post("/v1/public/eth/signatures/verify")
+        .json(&json!({
+            "signer": "0x742d35Cc6634C0532925a3b844Bc454e4438f44e",
+            "signature": signature.to_string(),
+            "message": "0x68656c6c6f",
+            "encoding": "hex"
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["recovered"], signer.address().to_string());
+}
+
+#[tokio::test]
+async fn test_siwe_sign_in_flow() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+    let signer = PrivateKeySigner::random();
+
+    // Private endpoints need a session
+    let response = server.get("/v1/private/me").await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let response = server.get("/v1/public/auth/nonce").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body: Value = response.json();
+    let nonce = body["nonce"].as_str().unwrap().to_string();
+
+    let message = format!(
+        "{} wants you to sign in with your Ethereum account:\n{}\n\nURI: http://localhost:3000\nVersion: 1\nChain ID: 11155111\nNonce: {}\nIssued At: {}",
+        CONFIG.auth.domain,
+        signer.address(),
+        nonce,
+        chrono::Utc::now().to_rfc3339(),
+    );
+    let signature = signer.sign_message_sync(message.as_bytes()).unwrap();
+
+    let response = server
+        .post("/v1/public/auth/verify")
+        .json(&json!({ "message": message, "signature": signature.to_string() }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body: Value = response.json();
+    let token = body["token"].as_str().unwrap().to_string();
+
+    // Nonces are single use
+    let response = server
+        .post("/v1/public/auth/verify")
+        .json(&json!({ "message": message, "signature": signature.to_string() }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let response = server
+        .get("/v1/private/me")
+        .authorization_bearer(&token)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["address"], signer.address().to_string());
+
+    let response = server
+        .post("/v1/private/auth/logout")
+        .authorization_bearer(&token)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    let response = server
+        .get("/v1/private/me")
+        .authorization_bearer(&token)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_watchlist_endpoints() {
+    let state = create_test_state().await;
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    // Test without an admin session
+    let response = server
+        .post("/v1/admin/eth/watchlist")
+        .json(&json!({ "address": "0x742d35Cc6634C0532925a3b844Bc454e4438f44e" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    let response = server.get("/v1/admin/eth/watchlist").await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    // Test with invalid address
+    let (status, _) = read_response(
+        create_watched_balance(
+            admin(),
+            State(state.clone()),
+            Json(serde_json::from_value(json!({ "address": "0xinvalid" })).unwrap()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Test create, update and delete
+    let (status, body) = read_response(
+        create_watched_balance(
+            admin(),
+            State(state.clone()),
+            Json(serde_json::from_value(json!({ "address": "0x742d35Cc6634C0532925a3b844Bc454e4438f44e", "label": "test" })).unwrap()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let id = body["id"].as_i64().unwrap();
+    assert_eq!(body["token_address"], "0x0000000000000000000000000000000000000000");
+
+    let (status, body) = read_response(
+        update_watched_balance(
+            admin(),
+            Path(id),
+            State(state.clone()),
+            Json(serde_json::from_value(json!({ "label": "renamed" })).unwrap()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["label"], "renamed");
+
+    let (status, _) =
+        read_response(delete_watched_balance(admin(), Path(id), State(state.clone())).await).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) =
+        read_response(get_watched_balance(admin(), Path(id), State(state.clone())).await).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_webhooks_require_admin() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    let response = server
+        .post("/v1/admin/webhooks")
+        .json(&json!({
+            "url": "https://example.org/hook",
+            "secret": "secret",
+            "address": "0x742d35Cc6634C0532925a3b844Bc454e4438f44e"
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let response = server.get("/v1/admin/webhooks").await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let response = server
+        .post("/v1/admin/webhooks/deliveries/1/replay")
+        .add_header("authorization", "Bearer unknown")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_tokens_endpoints() {
+    let state = create_test_state().await;
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    // Test without an admin session
+    let response = server
+        .post("/v1/admin/eth/tokens")
+        .json(&json!({ "address": "0x000000000000000000000000000000000000dEaD" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    let response = server
+        .post("/v1/admin/eth/tokens/import")
+        .json(&json!({ "name": "Test List", "tokens": [] }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    let response = server.get("/v1/admin/eth/tokens").await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    // Test with invalid address
+    let (status, _) = read_response(
+        create_token(
+            admin(),
+            State(state.clone()),
+            Json(serde_json::from_value(json!({ "address": "0xinvalid" })).unwrap()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The native asset is registered at startup and can't be removed
+    tokens::register_native_token(&state.repo, state.chain_id).await.unwrap();
+    let (status, body) = read_response(
+        get_token(
+            admin(),
+            Path("0x0000000000000000000000000000000000000000".to_string()),
+            State(state.clone()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["chain_id"], state.chain_id);
+    let (status, _) = read_response(
+        delete_token(
+            admin(),
+            Path("0x0000000000000000000000000000000000000000".to_string()),
+            State(state.clone()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Test import, update and delete
+    let (status, body) = read_response(
+        import_tokens(
+            admin(),
+            Query(serde_json::from_value(json!({})).unwrap()),
+            State(state.clone()),
+            Json(
+                serde_json::from_value(json!({
+                    "name": "Test List",
+                    "tokens": [{
+                        "chainId": state.chain_id,
+                        "address": "0x000000000000000000000000000000000000dEaD",
+                        "symbol": "DEAD",
+                        "name": "Dead Token",
+                        "decimals": 18
+                    }]
+                }))
+                .unwrap(),
+            ),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["imported"], 1);
+
+    let (status, body) = read_response(
+        update_token(
+            admin(),
+            Path("0x000000000000000000000000000000000000dead".to_string()),
+            State(state.clone()),
+            Json(serde_json::from_value(json!({ "verified": false })).unwrap()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["symbol"], "DEAD");
+    assert_eq!(body["verified"], false);
+
+    // A watched token can't be removed
+    let watched = state
+        .repo
+        .add_watched_balance(
+            "0x742d35Cc6634C0532925a3b844Bc454e4438f44e",
+            "0x000000000000000000000000000000000000dEaD",
+            None,
+        )
+        .await
+        .unwrap();
+    let (status, body) = read_response(
+        delete_token(
+            admin(),
+            Path("0x000000000000000000000000000000000000dead".to_string()),
+            State(state.clone()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "conflict");
+    state.repo.delete_watched_balance(watched.id).await.unwrap();
+
+    let (status, _) = read_response(
+        delete_token(
+            admin(),
+            Path("0x000000000000000000000000000000000000dead".to_string()),
+            State(state.clone()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = read_response(
+        get_token(
+            admin(),
+            Path("0x000000000000000000000000000000000000dead".to_string()),
+            State(state.clone()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_api_keys_require_admin() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    let response = server
+        .post("/v1/admin/api-keys")
+        .json(&json!({ "owner": "partner" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let response = server.get("/v1/admin/api-keys").await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let response = server
+        .patch("/v1/admin/api-keys/1")
+        .add_header("authorization", "Bearer unknown")
+        .json(&json!({ "active": false }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let response = server.get("/v1/admin/api-keys/usage").await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_api_keys_endpoints() {
+    let state = create_test_state().await;
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    // Test with an unknown scope
+    let (status, _) = read_response(
+        create_api_key(
+            admin(),
+            State(state.clone()),
+            Json(serde_json::from_value(json!({ "owner": "partner", "scopes": ["admin"] })).unwrap()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The key is only returned on creation
+    let (status, body) = read_response(
+        create_api_key(
+            admin(),
+            State(state.clone()),
+            Json(
+                serde_json::from_value(
+                    json!({ "owner": "partner", "scopes": ["private"], "daily_quota": 10 }),
+                )
+                .unwrap(),
+            ),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let key = body["key"].as_str().unwrap().to_string();
+    let id = body["id"].as_i64().unwrap();
+    assert_eq!(body["key_prefix"], key[..11]);
+    assert_eq!(body["daily_quota"], 10);
+
+    let (status, body) =
+        read_response(get_api_key(admin(), Path(id), State(state.clone())).await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("key").is_none());
+
+    // Unknown keys are rejected, and keys are limited to their scopes
+    let response = server
+        .get("/v1/public/auth/nonce")
+        .add_header("x-api-key", "ak_unknown")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    let response = server
+        .get("/v1/public/auth/nonce")
+        .add_header("x-api-key", &key)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    // Revoked keys are rejected
+    let (status, _) = read_response(
+        update_api_key(
+            admin(),
+            Path(id),
+            State(state.clone()),
+            Json(serde_json::from_value(json!({ "active": false })).unwrap()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let response = server
+        .get("/v1/private/me")
+        .add_header("x-api-key", &key)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    // Test usage export
+    let (status, _) = read_response(
+        get_api_key_usage(
+            admin(),
+            Query(serde_json::from_value(json!({ "from": "2025-07-22", "to": "2025-07-01" })).unwrap()),
+            State(state.clone()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = read_response(
+        get_api_key_usage(
+            admin(),
+            Query(serde_json::from_value(json!({ "api_key_id": id })).unwrap()),
+            State(state.clone()),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["items"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_wallet_endpoints_require_admin() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    let response = server
+        .post("/v1/admin/eth/my-token/mint")
+        .json(&json!({
+            "to": "0x000000000000000000000000000000000000dEaD",
+            "amount": "1000"
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let response = server
+        .get("/v1/admin/eth/transactions/0x0000000000000000000000000000000000000000000000000000000000000000")
+        .add_header("authorization", "Bearer unknown")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let response = server.get("/v1/admin/eth/transactions?status=pending").await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_faucet_endpoint() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+
+    // Test with invalid address and asset
+    let response = server
+        .post("/v1/public/eth/faucet")
+        .json(&json!({ "address": "0xinvalid" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let response = server
+        .post("/v1/public/eth/faucet")
+        .json(&json!({
+            "address": "0x000000000000000000000000000000000000dEaD",
+            "asset": "btc"
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // The test router has no hot wallet
+    let response = server
+        .post("/v1/public/eth/faucet")
+        .json(&json!({ "address": "0x000000000000000000000000000000000000dEaD" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_build_transaction_endpoint() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+    let from = "0x742d35Cc6634C0532925a3b844Bc454e4438f44e";
+
+    // Test with invalid addresses, amounts and calldata
+    let response = server
+        .post("/v1/public/eth/transactions/build")
+        .json(&json!({ "from": "0xinvalid", "type": "native_transfer", "to": from, "amount": "1" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let response = server
+        .post("/v1/public/eth/transactions/build")
+        .json(&json!({
+            "from": from,
+            "type": "erc20_transfer",
+            "token": "0xinvalid",
+            "to": from,
+            "amount": "1"
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let response = server
+        .post("/v1/public/eth/transactions/build")
+        .json(&json!({ "from": from, "type": "native_transfer", "to": from, "amount": "-1" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let response = server
+        .post("/v1/public/eth/transactions/build")
+        .json(&json!({ "from": from, "type": "call", "to": from, "data": "0xzz" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let response = server
+        .post("/v1/public/eth/transactions/build")
+        .json(&json!({ "from": from, "type": "swap", "to": from }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Test an approve, its calldata is encoded from the IERC20 ABI
+    let response = server
+        .post("/v1/public/eth/transactions/build")
+        .json(&json!({
+            "from": from,
+            "type": "erc20_approve",
+            "token": "0xdAC17F958D2ee523a2206206994597C13D831ec7",
+            "spender": "0x000000000000000000000000000000000000dEaD",
+            "amount": "0"
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body["to"], "0xdAC17F958D2ee523a2206206994597C13D831ec7");
+    assert_eq!(body["value"], "0");
+    assert_eq!(
+        body["data"],
+        "0x095ea7b3000000000000000000000000000000000000000000000000000000000000dead\
+         0000000000000000000000000000000000000000000000000000000000000000"
+    );
+    assert!(body["gas_limit"].as_u64().unwrap() > 21000);
+    let tx = &body["eth_send_transaction"];
+    assert_eq!(tx["type"], "0x2");
+    assert_eq!(tx["data"], body["data"]);
+    assert_eq!(tx["input"], body["data"]);
+    assert!(tx.get("nonce").is_some());
+    assert!(tx.get("gas").is_some());
+    assert!(tx.get("maxFeePerGas").is_some());
+    assert!(tx.get("maxPriorityFeePerGas").is_some());
+}
+
+#[tokio::test]
+async fn test_permit_endpoints() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+    // USDC on Sepolia, a FiatToken supporting EIP-2612 permits
+    let token = "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238";
+    let spender = "0x000000000000000000000000000000000000dEaD";
+    let signer = PrivateKeySigner::random();
+    let owner = signer.address().to_string();
+
+    // Test with invalid addresses and value
+    let response = server
+        .post("/v1/public/eth/permits/typed-data")
+        .json(&json!({ "token": "0xinvalid", "owner": owner, "spender": spender, "value": "1" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let response = server
+        .post("/v1/public/eth/permits/typed-data")
+        .json(&json!({ "token": token, "owner": owner, "spender": spender, "value": "-1" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    // Test with a token without permits
+    let response = server
+        .post("/v1/public/eth/permits/typed-data")
+        .json(&json!({ "token": spender, "owner": owner, "spender": spender, "value": "1" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    // Test building the typed data
+    let response = server
+        .post("/v1/public/eth/permits/typed-data")
+        .json(&json!({ "token": token, "owner": owner, "spender": spender, "value": "1000000" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body["nonce"], "0");
+    assert_eq!(body["typed_data"]["primaryType"], "Permit");
+    assert_eq!(body["typed_data"]["domain"]["name"], "USDC");
+    assert_eq!(body["typed_data"]["domain"]["version"], "2");
+    assert_eq!(body["typed_data"]["domain"]["chainId"], 11155111);
+    let deadline = body["deadline"].as_u64().unwrap();
+
+    // Test verifying the signed permit
+    let hash: alloy::primitives::B256 = body["hash"].as_str().unwrap().parse().unwrap();
+    let signature = signer.sign_hash_sync(&hash).unwrap().to_string();
+    let response = server
+        .post("/v1/public/eth/permits/verify")
+        .json(&json!({
+            "token": token,
+            "owner": owner,
+            "spender": spender,
+            "value": "1000000",
+            "deadline": deadline,
+            "signature": signature
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body["valid"], true);
+    assert_eq!(body["signature_valid"], true);
+    assert_eq!(body["expired"], false);
+    assert_eq!(body["recovered"], owner);
+    assert!(body["v"] == 27 || body["v"] == 28);
+    assert!(body["permit_calldata"].as_str().unwrap().starts_with("0xd505accf"));
+
+    // Test a permit signed for another value
+    let response = server
+        .post("/v1/public/eth/permits/verify")
+        .json(&json!({
+            "token": token,
+            "owner": owner,
+            "spender": spender,
+            "value": "1",
+            "deadline": deadline,
+            "signature": signature
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["signature_valid"], false);
+
+    // Test with an invalid signature
+    let response = server
+        .post("/v1/public/eth/permits/verify")
+        .json(&json!({
+            "token": token,
+            "owner": owner,
+            "spender": spender,
+            "value": "1",
+            "deadline": deadline,
+            "signature": "0x1234"
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_simulate_endpoint() {
+    let app = create_test_router().await;
+    let server = TestServer::new(app).expect("Failed to create test server");
+    let from = PrivateKeySigner::random().address().to_string();
+    let recipient = "0x000000000000000000000000000000000000dEaD";
+    // USDC on Sepolia
+    let token = "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238";
+
+    // Test with an invalid address and return type
+    let response = server
+        .post("/v1/public/eth/simulate")
+        .json(&json!({ "from": "0xinvalid", "type": "native_transfer", "to": recipient, "amount": "1" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let response = server
+        .post("/v1/public/eth/simulate")
+        .json(&json!({
+            "from": from,
+            "type": "call",
+            "to": token,
+            "data": "0x",
+            "returns": "uint257"
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    // Test with more extra tokens than are read
+    let tokens = vec![token; 1001];
+    let response = server
+        .post("/v1/public/eth/simulate")
+        .json(&json!({
+            "from": from,
+            "type": "native_transfer",
+            "to": recipient,
+            "amount": "1",
+            "tokens": tokens
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    // Test a transfer of an account without funds, it reverts
+    let response = server
+        .post("/v1/public/eth/simulate")
+        .json(&json!({
+            "from": from,
+            "type": "erc20_transfer",
+            "token": token,
+            "to": recipient,
+            "amount": "1"
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body["success"], false);
+    assert!(body["revert_reason"].is_string());
+    assert!(body["return_value"].is_null());
+
+    // Test a native transfer funded by a balance override
+    let response = server
+        .post("/v1/public/eth/simulate")
+        .json(&json!({
+            "from": from,
+            "type": "native_transfer",
+            "to": recipient,
+            "amount": "1000",
+            "state_overrides": { from.clone(): { "balance": "0xde0b6b3a7640000" } }
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body["success"], true);
+    assert_eq!(body["return_data"], "0x");
+    assert!(body["gas_used"].as_u64().unwrap() >= 21000);
+    if !body["balance_source"].is_null() {
+        let changes = body["balance_changes"].as_array().unwrap();
+        assert!(changes.iter().any(|change| change["address"] == from && change["delta"] == "-1000"));
+    }
+
+    // Test decoding a view call
+    let response = server
+        .post("/v1/public/eth/simulate")
+        .json(&json!({
+            "from": from,
+            "type": "call",
+            "to": token,
+            // decimals()
+            "data": "0x313ce567",
+            "returns": "uint8"
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body["success"], true);
+    assert_eq!(body["return_value"], "6");
+    assert!(body["balance_changes"].as_array().unwrap().is_empty());
 }
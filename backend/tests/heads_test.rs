@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use alloy::providers::{Provider, ProviderBuilder};
+use futures::StreamExt;
+use tokio_util::sync::CancellationToken;
+
+use backend::heads::{Config, HeadEvent, HeadTracker};
+
+// Helper function to create a test tracker with a small channel
+fn create_test_tracker() -> HeadTracker {
+    HeadTracker::new(&Config {
+        poll_interval_ms: 100,
+        channel_capacity: 2,
+    })
+}
+
+fn head(block_number: u64) -> HeadEvent {
+    HeadEvent {
+        block_number,
+        block_hash: format!("0x{:064x}", block_number),
+        parent_hash: format!("0x{:064x}", block_number.saturating_sub(1)),
+        timestamp: 1_700_000_000 + block_number * 12,
+        base_fee_per_gas: Some("1000000000".to_string()),
+        gas_price: "1500000000".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_subscribe_receives_new_heads() {
+    let tracker = create_test_tracker();
+    let mut first = Box::pin(tracker.subscribe());
+    let mut second = Box::pin(tracker.subscribe());
+
+    tracker.publish(head(1));
+    tracker.publish(head(2));
+
+    assert_eq!(first.next().await, Some(head(1)));
+    assert_eq!(first.next().await, Some(head(2)));
+    assert_eq!(second.next().await, Some(head(1)));
+    assert_eq!(second.next().await, Some(head(2)));
+}
+
+#[tokio::test]
+async fn test_subscribe_starts_with_latest_head() {
+    let tracker = create_test_tracker();
+    tracker.publish(head(5));
+
+    let mut stream = Box::pin(tracker.subscribe());
+    tracker.publish(head(6));
+
+    assert_eq!(stream.next().await, Some(head(5)));
+    assert_eq!(stream.next().await, Some(head(6)));
+    assert_eq!(tracker.latest(), Some(head(6)));
+}
+
+#[tokio::test]
+async fn test_publish_ignores_old_heads() {
+    let tracker = create_test_tracker();
+    let mut stream = Box::pin(tracker.subscribe());
+
+    tracker.publish(head(10));
+    tracker.publish(head(10));
+    tracker.publish(head(9));
+    tracker.publish(head(11));
+
+    assert_eq!(stream.next().await, Some(head(10)));
+    assert_eq!(stream.next().await, Some(head(11)));
+}
+
+#[tokio::test]
+async fn test_publish_replaced_head_at_same_height() {
+    let tracker = create_test_tracker();
+    let mut stream = Box::pin(tracker.subscribe());
+
+    let replaced = HeadEvent {
+        block_hash: format!("0x{:064x}", 0xbeef),
+        ..head(10)
+    };
+    tracker.publish(head(10));
+    tracker.publish(replaced.clone());
+
+    assert_eq!(stream.next().await, Some(head(10)));
+    assert_eq!(stream.next().await, Some(replaced));
+}
+
+#[tokio::test]
+async fn test_slow_subscriber_is_dropped() {
+    let tracker = create_test_tracker();
+    let mut slow = Box::pin(tracker.subscribe());
+    let mut fast = Box::pin(tracker.subscribe());
+
+    // The fast subscriber keeps up, the slow one falls more than the capacity behind
+    for block_number in 1..=5 {
+        tracker.publish(head(block_number));
+        assert_eq!(fast.next().await, Some(head(block_number)));
+    }
+
+    assert_eq!(slow.next().await, None);
+
+    tracker.publish(head(6));
+    assert_eq!(fast.next().await, Some(head(6)));
+}
+
+#[tokio::test]
+async fn test_latest_head_forgotten_while_idle() {
+    let tracker = create_test_tracker();
+    tracker.publish(head(7));
+
+    // Nobody is subscribed, so the node is never called
+    let provider = ProviderBuilder::new()
+        .connect_http("http://127.0.0.1:1".parse().unwrap())
+        .erased();
+    let shutdown = CancellationToken::new();
+    let config = Config {
+        poll_interval_ms: 10,
+        channel_capacity: 2,
+    };
+    let running = tokio::spawn(tracker.clone().run(provider, config, shutdown.clone()));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // A later subscriber doesn't start with the stale head
+    assert_eq!(tracker.latest(), None);
+
+    shutdown.cancel();
+    running.await.unwrap();
+}
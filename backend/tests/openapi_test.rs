@@ -0,0 +1,171 @@
+use std::collections::BTreeSet;
+
+use axum::http::{Method, StatusCode};
+use axum_test::TestServer;
+use serde_json::Value;
+use sqlx::PgPool;
+
+use backend::{openapi, routes};
+
+mod common;
+
+use common::create_state;
+
+// Helper function to build a request URI of a documented path, parameters are filled in
+fn to_uri(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if segment.starts_with('{') {
+                "1"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Helper function to check whether a path is served by a documented path with parameters
+fn matches_documented(documented: &BTreeSet<String>, path: &str) -> bool {
+    documented.iter().any(|template| {
+        let segments: Vec<&str> = path.split('/').collect();
+        let template: Vec<&str> = template.split('/').collect();
+        segments.len() == template.len()
+            && segments
+                .iter()
+                .zip(template)
+                .all(|(segment, template)| template.starts_with('{') || *segment == template)
+    })
+}
+
+#[sqlx::test()]
+async fn test_every_route_is_documented(pool: PgPool) {
+    let state = create_state(pool).await;
+    let (router, openapi) = routes::router(&state).split_for_parts();
+    let router = router.with_state(state);
+    let server = TestServer::new(router).unwrap();
+
+    let documented: BTreeSet<String> = openapi.paths.paths.keys().cloned().collect();
+    assert!(documented.contains("/v1/public/eth/accounts/{address}"));
+
+    // No operation uses TRACE, so a routed path answers it with 405 without running a handler
+    for path in &documented {
+        let response = server.method(Method::TRACE, &to_uri(path)).await;
+        assert_eq!(
+            response.status_code(),
+            StatusCode::METHOD_NOT_ALLOWED,
+            "{} is documented but not routed",
+            path
+        );
+    }
+
+    // Prefixes of the documented paths are not routed unless documented themselves
+    let prefixes: BTreeSet<String> = documented
+        .iter()
+        .flat_map(|path| {
+            path.match_indices('/')
+                .skip(1)
+                .map(|(index, _)| path[..index].to_string())
+                .collect::<Vec<_>>()
+        })
+        .filter(|prefix| !matches_documented(&documented, prefix))
+        .collect();
+    assert!(prefixes.contains("/v1/admin/eth"));
+    for prefix in &prefixes {
+        let response = server.method(Method::TRACE, &to_uri(prefix)).await;
+        assert_eq!(
+            response.status_code(),
+            StatusCode::NOT_FOUND,
+            "{} is routed but not documented",
+            prefix
+        );
+    }
+
+    // Methods without an operation are not routed either
+    let methods = [
+        Method::GET,
+        Method::POST,
+        Method::PUT,
+        Method::PATCH,
+        Method::DELETE,
+    ];
+    for (path, item) in &openapi.paths.paths {
+        let uri = to_uri(path);
+        for method in &methods {
+            let documented = match *method {
+                Method::GET => item.get.is_some(),
+                Method::POST => item.post.is_some(),
+                Method::PUT => item.put.is_some(),
+                Method::PATCH => item.patch.is_some(),
+                _ => item.delete.is_some(),
+            };
+            if !documented {
+                let response = server.method(method.clone(), &uri).await;
+                assert_eq!(
+                    response.status_code(),
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    "{} {} is routed but not documented",
+                    method,
+                    path
+                );
+            }
+        }
+    }
+}
+
+#[sqlx::test()]
+async fn test_openapi_document(pool: PgPool) {
+    let state = create_state(pool).await;
+    let (_, openapi) = routes::router(&state).split_for_parts();
+    let server = TestServer::new(openapi::docs::<()>(openapi)).unwrap();
+
+    let response = server.get(openapi::OPENAPI_PATH).await;
+    response.assert_status_ok();
+    let document: Value = response.json();
+    assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+
+    // Response types and the error body are components
+    let schemas = &document["components"]["schemas"];
+    for name in [
+        "AccountResponse",
+        "Erc20TokenResponse",
+        "BlockchainMiscResponse",
+        "ErrorResponse",
+    ] {
+        assert!(schemas[name].is_object(), "{} is not documented", name);
+    }
+    let codes = schemas["ErrorCode"]["enum"].as_array().unwrap();
+    assert!(codes.contains(&Value::from("upstream_timeout")));
+
+    // Fees don't fit in a JavaScript number
+    let gas_price = &schemas["BlockchainMiscResponse"]["properties"]["gas_price"];
+    assert_eq!(gas_price["type"], "integer");
+    assert_eq!(gas_price["format"], "uint128");
+
+    // Operations document their errors and security
+    let operation = &document["paths"]["/v1/public/eth/accounts/{address}"]["get"];
+    assert_eq!(
+        operation["responses"]["400"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/ErrorResponse"
+    );
+    assert!(operation["responses"]["429"].is_object());
+    assert!(document["components"]["securitySchemes"]["api_key"].is_object());
+    let operation = &document["paths"]["/v1/admin/eth/my-token/mint"]["post"];
+    assert_eq!(
+        operation["security"][0]["session"],
+        Value::Array(Vec::new())
+    );
+
+    // The Swagger UI loads the document
+    let response = server.get(&format!("{}/", openapi::SWAGGER_UI_PATH)).await;
+    response.assert_status_ok();
+    assert!(response.text().contains("<html"));
+    let response = server
+        .get(&format!(
+            "{}/swagger-initializer.js",
+            openapi::SWAGGER_UI_PATH
+        ))
+        .await;
+    response.assert_status_ok();
+    assert!(response.text().contains(openapi::OPENAPI_PATH));
+}
@@ -0,0 +1,108 @@
+use alloy::primitives::{Address, B256, U256, address, b256, keccak256};
+use alloy::signers::{SignerSync, local::PrivateKeySigner};
+use alloy::sol_types::{Eip712Domain, SolCall, SolStruct, eip712_domain};
+use backend::eth::IERC20Permit;
+use backend::permit::{
+    Permit, decode_permit_signature, encode_permit_call, is_expired, permit_signing_hash,
+    permit_typed_data,
+};
+
+const TOKEN_ADDRESS: Address = address!("0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238");
+
+// Helper function to build the domain of a test token
+fn domain() -> Eip712Domain {
+    eip712_domain! {
+        name: "USDC",
+        version: "2",
+        chain_id: 11155111,
+        verifying_contract: TOKEN_ADDRESS,
+    }
+}
+
+// Helper function to build a permit of `owner`
+fn permit(owner: Address) -> Permit {
+    Permit {
+        owner,
+        spender: address!("0x000000000000000000000000000000000000dEaD"),
+        value: U256::from(1_000_000u64),
+        nonce: U256::ZERO,
+        deadline: U256::from(1_900_000_000u64),
+    }
+}
+
+#[test]
+fn test_permit_typed_data() {
+    let permit = permit(Address::with_last_byte(1));
+
+    // The type hash is the one of EIP-2612
+    assert_eq!(
+        permit.eip712_type_hash(),
+        b256!("0x6e71edae12b1b97f4d1f60370fef10105fa2faae0126114a169c64845d6126c9")
+    );
+    assert_eq!(
+        permit.eip712_type_hash(),
+        keccak256(
+            "Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)"
+        )
+    );
+
+    // Wallets sign the same hash as the typed data returned to them
+    let typed_data = permit_typed_data(&domain(), &permit);
+    assert_eq!(
+        typed_data.eip712_signing_hash().unwrap(),
+        permit_signing_hash(&domain(), &permit)
+    );
+
+    let json = serde_json::to_value(&typed_data).unwrap();
+    assert_eq!(json["primaryType"], "Permit");
+    assert_eq!(json["domain"]["name"], "USDC");
+    assert_eq!(json["domain"]["version"], "2");
+    let fields: Vec<_> = json["types"]["Permit"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["owner", "spender", "value", "nonce", "deadline"]);
+    assert_eq!(json["types"]["EIP712Domain"].as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn test_decode_permit_signature() {
+    let signer = PrivateKeySigner::random();
+    let permit = permit(signer.address());
+    let hash = permit_signing_hash(&domain(), &permit);
+    let signature = signer.sign_hash_sync(&hash).unwrap();
+
+    let decoded = decode_permit_signature(&hash, &signature.as_bytes()).unwrap();
+    assert_eq!(decoded.recovered, Some(signer.address()));
+    assert_eq!(decoded.v, 27 + signature.v() as u8);
+    assert_eq!(decoded.r, B256::from(signature.r()));
+    assert_eq!(decoded.s, B256::from(signature.s()));
+
+    // The permit call carries the split signature
+    let call =
+        IERC20Permit::permitCall::abi_decode(&encode_permit_call(&permit, &decoded)).unwrap();
+    assert_eq!(call.owner, signer.address());
+    assert_eq!(call.value, permit.value);
+    assert_eq!(call.deadline, permit.deadline);
+    assert_eq!((call.v, call.r, call.s), (decoded.v, decoded.r, decoded.s));
+
+    // A signature over another permit recovers another address
+    let other = Permit {
+        nonce: U256::from(1u64),
+        ..permit
+    };
+    let other_hash = permit_signing_hash(&domain(), &other);
+    let decoded = decode_permit_signature(&other_hash, &signature.as_bytes()).unwrap();
+    assert_ne!(decoded.recovered, Some(signer.address()));
+
+    assert!(decode_permit_signature(&hash, &[0u8; 10]).is_err());
+}
+
+#[test]
+fn test_is_expired() {
+    assert!(!is_expired(U256::from(100u64), 100));
+    assert!(is_expired(U256::from(99u64), 100));
+    assert!(!is_expired(U256::MAX, 100));
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use alloy::primitives::{U256, address};
+use alloy::providers::{Provider, ProviderBuilder};
+use backend::cache::{self, DistCache};
+use backend::prices::{Config, FeedConfig, PriceOracle, usd_value};
+use rust_decimal::Decimal;
+
+// Helper function to create an oracle, neither the provider nor the cache are reached for static prices
+fn create_test_oracle(config: &Config) -> backend::error::Result<PriceOracle> {
+    let provider = ProviderBuilder::new()
+        .connect_http("http://localhost:8545".parse().unwrap())
+        .erased();
+    let cache = DistCache::new(&cache::Config {
+        redis_url: "redis://localhost:6379".to_string(),
+        connect_timeout: 1,
+    });
+    PriceOracle::new(provider, cache, config)
+}
+
+#[tokio::test]
+async fn test_static_prices() {
+    let token_address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3");
+    let config = Config {
+        feeds: Vec::new(),
+        static_prices: HashMap::from([(token_address.to_string(), Decimal::new(125, 2))]),
+    };
+    let oracle = create_test_oracle(&config).unwrap();
+
+    assert_eq!(oracle.get_usd_price(token_address).await, Some(Decimal::new(125, 2)));
+    assert_eq!(oracle.get_usd_price(address!("0x1c7d4b196cb0c7b01d743fbc6116a902379c7238")).await, None);
+}
+
+#[tokio::test]
+async fn test_unreachable_feed_has_no_price() {
+    let token_address = address!("0x0000000000000000000000000000000000000000");
+    let config = Config {
+        feeds: vec![FeedConfig {
+            token_address: token_address.to_string(),
+            feed_address: "0x694AA1769357215DE4FAC081bf1f309aDC325306".to_string(),
+            heartbeat: 3600,
+        }],
+        // Feeds take precedence over static prices
+        static_prices: HashMap::from([(token_address.to_string(), Decimal::ONE)]),
+    };
+    let oracle = create_test_oracle(&config).unwrap();
+
+    assert_eq!(oracle.get_usd_price(token_address).await, None);
+}
+
+#[test]
+fn test_invalid_config() {
+    let config = Config {
+        feeds: Vec::new(),
+        static_prices: HashMap::from([("0xinvalid".to_string(), Decimal::ONE)]),
+    };
+    assert!(create_test_oracle(&config).is_err());
+}
+
+#[test]
+fn test_usd_value() {
+    let price = Decimal::new(250012345678, 8); // 2500.12345678
+    let balance = U256::from(1_500_000_000_000_000_000u64);
+    assert_eq!(usd_value(balance, 18, price), Some(Decimal::new(375019, 2)));
+    assert_eq!(usd_value(U256::from(1u64), 6, Decimal::ONE), Some(Decimal::ZERO));
+    assert_eq!(usd_value(U256::ZERO, 18, price), Some(Decimal::ZERO));
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use axum::{http::StatusCode, response::IntoResponse};
+use axum_test::TestServer;
+use backend::api_keys::API_KEY_HEADER;
+use backend::cache::DistCache;
+use backend::config::CONFIG;
+use backend::error::{AppError, TooManyRequestsError};
+use backend::rate_limit::{BucketConfig, Config, RateLimitStatus, client_ip, take_token};
+use backend::routes;
+use ipnet::IpNet;
+use sqlx::PgPool;
+
+mod common;
+
+use common::create_state;
+
+// Helper function to parse trusted proxy networks
+fn networks(networks: &[&str]) -> Vec<IpNet> {
+    networks.iter().map(|net| net.parse().unwrap()).collect()
+}
+
+#[test]
+fn test_client_ip_ignores_header_of_untrusted_peer() {
+    let trusted = networks(&["10.0.0.0/8"]);
+    let ip = client_ip(
+        Some("203.0.113.7".parse().unwrap()),
+        Some("198.51.100.1"),
+        &trusted,
+    );
+    assert_eq!(ip, Some("203.0.113.7".parse().unwrap()));
+    assert_eq!(client_ip(None, Some("198.51.100.1"), &trusted), None);
+}
+
+#[test]
+fn test_client_ip_behind_trusted_proxies() {
+    let trusted = networks(&["10.0.0.0/8", "::1/128"]);
+    let peer = Some("10.0.0.2".parse().unwrap());
+
+    // The first untrusted hop from the right is the client, spoofed entries are ignored
+    let ip = client_ip(peer, Some("1.1.1.1, 203.0.113.7, 10.0.0.3"), &trusted);
+    assert_eq!(ip, Some("203.0.113.7".parse().unwrap()));
+
+    // Hops may carry a port
+    let ip = client_ip(peer, Some("203.0.113.7:5123"), &trusted);
+    assert_eq!(ip, Some("203.0.113.7".parse().unwrap()));
+
+    // Without the header, or with garbage, the proxy itself is the client
+    assert_eq!(client_ip(peer, None, &trusted), peer);
+    assert_eq!(client_ip(peer, Some("garbage"), &trusted), peer);
+
+    // Only trusted hops, the leftmost one is the client
+    let ip = client_ip(peer, Some("10.0.0.4, 10.0.0.3"), &trusted);
+    assert_eq!(ip, Some("10.0.0.4".parse().unwrap()));
+}
+
+#[test]
+fn test_too_many_requests_response() {
+    let err: AppError = TooManyRequestsError {
+        message: "Rate limit exceeded for public endpoints".to_string(),
+        retry_after: 3,
+        status: RateLimitStatus {
+            limit: 20,
+            remaining: 0,
+            reset: 4,
+        },
+    }
+    .into();
+
+    let response = err.into_response();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let headers = response.headers();
+    assert_eq!(headers["retry-after"], "3");
+    assert_eq!(headers["x-ratelimit-limit"], "20");
+    assert_eq!(headers["x-ratelimit-remaining"], "0");
+    assert_eq!(headers["x-ratelimit-reset"], "4");
+}
+
+#[test]
+fn test_bucket_config_validation() {
+    let bucket = BucketConfig {
+        capacity: 1,
+        refill_per_second: 0.5,
+    };
+    assert!(bucket.validate().is_ok());
+
+    // An empty bucket rejects every request
+    let empty = BucketConfig {
+        capacity: 0,
+        ..bucket.clone()
+    };
+    assert!(empty.validate().is_err());
+
+    // A bucket that never refills would divide by zero in the script
+    for refill_per_second in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let bucket = BucketConfig {
+            refill_per_second,
+            ..bucket.clone()
+        };
+        assert!(bucket.validate().is_err());
+    }
+
+    // The error names the group
+    let config = Config {
+        trusted_proxies: Vec::new(),
+        client_ip_header: "x-forwarded-for".to_string(),
+        groups: HashMap::from([("public".to_string(), empty)]),
+    };
+    let err = config.validate().unwrap_err();
+    assert!(err.starts_with("rate_limit.groups.public:"));
+
+    // The shipped configuration is valid
+    assert!(CONFIG.rate_limit.validate().is_ok());
+}
+
+#[tokio::test]
+async fn test_take_token() {
+    let cache = DistCache::new(&CONFIG.cache);
+    let key = format!("rate_limit:test:{}", chrono::Utc::now().timestamp_micros());
+    let bucket = BucketConfig {
+        capacity: 3,
+        refill_per_second: 1.0,
+    };
+
+    // The bucket starts full and allows a burst of its capacity
+    for remaining in [2, 1, 0] {
+        let decision = take_token(&cache, &key, &bucket).await.unwrap();
+        assert!(decision.allowed);
+        assert_eq!(decision.status.remaining, remaining);
+    }
+
+    let decision = take_token(&cache, &key, &bucket).await.unwrap();
+    assert!(!decision.allowed);
+    assert_eq!(decision.retry_after, 1);
+    assert_eq!(decision.status.limit, 3);
+}
+
+#[sqlx::test()]
+async fn test_invalid_api_key_is_limited(pool: PgPool) {
+    let state = create_state(pool).await;
+    let app = routes::router(&state).split_for_parts().0.with_state(state);
+    let server = TestServer::new(app).unwrap();
+    let capacity = CONFIG.rate_limit.groups["public"].capacity;
+
+    // A junk key is rejected, and limited like an anonymous request until the bucket is empty
+    let mut rejected = 0;
+    let limited = loop {
+        let response = server
+            .get("/v1/public/eth/misc")
+            .add_header(API_KEY_HEADER, "junk")
+            .await;
+        if response.status_code() != StatusCode::UNAUTHORIZED {
+            break response;
+        }
+        rejected += 1;
+        assert!(
+            rejected <= capacity,
+            "invalid API keys bypass the rate limit"
+        );
+    };
+    limited.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert!(limited.headers().contains_key("retry-after"));
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use alloy::providers::{Provider, ProviderBuilder};
+use backend::heads::{self, HeadTracker};
+use backend::shutdown::{Config, Shutdown};
+use tokio::time::timeout;
+
+fn config() -> Config {
+    Config {
+        readiness_delay: 0,
+        drain_timeout: 1,
+    }
+}
+
+#[tokio::test]
+async fn test_start_fails_readiness_then_drains() {
+    let shutdown = Shutdown::new();
+    assert!(shutdown.is_ready());
+    assert!(!shutdown.token().is_cancelled());
+
+    shutdown.start(&config()).await;
+    assert!(!shutdown.is_ready());
+    timeout(Duration::from_secs(1), shutdown.cancelled())
+        .await
+        .expect("draining didn't start");
+
+    // Clones share the state
+    assert!(!shutdown.clone().is_ready());
+}
+
+#[tokio::test]
+async fn test_wait_tasks_lets_tasks_finish() {
+    let shutdown = Shutdown::new();
+    let finished = Arc::new(AtomicBool::new(false));
+
+    let token = shutdown.token();
+    let task_finished = finished.clone();
+    shutdown.spawn(async move {
+        token.cancelled().await;
+        // Work still in progress when draining starts completes
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        task_finished.store(true, Ordering::Relaxed);
+    });
+
+    shutdown.start(&config()).await;
+    timeout(Duration::from_secs(1), shutdown.wait_tasks())
+        .await
+        .expect("tasks didn't stop");
+    assert!(finished.load(Ordering::Relaxed));
+}
+
+#[tokio::test]
+async fn test_drain_deadline_starts_with_draining() {
+    let shutdown = Shutdown::new();
+    let config = config();
+
+    // No deadline before shutdown started
+    assert!(
+        timeout(
+            Duration::from_millis(1500),
+            shutdown.drain_deadline(&config)
+        )
+        .await
+        .is_err()
+    );
+
+    shutdown.start(&config).await;
+    timeout(
+        Duration::from_millis(1500),
+        shutdown.drain_deadline(&config),
+    )
+    .await
+    .expect("deadline didn't pass");
+}
+
+#[tokio::test]
+async fn test_head_tracker_stops_on_shutdown() {
+    // Nobody subscribes, so the tracker never calls the unreachable provider
+    let provider = ProviderBuilder::new()
+        .connect_http("http://127.0.0.1:1".parse().unwrap())
+        .erased();
+    let config = heads::Config {
+        poll_interval_ms: 10,
+        channel_capacity: 2,
+    };
+    let tracker = HeadTracker::new(&config);
+
+    let shutdown = Shutdown::new();
+    shutdown.spawn(tracker.run(provider, config, shutdown.token()));
+
+    shutdown.start(&self::config()).await;
+    timeout(Duration::from_secs(1), shutdown.wait_tasks())
+        .await
+        .expect("head tracker didn't stop");
+}
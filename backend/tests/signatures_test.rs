@@ -0,0 +1,77 @@
+use alloy::dyn_abi::TypedData;
+use alloy::primitives::{B256, eip191_hash_message};
+use alloy::signers::{SignerSync, local::PrivateKeySigner};
+use backend::signatures::{parse_signature, recover_signer};
+
+// EIP-712 typed data from the EIP's example
+const TYPED_DATA: &str = r#"{
+    "types": {
+        "EIP712Domain": [
+            { "name": "name", "type": "string" },
+            { "name": "version", "type": "string" },
+            { "name": "chainId", "type": "uint256" },
+            { "name": "verifyingContract", "type": "address" }
+        ],
+        "Person": [
+            { "name": "name", "type": "string" },
+            { "name": "wallet", "type": "address" }
+        ],
+        "Mail": [
+            { "name": "from", "type": "Person" },
+            { "name": "to", "type": "Person" },
+            { "name": "contents", "type": "string" }
+        ]
+    },
+    "primaryType": "Mail",
+    "domain": {
+        "name": "Ether Mail",
+        "version": "1",
+        "chainId": 1,
+        "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+    },
+    "message": {
+        "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+        "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+        "contents": "Hello, Bob!"
+    }
+}"#;
+
+#[test]
+fn test_recover_personal_sign() {
+    let signer = PrivateKeySigner::random();
+    let signature = signer.sign_message_sync(b"Sign in to the dapp").unwrap();
+    let hash = eip191_hash_message(b"Sign in to the dapp");
+
+    let recovered = recover_signer(&hash, &signature.as_bytes()).unwrap();
+    assert_eq!(recovered, Some(signer.address()));
+
+    // A different message recovers a different address
+    let other_hash = eip191_hash_message(b"Something else");
+    let recovered = recover_signer(&other_hash, &signature.as_bytes()).unwrap();
+    assert_ne!(recovered, Some(signer.address()));
+}
+
+#[test]
+fn test_recover_typed_data() {
+    let typed_data: TypedData = serde_json::from_str(TYPED_DATA).unwrap();
+    let hash = typed_data.eip712_signing_hash().unwrap();
+    assert_eq!(
+        hash.to_string(),
+        "0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+    );
+
+    let signer = PrivateKeySigner::random();
+    let signature = signer.sign_hash_sync(&hash).unwrap();
+    let recovered = recover_signer(&hash, &signature.as_bytes()).unwrap();
+    assert_eq!(recovered, Some(signer.address()));
+
+    // ERC-2098 compact signatures are accepted too
+    let recovered = recover_signer(&hash, &signature.as_erc2098()).unwrap();
+    assert_eq!(recovered, Some(signer.address()));
+}
+
+#[test]
+fn test_parse_signature_invalid_length() {
+    assert!(parse_signature(&[0u8; 10]).is_err());
+    assert!(recover_signer(&B256::ZERO, &[0u8; 66]).is_err());
+}
@@ -0,0 +1,234 @@
+use alloy::dyn_abi::DynSolValue;
+use alloy::node_bindings::Anvil;
+use alloy::primitives::{Address, I256, U256, address};
+use alloy::rpc::types::TransactionRequest;
+use alloy::rpc::types::state::{AccountOverride, StateOverride};
+use alloy::sol_types::{Panic, PanicKind, Revert, SolError};
+use backend::eth::{IERC20, setup_provider};
+use backend::simulation::{
+    BalanceChange, CallFrame, decode_return_value, decode_revert, parse_return_type, simulate,
+    trace_balance_changes,
+};
+use serde_json::json;
+
+const SENDER: Address = address!("0x1111111111111111111111111111111111111111");
+const ROUTER: Address = address!("0x2222222222222222222222222222222222222222");
+const TOKEN: Address = address!("0x3333333333333333333333333333333333333333");
+const RECIPIENT: Address = address!("0x4444444444444444444444444444444444444444");
+
+// Helper function to build the topics of a `Transfer` log
+fn transfer_topics(from: Address, to: Address) -> serde_json::Value {
+    json!([
+        "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+        from.into_word(),
+        to.into_word()
+    ])
+}
+
+#[test]
+fn test_trace_balance_changes() {
+    let trace = json!({
+        "type": "CALL",
+        "from": SENDER,
+        "to": ROUTER,
+        "value": "0xde0b6b3a7640000",
+        "gasUsed": "0x1d4c0",
+        "calls": [
+            {
+                // Tokens minted to the router and sent to the recipient
+                "type": "CALL",
+                "from": ROUTER,
+                "to": TOKEN,
+                "value": "0x0",
+                "gasUsed": "0x5208",
+                "logs": [
+                    { "address": TOKEN, "topics": transfer_topics(Address::ZERO, ROUTER), "data": "0x00000000000000000000000000000000000000000000000000000000000003e8" },
+                    { "address": TOKEN, "topics": transfer_topics(ROUTER, RECIPIENT), "data": "0x00000000000000000000000000000000000000000000000000000000000003e8" }
+                ]
+            },
+            {
+                // A reverted call changes nothing
+                "type": "CALL",
+                "from": ROUTER,
+                "to": RECIPIENT,
+                "value": "0x1",
+                "gasUsed": "0x5208",
+                "error": "execution reverted",
+                "logs": [
+                    { "address": TOKEN, "topics": transfer_topics(ROUTER, SENDER), "data": "0x0000000000000000000000000000000000000000000000000000000000000001" }
+                ]
+            },
+            {
+                // Delegate calls don't move value
+                "type": "DELEGATECALL",
+                "from": ROUTER,
+                "to": TOKEN,
+                "value": "0xde0b6b3a7640000",
+                "gasUsed": "0x5208"
+            },
+            {
+                "type": "CALL",
+                "from": ROUTER,
+                "to": RECIPIENT,
+                "value": "0x6f05b59d3b20000",
+                "gasUsed": "0x5208"
+            }
+        ]
+    });
+    let frame: CallFrame = serde_json::from_value(trace).unwrap();
+    assert_eq!(frame.gas_used.to::<u64>(), 120000);
+
+    let ether =
+        |value: i64| I256::try_from(value).unwrap() * I256::try_from(10u64.pow(17)).unwrap();
+    let change = |address, token_address, delta| BalanceChange {
+        address,
+        token_address,
+        before: None,
+        after: None,
+        delta,
+    };
+    let mut changes = trace_balance_changes(&frame);
+    changes.sort_by_key(|change| (change.address, change.token_address));
+    assert_eq!(
+        changes,
+        vec![
+            change(SENDER, Address::ZERO, ether(-10)),
+            change(ROUTER, Address::ZERO, ether(5)),
+            change(RECIPIENT, Address::ZERO, ether(5)),
+            change(RECIPIENT, TOKEN, I256::try_from(1000).unwrap()),
+        ]
+    );
+
+    // Nothing changes when the call reverts
+    let trace = json!({
+        "type": "CALL",
+        "from": SENDER,
+        "to": ROUTER,
+        "value": "0x1",
+        "gasUsed": "0x5208",
+        "error": "execution reverted"
+    });
+    let frame: CallFrame = serde_json::from_value(trace).unwrap();
+    assert!(trace_balance_changes(&frame).is_empty());
+
+    // A token logging huge transfers saturates the deltas instead of overflowing
+    let max = "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff";
+    let trace = json!({
+        "type": "CALL",
+        "from": SENDER,
+        "to": TOKEN,
+        "value": "0x0",
+        "gasUsed": "0x5208",
+        "logs": [
+            { "address": TOKEN, "topics": transfer_topics(SENDER, RECIPIENT), "data": max },
+            { "address": TOKEN, "topics": transfer_topics(SENDER, RECIPIENT), "data": max }
+        ]
+    });
+    let frame: CallFrame = serde_json::from_value(trace).unwrap();
+    let mut changes = trace_balance_changes(&frame);
+    changes.sort_by_key(|change| (change.address, change.token_address));
+    assert_eq!(
+        changes,
+        vec![
+            change(SENDER, TOKEN, I256::MIN),
+            change(RECIPIENT, TOKEN, I256::MAX),
+        ]
+    );
+}
+
+#[test]
+fn test_decode_revert() {
+    let revert = Revert::from("insufficient balance").abi_encode();
+    assert_eq!(
+        decode_revert(&revert).unwrap(),
+        "revert: insufficient balance"
+    );
+
+    let panic = Panic::from(PanicKind::DivisionByZero).abi_encode();
+    assert!(
+        decode_revert(&panic)
+            .unwrap()
+            .contains("division or modulo by zero")
+    );
+
+    let error = IERC20::ERC20InsufficientBalance {
+        sender: SENDER,
+        balance: U256::ZERO,
+        needed: U256::from(1u64),
+    }
+    .abi_encode();
+    assert!(
+        decode_revert(&error)
+            .unwrap()
+            .contains("ERC20InsufficientBalance")
+    );
+
+    assert_eq!(decode_revert(&[]), None);
+    assert_eq!(decode_revert(&[0xde, 0xad, 0xbe, 0xef]), None);
+}
+
+#[test]
+fn test_decode_return_value() {
+    let ty = parse_return_type("(uint256,bool,address)").unwrap();
+    let data = DynSolValue::Tuple(vec![
+        DynSolValue::Uint(U256::from(42u64), 256),
+        DynSolValue::Bool(true),
+        DynSolValue::Address(SENDER),
+    ])
+    .abi_encode_params();
+    assert_eq!(
+        decode_return_value(&ty, &data).unwrap(),
+        json!(["42", true, SENDER.to_string()])
+    );
+
+    let ty = parse_return_type("bool").unwrap();
+    assert_eq!(
+        decode_return_value(&ty, &DynSolValue::Bool(true).abi_encode()).unwrap(),
+        json!(true)
+    );
+    assert_eq!(decode_return_value(&ty, &[]), None);
+
+    assert!(parse_return_type("uint257").is_err());
+}
+
+#[tokio::test]
+async fn test_simulate_with_overrides() {
+    let anvil = Anvil::new().spawn();
+    let provider = setup_provider(&anvil.endpoint()).await.unwrap();
+    let sender = Address::random();
+    let recipient = address!("0x000000000000000000000000000000000000dEaD");
+    let watched = [(sender, Address::ZERO), (recipient, Address::ZERO)];
+    let tx = TransactionRequest::default()
+        .from(sender)
+        .to(recipient)
+        .value(U256::from(1000u64));
+
+    // The sender has no funds on chain
+    let simulation = simulate(&provider, tx.clone(), StateOverride::default(), &watched)
+        .await
+        .unwrap();
+    assert!(!simulation.success);
+    assert!(simulation.revert_reason.is_some());
+
+    // Funded by an override, the transfer goes through without touching the chain
+    let mut overrides = StateOverride::default();
+    overrides.insert(
+        sender,
+        AccountOverride {
+            balance: Some(U256::from(10u64.pow(18))),
+            ..Default::default()
+        },
+    );
+    let simulation = simulate(&provider, tx, overrides, &watched).await.unwrap();
+    assert!(simulation.success);
+    assert!(simulation.gas_used.is_some());
+    let delta = |address| {
+        simulation
+            .balance_changes
+            .iter()
+            .find(|change| change.address == address && change.token_address.is_zero())
+            .map(|change| change.delta)
+    };
+    assert_eq!(delta(sender), Some(I256::try_from(-1000).unwrap()));
+    assert_eq!(delta(recipient), Some(I256::try_from(1000).unwrap()));
+}
@@ -0,0 +1,169 @@
+use std::task::{Context, Poll};
+
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::client::ClientBuilder;
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{TransportError, TransportErrorKind, TransportFut};
+use axum::{Router, extract::Path, middleware, routing::get};
+use axum_test::TestServer;
+use sqlx::PgPool;
+use tower::Service;
+
+use backend::db::Repository;
+use backend::telemetry::{self, RpcTelemetryLayer};
+
+// Helper function to find a rendered sample of a metric with all the given labels
+fn find_sample(name: &str, labels: &[&str]) -> Option<String> {
+    telemetry::install_metrics()
+        .render()
+        .lines()
+        .find(|line| line.starts_with(name) && labels.iter().all(|label| line.contains(label)))
+        .map(str::to_string)
+}
+
+/// Transport failing every request, as an unreachable node would
+#[derive(Clone)]
+struct DownTransport;
+
+impl Service<RequestPacket> for DownTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: RequestPacket) -> Self::Future {
+        Box::pin(async { Err(TransportErrorKind::custom_str("connection refused")) })
+    }
+}
+
+#[test]
+fn test_query_label() {
+    let cases = [
+        (
+            "SELECT id, balance FROM eth_accounts WHERE address = $1",
+            "select eth_accounts",
+        ),
+        (
+            "INSERT INTO balance_history (address) VALUES ($1)",
+            "insert balance_history",
+        ),
+        (
+            "\n  UPDATE watched_balances\n SET enabled = $1",
+            "update watched_balances",
+        ),
+        ("DELETE FROM tokens WHERE address = $1", "delete tokens"),
+        ("SELECT 1", "select"),
+        ("", "unknown"),
+    ];
+    for (sql, label) in cases {
+        assert_eq!(telemetry::query_label(sql), label, "{}", sql);
+    }
+}
+
+#[test]
+fn test_cache_lookup_labels() {
+    telemetry::install_metrics();
+    telemetry::record_cache_lookup("gas_price", "hit");
+    telemetry::record_cache_lookup(
+        "price:usd:0x0000000000000000000000000000000000000001",
+        "miss",
+    );
+
+    assert!(
+        find_sample(
+            "cache_requests_total",
+            &["cache=\"gas_price\"", "result=\"hit\""]
+        )
+        .is_some()
+    );
+    // Keys are grouped by their first segment
+    assert!(
+        find_sample(
+            "cache_requests_total",
+            &["cache=\"price\"", "result=\"miss\""]
+        )
+        .is_some()
+    );
+    assert!(find_sample("cache_requests_total", &["0x0000"]).is_none());
+}
+
+#[tokio::test]
+async fn test_track_requests() {
+    telemetry::install_metrics();
+    let app = Router::new()
+        .route(
+            "/items/{id}",
+            get(async |Path(id): Path<u64>| id.to_string()),
+        )
+        .layer(middleware::from_fn(telemetry::track_requests));
+    let server = TestServer::new(app).unwrap();
+
+    server.get("/items/1").await.assert_status_ok();
+    server.get("/items/2").await.assert_status_ok();
+    server.get("/missing").await.assert_status_not_found();
+
+    // Routes are labelled by their pattern
+    let sample = find_sample(
+        "http_requests_total",
+        &["method=\"GET\"", "route=\"/items/{id}\"", "status=\"200\""],
+    )
+    .expect("request not counted");
+    assert!(sample.ends_with(" 2"), "{}", sample);
+    assert!(
+        find_sample(
+            "http_request_duration_seconds_bucket",
+            &["route=\"/items/{id}\""]
+        )
+        .is_some()
+    );
+    assert!(
+        find_sample(
+            "http_requests_total",
+            &["route=\"unmatched\"", "status=\"404\""]
+        )
+        .is_some()
+    );
+}
+
+#[tokio::test]
+async fn test_rpc_metrics_layer() {
+    telemetry::install_metrics();
+    let url = "https://rpc.example.org/v2/secret-key".parse().unwrap();
+    let client = ClientBuilder::default()
+        .layer(RpcTelemetryLayer::new(&url))
+        .transport(DownTransport, false);
+    let provider = ProviderBuilder::new().connect_client(client);
+
+    assert!(provider.get_block_number().await.is_err());
+
+    let labels = ["method=\"eth_blockNumber\"", "endpoint=\"rpc.example.org\""];
+    assert!(find_sample("rpc_requests_total", &labels).is_some());
+    assert!(find_sample("rpc_request_duration_seconds_count", &labels).is_some());
+    assert!(find_sample("rpc_errors_total", &[labels[0], "kind=\"transport\""]).is_some());
+    // Only the host of the endpoint is kept
+    assert!(find_sample("rpc_requests_total", &["secret-key"]).is_none());
+}
+
+#[sqlx::test()]
+async fn test_repository_metrics(pool: PgPool) {
+    telemetry::install_metrics();
+    let repo = Repository::new(pool).await;
+
+    repo.ping().await.unwrap();
+    assert!(repo.list_watched_balances(0, 10).await.unwrap().is_empty());
+    repo.record_pool_metrics();
+
+    assert!(
+        find_sample(
+            "db_query_duration_seconds_count",
+            &["query=\"select watched_balances\"", "status=\"ok\""]
+        )
+        .is_some()
+    );
+    assert!(find_sample("db_query_duration_seconds_count", &["query=\"select\""]).is_some());
+    assert!(find_sample("db_pool_connections", &["state=\"idle\""]).is_some());
+    assert!(find_sample("db_pool_max_connections", &[]).is_some());
+}
@@ -0,0 +1,106 @@
+use backend::db::{NewToken, Repository};
+use backend::eth::ZERO_ADDRESS;
+use backend::tokens::{ImportSummary, TokenList, import_token_list, register_native_token};
+use sqlx::PgPool;
+
+// A token list in the Uniswap format, with one token on another chain and one invalid address
+const TOKEN_LIST: &str = r#"{
+    "name": "Test List",
+    "timestamp": "2025-07-15T00:00:00.000Z",
+    "version": { "major": 1, "minor": 0, "patch": 0 },
+    "tokens": [
+        {
+            "chainId": 11155111,
+            "address": "0x3B3adf1422F84254B7FbB0E7cA62BD0865133fe3",
+            "symbol": "MTK",
+            "name": "MyToken",
+            "decimals": 18,
+            "logoURI": "https://example.com/mtk.png",
+            "tags": ["test"]
+        },
+        {
+            "chainId": 11155111,
+            "address": "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238",
+            "symbol": "USDC",
+            "name": "USD Coin",
+            "decimals": 6
+        },
+        {
+            "chainId": 1,
+            "address": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            "symbol": "USDC",
+            "name": "USD Coin",
+            "decimals": 6
+        },
+        {
+            "chainId": 11155111,
+            "address": "not-an-address",
+            "symbol": "BAD",
+            "name": "Bad Token",
+            "decimals": 18
+        }
+    ]
+}"#;
+
+#[sqlx::test()]
+async fn test_import_token_list(pool: PgPool) {
+    let repo = Repository::new(pool.clone()).await;
+    let list: TokenList = serde_json::from_str(TOKEN_LIST).unwrap();
+
+    let summary = import_token_list(&repo, &list, 11155111, true).await.unwrap();
+    assert_eq!(summary, ImportSummary {
+        imported: 2,
+        skipped: 2,
+    });
+
+    let token = repo
+        .get_token(11155111, "0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(token.chain_id, 11155111);
+    assert_eq!(token.symbol.as_deref(), Some("MTK"));
+    assert_eq!(token.decimals, Some(18));
+    assert_eq!(token.logo_uri.as_deref(), Some("https://example.com/mtk.png"));
+    assert_eq!(token.tags, vec!["test".to_string()]);
+    assert!(token.verified);
+
+    let usdc = repo
+        .get_token(11155111, "0x1c7d4b196cb0c7b01d743fbc6116a902379c7238")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(usdc.decimals, Some(6));
+    assert!(usdc.tags.is_empty());
+
+    // Tokens of other chains are skipped
+    assert!(repo
+        .get_token(1, "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[sqlx::test()]
+async fn test_register_native_token(pool: PgPool) {
+    let repo = Repository::new(pool.clone()).await;
+
+    let native = register_native_token(&repo, 11155111).await.unwrap();
+    assert_eq!(native.address, ZERO_ADDRESS);
+    assert_eq!(native.chain_id, 11155111);
+    assert_eq!(native.symbol.as_deref(), Some("ETH"));
+    assert_eq!(native.decimals, Some(18));
+    assert!(native.verified);
+
+    // An admin's changes are kept on the next start
+    repo.upsert_token(&NewToken {
+        address: ZERO_ADDRESS,
+        chain_id: 11155111,
+        symbol: Some("SepoliaETH"),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let native = register_native_token(&repo, 11155111).await.unwrap();
+    assert_eq!(native.symbol.as_deref(), Some("SepoliaETH"));
+}
@@ -0,0 +1,267 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::client::ClientBuilder;
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{TransportError, TransportErrorKind, TransportFut};
+use axum::{Json, Router, extract::State, middleware, routing::get, routing::post};
+use axum_test::TestServer;
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use serde_json::Value;
+use sqlx::PgPool;
+use tower::Service;
+use tracing_subscriber::layer::SubscriberExt;
+
+use backend::db::Repository;
+use backend::telemetry::{self, Config, OtlpProtocol, RpcTelemetryLayer};
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+/// Transport failing every request, as an unreachable node would
+#[derive(Clone)]
+struct DownTransport;
+
+impl Service<RequestPacket> for DownTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: RequestPacket) -> Self::Future {
+        Box::pin(async { Err(TransportErrorKind::custom_str("connection refused")) })
+    }
+}
+
+/// Stand-in for an OpenTelemetry collector, keeping every OTLP/HTTP JSON export it receives
+/// It runs on its own thread, the exporter blocks while it waits for the response.
+fn start_collector() -> (SocketAddr, Arc<Mutex<Vec<Value>>>) {
+    let exports = Arc::new(Mutex::new(Vec::new()));
+    let (addr_tx, addr_rx) = std::sync::mpsc::channel();
+
+    let received = exports.clone();
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async move {
+            let app = Router::new()
+                .route(
+                    "/v1/traces",
+                    post(
+                        async |State(received): State<Arc<Mutex<Vec<Value>>>>,
+                               Json(export): Json<Value>| {
+                            received.lock().unwrap().push(export);
+                            Json(serde_json::json!({}))
+                        },
+                    ),
+                )
+                .with_state(received);
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            addr_tx.send(listener.local_addr().unwrap()).unwrap();
+            axum::serve(listener, app).await.unwrap();
+        });
+    });
+
+    (addr_rx.recv().unwrap(), exports)
+}
+
+// Helper function to flatten the spans of every export
+fn exported_spans(exports: &[Value]) -> Vec<Value> {
+    exports
+        .iter()
+        .flat_map(|export| {
+            export["resourceSpans"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+        })
+        .flat_map(|resource| {
+            resource["scopeSpans"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+        })
+        .flat_map(|scope| scope["spans"].as_array().cloned().unwrap_or_default())
+        .collect()
+}
+
+// Helper function to read a string or integer attribute of a span
+fn attribute(span: &Value, key: &str) -> Option<String> {
+    let value = span["attributes"]
+        .as_array()?
+        .iter()
+        .find(|attribute| attribute["key"] == key)?["value"]
+        .clone();
+    match (&value["stringValue"], &value["intValue"]) {
+        (Value::String(value), _) => Some(value.clone()),
+        (_, Value::String(value)) => Some(value.clone()),
+        (_, Value::Number(value)) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_request_id_validation() {
+    assert!(telemetry::is_valid_request_id("req-42"));
+    assert!(telemetry::is_valid_request_id(
+        "4bf92f35-77b3-4da6-a3ce-929d0e0e4736"
+    ));
+    assert!(!telemetry::is_valid_request_id(""));
+    assert!(!telemetry::is_valid_request_id("two words"));
+    assert!(!telemetry::is_valid_request_id("line\nbreak"));
+    assert!(!telemetry::is_valid_request_id(&"a".repeat(129)));
+}
+
+#[test]
+fn test_redis_command_names_and_keys() {
+    let get = redis::cmd("GET").arg("gas_price").clone();
+    assert_eq!(
+        telemetry::command_name_and_key(&get),
+        ("GET".to_string(), Some("gas_price".to_string()))
+    );
+
+    // Sessions and nonces are credentials
+    let session = redis::cmd("get").arg("siwe:session:secret-token").clone();
+    assert_eq!(
+        telemetry::command_name_and_key(&session),
+        ("GET".to_string(), Some("siwe:session:*".to_string()))
+    );
+    assert_eq!(telemetry::redact_key("siwe:nonce:abc"), "siwe:nonce:*");
+    assert_eq!(
+        telemetry::redact_key("rate_limit:public:1.2.3.4"),
+        "rate_limit:public:1.2.3.4"
+    );
+
+    // Scripts are named by their first key
+    let script = redis::cmd("EVALSHA")
+        .arg("a1b2")
+        .arg(1)
+        .arg("rate_limit:public:key:1")
+        .arg(10)
+        .clone();
+    assert_eq!(
+        telemetry::command_name_and_key(&script),
+        (
+            "EVALSHA".to_string(),
+            Some("rate_limit:public:key:1".to_string())
+        )
+    );
+}
+
+#[sqlx::test()]
+async fn test_request_spans_exported(pool: PgPool) {
+    let (collector, exports) = start_collector();
+    let provider = telemetry::tracer_provider(&Config {
+        otlp_endpoint: Some(format!("http://{}/v1/traces", collector)),
+        otlp_protocol: OtlpProtocol::HttpJson,
+        service_name: "backend-test".to_string(),
+    })
+    .unwrap()
+    .expect("export is configured");
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    // The handler reads the database and calls a node that is down
+    let url = "http://rpc.example.org".parse().unwrap();
+    let client = ClientBuilder::default()
+        .layer(RpcTelemetryLayer::new(&url))
+        .transport(DownTransport, false);
+    let eth_provider = ProviderBuilder::new().connect_client(client).erased();
+    let repo = Repository::new(pool).await;
+    let app = Router::new()
+        .route(
+            "/items",
+            get(async move || {
+                let watched = repo.list_watched_balances(0, 10).await.unwrap();
+                let block_number = eth_provider.get_block_number().await.ok();
+                format!("{} {:?}", watched.len(), block_number)
+            }),
+        )
+        .layer(middleware::from_fn(telemetry::trace_requests));
+    let server = TestServer::new(app).unwrap();
+
+    let response = server
+        .get("/items")
+        .add_header("x-request-id", "req-42")
+        .add_header(
+            "traceparent",
+            format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID),
+        )
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("x-request-id"), "req-42");
+
+    // Without a header an id is generated
+    let response = server.get("/items").await;
+    let generated = response.header("x-request-id");
+    assert_eq!(generated.len(), 32);
+
+    provider.force_flush().unwrap();
+    let spans = exported_spans(&exports.lock().unwrap());
+    let traced: Vec<_> = spans
+        .iter()
+        .filter(|span| span["traceId"] == TRACE_ID)
+        .collect();
+
+    // The server span continues the caller's trace
+    let server_span = traced
+        .iter()
+        .find(|span| span["name"] == "GET /items")
+        .expect("server span not exported");
+    assert_eq!(server_span["parentSpanId"], PARENT_SPAN_ID);
+    assert_eq!(
+        attribute(server_span, "request_id").as_deref(),
+        Some("req-42")
+    );
+    assert_eq!(
+        attribute(server_span, "http.response.status_code").as_deref(),
+        Some("200")
+    );
+
+    // RPC and database spans are children of the server span and carry the request id
+    let rpc_span = traced
+        .iter()
+        .find(|span| span["name"] == "eth_blockNumber")
+        .expect("rpc span not exported");
+    assert_eq!(rpc_span["parentSpanId"], server_span["spanId"]);
+    assert_eq!(attribute(rpc_span, "request_id").as_deref(), Some("req-42"));
+    assert_eq!(
+        attribute(rpc_span, "server.address").as_deref(),
+        Some("rpc.example.org")
+    );
+    assert_eq!(
+        attribute(rpc_span, "error.type").as_deref(),
+        Some("transport")
+    );
+
+    let db_span = traced
+        .iter()
+        .find(|span| span["name"] == "select watched_balances")
+        .expect("db span not exported");
+    assert_eq!(db_span["parentSpanId"], server_span["spanId"]);
+    assert_eq!(attribute(db_span, "request_id").as_deref(), Some("req-42"));
+    assert_eq!(
+        attribute(db_span, "db.response.returned_rows").as_deref(),
+        Some("0")
+    );
+    assert!(
+        attribute(db_span, "db.query.text")
+            .unwrap()
+            .contains("watched_balances")
+    );
+
+    // The second request started its own trace
+    assert!(spans.iter().any(|span| span["name"] == "GET /items"
+        && span["traceId"] != TRACE_ID
+        && attribute(span, "request_id").as_deref() == Some(generated.to_str().unwrap())));
+
+    provider.shutdown().unwrap();
+}
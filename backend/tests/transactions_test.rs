@@ -0,0 +1,304 @@
+use alloy::network::TransactionBuilder;
+use alloy::node_bindings::Anvil;
+use alloy::primitives::{U256, address};
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::PrivateKeySigner;
+use backend::db::{OutgoingTransactionStatus, Repository};
+use backend::eth::setup_provider;
+use backend::transactions::{CANCEL_PURPOSE, Config};
+use backend::wallet::HotWallet;
+use sqlx::PgPool;
+
+// Helper function to build a config resubmitting every pending transaction right away
+fn config(max_resubmissions: usize) -> Config {
+    Config {
+        poll_interval: 1,
+        confirmations: 2,
+        stuck_after: 0,
+        fee_bump_percent: 20,
+        max_resubmissions,
+        max_cancellations: 3,
+        max_fee_per_gas: 1_000_000_000_000,
+    }
+}
+
+// Helper function to mine blocks on the dev node
+async fn mine(provider: &DynProvider, blocks: usize) {
+    for _ in 0..blocks {
+        let _: String = provider.raw_request("evm_mine".into(), ()).await.unwrap();
+    }
+}
+
+#[sqlx::test()]
+async fn test_transaction_lifecycle(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let anvil = Anvil::new().spawn();
+    let provider = setup_provider(&anvil.endpoint()).await.unwrap();
+    let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let wallet = HotWallet::new(provider.clone(), signer, repo.clone(), anvil.chain_id());
+    let config = config(3);
+
+    let recipient = address!("0x000000000000000000000000000000000000dEaD");
+    let tx_hash = wallet
+        .send_eth(recipient, U256::from(1000u64), "test")
+        .await
+        .unwrap();
+
+    // Mined, then confirmed once enough blocks are on top
+    wallet.transactions().poll(&config).await.unwrap();
+    let tx = repo
+        .get_outgoing_transaction_by_hash(&tx_hash.to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tx.status, OutgoingTransactionStatus::Mined);
+    assert_eq!(tx.succeeded, Some(true));
+
+    mine(&provider, 2).await;
+    wallet.transactions().poll(&config).await.unwrap();
+    let tx = repo
+        .get_outgoing_transaction_by_hash(&tx_hash.to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tx.status, OutgoingTransactionStatus::Confirmed);
+}
+
+#[sqlx::test()]
+async fn test_unrecordable_transaction_is_not_sent(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let anvil = Anvil::new().spawn();
+    let provider = setup_provider(&anvil.endpoint()).await.unwrap();
+    let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let wallet = HotWallet::new(provider.clone(), signer, repo.clone(), anvil.chain_id());
+
+    // The value doesn't fit the stored amounts, the gas is set so the node isn't asked
+    let recipient = address!("0x000000000000000000000000000000000000dEaD");
+    let request = TransactionRequest::default()
+        .with_to(recipient)
+        .with_value(U256::MAX)
+        .with_gas_limit(21_000);
+    assert!(wallet.transactions().send(request, "test").await.is_err());
+    let sent = provider
+        .get_transaction_count(wallet.address())
+        .pending()
+        .await
+        .unwrap();
+    assert_eq!(sent, 0);
+
+    // The nonce was not used up
+    let tx_hash = wallet
+        .send_eth(recipient, U256::from(1000u64), "test")
+        .await
+        .unwrap();
+    let tx = repo
+        .get_outgoing_transaction_by_hash(&tx_hash.to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tx.nonce, 0);
+}
+
+#[sqlx::test()]
+async fn test_stuck_transactions(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let anvil = Anvil::new().arg("--no-mining").spawn();
+    let provider = setup_provider(&anvil.endpoint()).await.unwrap();
+    let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let wallet = HotWallet::new(provider.clone(), signer, repo.clone(), anvil.chain_id());
+
+    let recipient = address!("0x000000000000000000000000000000000000dEaD");
+    let tx_hash = wallet
+        .send_eth(recipient, U256::from(1000u64), "test")
+        .await
+        .unwrap();
+    let original = repo
+        .get_outgoing_transaction_by_hash(&tx_hash.to_string())
+        .await
+        .unwrap()
+        .unwrap();
+
+    // A stuck transaction is resubmitted with the same payload and bumped fees
+    wallet.transactions().poll(&config(1)).await.unwrap();
+    let attempts = repo
+        .list_nonce_attempts(&original.from_address, anvil.chain_id(), 0)
+        .await
+        .unwrap();
+    assert_eq!(attempts.len(), 2);
+    assert_eq!(attempts[0].status, OutgoingTransactionStatus::Replaced);
+    assert_eq!(attempts[0].replaced_by, Some(attempts[1].id));
+    assert_eq!(attempts[1].status, OutgoingTransactionStatus::Pending);
+    assert_eq!(attempts[1].purpose, "test");
+    assert_eq!(attempts[1].to_address, original.to_address);
+    assert!(attempts[1].max_priority_fee_per_gas > original.max_priority_fee_per_gas);
+    assert!(attempts[1].max_fee_per_gas > original.max_fee_per_gas);
+
+    // Past the resubmissions the nonce is cancelled
+    wallet.transactions().poll(&config(1)).await.unwrap();
+    let attempts = repo
+        .list_nonce_attempts(&original.from_address, anvil.chain_id(), 0)
+        .await
+        .unwrap();
+    assert_eq!(attempts.len(), 3);
+    assert_eq!(attempts[2].purpose, CANCEL_PURPOSE);
+    assert_eq!(attempts[2].to_address, Some(original.from_address.clone()));
+    assert_eq!(attempts[2].value.to_string(), "0");
+
+    // Only the cancellation makes it into a block
+    mine(&provider, 1).await;
+    wallet.transactions().poll(&config(1)).await.unwrap();
+    let attempts = repo
+        .list_nonce_attempts(&original.from_address, anvil.chain_id(), 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        attempts.iter().map(|tx| tx.status).collect::<Vec<_>>(),
+        vec![
+            OutgoingTransactionStatus::Replaced,
+            OutgoingTransactionStatus::Replaced,
+            OutgoingTransactionStatus::Mined
+        ]
+    );
+    assert_eq!(provider.get_balance(recipient).await.unwrap(), U256::ZERO);
+
+    // The next transaction takes the next nonce
+    let tx_hash = wallet
+        .send_eth(recipient, U256::from(1000u64), "test")
+        .await
+        .unwrap();
+    let tx = repo
+        .get_outgoing_transaction_by_hash(&tx_hash.to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tx.nonce, 1);
+}
+
+#[sqlx::test()]
+async fn test_stuck_nonce_is_given_up(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let anvil = Anvil::new().arg("--no-mining").spawn();
+    let provider = setup_provider(&anvil.endpoint()).await.unwrap();
+    let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let wallet = HotWallet::new(provider.clone(), signer, repo.clone(), anvil.chain_id());
+    let config = Config {
+        max_cancellations: 1,
+        ..config(0)
+    };
+
+    let recipient = address!("0x000000000000000000000000000000000000dEaD");
+    let tx_hash = wallet
+        .send_eth(recipient, U256::from(1000u64), "test")
+        .await
+        .unwrap();
+    let from_address = repo
+        .get_outgoing_transaction_by_hash(&tx_hash.to_string())
+        .await
+        .unwrap()
+        .unwrap()
+        .from_address;
+
+    // Cancelled right away, then given up on once the cancellation is stuck too
+    wallet.transactions().poll(&config).await.unwrap();
+    wallet.transactions().poll(&config).await.unwrap();
+    wallet.transactions().poll(&config).await.unwrap();
+    let attempts = repo
+        .list_nonce_attempts(&from_address, anvil.chain_id(), 0)
+        .await
+        .unwrap();
+    assert_eq!(attempts.len(), 2);
+    assert_eq!(attempts[1].purpose, CANCEL_PURPOSE);
+    assert_eq!(attempts[1].status, OutgoingTransactionStatus::Pending);
+    assert!(attempts[1].stuck_at.is_some());
+
+    // A stuck nonce is still followed
+    mine(&provider, 1).await;
+    wallet.transactions().poll(&config).await.unwrap();
+    let cancellation = repo
+        .get_outgoing_transaction_by_hash(&attempts[1].tx_hash)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cancellation.status, OutgoingTransactionStatus::Mined);
+}
+
+#[sqlx::test()]
+async fn test_resubmission_fee_cap(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let anvil = Anvil::new().arg("--no-mining").spawn();
+    let provider = setup_provider(&anvil.endpoint()).await.unwrap();
+    let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let wallet = HotWallet::new(provider.clone(), signer, repo.clone(), anvil.chain_id());
+
+    let recipient = address!("0x000000000000000000000000000000000000dEaD");
+    let tx_hash = wallet
+        .send_eth(recipient, U256::from(1000u64), "test")
+        .await
+        .unwrap();
+    let original = repo
+        .get_outgoing_transaction_by_hash(&tx_hash.to_string())
+        .await
+        .unwrap()
+        .unwrap();
+
+    // The bumped fee would be above the cap, nothing is sent
+    let capped = Config {
+        max_fee_per_gas: original.max_fee_per_gas.to_string().parse().unwrap(),
+        ..config(3)
+    };
+    wallet.transactions().poll(&capped).await.unwrap();
+    let attempts = repo
+        .list_nonce_attempts(&original.from_address, anvil.chain_id(), 0)
+        .await
+        .unwrap();
+    assert_eq!(attempts.len(), 1);
+    assert!(attempts[0].stuck_at.is_some());
+
+    // It stays stuck, even without the cap
+    wallet.transactions().poll(&config(3)).await.unwrap();
+    let attempts = repo
+        .list_nonce_attempts(&original.from_address, anvil.chain_id(), 0)
+        .await
+        .unwrap();
+    assert_eq!(attempts.len(), 1);
+}
+
+#[sqlx::test()]
+async fn test_poll_is_skipped_while_another_instance_polls(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let anvil = Anvil::new().arg("--no-mining").spawn();
+    let provider = setup_provider(&anvil.endpoint()).await.unwrap();
+    let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let wallet = HotWallet::new(provider.clone(), signer, repo.clone(), anvil.chain_id());
+
+    let recipient = address!("0x000000000000000000000000000000000000dEaD");
+    let tx_hash = wallet
+        .send_eth(recipient, U256::from(1000u64), "test")
+        .await
+        .unwrap();
+    let from_address = repo
+        .get_outgoing_transaction_by_hash(&tx_hash.to_string())
+        .await
+        .unwrap()
+        .unwrap()
+        .from_address;
+
+    // Another instance holds the lock of the wallet, nothing is resubmitted
+    let lock_key = format!("outgoing_transactions:{}:{}", wallet.address(), anvil.chain_id());
+    let lock = repo.try_advisory_lock(&lock_key).await.unwrap().unwrap();
+    wallet.transactions().poll(&config(3)).await.unwrap();
+    let attempts = repo
+        .list_nonce_attempts(&from_address, anvil.chain_id(), 0)
+        .await
+        .unwrap();
+    assert_eq!(attempts.len(), 1);
+
+    drop(lock);
+    wallet.transactions().poll(&config(3)).await.unwrap();
+    let attempts = repo
+        .list_nonce_attempts(&from_address, anvil.chain_id(), 0)
+        .await
+        .unwrap();
+    assert_eq!(attempts.len(), 2);
+}
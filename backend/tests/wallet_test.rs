@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use alloy::network::EthereumWallet;
+use alloy::node_bindings::Anvil;
+use alloy::primitives::{Address, B256, U256, address};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::signers::local::PrivateKeySigner;
+use backend::db::Repository;
+use backend::eth::setup_provider;
+use backend::wallet::{Config, HotWallet, MyToken};
+use sqlx::PgPool;
+
+const KEYSTORE_PATH: &str = "tests/fixtures/hot_wallet_keystore.json";
+
+/// The keystore holds the first development account of anvil
+const WALLET_ADDRESS: Address = address!("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+
+// Helper function to build the wallet config of the test keystore
+fn config(password: &str) -> Config {
+    Config {
+        keystore_path: Some(KEYSTORE_PATH.to_string()),
+        keystore_password: Some(password.to_string()),
+        my_token_address: Address::ZERO,
+    }
+}
+
+// Helper function to wait for a transaction to be mined by the dev node
+async fn wait_for_receipt(provider: &DynProvider, tx_hash: B256) -> bool {
+    for _ in 0..50 {
+        if let Some(receipt) = provider.get_transaction_receipt(tx_hash).await.unwrap() {
+            return receipt.status();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("transaction {} was not mined", tx_hash);
+}
+
+#[sqlx::test()]
+async fn test_load_keystore(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let provider = ProviderBuilder::new()
+        .connect_http("http://localhost:8545".parse().unwrap())
+        .erased();
+
+    let wallet = HotWallet::from_config(provider.clone(), repo.clone(), 1, &config("password"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(wallet.address(), WALLET_ADDRESS);
+
+    assert!(HotWallet::from_config(provider.clone(), repo.clone(), 1, &config("wrong")).is_err());
+
+    let disabled = Config {
+        keystore_path: None,
+        ..config("password")
+    };
+    assert!(
+        HotWallet::from_config(provider, repo, 1, &disabled)
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[sqlx::test()]
+async fn test_mint_my_token(pool: PgPool) {
+    let repo = Repository::new(pool).await;
+    let anvil = Anvil::new().spawn();
+    let provider = setup_provider(&anvil.endpoint()).await.unwrap();
+
+    // Deploy MyToken from the bytecode of its Ignition artifact
+    let deployer: PrivateKeySigner = anvil.keys()[1].clone().into();
+    let deployer_provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::from(deployer))
+        .connect_provider(provider.clone());
+    let token = MyToken::deploy(deployer_provider).await.unwrap();
+    let token_address = *token.address();
+
+    let wallet = HotWallet::from_config(
+        provider.clone(),
+        repo.clone(),
+        anvil.chain_id(),
+        &config("password"),
+    )
+    .unwrap()
+    .unwrap();
+    let recipient = address!("0x000000000000000000000000000000000000dEaD");
+    let amount = U256::from(1_000_000u64);
+
+    // Minting to another account transfers the minted tokens
+    let transactions = wallet
+        .mint_my_token(token_address, recipient, amount)
+        .await
+        .unwrap();
+    assert!(wait_for_receipt(&provider, transactions.mint_tx_hash).await);
+    assert!(wait_for_receipt(&provider, transactions.transfer_tx_hash.unwrap()).await);
+
+    let token = MyToken::new(token_address, provider.clone());
+    assert_eq!(token.balanceOf(recipient).call().await.unwrap(), amount);
+    assert_eq!(token.balanceOf(WALLET_ADDRESS).call().await.unwrap(), U256::ZERO);
+
+    // Both transactions are recorded with consecutive nonces
+    let mint = repo
+        .get_outgoing_transaction_by_hash(&transactions.mint_tx_hash.to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    let transfer = repo
+        .get_outgoing_transaction_by_hash(&transactions.transfer_tx_hash.unwrap().to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(mint.purpose, "mint");
+    assert_eq!(transfer.nonce, mint.nonce + 1);
+
+    // Minting to the wallet itself needs no transfer
+    let transactions = wallet
+        .mint_my_token(token_address, WALLET_ADDRESS, amount)
+        .await
+        .unwrap();
+    assert!(transactions.transfer_tx_hash.is_none());
+    assert!(wait_for_receipt(&provider, transactions.mint_tx_hash).await);
+    assert_eq!(token.balanceOf(WALLET_ADDRESS).call().await.unwrap(), amount);
+}
@@ -0,0 +1,207 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::time::Duration;
+
+use alloy::primitives::address;
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+};
+use hmac::{Hmac, Mac};
+use rust_decimal::Decimal;
+use sha2::Sha256;
+use sqlx::PgPool;
+use tokio::net::TcpListener;
+
+use backend::db::Repository;
+use backend::shutdown::{self, Shutdown};
+use backend::webhook::{Config, EVENT_ID_HEADER, SIGNATURE_HEADER, WebhookNotifier};
+
+const SECRET: &str = "0123456789abcdef";
+
+// State of the local receiver, which fails the first `fail_first` requests
+#[derive(Clone)]
+struct Receiver {
+    secret: &'static str,
+    fail_first: usize,
+    received: Arc<AtomicUsize>,
+}
+
+async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    let count = receiver.received.fetch_add(1, Ordering::SeqCst);
+
+    // Verify the HMAC-SHA256 signature of the raw body
+    let mut mac = Hmac::<Sha256>::new_from_slice(receiver.secret.as_bytes()).unwrap();
+    mac.update(&body);
+    let expected = format!("sha256={}", alloy::hex::encode(mac.finalize().into_bytes()));
+    let signature = headers.get(SIGNATURE_HEADER).and_then(|value| value.to_str().ok());
+    if signature != Some(expected.as_str()) || headers.get(EVENT_ID_HEADER).is_none() {
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    if count < receiver.fail_first {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+    StatusCode::OK
+}
+
+// Helper function to start a local webhook receiver, returns its url and request counter
+async fn start_receiver(secret: &'static str, fail_first: usize) -> (String, Arc<AtomicUsize>) {
+    let received = Arc::new(AtomicUsize::new(0));
+    let app = Router::new().route("/hook", post(receive)).with_state(Receiver {
+        secret,
+        fail_first,
+        received: received.clone(),
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    (format!("http://{}/hook", addr), received)
+}
+
+// Helper function to create a notifier with fast retries
+fn create_test_notifier(repo: Repository, shutdown: Shutdown) -> WebhookNotifier {
+    let config = Config {
+        max_attempts: 4,
+        initial_backoff_ms: 10,
+        request_timeout: 5,
+    };
+    WebhookNotifier::new(repo, &config, shutdown).unwrap()
+}
+
+#[sqlx::test()]
+async fn test_deliver_retries_until_success(pool: PgPool) {
+    let (url, received) = start_receiver(SECRET, 2).await;
+    let repo = Repository::new(pool.clone()).await;
+    let notifier = create_test_notifier(repo.clone(), Shutdown::new());
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+
+    let webhook = repo
+        .create_webhook(&url, SECRET, &address, None, Decimal::ZERO)
+        .await
+        .unwrap();
+    let payload = serde_json::json!({ "event": "balance.changed" });
+
+    let delivered = notifier.deliver(&webhook, "event-1", &payload).await.unwrap();
+    assert!(delivered);
+    assert_eq!(received.load(Ordering::SeqCst), 3);
+
+    // Every attempt is persisted, newest first
+    let deliveries = repo.list_webhook_deliveries(webhook.id, 10).await.unwrap();
+    let attempts: Vec<i32> = deliveries.iter().map(|delivery| delivery.attempt).collect();
+    assert_eq!(attempts, vec![3, 2, 1]);
+    assert!(deliveries[0].succeeded);
+    assert_eq!(deliveries[0].status_code, Some(200));
+    assert!(!deliveries[1].succeeded);
+    assert_eq!(deliveries[1].status_code, Some(500));
+    assert!(deliveries.iter().all(|delivery| delivery.event_id == "event-1"));
+}
+
+#[sqlx::test()]
+async fn test_deliver_gives_up_on_bad_signature(pool: PgPool) {
+    // The receiver expects a different secret, so every attempt is rejected
+    let (url, received) = start_receiver("fedcba9876543210", 0).await;
+    let repo = Repository::new(pool.clone()).await;
+    let notifier = create_test_notifier(repo.clone(), Shutdown::new());
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+
+    let webhook = repo
+        .create_webhook(&url, SECRET, &address, None, Decimal::ZERO)
+        .await
+        .unwrap();
+    let payload = serde_json::json!({ "event": "balance.changed" });
+
+    let delivered = notifier.deliver(&webhook, "event-2", &payload).await.unwrap();
+    assert!(!delivered);
+    assert_eq!(received.load(Ordering::SeqCst), 4);
+
+    let deliveries = repo.list_webhook_deliveries(webhook.id, 10).await.unwrap();
+    assert_eq!(deliveries.len(), 4);
+    assert!(deliveries
+        .iter()
+        .all(|delivery| !delivery.succeeded && delivery.status_code == Some(401)));
+}
+
+#[sqlx::test()]
+async fn test_deliver_stops_retrying_on_shutdown(pool: PgPool) {
+    // The receiver always fails, so only shutdown ends the retries early
+    let (url, received) = start_receiver(SECRET, usize::MAX).await;
+    let repo = Repository::new(pool.clone()).await;
+    let shutdown = Shutdown::new();
+    let notifier = create_test_notifier(repo.clone(), shutdown.clone());
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+
+    let webhook = repo
+        .create_webhook(&url, SECRET, &address, None, Decimal::ZERO)
+        .await
+        .unwrap();
+    let payload = serde_json::json!({ "event": "balance.changed" });
+
+    let config = shutdown::Config {
+        readiness_delay: 0,
+        drain_timeout: 0,
+    };
+    shutdown.start(&config).await;
+
+    let delivered = notifier.deliver(&webhook, "event-3", &payload).await.unwrap();
+    assert!(!delivered);
+    assert_eq!(received.load(Ordering::SeqCst), 1);
+
+    // The failed attempt is still persisted, so it can be replayed later
+    let deliveries = repo.list_webhook_deliveries(webhook.id, 10).await.unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert!(!deliveries[0].succeeded);
+}
+
+#[sqlx::test()]
+async fn test_notify_balance_change_threshold(pool: PgPool) {
+    let (url, received) = start_receiver(SECRET, 0).await;
+    let repo = Repository::new(pool.clone()).await;
+    let notifier = create_test_notifier(repo.clone(), Shutdown::new());
+    let address = address!("0xea921fb6d4cf7f5ced3e5a774dea51496d1ed2bf").to_string();
+    let token_address = address!("0x3b3adf1422f84254b7fbb0e7ca62bd0865133fe3").to_string();
+
+    let webhook = repo
+        .create_webhook(&url, SECRET, &address, Some(&token_address), Decimal::new(100, 0))
+        .await
+        .unwrap();
+
+    // Changes of other tokens or within the threshold are ignored
+    let notified = notifier
+        .notify_balance_change(&address, &address, 1, Decimal::ZERO, Decimal::new(1000, 0))
+        .await
+        .unwrap();
+    assert_eq!(notified, 0);
+    let notified = notifier
+        .notify_balance_change(&address, &token_address, 1, Decimal::new(1000, 0), Decimal::new(900, 0))
+        .await
+        .unwrap();
+    assert_eq!(notified, 0);
+
+    let notified = notifier
+        .notify_balance_change(&address, &token_address, 2, Decimal::new(1000, 0), Decimal::new(899, 0))
+        .await
+        .unwrap();
+    assert_eq!(notified, 1);
+
+    // Delivery runs in the background
+    let mut deliveries = Vec::new();
+    for _ in 0..50 {
+        deliveries = repo.list_webhook_deliveries(webhook.id, 10).await.unwrap();
+        if !deliveries.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(received.load(Ordering::SeqCst), 1);
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].payload["delta"], "-101");
+    assert_eq!(deliveries[0].payload["previous_balance"], "1000");
+}